@@ -0,0 +1,978 @@
+use crate::metrics::Metrics;
+use crate::notify::{
+    Alert, AlertState, Breach, ComparisonOutOfSync, ContentChurn, Destructive, FacetDrops, IndexDeleted,
+    IndexRecreated, ItemsDropped, Notice, Notifier, PendingTasks, Recovery, ReplicasOutOfSync, SearchErrors,
+    SettingsChanged, Unreachable,
+};
+use crate::output::Output;
+use crate::quiet_hours::is_quiet;
+use crate::{
+    now, AlgoliaLogEntry, Attribution, BrowseCount, Check, Churn, CompareTarget, Comparison,
+    DestructiveOperation, DigestSink, ErrorRate, FacetCounts, IndexItems, ItemsCount, MonitorError, MonitorEvent,
+    Poll, QuietHours, ReplicaCount, SettingsDiff, Summary, TaskBacklog, ThresholdBreach,
+};
+use chrono::Utc;
+use chrono_tz::Tz;
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::{debug, error, info, warn};
+
+/// Limits and cooldown of the alerts on the events of the monitors, along with when they are held back
+#[derive(Clone, Debug, Default)]
+pub struct AlertOptions {
+    /// Time after which an ongoing breach is notified again
+    pub alert_cooldown: Duration,
+    /// Whether an index that can't be read is notified besides being printed
+    pub alert_on_error: bool,
+    pub max_consecutive_errors: u32,
+    pub max_pending_tasks: Option<u64>,
+    pub max_task_age: Option<Duration>,
+    pub max_churn_percent: f64,
+    /// Failed searches per minute past which the errors are alerted on, `None` without checking them
+    pub error_rate_threshold: Option<f64>,
+    /// Whether the expected records count comes from a command, which the printed counts tell
+    pub expected_by_command: bool,
+    /// Whether the expected records count starts again from the count of a recreated index
+    pub rebaseline_on_recreate: bool,
+    /// Daily windows during which breaches are only printed
+    pub quiet_hours: Vec<QuietHours>,
+    /// Timezone of the quiet hours, the local one when `None`
+    pub timezone: Option<Tz>,
+    /// Whether the breaches are reported to Sentry besides being notified
+    #[cfg(feature = "sentry")]
+    pub sentry_report_breaches: bool,
+}
+
+impl AlertOptions {
+    /// Whether breaches are only printed right now
+    pub fn is_quiet_hours(&self) -> bool {
+        is_quiet(&self.quiet_hours, self.timezone, Utc::now())
+    }
+}
+
+/// Alert states of the checks of an index over its run
+#[derive(Debug, Default)]
+pub struct IndexAlerts {
+    pub records: AlertState,
+    pub errors: AlertState,
+    pub tasks: AlertState,
+    pub churn: AlertState,
+    pub replicas: AlertState,
+    pub comparison: AlertState,
+    pub items: HashMap<IndexItems, AlertState>,
+    pub facets: HashMap<(String, String), AlertState>,
+}
+
+impl IndexAlerts {
+    /// Whether an alert of any of the checks is ongoing
+    pub fn breached(&self) -> bool {
+        [&self.records, &self.errors, &self.tasks, &self.churn, &self.replicas, &self.comparison]
+            .into_iter()
+            .chain(self.items.values())
+            .chain(self.facets.values())
+            .any(|alert_state| alert_state.breached_for().is_some())
+    }
+}
+
+/// Breach as a sentence naming what breached, such as `Records count dropped to 0 records`
+fn describe(breach: &ThresholdBreach) -> String {
+    match breach {
+        ThresholdBreach::DataSize { .. } => format!("Index {breach}"),
+        ThresholdBreach::Latency { .. } => format!("Search {breach}"),
+        breach => format!("Records count {breach}"),
+    }
+}
+
+/// Index being polled together with where its results go
+pub struct MonitoredIndex<'a> {
+    pub options: &'a AlertOptions,
+    pub notifier: &'a Notifier,
+    pub metrics: &'a Metrics,
+    pub output: &'a Output,
+    pub index_name: &'a str,
+    /// Name of the index in the output and the metrics, along with the subset
+    pub name: &'a str,
+    pub subset: Option<&'a str>,
+    pub app_id: &'a str,
+    pub compare: Option<&'a CompareTarget>,
+}
+
+impl MonitoredIndex<'_> {
+    /// Alerts on the event of the run of the monitor and records it in the summary and the metrics
+    pub async fn report_event(
+        &self,
+        event: MonitorEvent,
+        alerts: &mut IndexAlerts,
+        summary: &mut Summary,
+        digest: Option<&DigestSink>,
+    ) {
+        let name = self.name;
+        match event {
+            MonitorEvent::Polled(poll) => {
+                let records = poll.records.current_records;
+                self.report_replicas(records, &poll.replicas, &mut alerts.replicas);
+                self.report_comparison(records, &poll.comparison, &mut alerts.comparison);
+                self.report_facets(&poll.facets, &mut alerts.facets);
+                self.report(*poll, &mut alerts.records, summary);
+            }
+            MonitorEvent::ErrorsCounted(errors) => {
+                if let Some(threshold) = self.options.error_rate_threshold {
+                    self.report_errors(Some(errors), threshold, &mut alerts.errors);
+                }
+            }
+            MonitorEvent::TasksChecked(backlog) => self.report_tasks(backlog, &mut alerts.tasks),
+            MonitorEvent::SettingsChecked { diff: Some(diff), .. } => self.report_settings(diff),
+            MonitorEvent::ItemsChecked(count) => {
+                self.report_items(count, alerts.items.entry(count.items).or_default())
+            }
+            MonitorEvent::ChurnSampled(churn) => self.report_churn(churn, &mut alerts.churn),
+            MonitorEvent::Verified { count, records } => self.report_browse(count, records),
+            MonitorEvent::CheckFailed { check, error, disabled: true } => match check {
+                Check::Tasks => {
+                    warn!("[{}] Pending tasks of {} are not checked any more: {}", now(), name, error)
+                }
+                Check::Settings => warn!("[{}] Settings of {} are not checked any more: {}", now(), name, error),
+                Check::Items(items) => {
+                    warn!("[{}] The {} of {} are not checked any more: {}", now(), items, name, error)
+                }
+                Check::Churn => warn!("[{}] ObjectIDs of {} are not sampled any more: {}", now(), name, error),
+                Check::Verification => {
+                    warn!("[{}] Breaches of {} are not verified any more: {}", now(), name, error)
+                }
+            },
+            MonitorEvent::CheckFailed { error, .. } => warn!("[{}] Failed to browse {}: {}", now(), name, error),
+            MonitorEvent::PollEnded(end) => {
+                let error = end.error.as_ref().map(ToString::to_string);
+                self.metrics.poll(name, end.elapsed, error.as_deref());
+                self.output.state.logs_since(name, end.logs_since);
+                match &end.error {
+                    None => {
+                        self.notifier.heartbeat(name, end.records);
+                        alerts.records.reachable();
+                    }
+                    Some(error) => {
+                        self.notifier.heartbeat_failed(name, error);
+                        #[cfg(feature = "sentry")]
+                        if end.gives_up {
+                            crate::reporting::capture_error(self.app_id, name, end.polls, error);
+                        }
+                        let consecutive_errors = end.consecutive_errors;
+                        self.report_failure(error, &mut alerts.records, consecutive_errors, end.gives_up, digest)
+                            .await;
+                    }
+                }
+                summary.polls = end.polls;
+            }
+            // The settings, the progress of the browses and the next poll are the outputs' to tell, and the rest
+            // of the outcome of the poll comes out with its Polled event
+            _ => {}
+        }
+    }
+
+    /// Tells of a failed poll, alerting once when the index turns unreachable, and that the monitor gives up on
+    /// the index when it does, on a permanent error or too many consecutive ones
+    ///
+    /// A deleted index is usually about to be recreated by a reindex, so it isn't counted as a failure.
+    pub async fn report_failure(
+        &self,
+        error: &MonitorError,
+        alert_state: &mut AlertState,
+        consecutive_errors: u32,
+        gives_up: bool,
+        digest: Option<&DigestSink>,
+    ) {
+        let index_name = self.name;
+        if let MonitorError::IndexDeleted(_) = error {
+            if alert_state.unreachable() {
+                self.output.event(index_name, "deleted", "index was deleted");
+                error!(
+                    "{}",
+                    self.output.stderr.error(format_args!(
+                        "[{}] Index {} was deleted, waiting for it to be recreated",
+                        now(),
+                        index_name
+                    ))
+                );
+                if let Some(digest) = digest {
+                    digest.critical("index was deleted");
+                }
+                if self.subset.is_none() {
+                    self.notifier.notify(Alert::IndexDeleted(IndexDeleted {
+                        app_id: self.app_id.to_string(),
+                        index_name: self.index_name.to_string(),
+                        timestamp: Utc::now(),
+                    }));
+                }
+            }
+            return;
+        }
+
+        self.metrics.poll_error(index_name);
+        self.output.event(index_name, "error", error);
+
+        // An index gone from the list of indices is alerted on even without --alert-on-error
+        let alert_on_error = self.options.alert_on_error || matches!(error, MonitorError::IndexNotFound(_));
+        if error.is_unreachable() && alert_on_error && alert_state.unreachable() {
+            let alert = Alert::Unreachable(Unreachable {
+                app_id: self.app_id.to_string(),
+                index_name: self.index_name.to_string(),
+                error: error.to_string(),
+                timestamp: Utc::now(),
+            });
+
+            // The monitor stops right away on permanent errors, so the delivery has to finish first
+            match error.is_permanent() {
+                true => self.notifier.notify_and_wait(alert).await,
+                false => self.notifier.notify(alert),
+            }
+        }
+
+        let stderr = self.output.stderr;
+        let max_consecutive_errors = self.options.max_consecutive_errors;
+        if error.is_permanent() {
+            error!(
+                "{}",
+                stderr.error(format_args!(
+                    "[{}] Index {} is not reachable, giving up: {}",
+                    now(),
+                    index_name,
+                    error
+                ))
+            );
+            return;
+        }
+
+        error!(
+            "{}",
+            stderr.error(format_args!(
+                "[{}] {} ({}/{}): {}",
+                now(),
+                match error.is_unreachable() {
+                    true => format!("Index {index_name} is not reachable"),
+                    false => format!("Poll of {index_name} failed"),
+                },
+                consecutive_errors,
+                max_consecutive_errors,
+                error
+            ))
+        );
+        if gives_up {
+            error!(
+                "{}",
+                stderr.error(format_args!(
+                    "[{}] Too many consecutive errors on {}, giving up",
+                    now(),
+                    index_name
+                ))
+            );
+        }
+    }
+
+    /// Alerts when the server errors since the previous check are past the threshold
+    pub fn report_errors(&self, errors: Option<ErrorRate>, threshold: f64, alert_state: &mut AlertState) {
+        let Some(errors) = errors else {
+            return;
+        };
+        if errors.per_minute <= threshold {
+            alert_state.recover();
+            return;
+        }
+
+        let codes: Vec<_> = errors.by_code.iter().map(|(code, count)| format!("{code}: {count}")).collect();
+        error!(
+            "{}{}",
+            self.output.prefix,
+            self.output.stderr.error(format_args!(
+                "[{}] {} server errors on {} ({:.1} per minute, threshold {}): {}",
+                now(),
+                errors.errors,
+                self.index_name,
+                errors.per_minute,
+                threshold,
+                codes.join(", ")
+            ))
+        );
+        for example in &errors.examples {
+            error!("{}  {}", self.output.prefix, example.to_json());
+        }
+
+        if alert_state.breach(self.options.alert_cooldown) {
+            self.notifier.notify(Alert::SearchErrors(SearchErrors {
+                app_id: self.app_id.to_string(),
+                index_name: self.index_name.to_string(),
+                errors: errors.errors,
+                per_minute: errors.per_minute,
+                by_code: errors.by_code,
+                timestamp: Utc::now(),
+                examples: errors.examples,
+            }));
+        }
+    }
+
+    /// Publishes the number of tasks waiting to be published and notifies when they pile up past the limits
+    fn report_tasks(&self, backlog: TaskBacklog, alert_state: &mut AlertState) {
+        self.metrics.pending_tasks(self.name, backlog.pending);
+        let too_many = self.options.max_pending_tasks.is_some_and(|limit| backlog.pending > limit);
+        let too_old = self.options.max_task_age.zip(backlog.oldest_age).is_some_and(|(limit, age)| age > limit);
+        if !too_many && !too_old {
+            if alert_state.is_breached() {
+                alert_state.recover();
+                info!(
+                    "{}{}",
+                    self.output.prefix,
+                    self.output.stderr.recovery(format_args!(
+                        "[{}] Indexing of {} caught up, {} tasks waiting",
+                        now(),
+                        self.index_name,
+                        backlog.pending
+                    ))
+                );
+            }
+            return;
+        }
+
+        let oldest_age = backlog.oldest_age.map(|age| Duration::from_secs(age.as_secs()));
+        warn!(
+            "{}{}",
+            self.output.prefix,
+            self.output.stderr.breach(format_args!(
+                "[{}] Indexing of {} is falling behind, {} tasks waiting to be published{}",
+                now(),
+                self.index_name,
+                backlog.pending,
+                match oldest_age {
+                    Some(age) => format!(", the oldest for {}", humantime::format_duration(age)),
+                    None => String::new(),
+                }
+            ))
+        );
+
+        if alert_state.breach(self.options.alert_cooldown) {
+            self.notifier.notify(Alert::PendingTasks(PendingTasks {
+                app_id: self.app_id.to_string(),
+                index_name: self.index_name.to_string(),
+                pending_tasks: backlog.pending,
+                oldest_task_age_secs: oldest_age.map(|age| age.as_secs()),
+                timestamp: Utc::now(),
+            }));
+        }
+    }
+
+    /// Notifies when more of the objectID sample disappeared than allowed, with examples of the missing ones
+    fn report_churn(&self, churn: Churn, alert_state: &mut AlertState) {
+        debug!(
+            "[{}] {}: {} of {} sampled objectIDs disappeared since the last poll",
+            now(),
+            self.name,
+            churn.disappeared,
+            churn.sampled
+        );
+        if churn.percent <= self.options.max_churn_percent {
+            if alert_state.is_breached() {
+                alert_state.recover();
+                info!(
+                    "{}{}",
+                    self.output.prefix,
+                    self.output.stderr.recovery(format_args!(
+                        "[{}] Records of {} are stable again, {:.1}% of the objectID sample changed",
+                        now(),
+                        self.name,
+                        churn.percent
+                    ))
+                );
+            }
+            return;
+        }
+
+        warn!(
+            "{}{}",
+            self.output.prefix,
+            self.output.stderr.breach(format_args!(
+                "[{}] Records of {} churned, {} of {} sampled objectIDs ({:.1}%) disappeared since the last \
+                 poll, such as {}",
+                now(),
+                self.name,
+                churn.disappeared,
+                churn.sampled,
+                churn.percent,
+                churn.examples.join(", ")
+            ))
+        );
+
+        if alert_state.breach(self.options.alert_cooldown) {
+            self.notifier.notify(Alert::ContentChurn(ContentChurn {
+                app_id: self.app_id.to_string(),
+                index_name: self.index_name.to_string(),
+                subset: self.subset.map(str::to_string),
+                sampled: churn.sampled,
+                disappeared: churn.disappeared,
+                churn_percent: churn.percent,
+                examples: churn.examples,
+                timestamp: Utc::now(),
+            }));
+        }
+    }
+
+    /// Prints the records count a browse found next to `nbHits`, warning when they differ
+    fn report_browse(&self, count: BrowseCount, records: u64) {
+        let message = match count.complete {
+            true => format!(
+                "{} records by browsing, nbHits said {} ({:+})",
+                count.records,
+                records,
+                records as i64 - count.records as i64
+            ),
+            false => format!(
+                "at least {} records by browsing, stopped at --browse-max-records, nbHits said {}",
+                count.records, records
+            ),
+        };
+        match count.complete && count.records != records {
+            true => warn!(
+                "{}{}",
+                self.output.prefix,
+                self.output.stderr.breach(format_args!("[{}] {}: {}", now(), self.name, message))
+            ),
+            false => info!("{}[{}] {}: {}", self.output.prefix, now(), self.name, message),
+        }
+        self.output.event(self.name, "browse", message);
+    }
+
+    /// Notifies when the synonyms or the rules count dropped by more than its delta, and when it recovers
+    fn report_items(&self, count: ItemsCount, alert_state: &mut AlertState) {
+        if !count.dropped {
+            if alert_state.is_breached() {
+                alert_state.recover();
+                info!(
+                    "{}{}",
+                    self.output.prefix,
+                    self.output.stderr.recovery(format_args!(
+                        "[{}] The {} of {} are back within the delta, {} of them",
+                        now(),
+                        count.items,
+                        self.index_name,
+                        count.current
+                    ))
+                );
+            }
+            return;
+        }
+
+        warn!(
+            "{}{}",
+            self.output.prefix,
+            self.output.stderr.breach(format_args!(
+                "[{}] The {} of {} dropped to {}, expected {} ({:+})",
+                now(),
+                count.items,
+                self.index_name,
+                count.current,
+                count.expected,
+                count.current as i64 - count.expected as i64
+            ))
+        );
+
+        if alert_state.breach(self.options.alert_cooldown) {
+            self.notifier.notify(Alert::ItemsDropped(ItemsDropped {
+                app_id: self.app_id.to_string(),
+                index_name: self.index_name.to_string(),
+                items: count.items,
+                expected: count.expected,
+                current: count.current,
+                timestamp: Utc::now(),
+            }));
+        }
+    }
+
+    /// Prints the settings keys that changed since the previous check along with their values, and notifies
+    fn report_settings(&self, diff: SettingsDiff) {
+        warn!(
+            "{}{}",
+            self.output.prefix,
+            self.output.stderr.breach(format_args!(
+                "[{}] Settings of {} changed: {}",
+                now(),
+                self.index_name,
+                diff.summary()
+            ))
+        );
+        let json = serde_json::to_string(&diff).unwrap_or_default();
+        warn!("{}  {}", self.output.prefix, json);
+        self.output.event(self.name, "settings", json);
+
+        self.notifier.notify(Alert::SettingsChanged(SettingsChanged {
+            app_id: self.app_id.to_string(),
+            index_name: self.index_name.to_string(),
+            diff,
+            timestamp: Utc::now(),
+        }));
+    }
+
+    /// Prints the records counts of the replicas next to the index's and notifies when they are out of sync
+    fn report_replicas(&self, records: u64, replicas: &[ReplicaCount], alert_state: &mut AlertState) {
+        if !replicas.iter().any(|replica| replica.out_of_sync) {
+            if alert_state.is_breached() && !replicas.is_empty() {
+                alert_state.recover();
+                info!(
+                    "{}{}",
+                    self.output.prefix,
+                    self.output
+                        .stderr
+                        .recovery(format_args!("Replicas of {} are back in sync", self.index_name))
+                );
+            }
+            return;
+        }
+
+        if !alert_state.breach(self.options.alert_cooldown) {
+            return;
+        }
+
+        warn!(
+            "{}{}",
+            self.output.prefix,
+            self.output
+                .stderr
+                .breach(format_args!("[{}] Replicas of {} are out of sync:", now(), self.index_name))
+        );
+        let width = replicas
+            .iter()
+            .map(|replica| replica.index_name.len())
+            .chain([self.index_name.len()])
+            .max()
+            .unwrap_or_default();
+        warn!("{}  {:width$}  {:>12}", self.output.prefix, self.index_name, records);
+        for replica in replicas {
+            let line = match replica.records {
+                Some(replica_records) => format!(
+                    "  {:width$}  {:>12}  {:+}",
+                    replica.index_name,
+                    replica_records,
+                    replica_records as i64 - records as i64
+                ),
+                None => format!("  {:width$}  {:>12}", replica.index_name, "missing"),
+            };
+            match replica.out_of_sync {
+                true => warn!("{}{}", self.output.prefix, self.output.stderr.breach(line)),
+                false => warn!("{}{}", self.output.prefix, line),
+            }
+        }
+
+        self.notifier.notify(Alert::ReplicasOutOfSync(ReplicasOutOfSync {
+            app_id: self.app_id.to_string(),
+            index_name: self.index_name.to_string(),
+            records,
+            replicas: replicas.to_vec(),
+            timestamp: Utc::now(),
+        }));
+    }
+
+    /// Prints the facet counts in JSON output and notifies about the facet values that dropped, each value
+    /// with its own cooldown
+    fn report_facets(&self, facets: &[FacetCounts], alert_states: &mut HashMap<(String, String), AlertState>) {
+        let output = self.output;
+
+        for facet in facets {
+            output.print_facets(self.index_name, facet);
+
+            let mut recovered = vec![];
+            for ((attribute, value), alert_state) in alert_states.iter_mut() {
+                let dropped = facet.drops.iter().any(|drop| &drop.value == value);
+                if attribute == &facet.attribute && !dropped && alert_state.is_breached() {
+                    alert_state.recover();
+                    recovered.push(value.as_str());
+                }
+            }
+            if !recovered.is_empty() {
+                recovered.sort_unstable();
+                info!(
+                    "{}{}",
+                    output.prefix,
+                    output.stderr.recovery(format_args!(
+                        "Values of {} in {} are back within the facet delta: {}",
+                        facet.attribute,
+                        self.index_name,
+                        recovered.join(", ")
+                    ))
+                );
+            }
+
+            let drops: Vec<_> = facet
+                .drops
+                .iter()
+                .filter(|drop| {
+                    alert_states
+                        .entry((facet.attribute.clone(), drop.value.clone()))
+                        .or_default()
+                        .breach(self.options.alert_cooldown)
+                })
+                .cloned()
+                .collect();
+            if drops.is_empty() {
+                continue;
+            }
+
+            let descriptions: Vec<_> = drops
+                .iter()
+                .map(|drop| match drop.current_records {
+                    0 => format!("{} gone (had {})", drop.value, drop.expected_records),
+                    current_records => {
+                        format!("{} {} (had {})", drop.value, current_records, drop.expected_records)
+                    }
+                })
+                .collect();
+            warn!(
+                "{}{}",
+                output.prefix,
+                output.stderr.breach(format_args!(
+                    "[{}] Values of {} in {} lost records: {}",
+                    now(),
+                    facet.attribute,
+                    self.index_name,
+                    descriptions.join(", ")
+                ))
+            );
+            self.notifier.notify(Alert::FacetDrops(FacetDrops {
+                app_id: self.app_id.to_string(),
+                index_name: self.index_name.to_string(),
+                attribute: facet.attribute.clone(),
+                drops,
+                timestamp: Utc::now(),
+            }));
+        }
+    }
+
+    /// Prints and notifies when the compared index is out of sync, a failure to count it is only a warning
+    fn report_comparison(
+        &self,
+        records: u64,
+        comparison: &Option<Result<Comparison, String>>,
+        alert_state: &mut AlertState,
+    ) {
+        let (Some(comparison), Some(compare)) = (comparison, self.compare) else {
+            return;
+        };
+        let output = self.output;
+        let compared = format!("{}/{}", compare.app_id, compare.index_name);
+
+        let comparison = match comparison {
+            Ok(comparison) => {
+                alert_state.reachable();
+                comparison
+            }
+            Err(error) => {
+                if alert_state.unreachable() {
+                    warn!(
+                        "{}{}",
+                        output.prefix,
+                        output.stderr.error(format_args!(
+                            "[{}] Could not count the records of compared index {}, monitoring goes on: {}",
+                            now(),
+                            compared,
+                            error
+                        ))
+                    );
+                }
+                return;
+            }
+        };
+
+        if !comparison.out_of_sync {
+            if alert_state.is_breached() {
+                alert_state.recover();
+                info!(
+                    "{}{}",
+                    output.prefix,
+                    output.stderr.recovery(format_args!(
+                        "{}/{} ({} records) and compared {} ({} records) are back in sync",
+                        self.app_id,
+                        self.index_name,
+                        records,
+                        compared,
+                        comparison.records
+                    ))
+                );
+            }
+            return;
+        }
+
+        if !alert_state.breach(self.options.alert_cooldown) {
+            return;
+        }
+
+        let delta = comparison.records as i64 - records as i64;
+        warn!(
+            "{}{}",
+            output.prefix,
+            output.stderr.breach(format_args!(
+                "[{}] {}/{} ({} records) and compared {} ({} records, {:+}) are out of sync",
+                now(),
+                self.app_id,
+                self.index_name,
+                records,
+                compared,
+                comparison.records,
+                delta
+            ))
+        );
+        self.notifier.notify(Alert::ComparisonOutOfSync(ComparisonOutOfSync {
+            app_id: self.app_id.to_string(),
+            index_name: self.index_name.to_string(),
+            records,
+            compared_app_id: compare.app_id.clone(),
+            compared_index_name: compare.index_name.clone(),
+            compared_records: comparison.records,
+            delta,
+            timestamp: Utc::now(),
+        }));
+    }
+
+    /// Records a poll in the metrics and the summary and notifies about breaches and recoveries
+    fn report(&self, poll: Poll, alert_state: &mut AlertState, summary: &mut Summary) {
+        let MonitoredIndex {
+            options,
+            notifier,
+            metrics,
+            output,
+            index_name,
+            name,
+            subset,
+            ..
+        } = *self;
+        let records = poll.records;
+        metrics.records(name, records.current_records, records.expected_records);
+        output.sample(name, records, poll.processing_time_ms);
+        summary.records(records.current_records);
+        if let Some(data_size) = poll.data_size {
+            metrics.data_size(name, data_size);
+        }
+        if let Some(latency) = &poll.latency {
+            metrics.latency(name, latency);
+            summary.latency = Some(*latency);
+        }
+        summary.log_lines += poll.logs.len() as u64;
+
+        info!(
+            "{}[{}] {}: {} records{}{}{}",
+            output.prefix,
+            now(),
+            name,
+            records.current_records,
+            match options.expected_by_command {
+                true => {
+                    format!(", {} expected by --expected-cmd ({:+})", records.expected_records, records.delta())
+                }
+                false => String::new(),
+            },
+            match &poll.rates {
+                Some(rates) => format!(
+                    ", {:+.1} per minute since the last poll, {:+.1} over {} polls",
+                    rates.latest,
+                    rates.window,
+                    rates.polls + 1
+                ),
+                None => String::new(),
+            },
+            output.trend(name)
+        );
+
+        if poll.recreated {
+            output.event(name, "recreated", format_args!("{} records", records.current_records));
+            warn!(
+                "{}{}",
+                output.prefix,
+                output.stderr.breach(format_args!(
+                    "[{}] Index {} was recreated with {} records{}",
+                    now(),
+                    index_name,
+                    records.current_records,
+                    match options.rebaseline_on_recreate {
+                        true => ", expected records count is reset to it",
+                        false => "",
+                    }
+                ))
+            );
+            if subset.is_none() {
+                notifier.notify(Alert::IndexRecreated(IndexRecreated {
+                    app_id: self.app_id.to_string(),
+                    index_name: index_name.to_string(),
+                    expected_records: records.expected_records,
+                    current_records: records.current_records,
+                    rebaselined: options.rebaseline_on_recreate,
+                    timestamp: Utc::now(),
+                }));
+            }
+        }
+
+        if let Some(breach) = &poll.breach {
+            metrics.threshold_breach(name);
+            summary.breaches += 1;
+
+            // The count and the logs are still recorded, the alert waiting for the end of the quiet hours
+            if options.is_quiet_hours() {
+                alert_state.quiet();
+                alert_state.breached_records(records.current_records);
+                info!("{}[{}] {}, not alerting during the quiet hours", output.prefix, now(), describe(breach));
+                output.event(name, "quiet-breach", describe(breach));
+                return;
+            }
+
+            // Every output goes through the alert state, so an ongoing breach isn't repeated on each poll
+            let notice = match breach {
+                ThresholdBreach::ZeroRecords => alert_state.empty(options.alert_cooldown),
+                _ => alert_state.notice(options.alert_cooldown),
+            };
+            alert_state.breached_records(records.current_records);
+            let quiet_hours = notice == Notice::New && alert_state.started_quietly();
+            let note = match quiet_hours {
+                true => format!(
+                    ", started during the quiet hours {} ago",
+                    humantime::format_duration(Duration::from_secs(
+                        alert_state.breached_for().unwrap_or_default().as_secs()
+                    ))
+                ),
+                false => String::new(),
+            };
+            match (notice, breach) {
+                (Notice::Quiet, _) => {}
+                (Notice::New, ThresholdBreach::ZeroRecords) => error!(
+                    "{}{}",
+                    output.prefix,
+                    output.stderr.error(format_args!("[{}] CRITICAL: Records count dropped to 0{}", now(), note))
+                ),
+                (Notice::New, breach) => warn!(
+                    "{}{}",
+                    output.prefix,
+                    output.stderr.breach(format_args!("{}{}", describe(breach), note))
+                ),
+                (Notice::Reminder, breach) => warn!(
+                    "{}{}",
+                    output.prefix,
+                    output.stderr.breach(format_args!(
+                        "{}, still breached after {}",
+                        describe(breach),
+                        humantime::format_duration(Duration::from_secs(
+                            alert_state.breached_for().unwrap_or_default().as_secs()
+                        ))
+                    ))
+                ),
+            }
+
+            let attribution = Attribution::from_logs(index_name, records.delta(), &poll.logs);
+            if !attribution.is_empty() {
+                warn!("{}Logged writes: {}", output.prefix, attribution);
+            }
+
+            // The poll is only counted once it ended, after its checks
+            #[cfg(feature = "sentry")]
+            if options.sentry_report_breaches && notice == Notice::New {
+                crate::reporting::capture_breach(self.app_id, name, summary.polls + 1, &describe(breach));
+            }
+            if notice != Notice::Quiet {
+                output.event(name, "breach", describe(breach));
+                notifier.notify(Alert::Breach(Breach {
+                    app_id: self.app_id.to_string(),
+                    index_name: index_name.to_string(),
+                    subset: subset.map(str::to_string),
+                    expected_records: records.expected_records,
+                    current_records: records.current_records,
+                    delta: records.delta(),
+                    reminder: notice == Notice::Reminder,
+                    quiet_hours,
+                    timestamp: Utc::now(),
+                    logs: poll.logs,
+                }));
+            }
+            return;
+        }
+
+        // A breach that only lasted during the quiet hours was never alerted, and neither is its recovery
+        if poll.recovered && alert_state.started_quietly() && !alert_state.is_breached() {
+            let message = format!(
+                "Records count recovered during the quiet hours after {}, back to {} records",
+                humantime::format_duration(Duration::from_secs(
+                    alert_state.breached_for().unwrap_or_default().as_secs()
+                )),
+                records.current_records
+            );
+            alert_state.recover();
+            info!("{}[{}] {}", output.prefix, now(), message);
+            output.event(name, "quiet-recovery", message);
+        } else if poll.recovered {
+            let recovery = Recovery {
+                app_id: self.app_id.to_string(),
+                index_name: index_name.to_string(),
+                subset: subset.map(str::to_string),
+                expected_records: records.expected_records,
+                current_records: records.current_records,
+                delta: records.delta(),
+                breach_duration_secs: alert_state.breached_for().unwrap_or_default().as_secs(),
+                min_records: alert_state.min_records().unwrap_or(records.current_records),
+                timestamp: Utc::now(),
+            };
+            alert_state.recover();
+
+            let message = format!(
+                "Records count recovered after {}, back to {} records ({:+}), lowest {} during the breach",
+                humantime::format_duration(Duration::from_secs(recovery.breach_duration_secs)),
+                recovery.current_records,
+                recovery.delta,
+                recovery.min_records
+            );
+            info!("{}{}", output.prefix, output.stderr.recovery(&message));
+            output.print_recovery(&recovery);
+            output.event(name, "recovery", message);
+            notifier.notify(Alert::Recovery(recovery));
+        }
+
+        if let Some(previous_records) = poll.refreshed_from {
+            info!(
+                "{}Refreshed expected records count from {} to {}",
+                output.prefix,
+                previous_records,
+                records.current_records
+            );
+        }
+    }
+}
+
+/// Alerts on a log entry clearing or deleting the index
+pub fn report_destructive(
+    output: &Output,
+    notifier: &Notifier,
+    app_id: &str,
+    index_name: &str,
+    log: &AlgoliaLogEntry,
+    operation: DestructiveOperation,
+) {
+    error!(
+        "{}{}",
+        output.prefix,
+        output.stderr.error(format_args!(
+            "[{}] {} on {}: {} {}",
+            now(),
+            operation,
+            index_name,
+            log.method.as_deref().unwrap_or("-"),
+            log.url.as_deref().unwrap_or("-")
+        ))
+    );
+    output.event(
+        index_name,
+        "destructive",
+        format_args!(
+            "{} {} {}",
+            operation,
+            log.method.as_deref().unwrap_or("-"),
+            log.url.as_deref().unwrap_or("-")
+        ),
+    );
+    notifier.notify(Alert::Destructive(Box::new(Destructive {
+        app_id: app_id.to_string(),
+        index_name: index_name.to_string(),
+        operation,
+        timestamp: Utc::now(),
+        log: log.clone(),
+    })));
+}
+
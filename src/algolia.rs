@@ -0,0 +1,1271 @@
+use crate::api_key::{redact_keys, ApiKey};
+use crate::error::MonitorError;
+use crate::metrics::Metrics;
+use crate::now;
+use crate::pacing::RequestPacer;
+use crate::tls::TlsConfig;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use reqwest::header::{HeaderMap, HeaderValue};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{json, to_string, Map, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio::time::sleep;
+use tracing::{debug, trace, warn, Level};
+
+/// Client of the Algolia search API for a single application
+///
+/// Requests go to the DSN host first and move on to the fallback hosts when it fails.
+pub struct AlgoliaClient {
+    client: reqwest::Client,
+    /// Credentials and content type sent with every request, apart from the client that may be shared
+    headers: HeaderMap,
+    timeout: Duration,
+    connect_timeout: Duration,
+    base_urls: Vec<String>,
+    /// Index in `base_urls` of the host that answered last
+    current_host: AtomicUsize,
+    retry_attempts: u32,
+    retry_base_delay: Duration,
+    /// Permits of the requests that may be in flight at once, shared with the clients of other applications
+    concurrency: Option<Arc<Semaphore>>,
+    /// Pace of the requests of the application, shared with the clients of its other keys
+    pacer: Option<Arc<RequestPacer>>,
+    metrics: Metrics,
+    log_type: LogType,
+    log_offset: u32,
+    log_length: u32,
+    max_log_pages: u32,
+    count_source: CountSource,
+    proxy: ProxyConfig,
+    tls: TlsConfig,
+}
+
+/// Where the records count of an index comes from, given as `query` or `list-indexes`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CountSource {
+    /// `nbHits` of an empty query, which costs a search operation
+    Query,
+    /// `entries` of the index in the list of indices, which also tells its data size
+    ListIndexes,
+}
+
+impl FromStr for CountSource {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "query" => Ok(CountSource::Query),
+            "list-indexes" => Ok(CountSource::ListIndexes),
+            _ => Err("expected `query` or `list-indexes`".to_string()),
+        }
+    }
+}
+
+/// Proxy the requests to Algolia go through
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ProxyConfig {
+    /// The one of the `HTTPS_PROXY` or `HTTP_PROXY` environment variables, for the hosts `NO_PROXY` leaves in
+    #[default]
+    Environment,
+    /// No proxy, even when the environment sets one
+    Disabled,
+    /// Proxy at this URL such as `http://proxy.internal:3128`, with the user and password to authenticate with,
+    /// for the hosts `NO_PROXY` leaves in
+    Url {
+        url: String,
+        /// Password kept as an [`ApiKey`], so it is redacted from the messages the same way
+        auth: Option<(String, ApiKey)>,
+    },
+}
+
+/// Permissions of an API key, as Algolia reports them for the key itself
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct KeyPermissions {
+    /// Operations the key allows, such as `search`, `listIndexes` or `logs`
+    #[serde(default)]
+    pub acl: Vec<String>,
+    /// Patterns of the names of the indices the key is restricted to, every index when empty
+    #[serde(default)]
+    pub indexes: Vec<String>,
+}
+
+impl KeyPermissions {
+    pub fn allows(&self, acl: &str) -> bool {
+        self.acl.iter().any(|allowed| allowed == acl)
+    }
+
+    /// Whether the key can access the index, a `*` in a pattern standing for any characters
+    pub fn covers(&self, index_name: &str) -> bool {
+        self.indexes.is_empty() || self.indexes.iter().any(|pattern| matches_pattern(pattern, index_name))
+    }
+}
+
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<_> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(position) => rest = &rest[position + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// Records count of an index, along with what the list of indices tells about it
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct IndexStats {
+    pub records: u64,
+    /// Size of the index data in bytes
+    pub data_size: Option<u64>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Time Algolia took to process the counting query, in milliseconds
+    pub processing_time_ms: Option<u64>,
+}
+
+/// Records count of each value of a facet attribute
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FacetValues {
+    pub counts: BTreeMap<String, u64>,
+    /// Whether `counts` has every value, Algolia returns at most [`MAX_FACET_VALUES`] of them
+    pub complete: bool,
+}
+
+/// Most values of a facet attribute Algolia returns for a query
+pub const MAX_FACET_VALUES: usize = 1000;
+
+/// Objects of an index counted by browsing it, which unlike `nbHits` isn't an estimate
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct BrowseCount {
+    pub records: u64,
+    pub pages: u64,
+    /// Whether every object was counted, the browse stopping at the most records asked for otherwise
+    pub complete: bool,
+}
+
+/// Objects of each page of a browse, the most Algolia returns
+const BROWSE_PAGE_SIZE: u64 = 1000;
+
+/// How a sample of the objectIDs of an index is fetched, given as `search` or `browse`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SampleStrategy {
+    /// The first hits of an empty query in the ranking order, which costs a search operation
+    Search,
+    /// The first page of a browse in the order of the index, which needs the `browse` ACL
+    Browse,
+}
+
+impl FromStr for SampleStrategy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "search" => Ok(SampleStrategy::Search),
+            "browse" => Ok(SampleStrategy::Browse),
+            _ => Err("expected `search` or `browse`".to_string()),
+        }
+    }
+}
+
+/// Kind of entries requested from the logs endpoint, given as `all`, `query`, `build` or `error`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogType {
+    All,
+    Query,
+    Build,
+    Error,
+}
+
+impl LogType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            LogType::All => "all",
+            LogType::Query => "query",
+            LogType::Build => "build",
+            LogType::Error => "error",
+        }
+    }
+}
+
+impl fmt::Display for LogType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+impl FromStr for LogType {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "all" => Ok(LogType::All),
+            "query" => Ok(LogType::Query),
+            "build" => Ok(LogType::Build),
+            "error" => Ok(LogType::Error),
+            _ => Err("expected `all`, `query`, `build` or `error`".to_string()),
+        }
+    }
+}
+
+/// Items of an index searched apart from its records, which need the `settings` ACL
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexItems {
+    Synonyms,
+    Rules,
+}
+
+impl IndexItems {
+    fn as_str(&self) -> &'static str {
+        match self {
+            IndexItems::Synonyms => "synonyms",
+            IndexItems::Rules => "rules",
+        }
+    }
+}
+
+impl fmt::Display for IndexItems {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Characters left as they are in URL path segments and query values, the unreserved ones of RFC 3986
+const URL_COMPONENT: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
+/// RFC 3339 timestamp in a field of an item of the list of indices
+fn timestamp(item: &Value, field: &str) -> Option<DateTime<Utc>> {
+    let timestamp = DateTime::parse_from_rfc3339(item.get(field)?.as_str()?).ok()?;
+    Some(timestamp.with_timezone(&Utc))
+}
+
+/// Query parameters of a request retrieving only the IDs of the objects
+fn object_id_params(hits_per_page: u64, filters: Option<&str>) -> String {
+    let mut params = format!("hitsPerPage={hits_per_page}&attributesToRetrieve=");
+    params.push_str(&encode(r#"["objectID"]"#).to_string());
+    if let Some(filters) = filters {
+        params.push_str(&format!("&filters={}", encode(filters)));
+    }
+    params
+}
+
+/// Adds what the key lacks to a 403, which Algolia answers without telling which ACL is missing
+fn lacking_acl(error: MonitorError, explanation: &str) -> MonitorError {
+    match error {
+        MonitorError::Api {
+            status: StatusCode::FORBIDDEN,
+            message,
+        } => MonitorError::Api {
+            status: StatusCode::FORBIDDEN,
+            message: format!("{message}, {explanation}"),
+        },
+        error => error,
+    }
+}
+
+fn encode(value: &str) -> percent_encoding::PercentEncode<'_> {
+    utf8_percent_encode(value, URL_COMPONENT)
+}
+
+/// Entry of the Algolia logs endpoint
+///
+/// Algolia reports most values as strings, so they are kept as received
+/// and fields this monitor doesn't know about are preserved in `extra`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AlgoliaLogEntry {
+    #[serde(deserialize_with = "lenient_timestamp")]
+    pub timestamp: DateTime<Utc>,
+    #[serde(default, deserialize_with = "lenient_string", skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    #[serde(default, deserialize_with = "lenient_string", skip_serializing_if = "Option::is_none")]
+    pub answer_code: Option<String>,
+    #[serde(default, deserialize_with = "lenient_string", skip_serializing_if = "Option::is_none")]
+    pub query_body: Option<String>,
+    #[serde(default, deserialize_with = "lenient_string", skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, deserialize_with = "lenient_string", skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+    #[serde(default, deserialize_with = "lenient_string", skip_serializing_if = "Option::is_none")]
+    pub query_headers: Option<String>,
+    #[serde(default, deserialize_with = "lenient_string", skip_serializing_if = "Option::is_none")]
+    pub nb_api_calls: Option<String>,
+    #[serde(default, deserialize_with = "lenient_string", skip_serializing_if = "Option::is_none")]
+    pub processing_time_ms: Option<String>,
+    #[serde(default, deserialize_with = "lenient_string", skip_serializing_if = "Option::is_none")]
+    pub index: Option<String>,
+    /// Type the entry was fetched as, set when entries of several types are merged
+    #[serde(rename = "type", skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub log_type: Option<LogType>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Accepts RFC 3339 timestamps as well as `2024-05-02 10:14:03` ones without a time zone, taken as UTC
+fn lenient_timestamp<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime<Utc>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+
+    DateTime::parse_from_rfc3339(&value)
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(&value, "%Y-%m-%d %H:%M:%S%.f").map(|timestamp| timestamp.and_utc())
+        })
+        .map_err(|_| serde::de::Error::custom(format!("invalid timestamp `{value}`")))
+}
+
+/// Accepts strings as well as numbers or other JSON values for loosely typed log fields
+fn lenient_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Null => None,
+        Value::String(value) => Some(value),
+        value => Some(value.to_string()),
+    })
+}
+
+impl AlgoliaLogEntry {
+    /// Whether the entry is more recent than `timestamp`, which is the case for every entry without it
+    pub fn is_newer(&self, timestamp: Option<DateTime<Utc>>) -> bool {
+        timestamp.is_none_or(|timestamp| self.timestamp > timestamp)
+    }
+
+    /// Status code Algolia answered the request with
+    pub fn status_code(&self) -> Option<u16> {
+        self.answer_code.as_deref()?.trim().parse().ok()
+    }
+
+    /// Time Algolia took to process the request, in milliseconds
+    pub fn processing_time(&self) -> Option<u64> {
+        self.processing_time_ms.as_deref()?.trim().parse().ok()
+    }
+
+    /// ID of the task an indexing request was answered with, read from the answer even when it is truncated
+    pub fn task_id(&self) -> Option<u64> {
+        let answer = self.extra.get("answer")?.as_str()?;
+        let (_, rest) = answer.split_once("\"taskID\"")?;
+        let digits = rest.trim_start().strip_prefix(':')?.trim_start();
+        let end = digits.find(|c: char| !c.is_ascii_digit()).unwrap_or(digits.len());
+        digits[..end].parse().ok()
+    }
+
+    /// Whether the entry is a request adding, updating or deleting records
+    pub fn is_write(&self) -> bool {
+        let (Some(method), Some(url)) = (self.method.as_deref(), self.url.as_deref()) else {
+            return false;
+        };
+
+        if !matches!(method, "POST" | "PUT" | "DELETE") {
+            return false;
+        }
+
+        let path = url.split(['?', '#']).next().unwrap_or_default();
+        let Some(path) = path.strip_prefix("/1/indexes/") else {
+            return false;
+        };
+
+        let mut segments = path.trim_end_matches('/').split('/').skip(1);
+        match (segments.next(), segments.next()) {
+            // Adding an object with a generated objectID or deleting the whole index
+            (None, _) => true,
+            (Some("batch" | "clear" | "deleteByQuery" | "deleteBy"), None) => true,
+            (
+                Some(
+                    "query" | "queries" | "browse" | "objects" | "settings" | "synonyms" | "rules"
+                    | "task" | "operation" | "facets" | "keys",
+                ),
+                _,
+            ) => false,
+            // Saving, updating or deleting a single object by its objectID
+            (Some(_), None) => matches!(method, "PUT" | "DELETE"),
+            (Some(_), Some("partial")) => method == "POST",
+            _ => false,
+        }
+    }
+
+    /// Entry serialized back into a single line of JSON
+    pub fn to_json(&self) -> String {
+        to_string(self).unwrap_or_default()
+    }
+
+    /// Operation wiping the records of `index_name` at once that the entry records, if any
+    pub fn destructive_operation(&self, index_name: &str) -> Option<DestructiveOperation> {
+        let method = self.method.as_deref()?;
+        let path = self.url.as_deref()?.split(['?', '#']).next().unwrap_or_default();
+        let mut segments = path.strip_prefix("/1/indexes/")?.trim_end_matches('/').split('/');
+
+        // The whole segment is compared, so `products` doesn't match operations on `products_tmp`
+        let name = percent_decode_str(segments.next()?).decode_utf8().ok()?;
+        if name != index_name {
+            return None;
+        }
+
+        match (method, segments.next(), segments.next()) {
+            ("POST", Some("clear"), None) => Some(DestructiveOperation::ClearObjects),
+            ("DELETE", None, _) => Some(DestructiveOperation::DeleteIndex),
+            _ => None,
+        }
+    }
+}
+
+/// Operation removing every record of an index in a single request
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DestructiveOperation {
+    ClearObjects,
+    DeleteIndex,
+}
+
+impl fmt::Display for DestructiveOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DestructiveOperation::ClearObjects => write!(f, "clearObjects"),
+            DestructiveOperation::DeleteIndex => write!(f, "deleteIndex"),
+        }
+    }
+}
+
+/// Reason a request to every host failed
+enum SendError {
+    /// Not worth retrying, such as a rejected API key
+    Fatal(MonitorError),
+    /// Worth retrying after a backoff, or after the delay requested by Algolia
+    Transient(MonitorError, Option<Duration>),
+}
+
+/// Hides the API key in the URL of a failed key validation request
+fn redact_key(error: reqwest::Error) -> reqwest::Error {
+    match error.url().cloned() {
+        Some(url) => error.with_url(redact_url(url)),
+        None => error,
+    }
+}
+
+/// Whether the proxy refused to open a tunnel to Algolia without valid credentials, which reqwest only tells in
+/// the message of the error
+fn is_proxy_authentication(error: &reqwest::Error) -> bool {
+    let mut source = std::error::Error::source(error);
+    while let Some(error) = source {
+        let message = error.to_string();
+        let refused = ["proxy authentication required", "proxy authorization required"];
+        if refused.iter().any(|part| message.contains(part)) {
+            return true;
+        }
+        source = error.source();
+    }
+    false
+}
+
+/// URL with the key left out of the `keys/{key}` path, the other requests carry it in a header
+fn redact_url(mut url: reqwest::Url) -> reqwest::Url {
+    if let Some((path, _)) = url.path().split_once("/keys/") {
+        url.set_path(&format!("{path}/keys/***"));
+    }
+    url
+}
+
+/// Default total time a request may take
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Default time to wait for a connection to a host
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Time an idle connection is kept in the pool, longer than the usual delay between polls
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+/// Interval of the TCP keep-alive probes, keeping the pooled connections through the idle timeouts of NATs
+const TCP_KEEPALIVE: Duration = Duration::from_secs(60);
+/// User agent of the requests, telling them apart in the logs of the application
+const USER_AGENT: &str = concat!("algolia-monitor/", env!("CARGO_PKG_VERSION"));
+
+/// Delay a 429 asks for with `Retry-After`, in seconds or until an HTTP date, none for a date in the past
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(reqwest::header::RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    match DateTime::parse_from_rfc2822(value) {
+        Ok(date) => Some((date.with_timezone(&Utc) - Utc::now()).to_std().unwrap_or_default()),
+        Err(_) => {
+            debug!("[{}] Ignoring Retry-After `{}`, neither seconds nor an HTTP date", now(), value);
+            None
+        }
+    }
+}
+
+/// HTTP client asking for compressed responses, which it decompresses before they are read
+fn build_client(
+    timeout: Duration,
+    connect_timeout: Duration,
+    proxy: &ProxyConfig,
+    tls: &TlsConfig,
+) -> Result<reqwest::Client, MonitorError> {
+    let builder = match proxy {
+        ProxyConfig::Environment => tls.client_builder(),
+        ProxyConfig::Disabled => tls.client_builder().no_proxy(),
+        ProxyConfig::Url { url, auth } => {
+            // The error would quote the URL along with the password it may contain
+            let mut proxy = reqwest::Proxy::all(url)
+                .map_err(|_| MonitorError::InvalidArgument("Invalid proxy URL".to_string()))?
+                .no_proxy(reqwest::NoProxy::from_env());
+            if let Some((user, password)) = auth {
+                proxy = proxy.basic_auth(user, password.expose());
+            }
+            tls.client_builder().proxy(proxy)
+        }
+    };
+
+    Ok(builder
+        .user_agent(USER_AGENT)
+        .gzip(true)
+        .brotli(true)
+        .deflate(true)
+        .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+        .tcp_keepalive(TCP_KEEPALIVE)
+        .timeout(timeout)
+        .connect_timeout(connect_timeout)
+        .build()?)
+}
+
+/// DSN host of the application followed by its fallback hosts in random order
+pub fn default_hosts(app_id: &str) -> Vec<String> {
+    let mut fallback_hosts: Vec<_> = (1..=3)
+        .map(|number| format!("{app_id}-{number}.algolianet.com"))
+        .collect();
+    fallback_hosts.shuffle(&mut rand::thread_rng());
+
+    let mut hosts = vec![format!("{app_id}-dsn.algolia.net")];
+    hosts.extend(fallback_hosts);
+    hosts
+}
+
+impl AlgoliaClient {
+    /// Client sending the credentials with every request, errors never contain the key
+    pub fn new(app_id: &str, key: &ApiKey) -> Result<Self, MonitorError> {
+        let mut key_header: HeaderValue = key.expose().parse().map_err(|_| {
+            MonitorError::InvalidArgument("API key contains characters not allowed in a header".to_string())
+        })?;
+        key_header.set_sensitive(true);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-algolia-application-id",
+            app_id.parse().map_err(|_| {
+                MonitorError::InvalidArgument(format!("Application ID `{app_id}` contains characters not allowed in a header"))
+            })?,
+        );
+        headers.insert("x-algolia-api-key", key_header);
+        headers.insert("content-type", HeaderValue::from_static("application/json"));
+        headers.insert("accept", HeaderValue::from_static("application/json"));
+
+        let (proxy, tls) = (ProxyConfig::Environment, TlsConfig::default());
+        let client = build_client(DEFAULT_TIMEOUT, DEFAULT_CONNECT_TIMEOUT, &proxy, &tls)?;
+
+        Ok(AlgoliaClient {
+            client,
+            headers,
+            timeout: DEFAULT_TIMEOUT,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            base_urls: vec![],
+            current_host: AtomicUsize::new(0),
+            retry_attempts: 0,
+            retry_base_delay: Duration::ZERO,
+            concurrency: None,
+            pacer: None,
+            metrics: Metrics::default(),
+            log_type: LogType::Build,
+            log_offset: 0,
+            log_length: 1000,
+            max_log_pages: 10,
+            count_source: CountSource::Query,
+            proxy,
+            tls,
+        }
+        .with_hosts(&default_hosts(app_id)))
+    }
+
+    /// Limits the total time of a request and the time to connect to a host,
+    /// after which the request counts as failed and moves on to the next host
+    pub fn with_timeouts(mut self, timeout: Duration, connect_timeout: Duration) -> Result<Self, MonitorError> {
+        self.client = build_client(timeout, connect_timeout, &self.proxy, &self.tls)?;
+        self.timeout = timeout;
+        self.connect_timeout = connect_timeout;
+        Ok(self)
+    }
+
+    /// Sends the requests through this proxy instead of the one of the environment
+    pub fn with_proxy(mut self, proxy: ProxyConfig) -> Result<Self, MonitorError> {
+        self.client = build_client(self.timeout, self.connect_timeout, &proxy, &self.tls)?;
+        self.proxy = proxy;
+        Ok(self)
+    }
+
+    /// Trusts the certificates of the config on top of the ones of the system
+    pub fn with_tls(mut self, tls: TlsConfig) -> Result<Self, MonitorError> {
+        self.client = build_client(self.timeout, self.connect_timeout, &self.proxy, &tls)?;
+        self.tls = tls;
+        Ok(self)
+    }
+
+    /// Sends the requests through the HTTP client of the other one, with its timeouts, its proxy, its
+    /// certificates and its limit of concurrent requests, such as for the clients of several applications to
+    /// share a connection pool
+    ///
+    /// The credentials, the hosts, the retries and the pacing stay the ones of this client, so a 429 of one
+    /// application never holds up the requests of another.
+    pub fn with_connections_of(mut self, other: &AlgoliaClient) -> Self {
+        self.client = other.client.clone();
+        self.timeout = other.timeout;
+        self.connect_timeout = other.connect_timeout;
+        self.proxy = other.proxy.clone();
+        self.tls = other.tls.clone();
+        self.concurrency = other.concurrency.clone();
+        self
+    }
+
+    /// Waits for one of the permits before sending a request, the retries waiting for one again
+    pub fn with_concurrency_limit(mut self, permits: Arc<Semaphore>) -> Self {
+        self.concurrency = Some(permits);
+        self
+    }
+
+    /// Waits for the turn of each request with the pacer, which the clients of an application share
+    pub fn with_pacer(mut self, pacer: Arc<RequestPacer>) -> Self {
+        self.pacer = Some(pacer);
+        self
+    }
+
+    pub fn pacer(&self) -> Option<&Arc<RequestPacer>> {
+        self.pacer.as_ref()
+    }
+
+    /// How long a request sent now would wait for its turn, zero without a pacer
+    pub fn pacing_backlog(&self) -> Duration {
+        self.pacer.as_ref().map_or(Duration::ZERO, |pacer| pacer.backlog())
+    }
+
+    /// Sends the requests to these hosts instead of the default ones, in the given order
+    pub fn with_hosts(self, hosts: &[String]) -> Self {
+        let base_urls = hosts.iter().map(|host| format!("https://{host}/1/")).collect();
+        self.with_base_urls(base_urls)
+    }
+
+    /// Sends the requests to these API base URLs such as `http://localhost:8080/1/`, in the given order
+    pub fn with_base_urls(mut self, base_urls: Vec<String>) -> Self {
+        self.base_urls = base_urls
+            .into_iter()
+            .map(|base_url| match base_url.ends_with('/') {
+                true => base_url,
+                false => format!("{base_url}/"),
+            })
+            .collect();
+        self.current_host = AtomicUsize::new(0);
+        self
+    }
+
+    /// Reports the response times of the requests
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Retries transient failures up to `attempts` times, doubling the delay from `base_delay`
+    pub fn with_retries(mut self, attempts: u32, base_delay: Duration) -> Self {
+        self.retry_attempts = attempts;
+        self.retry_base_delay = base_delay;
+        self
+    }
+
+    /// Requests `length` log entries of the given type, skipping the `offset` newest ones
+    pub fn with_logs(mut self, log_type: LogType, offset: u32, length: u32) -> Self {
+        self.log_type = log_type;
+        self.log_offset = offset;
+        self.log_length = length;
+        self
+    }
+
+    pub fn with_count_source(mut self, count_source: CountSource) -> Self {
+        self.count_source = count_source;
+        self
+    }
+
+    /// Limits the pages of log entries fetched to catch up with the last seen one
+    pub fn with_max_log_pages(mut self, pages: u32) -> Self {
+        self.max_log_pages = pages;
+        self
+    }
+
+    /// Sends a request, retrying transient failures with exponential backoff and jitter, and reads the body of
+    /// the response
+    async fn send(
+        &self,
+        endpoint: &str,
+        request: impl Fn(&str) -> RequestBuilder,
+    ) -> Result<Vec<u8>, MonitorError> {
+        let mut attempt = 0;
+
+        loop {
+            let permit = self.wait_for_turn(endpoint).await;
+            let started = Instant::now();
+            let result = self.send_to_hosts(&request).await;
+            drop(permit);
+            self.metrics.api_response(endpoint, started.elapsed());
+
+            let (error, retry_after) = match result {
+                // A body cut off by the timeout is retried like a request that timed out
+                Ok(response) => match self.body(endpoint, response).await {
+                    Err(error @ MonitorError::Timeout(_)) => (error, None),
+                    body => return body,
+                },
+                Err(SendError::Fatal(error)) => return Err(error),
+                Err(SendError::Transient(error, retry_after)) => (error, retry_after),
+            };
+
+            if attempt >= self.retry_attempts {
+                return Err(error);
+            }
+            attempt += 1;
+
+            let delay = retry_after.unwrap_or_else(|| self.backoff_delay(attempt));
+            debug!(
+                "[{}] Retrying request ({}/{}) in {}ms: {}",
+                now(),
+                attempt,
+                self.retry_attempts,
+                delay.as_millis(),
+                error
+            );
+            sleep(delay).await;
+        }
+    }
+
+    /// Waits for the turn of a request with the pacer and then for a permit to send it, returning the permit
+    /// to hold while it is in flight
+    async fn wait_for_turn(&self, endpoint: &str) -> Option<SemaphorePermit<'_>> {
+        let started = Instant::now();
+        if let Some(pacer) = &self.pacer {
+            sleep(pacer.reserve()).await;
+        }
+        // The semaphore is never closed
+        let permit = match &self.concurrency {
+            Some(concurrency) => concurrency.acquire().await.ok(),
+            None => None,
+        };
+        self.metrics.request_wait(endpoint, started.elapsed());
+
+        permit
+    }
+
+    /// Sends a request starting from the host that answered last,
+    /// moving on to the next host on connection errors and 5xx responses
+    async fn send_to_hosts(&self, request: &impl Fn(&str) -> RequestBuilder) -> Result<Response, SendError> {
+        let first_host = self.current_host.load(Ordering::Relaxed);
+        let mut last_error = None;
+
+        for offset in 0..self.base_urls.len() {
+            let host = (first_host + offset) % self.base_urls.len();
+
+            let started = Instant::now();
+            let response = request(&self.base_urls[host]).headers(self.headers.clone()).send().await;
+            match &response {
+                Ok(response) => debug!(
+                    "[{}] {} answered {} in {}ms",
+                    now(),
+                    redact_url(response.url().clone()),
+                    response.status().as_u16(),
+                    started.elapsed().as_millis()
+                ),
+                Err(_) => debug!(
+                    "[{}] Request to {} failed after {}ms",
+                    now(),
+                    self.base_urls[host],
+                    started.elapsed().as_millis()
+                ),
+            }
+
+            let error = match response {
+                Ok(response) if response.status().is_server_error() => {
+                    MonitorError::from_response(response).await
+                }
+                Ok(response) if response.status() == StatusCode::TOO_MANY_REQUESTS => {
+                    let retry_after = retry_after(&response);
+                    return Err(SendError::Transient(
+                        MonitorError::from_response(response).await,
+                        retry_after,
+                    ));
+                }
+                Ok(response) if response.status() == StatusCode::PROXY_AUTHENTICATION_REQUIRED => {
+                    return Err(SendError::Fatal(MonitorError::ProxyAuthentication));
+                }
+                Ok(response) if !response.status().is_success() => {
+                    self.current_host.store(host, Ordering::Relaxed);
+                    return Err(SendError::Fatal(MonitorError::from_response(response).await));
+                }
+                Ok(response) => {
+                    self.current_host.store(host, Ordering::Relaxed);
+                    return Ok(response);
+                }
+                Err(error) if error.is_timeout() => MonitorError::Timeout(match error.is_connect() {
+                    true => self.connect_timeout,
+                    false => self.timeout,
+                }),
+                Err(error) if is_proxy_authentication(&error) => {
+                    return Err(SendError::Fatal(MonitorError::ProxyAuthentication));
+                }
+                Err(error) if error.is_connect() => MonitorError::Http(redact_key(error)),
+                Err(error) => return Err(SendError::Fatal(MonitorError::Http(redact_key(error)))),
+            };
+
+            debug!("[{}] Host {} failed: {}", now(), self.base_urls[host], error);
+            last_error = Some(error);
+        }
+
+        match last_error {
+            Some(error) => Err(SendError::Transient(error, None)),
+            None => unreachable!("Algolia client has no hosts"),
+        }
+    }
+
+    /// Reads the body of a response, decompressed, which is traced with the API key left out
+    async fn body(&self, endpoint: &str, response: Response) -> Result<Vec<u8>, MonitorError> {
+        let url = redact_url(response.url().clone());
+        let body = response.bytes().await.map_err(|error| match error.is_timeout() {
+            true => MonitorError::Timeout(self.timeout),
+            false => MonitorError::Http(redact_key(error)),
+        })?;
+        let body = body.to_vec();
+        self.metrics.response_size(endpoint, body.len() as u64);
+        if tracing::enabled!(Level::TRACE) {
+            trace!("[{}] Response of {}: {}", now(), url, redact_keys(&String::from_utf8_lossy(&body)));
+        }
+
+        Ok(body)
+    }
+
+    fn backoff_delay(&self, attempt: u32) -> Duration {
+        let delay = self.retry_base_delay.saturating_mul(1 << (attempt - 1).min(16));
+        delay / 2 + delay.mul_f64(rand::thread_rng().gen_range(0.0..0.5))
+    }
+
+    /// Checks that Algolia accepts the credentials, by requesting the permissions of the key itself
+    pub async fn validate_key(&self, key: &ApiKey) -> Result<KeyPermissions, MonitorError> {
+        let response = self
+            .send("keys", |base_url| self.client.get(format!("{}keys/{}", base_url, encode(key.expose()))))
+            .await?;
+
+        Ok(serde_json::from_slice(&response)?)
+    }
+
+    /// Whether Algolia published the task of the index, such as the one of an indexing request, so its changes
+    /// show in the searches
+    pub async fn is_task_published(&self, index_name: &str, task_id: u64) -> Result<bool, MonitorError> {
+        let response = self
+            .send("task", |base_url| {
+                self.client.get(format!("{}indexes/{}/task/{}", base_url, encode(index_name), task_id))
+            })
+            .await?;
+        let response: Value = serde_json::from_slice(&response)?;
+
+        match response.get("status").and_then(Value::as_str) {
+            Some(status) => Ok(status == "published"),
+            None => Err(MonitorError::MissingField("status")),
+        }
+    }
+
+    /// Names of all the indices of the application, which needs the `listIndexes` ACL
+    pub async fn index_names(&self) -> Result<Vec<String>, MonitorError> {
+        let mut names = vec![];
+        let mut page = 0;
+
+        loop {
+            let (items, pages) = self.indices_page(page).await?;
+            names.extend(items.iter().filter_map(|item| item.get("name")?.as_str().map(str::to_string)));
+
+            page += 1;
+            if items.is_empty() || page >= pages {
+                return Ok(names);
+            }
+        }
+    }
+
+    /// Number of records in the index, as reported by the count source
+    pub async fn total_records(&self, index_name: &str) -> Result<u64, MonitorError> {
+        Ok(self.index_stats(index_name).await?.records)
+    }
+
+    /// Records count of the index, with the data size when it comes from the list of indices
+    pub async fn index_stats(&self, index_name: &str) -> Result<IndexStats, MonitorError> {
+        match self.count_source {
+            CountSource::Query => self.query_records(index_name, None).await,
+            CountSource::ListIndexes => self.list_index(index_name).await,
+        }
+    }
+
+    /// Records count of the subset of the index matching the filters, such as `store_id:1 AND visibility:search`,
+    /// always counted with a query whatever the count source
+    pub async fn filtered_stats(&self, index_name: &str, filters: &str) -> Result<IndexStats, MonitorError> {
+        self.query_records(index_name, Some(filters)).await
+    }
+
+    /// Number of records matching an empty query, only the ones matching the filters when given
+    async fn query_records(&self, index_name: &str, filters: Option<&str>) -> Result<IndexStats, MonitorError> {
+        let mut params = vec![];
+        if let Some(filters) = filters {
+            params.push(("filters", filters.to_string()));
+        }
+        let response = self.query(index_name, &params).await?;
+
+        Ok(IndexStats {
+            records: response
+                .get("nbHits")
+                .and_then(Value::as_u64)
+                .ok_or(MonitorError::MissingField("nbHits"))?,
+            data_size: None,
+            created_at: None,
+            updated_at: None,
+            processing_time_ms: response.get("processingTimeMS").and_then(Value::as_u64),
+        })
+    }
+
+    /// Records count of each value of the facet attribute, such as the count of every category
+    pub async fn facet_values(&self, index_name: &str, attribute: &str) -> Result<FacetValues, MonitorError> {
+        let params = [
+            ("facets", json!([attribute]).to_string()),
+            ("maxValuesPerFacet", MAX_FACET_VALUES.to_string()),
+        ];
+        let response = self.query(index_name, &params).await?;
+
+        // Algolia leaves out attributes without values, or that aren't set up for faceting
+        let values = response
+            .get("facets")
+            .and_then(|facets| facets.get(attribute))
+            .and_then(Value::as_object);
+        let counts: BTreeMap<_, _> = values
+            .into_iter()
+            .flatten()
+            .filter_map(|(value, count)| Some((value.clone(), count.as_u64()?)))
+            .collect();
+
+        Ok(FacetValues {
+            complete: counts.len() < MAX_FACET_VALUES,
+            counts,
+        })
+    }
+
+    /// Response to an empty query retrieving no hits, with the params added to the ones doing so
+    async fn query(&self, index_name: &str, params: &[(&str, String)]) -> Result<Value, MonitorError> {
+        let mut query = "hitsPerPage=0&getRankingInfo=0&query=*".to_string();
+        for (name, value) in params {
+            query.push_str(&format!("&{}={}", name, encode(value)));
+        }
+        let body = json!({ "params": query }).to_string();
+
+        let response = self
+            .send("query", |base_url| {
+                self.client
+                    .post(format!("{}indexes/{}/query", base_url, encode(index_name)))
+                    .body(body.clone())
+            })
+            .await?;
+
+        Ok(serde_json::from_slice(&response)?)
+    }
+
+    /// Records count of the index from its entry in the list of indices
+    async fn list_index(&self, index_name: &str) -> Result<IndexStats, MonitorError> {
+        let item = self.index_entry(index_name).await?;
+
+        Ok(IndexStats {
+            records: item
+                .get("entries")
+                .and_then(Value::as_u64)
+                .ok_or(MonitorError::MissingField("entries"))?,
+            data_size: item.get("dataSize").and_then(Value::as_u64),
+            created_at: timestamp(&item, "createdAt"),
+            updated_at: timestamp(&item, "updatedAt"),
+            processing_time_ms: None,
+        })
+    }
+
+    /// Number of indexing tasks of the index Algolia didn't publish yet, from the list of indices
+    pub async fn pending_tasks(&self, index_name: &str) -> Result<u64, MonitorError> {
+        self.index_entry(index_name)
+            .await?
+            .get("numberOfPendingTasks")
+            .and_then(Value::as_u64)
+            .ok_or(MonitorError::MissingField("numberOfPendingTasks"))
+    }
+
+    /// IDs of a sample of the objects of the index, or of the ones matching the filters, the same ones as long
+    /// as the index doesn't change
+    pub async fn object_ids(
+        &self,
+        index_name: &str,
+        filters: Option<&str>,
+        strategy: SampleStrategy,
+        size: u64,
+    ) -> Result<Vec<String>, MonitorError> {
+        let params = object_id_params(size, filters);
+        let (endpoint, body) = match strategy {
+            SampleStrategy::Browse => ("browse", json!({ "params": params })),
+            SampleStrategy::Search => ("query", json!({ "params": format!("{params}&query=") })),
+        };
+        let body = body.to_string();
+        let response = self
+            .send(endpoint, |base_url| {
+                self.client
+                    .post(format!("{}indexes/{}/{}", base_url, encode(index_name), endpoint))
+                    .body(body.clone())
+            })
+            .await
+            .map_err(|error| match strategy {
+                SampleStrategy::Browse => {
+                    lacking_acl(error, "the API key needs the `browse` ACL to sample the objectIDs by browsing")
+                }
+                SampleStrategy::Search => error,
+            })?;
+        let response: Value = serde_json::from_slice(&response)?;
+        let hits = response
+            .get("hits")
+            .and_then(Value::as_array)
+            .ok_or(MonitorError::MissingField("hits"))?;
+
+        Ok(hits
+            .iter()
+            .filter_map(|hit| hit.get("objectID")?.as_str().map(str::to_string))
+            .collect())
+    }
+
+    /// Counts the objects of the index, or of the ones matching the filters, by browsing through all of them
+    /// with the `browse` ACL, retrieving only their IDs
+    ///
+    /// The browse stops once `max_records` are counted, the call after each page telling how far it went.
+    pub async fn browse_count(
+        &self,
+        index_name: &str,
+        filters: Option<&str>,
+        max_records: u64,
+        mut progress: impl FnMut(&BrowseCount),
+    ) -> Result<BrowseCount, MonitorError> {
+        let mut body = json!({ "params": object_id_params(BROWSE_PAGE_SIZE, filters) }).to_string();
+        let mut count = BrowseCount::default();
+        let explanation = "the API key needs the `browse` ACL to count the records exactly";
+
+        loop {
+            let response = self
+                .send("browse", |base_url| {
+                    self.client
+                        .post(format!("{}indexes/{}/browse", base_url, encode(index_name)))
+                        .body(body.clone())
+                })
+                .await
+                .map_err(|error| lacking_acl(error, explanation))?;
+            let response: Value = serde_json::from_slice(&response)?;
+            let hits = response
+                .get("hits")
+                .and_then(Value::as_array)
+                .ok_or(MonitorError::MissingField("hits"))?;
+
+            count.records += hits.len() as u64;
+            count.pages += 1;
+            let cursor = response.get("cursor").and_then(Value::as_str);
+            count.complete = cursor.is_none();
+            progress(&count);
+
+            match cursor {
+                Some(cursor) if count.records < max_records => body = json!({ "cursor": cursor }).to_string(),
+                _ => return Ok(count),
+            }
+        }
+    }
+
+    /// Number of synonyms or rules of the index, from `nbHits` of a search for all of them
+    pub async fn items_count(&self, index_name: &str, items: IndexItems) -> Result<u64, MonitorError> {
+        let body = json!({ "query": "", "hitsPerPage": 1 }).to_string();
+        let response = self
+            .send(items.as_str(), |base_url| {
+                self.client
+                    .post(format!("{}indexes/{}/{}/search", base_url, encode(index_name), items))
+                    .body(body.clone())
+            })
+            .await?;
+        let response: Value = serde_json::from_slice(&response)?;
+
+        response.get("nbHits").and_then(Value::as_u64).ok_or(MonitorError::MissingField("nbHits"))
+    }
+
+    /// Settings of the index, such as its searchable attributes and ranking, which needs the `settings` ACL
+    pub async fn settings(&self, index_name: &str) -> Result<Map<String, Value>, MonitorError> {
+        let response = self
+            .send("settings", |base_url| {
+                self.client.get(format!("{}indexes/{}/settings", base_url, encode(index_name)))
+            })
+            .await?;
+
+        Ok(serde_json::from_slice(&response)?)
+    }
+
+    /// Entry of the index in the list of indices, paging through it for applications with many indices
+    async fn index_entry(&self, index_name: &str) -> Result<Value, MonitorError> {
+        let mut page = 0;
+
+        loop {
+            let (mut items, pages) = self.indices_page(page).await?;
+            let position = items
+                .iter()
+                .position(|item| item.get("name").and_then(Value::as_str) == Some(index_name));
+            if let Some(position) = position {
+                return Ok(items.swap_remove(position));
+            }
+
+            page += 1;
+            if items.is_empty() || page >= pages {
+                return Err(MonitorError::IndexNotFound(index_name.to_string()));
+            }
+        }
+    }
+
+    /// Entries of a page of the list of indices, along with the number of pages
+    async fn indices_page(&self, page: u64) -> Result<(Vec<Value>, u64), MonitorError> {
+        let response = self
+            .send("indexes", |base_url| self.client.get(format!("{base_url}indexes?page={page}")))
+            .await
+            .map_err(|error| lacking_acl(error, "the API key needs the `listIndexes` ACL to list the indices"))?;
+        let mut response: Value = serde_json::from_slice(&response)?;
+        let pages = response.get("nbPages").and_then(Value::as_u64).unwrap_or(1);
+
+        match response.get_mut("items").map(Value::take) {
+            Some(Value::Array(items)) => Ok((items, pages)),
+            _ => Err(MonitorError::MissingField("items")),
+        }
+    }
+
+    /// Log entries from `since` on, newest first, or the latest page of them without it
+    ///
+    /// Entries logged at `since` itself are included, as other ones may have been logged in the same
+    /// millisecond after the last fetch. Pages are fetched until one comes back short, reaches an entry
+    /// older than `since` or the page limit is hit. New entries shift the older ones to later pages
+    /// while paginating, so entries seen on an earlier page are left out.
+    pub async fn get_logs(
+        &self,
+        index_name: &str,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<AlgoliaLogEntry>, MonitorError> {
+        self.get_logs_of_type(index_name, self.log_type, since).await
+    }
+
+    /// Log entries logged at `timestamp` or after it, newest first, as [`AlgoliaClient::get_logs`] fetches them
+    pub async fn logs_since(
+        &self,
+        index_name: &str,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Vec<AlgoliaLogEntry>, MonitorError> {
+        self.get_logs(index_name, Some(timestamp)).await
+    }
+
+    /// Log entries of the type from `since` on, like [`AlgoliaClient::get_logs`] does for the configured one
+    pub async fn get_logs_of_type(
+        &self,
+        index_name: &str,
+        log_type: LogType,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<AlgoliaLogEntry>, MonitorError> {
+        self.get_logs_paged(index_name, log_type, since, self.log_length).await
+    }
+
+    /// Log entries of the type from `since` on, requested `length` at a time instead of the configured length
+    pub async fn get_logs_paged(
+        &self,
+        index_name: &str,
+        log_type: LogType,
+        since: Option<DateTime<Utc>>,
+        length: u32,
+    ) -> Result<Vec<AlgoliaLogEntry>, MonitorError> {
+        let mut logs = vec![];
+        let mut seen = HashSet::new();
+
+        for page in 0..self.max_log_pages {
+            // Offsets past the largest one the request takes are never reached, the paging stops short of them
+            let Some(offset) = page.checked_mul(length).and_then(|skipped| skipped.checked_add(self.log_offset))
+            else {
+                warn!(
+                    "[{}] Stopped fetching logs of {} at the largest offset, older entries are left out",
+                    now(),
+                    index_name
+                );
+                return Ok(logs);
+            };
+            let entries = self.get_logs_page(index_name, log_type, offset, length).await?;
+            let complete = entries.len() < length as usize;
+            let mut reached_since = false;
+
+            for json in entries {
+                if !seen.insert(json.to_string()) {
+                    continue;
+                }
+
+                match AlgoliaLogEntry::deserialize(&json) {
+                    Ok(log) if since.is_none_or(|since| log.timestamp >= since) => logs.push(log),
+                    Ok(_) => reached_since = true,
+                    Err(error) => warn!("[{}] Skipping malformed log entry ({}): {}", now(), error, json),
+                }
+            }
+
+            if complete || reached_since || since.is_none() {
+                return Ok(logs);
+            }
+        }
+
+        warn!(
+            "[{}] Stopped fetching logs of {} after {} pages, older entries are left out",
+            now(),
+            index_name,
+            self.max_log_pages
+        );
+        Ok(logs)
+    }
+
+    async fn get_logs_page(
+        &self,
+        index_name: &str,
+        log_type: LogType,
+        offset: u32,
+        length: u32,
+    ) -> Result<Vec<Value>, MonitorError> {
+        let response = self
+            .send("logs", |base_url| {
+                self.client.get(format!(
+                    "{}logs?indexName={}&type={}&offset={}&length={}",
+                    base_url,
+                    encode(index_name),
+                    log_type.as_str(),
+                    offset,
+                    length
+                ))
+            })
+            .await
+            .map_err(|error| lacking_acl(error, "the API key needs the `logs` ACL to read the logs"))?;
+        let response: Value = serde_json::from_slice(&response)?;
+
+        // Without the field the logs can't be told apart from an empty page, so it isn't taken as one
+        match response.get("logs") {
+            Some(Value::Array(logs)) => Ok(logs.clone()),
+            _ => Err(MonitorError::MissingField("logs")),
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use serde::Deserialize;
+use std::convert::Infallible;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{PoisonError, RwLock};
+
+/// Every key the process was given, so [`redact_keys`] can scrub them from text it didn't format itself
+static KEYS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// Shortest key whose last characters are shown, the shorter ones being too easy to guess from them
+const MIN_REVEALED_LENGTH: usize = 16;
+
+/// Algolia API key that formats as `***` and its last 4 characters, so it can't end up in a message
+///
+/// [`ApiKey::expose`] is the only way to the key itself, for the requests that send it.
+#[derive(Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(from = "String")]
+pub struct ApiKey(String);
+
+impl ApiKey {
+    pub fn new(key: impl Into<String>) -> Self {
+        let key = key.into();
+        if !key.is_empty() {
+            let mut keys = KEYS.write().unwrap_or_else(PoisonError::into_inner);
+            if !keys.contains(&key) {
+                keys.push(key.clone());
+            }
+        }
+        ApiKey(key)
+    }
+
+    /// The key itself, to be sent to Algolia and never formatted
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn masked(key: &str) -> String {
+        match key.char_indices().rev().nth(3) {
+            Some((start, _)) if key.len() >= MIN_REVEALED_LENGTH => format!("***{}", &key[start..]),
+            _ => "***".to_string(),
+        }
+    }
+}
+
+impl From<String> for ApiKey {
+    fn from(key: String) -> Self {
+        ApiKey::new(key)
+    }
+}
+
+impl FromStr for ApiKey {
+    type Err = Infallible;
+
+    fn from_str(key: &str) -> Result<Self, Self::Err> {
+        Ok(ApiKey::new(key))
+    }
+}
+
+impl fmt::Display for ApiKey {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(&ApiKey::masked(&self.0))
+    }
+}
+
+impl fmt::Debug for ApiKey {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "ApiKey({self})")
+    }
+}
+
+/// Replaces the API keys the process was given with their masked form, for text such as a panic message
+/// that may hold one
+pub fn redact_keys(text: &str) -> String {
+    let keys = KEYS.read().unwrap_or_else(PoisonError::into_inner);
+    keys.iter()
+        .filter(|key| text.contains(key.as_str()))
+        .fold(text.to_string(), |text, key| text.replace(key.as_str(), &ApiKey::masked(key)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_key_shows_its_last_4_characters() {
+        let key = ApiKey::new("0123456789abcdef1234");
+
+        assert_eq!(key.to_string(), "***1234");
+        assert_eq!(format!("{key:?}"), "ApiKey(***1234)");
+    }
+
+    #[test]
+    fn key_shorter_than_the_min_revealed_length_shows_nothing() {
+        let key = ApiKey::new("0123456789abcde");
+        assert_eq!(key.expose().len(), MIN_REVEALED_LENGTH - 1);
+
+        assert_eq!(key.to_string(), "***");
+        assert_eq!(format!("{key:?}"), "ApiKey(***)");
+    }
+
+    #[test]
+    fn key_of_the_min_revealed_length_shows_its_last_4_characters() {
+        let key = ApiKey::new("fedcba9876543210");
+        assert_eq!(key.expose().len(), MIN_REVEALED_LENGTH);
+
+        assert_eq!(key.to_string(), "***3210");
+    }
+
+    #[test]
+    fn last_characters_of_a_multibyte_key_are_whole_characters() {
+        assert_eq!(ApiKey::masked("clé-de-test-longue-éèêë"), "***éèêë");
+    }
+
+    #[test]
+    fn redact_keys_masks_every_key_given_to_the_process() {
+        let search = ApiKey::new("search-key-0000000000aaaa");
+        let admin = ApiKey::new("short-admin");
+
+        let text = format!("panicked with {} and {} twice: {}", search.expose(), admin.expose(), admin.expose());
+
+        assert_eq!(redact_keys(&text), "panicked with ***aaaa and *** twice: ***");
+    }
+
+    #[test]
+    fn redact_keys_leaves_text_without_keys_alone() {
+        ApiKey::new("unrelated-key-000000000bbbb");
+
+        let text = "Algolia API error 403: Invalid API key";
+
+        assert_eq!(redact_keys(text), text);
+    }
+
+    #[test]
+    fn empty_key_is_not_redacted() {
+        ApiKey::new("");
+
+        assert_eq!(redact_keys("no key here"), "no key here");
+    }
+}
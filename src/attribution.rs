@@ -0,0 +1,276 @@
+use crate::algolia::AlgoliaLogEntry;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Summary of the write requests in a set of log entries, to point at what changed the records count
+///
+/// Requests are grouped by kind and client IP, with the actions of batch requests summed up from their
+/// bodies, such as `net -11,874 from 3 requests: 2x deleteBy (ip 1.2.3.4), 1x batch with 11,000
+/// deleteObject`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attribution {
+    net: i64,
+    requests: u64,
+    groups: Vec<Group>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Group {
+    kind: &'static str,
+    ip: Option<String>,
+    requests: u64,
+    /// Counts of the batch actions by name
+    actions: BTreeMap<String, u64>,
+    /// Batch requests with a body Algolia cut short in its logs, so their actions are unknown
+    unknown: u64,
+}
+
+impl Attribution {
+    /// Attribution of the `net` records count change to the write requests on `index_name` among `logs`
+    pub fn from_logs(index_name: &str, net: i64, logs: &[AlgoliaLogEntry]) -> Self {
+        let mut attribution = Attribution {
+            net,
+            requests: 0,
+            groups: vec![],
+        };
+
+        for log in logs.iter().filter(|log| log.is_write()) {
+            let Some(kind) = write_kind(log) else {
+                continue;
+            };
+
+            attribution.requests += 1;
+            let position = attribution
+                .groups
+                .iter()
+                .position(|group| group.kind == kind && group.ip == log.ip);
+            let group = match position {
+                Some(position) => &mut attribution.groups[position],
+                None => {
+                    attribution.groups.push(Group {
+                        kind,
+                        ip: log.ip.clone(),
+                        requests: 0,
+                        actions: BTreeMap::new(),
+                        unknown: 0,
+                    });
+                    attribution.groups.last_mut().expect("group was just added")
+                }
+            };
+
+            group.requests += 1;
+            if kind == "batch" {
+                match batch_actions(index_name, log.query_body.as_deref().unwrap_or_default()) {
+                    Some(actions) => {
+                        for (action, count) in actions {
+                            *group.actions.entry(action).or_default() += count;
+                        }
+                    }
+                    None => group.unknown += 1,
+                }
+            }
+        }
+
+        attribution
+    }
+
+    /// Whether no write request was found
+    pub fn is_empty(&self) -> bool {
+        self.requests == 0
+    }
+}
+
+impl fmt::Display for Attribution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let net = match self.net {
+            net if net < 0 => format!("-{}", thousands(net.unsigned_abs())),
+            net => format!("+{}", thousands(net as u64)),
+        };
+        write!(
+            f,
+            "net {} from {} {}",
+            net,
+            thousands(self.requests),
+            if self.requests == 1 { "request" } else { "requests" }
+        )?;
+
+        for (position, group) in self.groups.iter().enumerate() {
+            write!(f, "{}{}x {}", if position == 0 { ": " } else { ", " }, group.requests, group.kind)?;
+
+            let mut actions: Vec<_> = group
+                .actions
+                .iter()
+                .map(|(action, count)| format!("{} {}", thousands(*count), action))
+                .collect();
+            match group.unknown {
+                0 => {}
+                unknown if unknown == group.requests => actions.push("unknown actions".to_string()),
+                unknown => actions.push(format!("unknown actions in {unknown}")),
+            }
+            if !actions.is_empty() {
+                write!(f, " with {}", actions.join(" and "))?;
+            }
+
+            if let Some(ip) = &group.ip {
+                write!(f, " (ip {ip})")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Name of the operation a write request makes, from its method and URL
+fn write_kind(log: &AlgoliaLogEntry) -> Option<&'static str> {
+    let method = log.method.as_deref()?;
+    let path = log.url.as_deref()?.split(['?', '#']).next().unwrap_or_default();
+    let mut segments = path.strip_prefix("/1/indexes/")?.trim_end_matches('/').split('/').skip(1);
+
+    Some(match (method, segments.next(), segments.next()) {
+        ("POST", None, _) => "addObject",
+        ("DELETE", None, _) => "deleteIndex",
+        (_, Some("batch"), None) => "batch",
+        (_, Some("clear"), None) => "clearObjects",
+        (_, Some("deleteByQuery" | "deleteBy"), None) => "deleteBy",
+        ("PUT", Some(_), None) => "updateObject",
+        ("DELETE", Some(_), None) => "deleteObject",
+        (_, Some(_), Some("partial")) => "partialUpdateObject",
+        _ => return None,
+    })
+}
+
+/// Counts of the actions in a batch body, `None` when the body isn't complete JSON
+///
+/// Requests of multi-index batches that name another index are left out.
+fn batch_actions(index_name: &str, body: &str) -> Option<BTreeMap<String, u64>> {
+    let body: Value = serde_json::from_str(body).ok()?;
+    let mut actions = BTreeMap::new();
+
+    for request in body.get("requests")?.as_array()? {
+        let other_index = request
+            .get("indexName")
+            .and_then(Value::as_str)
+            .is_some_and(|name| name != index_name);
+        if other_index {
+            continue;
+        }
+
+        let action = request.get("action").and_then(Value::as_str).unwrap_or("unknown");
+        *actions.entry(action.to_string()).or_default() += 1;
+    }
+
+    Some(actions)
+}
+
+/// `11874` as `11,874`
+fn thousands(value: u64) -> String {
+    let digits = value.to_string();
+    let mut formatted = String::new();
+
+    for (position, digit) in digits.chars().enumerate() {
+        if position > 0 && (digits.len() - position).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+
+    formatted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn batch(body: &str, ip: &str) -> AlgoliaLogEntry {
+        serde_json::from_value(json!({
+            "timestamp": "2024-05-02T10:14:03Z",
+            "method": "POST",
+            "url": "/1/indexes/products/batch",
+            "ip": ip,
+            "query_body": body,
+        }))
+        .unwrap()
+    }
+
+    fn actions(pairs: &[(&str, u64)]) -> Option<BTreeMap<String, u64>> {
+        Some(pairs.iter().map(|&(action, count)| (action.to_string(), count)).collect())
+    }
+
+    #[test]
+    fn batch_body_counts_its_actions() {
+        let body = json!({ "requests": [
+            { "action": "deleteObject", "body": { "objectID": "1" } },
+            { "action": "deleteObject", "body": { "objectID": "2" } },
+            { "action": "addObject", "body": { "name": "shoe" } },
+        ]});
+
+        let counted = batch_actions("products", &body.to_string());
+
+        assert_eq!(counted, actions(&[("addObject", 1), ("deleteObject", 2)]));
+    }
+
+    #[test]
+    fn multi_index_batch_only_counts_the_requests_of_the_index() {
+        let body = json!({ "requests": [
+            { "action": "deleteObject", "indexName": "products" },
+            { "action": "deleteObject", "indexName": "products_fr" },
+            { "action": "clear", "indexName": "categories" },
+        ]});
+
+        assert_eq!(batch_actions("products", &body.to_string()), actions(&[("deleteObject", 1)]));
+    }
+
+    #[test]
+    fn batch_body_cut_short_has_unknown_actions() {
+        let body = r#"{"requests":[{"action":"deleteObject","body":{"objectID":"1"}},{"action":"delet"#;
+
+        assert_eq!(batch_actions("products", body), None);
+    }
+
+    #[test]
+    fn batch_body_that_isnt_json_has_unknown_actions() {
+        assert_eq!(batch_actions("products", "requests=deleteObject"), None);
+        assert_eq!(batch_actions("products", ""), None);
+    }
+
+    #[test]
+    fn batch_body_without_requests_has_unknown_actions() {
+        assert_eq!(batch_actions("products", r#"{"objects": []}"#), None);
+        assert_eq!(batch_actions("products", r#"{"requests": {"action": "addObject"}}"#), None);
+    }
+
+    #[test]
+    fn request_without_an_action_counts_as_unknown() {
+        let body = json!({ "requests": [{ "body": { "objectID": "1" } }, { "action": 3 }] });
+
+        assert_eq!(batch_actions("products", &body.to_string()), actions(&[("unknown", 2)]));
+    }
+
+    #[test]
+    fn attribution_tells_the_batches_with_unknown_actions() {
+        let complete = json!({ "requests": [{ "action": "deleteObject" }, { "action": "deleteObject" }] });
+        let logs = [
+            batch(&complete.to_string(), "1.2.3.4"),
+            batch(r#"{"requests":[{"action":"deleteOb"#, "1.2.3.4"),
+            batch("not json", "5.6.7.8"),
+        ];
+
+        let attribution = Attribution::from_logs("products", -11_874, &logs);
+
+        assert_eq!(
+            attribution.to_string(),
+            "net -11,874 from 3 requests: 2x batch with 2 deleteObject and unknown actions in 1 (ip 1.2.3.4), \
+             1x batch with unknown actions (ip 5.6.7.8)"
+        );
+    }
+
+    #[test]
+    fn thousands_are_separated() {
+        assert_eq!(thousands(0), "0");
+        assert_eq!(thousands(999), "999");
+        assert_eq!(thousands(1_000), "1,000");
+        assert_eq!(thousands(11_874_000), "11,874,000");
+    }
+}
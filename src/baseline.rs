@@ -0,0 +1,120 @@
+use std::collections::VecDeque;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Strategy for the expected records count, given as `fixed` or `rolling:<N>`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BaselineMode {
+    Fixed,
+    Rolling(usize),
+}
+
+impl FromStr for BaselineMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.split_once(':') {
+            None if value == "fixed" => Ok(BaselineMode::Fixed),
+            Some(("rolling", size)) => match size.parse() {
+                Ok(size) if size > 0 => Ok(BaselineMode::Rolling(size)),
+                _ => Err(format!("invalid number of samples `{size}`")),
+            },
+            _ => Err("expected `fixed` or `rolling:<N>`".to_string()),
+        }
+    }
+}
+
+/// Records count the index is expected to have
+#[derive(Debug)]
+pub enum Baseline {
+    /// Count taken at startup or given on the command line, optionally refreshed on a schedule
+    Fixed {
+        expected_records: u64,
+        refresh_interval: Option<Duration>,
+        refreshed_at: Instant,
+    },
+    /// Moving average of the latest polls that were within threshold
+    Rolling { samples: VecDeque<u64>, size: usize },
+}
+
+impl Baseline {
+    pub fn fixed(expected_records: u64, refresh_interval: Option<Duration>) -> Self {
+        Baseline::Fixed {
+            expected_records,
+            refresh_interval,
+            refreshed_at: Instant::now(),
+        }
+    }
+
+    pub fn rolling(size: usize) -> Self {
+        Baseline::Rolling {
+            samples: VecDeque::with_capacity(size + 1),
+            size,
+        }
+    }
+
+    pub fn expected_records(&self) -> u64 {
+        match self {
+            Baseline::Fixed {
+                expected_records, ..
+            } => *expected_records,
+            Baseline::Rolling { samples, .. } if samples.is_empty() => 0,
+            Baseline::Rolling { samples, .. } => {
+                let sum: u128 = samples.iter().map(|&sample| sample as u128).sum();
+                (sum as f64 / samples.len() as f64).round() as u64
+            }
+        }
+    }
+
+    /// Whether a rolling baseline has fewer samples than its window
+    pub fn is_warming_up(&self) -> bool {
+        match self {
+            Baseline::Fixed { .. } => false,
+            Baseline::Rolling { samples, size } => samples.len() < *size,
+        }
+    }
+
+    /// Starts over from the records count, such as after the index was recreated
+    pub fn reset(&mut self, current_records: u64) {
+        match self {
+            Baseline::Fixed {
+                expected_records,
+                refreshed_at,
+                ..
+            } => {
+                *expected_records = current_records;
+                *refreshed_at = Instant::now();
+            }
+            Baseline::Rolling { samples, .. } => {
+                samples.clear();
+                samples.push_back(current_records);
+            }
+        }
+    }
+
+    /// Takes a records count within threshold into account, returning the previous
+    /// expected count when a fixed baseline got refreshed
+    pub fn record(&mut self, current_records: u64) -> Option<u64> {
+        match self {
+            Baseline::Fixed {
+                expected_records,
+                refresh_interval,
+                refreshed_at,
+            } => {
+                if refreshed_at.elapsed() < (*refresh_interval)? {
+                    return None;
+                }
+
+                *refreshed_at = Instant::now();
+                Some(std::mem::replace(expected_records, current_records))
+            }
+            Baseline::Rolling { samples, size } => {
+                samples.push_back(current_records);
+                if samples.len() > *size {
+                    samples.pop_front();
+                }
+                None
+            }
+        }
+    }
+}
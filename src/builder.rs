@@ -0,0 +1,312 @@
+use crate::algolia::{AlgoliaLogEntry, DestructiveOperation, IndexItems, LogType, SampleStrategy};
+use crate::baseline::Baseline;
+use crate::churn::ObjectSample;
+use crate::facet::FacetBaseline;
+use crate::items::ItemsBaseline;
+use crate::latency::LatencyWindow;
+use crate::log_filter::LogFilter;
+use crate::monitor::{ComparedIndex, Monitor, MonitorEvent, RecordsCount};
+use crate::poll_delay::PollDelay;
+use crate::rate::RateWindow;
+use crate::runner::Control;
+use crate::settings::SettingsBaseline;
+use crate::source::{DataSource, RecordSource};
+use crate::threshold::{CountDelta, ThresholdBreach, Thresholds};
+use chrono::{DateTime, Utc};
+use serde_json::{Map, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+
+/// Builder of a [`Monitor`] of the records count of an index, the outcome of its polls coming out as
+/// [`MonitorEvent`]s
+///
+/// Every option of the monitor is set here, the monitor it builds only taking the thresholds, the log filter
+/// and the expected records count again while it runs, or the [`Control`]s of [`Monitor::run`].
+pub struct MonitorBuilder<S: ?Sized = dyn DataSource> {
+    monitor: Monitor<S>,
+}
+
+impl<S: DataSource + ?Sized> MonitorBuilder<S> {
+    /// Builder of a monitor of the index reading it through the source, against the baseline
+    pub fn new(source: Arc<S>, index_name: impl Into<String>, baseline: Baseline) -> Self {
+        MonitorBuilder {
+            monitor: Monitor::new(source, index_name, baseline),
+        }
+    }
+
+    pub fn with_thresholds(mut self, thresholds: Thresholds) -> Self {
+        self.monitor.thresholds = thresholds;
+        self
+    }
+
+    /// Counts only the records matching the filters, such as `store_id:1`, to watch a subset of the index
+    pub fn with_filters(mut self, filters: impl Into<String>) -> Self {
+        self.monitor.filters = Some(filters.into());
+        self
+    }
+
+    /// Largest allowed index data size in bytes, checked when the client counts from the list of indices
+    pub fn with_max_data_size(mut self, max_data_size: Option<u64>) -> Self {
+        self.monitor.max_data_size = max_data_size;
+        self
+    }
+
+    /// Takes the latency percentiles from the latest `size` search processing times
+    pub fn with_latency_window(mut self, size: usize) -> Self {
+        self.monitor.latency = LatencyWindow::new(size);
+        self
+    }
+
+    /// Highest allowed 95th percentile of the search processing times in milliseconds
+    pub fn with_max_p95_ms(mut self, max_p95_ms: Option<u64>) -> Self {
+        self.monitor.max_p95_ms = max_p95_ms;
+        self
+    }
+
+    /// Takes the window rate of change from the latest `size` of them, one between each two polls
+    pub fn with_rate_window(mut self, size: usize) -> Self {
+        self.monitor.rates = RateWindow::new(size);
+        self
+    }
+
+    /// Highest allowed loss in records per minute, breached only when every rate of the window is faster
+    pub fn with_max_loss_rate(mut self, max_loss_rate: Option<f64>) -> Self {
+        self.monitor.max_loss_rate = max_loss_rate;
+        self
+    }
+
+    /// Fetches the records counts of the replicas on every poll, marking the ones further from the count
+    /// of the index than `delta` as out of sync
+    pub fn with_replicas(mut self, replicas: Vec<String>, delta: CountDelta) -> Self {
+        self.monitor.replicas = replicas;
+        self.monitor.replica_delta = delta;
+        self
+    }
+
+    /// Fetches the records count of an index through another source on every poll, such as the same index
+    /// in another environment, marking it as out of sync when it is further from the count than `delta`
+    pub fn with_comparison(
+        mut self,
+        source: Arc<dyn RecordSource>,
+        index_name: impl Into<String>,
+        delta: CountDelta,
+    ) -> Self {
+        self.monitor.compared = Some(ComparedIndex {
+            source,
+            index_name: index_name.into(),
+            delta,
+        });
+        self
+    }
+
+    /// Counts the records of every value of the facet attributes on each poll, reporting the values that
+    /// dropped by more than `delta` or disappeared
+    pub fn with_facets(mut self, attributes: Vec<String>, delta: CountDelta) -> Self {
+        self.monitor.facets = attributes
+            .into_iter()
+            .map(|attribute| FacetBaseline::new(attribute, delta))
+            .collect();
+        self
+    }
+
+    /// Leaves out the log entries the filter doesn't match, they still count as seen
+    pub fn with_log_filter(mut self, log_filter: LogFilter) -> Self {
+        self.monitor.log_filter = log_filter;
+        self
+    }
+
+    /// Hands out the log entries from the time on, instead of only the ones after the first fetch
+    pub fn with_logs_since(mut self, since: Option<DateTime<Utc>>) -> Self {
+        self.monitor.log_streams = std::mem::take(&mut self.monitor.log_streams)
+            .into_iter()
+            .map(|log_stream| log_stream.with_since(since))
+            .collect();
+        self
+    }
+
+    /// Fetches the query, build and error logs instead of the client's type, merged by timestamp
+    /// with the type set on every entry
+    pub fn with_follow(mut self, follow: bool) -> Self {
+        let monitor = &mut self.monitor;
+        let since = monitor.log_streams[0].since();
+        monitor.log_streams = match follow {
+            true => [LogType::Query, LogType::Build, LogType::Error]
+                .into_iter()
+                .map(|log_type| Monitor::log_stream(&monitor.source, &monitor.index_name, Some(log_type), since))
+                .collect(),
+            false => vec![Monitor::log_stream(&monitor.source, &monitor.index_name, None, since)],
+        };
+        self
+    }
+
+    /// Hands out only every `sample`th entry of the query logs while following, which can be plenty
+    pub fn with_query_sample(mut self, sample: u32) -> Self {
+        self.monitor.query_sample = sample.max(1);
+        self
+    }
+
+    /// Fetches the logs on every poll to catch destructive operations before the records count shows them
+    pub fn with_destructive_watch(mut self, watch_destructive: bool) -> Self {
+        self.monitor.watch_destructive = watch_destructive;
+        self
+    }
+
+    /// Resets the baseline to the records count when the index was recreated, instead of comparing the new
+    /// index to the count of the old one
+    pub fn with_rebaseline_on_recreate(mut self, rebaseline: bool) -> Self {
+        self.monitor.rebaseline_on_recreate = rebaseline;
+        self
+    }
+
+    /// Breaches on a count of 0 when records are expected whatever the thresholds, which is on by default
+    pub fn with_zero_guard(mut self, zero_guard: bool) -> Self {
+        self.monitor.zero_guard = zero_guard;
+        self
+    }
+
+    /// Watches the settings of the index for changes, leaving out the ignored keys, from the snapshot kept by a
+    /// previous run when there is one
+    pub fn with_settings_watch(mut self, ignored: Vec<String>, snapshot: Option<Map<String, Value>>) -> Self {
+        self.monitor.settings = Some(SettingsBaseline::new(ignored, snapshot));
+        self
+    }
+
+    /// Watches the count of the synonyms or the rules of the index for drops larger than the delta
+    pub fn with_items_watch(mut self, items: IndexItems, delta: CountDelta) -> Self {
+        self.monitor.items.retain(|baseline| baseline.items() != items);
+        self.monitor.items.push(ItemsBaseline::new(items, delta));
+        self
+    }
+
+    /// Samples this many objectIDs of the index, or of the subset, to tell how many records got replaced since
+    /// the previous sample
+    pub fn with_object_sample(mut self, strategy: SampleStrategy, size: Option<u64>) -> Self {
+        self.monitor.object_sample = size.map(|size| ObjectSample::new(strategy, size));
+        self
+    }
+
+    /// Interval between the starts of the polls of [`Monitor::run`]
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.monitor.run.poll_delay = PollDelay::new(interval, None, None);
+        self
+    }
+
+    /// Delay between the starts of the polls of [`Monitor::run`], in place of a fixed interval
+    pub fn with_poll_delay(mut self, poll_delay: PollDelay) -> Self {
+        self.monitor.run.poll_delay = poll_delay;
+        self
+    }
+
+    /// Counts the server errors of the error logs after every poll of [`Monitor::run`]
+    pub fn with_error_check(mut self, check_errors: bool) -> Self {
+        self.monitor.run.check_errors = check_errors;
+        self
+    }
+
+    /// Checks the tasks waiting to be published after every poll of [`Monitor::run`]
+    pub fn with_task_check(mut self, check_tasks: bool) -> Self {
+        self.monitor.run.check_tasks = check_tasks;
+        self
+    }
+
+    /// Interval between the checks of the watched settings, synonyms and rules of [`Monitor::run`], 5 minutes
+    /// by default
+    pub fn with_settings_interval(mut self, interval: Duration) -> Self {
+        self.monitor.run.settings_interval = interval;
+        self
+    }
+
+    /// Counts the records exactly by browsing them, up to `max_records`, when a poll of [`Monitor::run`]
+    /// starts a breach
+    pub fn with_browse_verification(mut self, max_records: Option<u64>) -> Self {
+        self.monitor.run.browse_max_records = max_records;
+        self
+    }
+
+    /// Gives up [`Monitor::run`] after this many polls in a row failed, instead of only on a permanent error
+    pub fn with_max_consecutive_errors(mut self, max: Option<u32>) -> Self {
+        self.monitor.run.max_consecutive_errors = max;
+        self
+    }
+
+    /// Ends [`Monitor::run`] after this many polls
+    pub fn with_max_polls(mut self, max_polls: Option<u64>) -> Self {
+        self.monitor.run.max_polls = max_polls;
+        self
+    }
+
+    /// Ends [`Monitor::run`] after the first poll past the time
+    pub fn with_until(mut self, until: Option<DateTime<Utc>>) -> Self {
+        self.monitor.run.until = until;
+        self
+    }
+
+    /// Stretches each delay between the polls of [`Monitor::run`], such as to keep within a budget of API calls
+    pub fn with_delay_stretch(mut self, stretch: impl Fn(Duration) -> Duration + Send + 'static) -> Self {
+        self.monitor.run.stretch = Some(Box::new(stretch));
+        self
+    }
+
+    /// Tells how far behind the pacing of the requests is, such as [`AlgoliaClient::pacing_backlog`], for
+    /// [`Monitor::run`] to skip the polls it would hold past the next one
+    ///
+    /// [`AlgoliaClient::pacing_backlog`]: crate::AlgoliaClient::pacing_backlog
+    pub fn with_pacing(mut self, backlog: impl Fn() -> Duration + Send + 'static) -> Self {
+        self.monitor.run.pacing = Some(Box::new(backlog));
+        self
+    }
+
+    /// Takes the [`Control`]s of the channel while [`Monitor::run`] waits for the next poll
+    pub fn with_controls(mut self, controls: UnboundedReceiver<Control>) -> Self {
+        self.monitor.run.controls = Some(controls);
+        self
+    }
+
+    /// Called when a poll breaches the thresholds, before the logs are fetched
+    pub fn on_breach(mut self, callback: impl FnMut(&RecordsCount, &ThresholdBreach) + Send + 'static) -> Self {
+        self.monitor.on_breach = Some(Box::new(callback));
+        self
+    }
+
+    /// Called when the count returns within the thresholds after a breach
+    pub fn on_recovery(mut self, callback: impl FnMut(&RecordsCount) + Send + 'static) -> Self {
+        self.monitor.on_recovery = Some(Box::new(callback));
+        self
+    }
+
+    /// Called with the log entries that are newer than the ones seen before
+    pub fn on_logs(mut self, callback: impl FnMut(&[AlgoliaLogEntry]) + Send + 'static) -> Self {
+        self.monitor.on_logs = Some(Box::new(callback));
+        self
+    }
+
+    /// Called for every fetched log entry that clears or deletes the index
+    pub fn on_destructive(
+        mut self,
+        callback: impl FnMut(&AlgoliaLogEntry, DestructiveOperation) + Send + 'static,
+    ) -> Self {
+        self.monitor.on_destructive = Some(Box::new(callback));
+        self
+    }
+
+    /// Calls the callback with each event of the polls, in the order they happen
+    pub fn on_event(mut self, callback: impl FnMut(&MonitorEvent) + Send + 'static) -> Self {
+        self.monitor.on_event = Some(Box::new(callback));
+        self
+    }
+
+    /// Sends each event of the polls to the channel, in place of a callback given to
+    /// [`MonitorBuilder::on_event`]
+    ///
+    /// The polls go on once the receiver is dropped, their events being left out.
+    pub fn with_event_channel(self, sender: UnboundedSender<MonitorEvent>) -> Self {
+        self.on_event(move |event| {
+            let _ = sender.send(event.clone());
+        })
+    }
+
+    pub fn build(self) -> Monitor<S> {
+        self.monitor
+    }
+}
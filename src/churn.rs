@@ -0,0 +1,65 @@
+use crate::algolia::SampleStrategy;
+use std::collections::BTreeSet;
+
+/// Most objectIDs that disappeared from the sample kept as examples in a [`Churn`]
+const CHURN_EXAMPLES: usize = 5;
+
+/// Sample of the objectIDs of an index, compared between polls to catch records replaced by different ones
+/// while the count stays the same
+///
+/// The sample is the first objects in the order of the strategy, so new records ranking first push others out
+/// of it as well, which shows as churn the same way.
+#[derive(Debug, Clone)]
+pub struct ObjectSample {
+    strategy: SampleStrategy,
+    size: u64,
+    previous: Option<BTreeSet<String>>,
+}
+
+/// Objects of the previous sample missing from the latest one
+#[derive(Debug, Clone, PartialEq)]
+pub struct Churn {
+    /// Size of the previous sample the latest one is compared to
+    pub sampled: usize,
+    pub disappeared: usize,
+    /// Share of the previous sample that disappeared, as a percentage
+    pub percent: f64,
+    /// First of the objectIDs that disappeared, in order
+    pub examples: Vec<String>,
+}
+
+impl ObjectSample {
+    pub fn new(strategy: SampleStrategy, size: u64) -> Self {
+        ObjectSample {
+            strategy,
+            size,
+            previous: None,
+        }
+    }
+
+    pub fn strategy(&self) -> SampleStrategy {
+        self.strategy
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Compares the objectIDs to the previous sample and keeps them for the next one, `None` on the first sample
+    /// or after an empty one
+    pub fn check(&mut self, object_ids: Vec<String>) -> Option<Churn> {
+        let sample: BTreeSet<_> = object_ids.into_iter().collect();
+        let churn = self.previous.take().filter(|previous| !previous.is_empty()).map(|previous| {
+            let disappeared: Vec<_> = previous.difference(&sample).collect();
+            Churn {
+                sampled: previous.len(),
+                disappeared: disappeared.len(),
+                percent: disappeared.len() as f64 * 100.0 / previous.len() as f64,
+                examples: disappeared.into_iter().take(CHURN_EXAMPLES).cloned().collect(),
+            }
+        });
+        self.previous = Some(sample);
+
+        churn
+    }
+}
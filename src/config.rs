@@ -0,0 +1,199 @@
+use crate::{ApiKey, MonitorError, Thresholds};
+use serde::Deserialize;
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+
+/// Settings read from the `--config` TOML file
+///
+/// Top level values are defaults for options not given on the command line,
+/// each `[[index]]` table adds an index to monitor.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub delay: Option<DurationValue>,
+    pub delta: Option<i64>,
+    pub min_delta: Option<i64>,
+    pub max_delta: Option<i64>,
+    pub delta_percent: Option<f64>,
+    pub all_logs: Option<bool>,
+    pub only_writes: Option<bool>,
+    pub max_consecutive_errors: Option<u32>,
+    pub alert_cooldown: Option<DurationValue>,
+    /// API base URLs separated by commas, as for `--base-url`
+    pub base_url: Option<String>,
+    pub webhook_url: Option<String>,
+    pub slack_webhook: Option<String>,
+    pub telegram_token: Option<String>,
+    pub telegram_chat_id: Option<String>,
+    pub pagerduty_routing_key: Option<String>,
+    pub heartbeat_url: Option<String>,
+    pub heartbeat_fail_url: Option<String>,
+    #[serde(default, rename = "index")]
+    pub indices: Vec<IndexConfig>,
+}
+
+/// Duration given as seconds or as a string such as `5m`
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum DurationValue {
+    Seconds(u64),
+    Text(String),
+}
+
+impl fmt::Display for DurationValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DurationValue::Seconds(seconds) => write!(f, "{seconds}"),
+            DurationValue::Text(text) => write!(f, "{text}"),
+        }
+    }
+}
+
+/// Index to monitor together with the credentials of its application
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IndexConfig {
+    pub app_id: String,
+    /// Name of the application in the output and the metrics, such as `b2c-prod`
+    pub app_name: Option<String>,
+    pub key: Option<ApiKey>,
+    /// Environment variable holding the API key
+    pub key_env: Option<String>,
+    pub name: String,
+    pub expected_records: Option<u64>,
+    pub delta: Option<i64>,
+    pub min_delta: Option<i64>,
+    pub max_delta: Option<i64>,
+    pub delta_percent: Option<f64>,
+    /// Delay between the polls of this index, such as `30s` for an index changing often
+    pub delay: Option<DurationValue>,
+    pub all_logs: Option<bool>,
+    pub only_writes: Option<bool>,
+    /// Index in another application to compare the records count to
+    pub compare: Option<CompareConfig>,
+    #[serde(default, rename = "subset")]
+    pub subsets: Vec<SubsetConfig>,
+}
+
+/// Subset of the records of an index watched with its own baseline, falling back to the thresholds of the index
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SubsetConfig {
+    pub name: String,
+    /// Algolia filters the records of the subset match, such as `store_id:1`
+    pub filters: String,
+    pub expected_records: Option<u64>,
+    pub delta: Option<i64>,
+    pub min_delta: Option<i64>,
+    pub max_delta: Option<i64>,
+    pub delta_percent: Option<f64>,
+}
+
+/// Index to compare the monitored one to, such as the same index in the staging application
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CompareConfig {
+    pub app_id: String,
+    pub key: Option<ApiKey>,
+    /// Environment variable holding the API key
+    pub key_env: Option<String>,
+    pub name: String,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Config, MonitorError> {
+        let contents = std::fs::read_to_string(path).map_err(|source| MonitorError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        // The error points at the line instead of quoting it, as the line may hold a key
+        toml::from_str(&contents).map_err(|error| {
+            let location = match error.span() {
+                Some(span) => {
+                    let before = &contents[..span.start];
+                    let line = before.lines().count().max(1) + before.ends_with('\n') as usize;
+                    let column = before.len() - before.rfind('\n').map_or(0, |position| position + 1) + 1;
+                    format!(" at line {line}, column {column}")
+                }
+                None => String::new(),
+            };
+            MonitorError::InvalidArgument(format!(
+                "Invalid config file {}{}: {}",
+                path.display(),
+                location,
+                error.message()
+            ))
+        })
+    }
+
+    /// Default thresholds, `None` when the file sets none of them
+    pub fn thresholds(&self) -> Result<Option<Thresholds>, MonitorError> {
+        Thresholds::from_limits(self.delta, self.min_delta, self.max_delta, self.delta_percent)
+    }
+}
+
+impl IndexConfig {
+    pub fn key(&self) -> Result<ApiKey, MonitorError> {
+        key(&self.key, &self.key_env, &self.name)
+    }
+
+    /// Thresholds of this index, `None` when it uses the defaults
+    pub fn thresholds(&self) -> Result<Option<Thresholds>, MonitorError> {
+        Thresholds::from_limits(self.delta, self.min_delta, self.max_delta, self.delta_percent).map_err(|error| {
+            MonitorError::InvalidArgument(format!("Invalid thresholds of index {}: {}", self.name, error))
+        })
+    }
+}
+
+impl SubsetConfig {
+    /// Thresholds of this subset, `None` when it uses the ones of the index
+    pub fn thresholds(&self, index_name: &str) -> Result<Option<Thresholds>, MonitorError> {
+        Thresholds::from_limits(self.delta, self.min_delta, self.max_delta, self.delta_percent).map_err(|error| {
+            MonitorError::InvalidArgument(format!(
+                "Invalid thresholds of subset {} of index {}: {}",
+                self.name, index_name, error
+            ))
+        })
+    }
+}
+
+impl CompareConfig {
+    pub fn key(&self) -> Result<ApiKey, MonitorError> {
+        key(&self.key, &self.key_env, &self.name)
+    }
+}
+
+/// API key given in the config file or read from the environment variable it names
+fn key(key: &Option<ApiKey>, key_env: &Option<String>, index_name: &str) -> Result<ApiKey, MonitorError> {
+    match (key, key_env) {
+        (Some(key), None) => Ok(key.clone()),
+        (None, Some(key_env)) => std::env::var(key_env).map(ApiKey::new).map_err(|_| {
+            MonitorError::InvalidArgument(format!(
+                "Environment variable {key_env} with the key of index {index_name} is not set"
+            ))
+        }),
+        _ => Err(MonitorError::InvalidArgument(format!(
+            "Index {index_name} needs exactly one of `key` or `key_env`"
+        ))),
+    }
+}
+
+/// Duration such as `90s` or `1h 30m`, with bare numbers taken as seconds
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    match value.trim().parse::<u64>() {
+        Ok(seconds) => Ok(Duration::from_secs(seconds)),
+        Err(_) => humantime::parse_duration(value).map_err(|error| format!("invalid duration `{value}`: {error}")),
+    }
+}
+
+/// Poll interval of at least a second, shorter ones would burn through the operations quota
+pub fn parse_delay(value: &str) -> Result<Duration, String> {
+    match parse_duration(value)? {
+        delay if delay < Duration::from_secs(1) => Err(format!(
+            "delay `{value}` is too short, polls need to be at least 1s apart to spare the Algolia operations quota"
+        )),
+        delay => Ok(delay),
+    }
+}
@@ -0,0 +1,66 @@
+use algolia_monitor::{now, redact_keys};
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::fmt;
+use tracing::{Event, Subscriber};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::EnvFilter;
+
+/// Sends the diagnostics to stderr, at the level of the `-v` and `-q` flags unless `RUST_LOG` is set
+///
+/// Info shows the records counts and the notices of the monitor, every `-v` goes down a level to debug and
+/// trace, and every `-q` up to warn and error. Other crates only show their warnings, `RUST_LOG` such as
+/// `algolia_monitor=debug,reqwest=trace` taking over all of it. The API keys are masked in every message,
+/// the panics included.
+pub fn init(verbose: u8, quiet: u8) {
+    let level = match (verbose, quiet) {
+        (0, 0) => "info",
+        (0, 1) => "warn",
+        (0, _) => "error",
+        (1, _) => "debug",
+        (_, _) => "trace",
+    };
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(format!("warn,{}={level}", env!("CARGO_CRATE_NAME"))));
+
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_ansi(false)
+        .event_format(Diagnostics)
+        .init();
+
+    // Prints what the default hook would, a panic message can hold a key such as in a formatted header map
+    std::panic::set_hook(Box::new(|info| {
+        let backtrace = Backtrace::capture();
+        let mut message = format!("thread '{}' {}", std::thread::current().name().unwrap_or("<unnamed>"), info);
+        match backtrace.status() {
+            BacktraceStatus::Captured => message.push_str(&format!("\nstack backtrace:\n{backtrace}")),
+            _ => message.push_str(
+                "\nnote: run with `RUST_BACKTRACE=1` environment variable to display a backtrace",
+            ),
+        }
+        eprintln!("{}", redact_keys(&message));
+    }));
+}
+
+/// Prints the messages of the monitor as they are, already timestamped and colored, and the ones of other
+/// crates with their time, level and target, with the API keys masked in both
+struct Diagnostics;
+
+impl<S, N> FormatEvent<S, N> for Diagnostics
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, context: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        let metadata = event.metadata();
+        if !metadata.target().starts_with(env!("CARGO_CRATE_NAME")) {
+            write!(writer, "[{}] {} {}: ", now(), metadata.level(), metadata.target())?;
+        }
+        let mut message = String::new();
+        context.field_format().format_fields(Writer::new(&mut message), event)?;
+        writeln!(writer, "{}", redact_keys(&message))
+    }
+}
@@ -0,0 +1,187 @@
+use crate::notify::{Alert, Digest, DigestBreach, Notifier};
+use crate::output::Output;
+use crate::{EventSink, MonitorError, MonitorEvent, ThresholdBreach};
+use chrono::{DateTime, Utc};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Events of an index collected over a `--digest` period, sent as a single [`Digest`] once it is over
+///
+/// The period ends with the first event after the interval, usually the count of the next poll, or right
+/// away with a critical event such as the index left with no records or deleted. The clones collect into the
+/// same period, so the monitor loop can report what the events don't tell, like the deletion of the index.
+#[derive(Clone)]
+pub struct DigestSink {
+    app_id: String,
+    index_name: String,
+    subset: Option<String>,
+    interval: Duration,
+    /// Notifier the digests go to, the one of the latest reload of the config file
+    notifier: Arc<dyn Fn() -> Notifier + Send + Sync>,
+    output: Output,
+    period: Arc<Mutex<Period>>,
+}
+
+/// Samples and breaches of the ongoing period
+struct Period {
+    started: Instant,
+    started_at: DateTime<Utc>,
+    polls: u64,
+    failed_polls: u64,
+    first_records: Option<u64>,
+    last_records: Option<u64>,
+    min_records: Option<u64>,
+    max_records: Option<u64>,
+    write_operations: u64,
+    breaches: Vec<PeriodBreach>,
+}
+
+struct PeriodBreach {
+    breach: ThresholdBreach,
+    started: Instant,
+    started_at: DateTime<Utc>,
+    /// Time the breach lasted, `None` while it is ongoing
+    duration: Option<Duration>,
+}
+
+impl Period {
+    /// Period starting now, with the breaches still ongoing at the end of the previous one
+    fn new(breaches: Vec<PeriodBreach>) -> Self {
+        Period {
+            started: Instant::now(),
+            started_at: Utc::now(),
+            polls: 0,
+            failed_polls: 0,
+            first_records: None,
+            last_records: None,
+            min_records: None,
+            max_records: None,
+            write_operations: 0,
+            breaches,
+        }
+    }
+}
+
+impl DigestSink {
+    pub fn new(
+        app_id: String,
+        index_name: String,
+        subset: Option<String>,
+        interval: Duration,
+        notifier: impl Fn() -> Notifier + Send + Sync + 'static,
+        output: Output,
+    ) -> Self {
+        DigestSink {
+            app_id,
+            index_name,
+            subset,
+            interval,
+            notifier: Arc::new(notifier),
+            output,
+            period: Arc::new(Mutex::new(Period::new(vec![]))),
+        }
+    }
+
+    /// Sends the digest of the period right away because of the event, such as `index was deleted`
+    pub fn critical(&self, event: &str) {
+        let mut period = self.period.lock().unwrap();
+        self.send(&mut period, Some(event.to_string()));
+    }
+
+    /// Sends the digest to the channels and prints it, starting the next period
+    fn send(&self, period: &mut Period, critical: Option<String>) {
+        let ended = Instant::now();
+        let ended_at = Utc::now();
+        let ongoing = std::mem::take(&mut period.breaches);
+        let breaches = ongoing
+            .iter()
+            .map(|breach| DigestBreach {
+                breach: breach.breach.to_string(),
+                started_at: breach.started_at,
+                duration_secs: breach.duration.unwrap_or(ended - breach.started).as_secs(),
+                ongoing: breach.duration.is_none(),
+            })
+            .collect();
+        let digest = Digest {
+            app_id: self.app_id.clone(),
+            index_name: self.index_name.clone(),
+            subset: self.subset.clone(),
+            started_at: period.started_at,
+            ended_at,
+            polls: period.polls,
+            failed_polls: period.failed_polls,
+            first_records: period.first_records,
+            last_records: period.last_records,
+            min_records: period.min_records,
+            max_records: period.max_records,
+            write_operations: period.write_operations,
+            breaches,
+            api_calls_today: self.output.usage.today(),
+            projected_daily_api_calls: self.output.usage.projected_daily(),
+            critical,
+            timestamp: ended_at,
+        };
+
+        self.output.print_digest(&digest);
+        (self.notifier)().notify(Alert::Digest(Box::new(digest)));
+        *period = Period::new(ongoing.into_iter().filter(|breach| breach.duration.is_none()).collect());
+    }
+}
+
+impl EventSink for DigestSink {
+    fn name(&self) -> String {
+        "digest".to_string()
+    }
+
+    fn handle(&mut self, event: &MonitorEvent) -> Result<(), MonitorError> {
+        let mut period = self.period.lock().unwrap();
+        let mut critical = None;
+
+        match event {
+            MonitorEvent::CountSampled(records) => {
+                let records = records.current_records;
+                period.polls += 1;
+                period.first_records = period.first_records.or(Some(records));
+                period.last_records = Some(records);
+                period.min_records = Some(period.min_records.map_or(records, |min| min.min(records)));
+                period.max_records = Some(period.max_records.map_or(records, |max| max.max(records)));
+            }
+            MonitorEvent::BreachStarted { breach, .. } => {
+                if let ThresholdBreach::ZeroRecords = breach {
+                    critical = Some(breach.to_string());
+                }
+                period.breaches.push(PeriodBreach {
+                    breach: *breach,
+                    started: Instant::now(),
+                    started_at: Utc::now(),
+                    duration: None,
+                });
+            }
+            MonitorEvent::BreachResolved(_) => {
+                if let Some(breach) = period.breaches.iter_mut().rev().find(|breach| breach.duration.is_none()) {
+                    breach.duration = Some(breach.started.elapsed());
+                }
+            }
+            MonitorEvent::LogEntry(log) => {
+                if log.is_write() {
+                    period.write_operations += 1;
+                }
+                if let Some(operation) = log.destructive_operation(&self.index_name) {
+                    critical = Some(format!("{operation} ran on the index"));
+                }
+            }
+            MonitorEvent::Error(_) => {
+                period.polls += 1;
+                period.failed_polls += 1;
+            }
+            _ => {}
+        }
+
+        if critical.is_some() || period.started.elapsed() >= self.interval {
+            self.send(&mut period, critical);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+use crate::api_key::redact_keys;
+use reqwest::{Response, StatusCode};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Failure while monitoring an index or setting up the monitor
+#[derive(Debug, thiserror::Error)]
+pub enum MonitorError {
+    /// Request could not be sent or its response could not be read
+    #[error("{0}")]
+    Http(#[from] reqwest::Error),
+    /// Request that took longer than the configured timeout
+    #[error("Algolia request timed out after {}", humantime::format_duration(*.0))]
+    Timeout(Duration),
+    /// Algolia answered with an error status and the message from the response body
+    #[error("Algolia API error {}: {message}", status.as_u16())]
+    Api { status: StatusCode, message: String },
+    /// Response body that isn't valid JSON, such as a truncated one
+    #[error("Invalid JSON in Algolia response: {0}")]
+    Json(#[from] serde_json::Error),
+    /// Successful response that lacks a field the monitor relies on
+    #[error("Algolia response is missing the `{0}` field")]
+    MissingField(&'static str),
+    /// Index that is missing from the list of indices of the application
+    #[error("Index {0} is missing from the list of indices")]
+    IndexNotFound(String),
+    /// Index that was found by earlier polls and is gone now
+    #[error("Index {0} was deleted")]
+    IndexDeleted(String),
+    /// Proxy that answered 407, lacking or rejecting the credentials to authenticate with
+    #[error("Proxy authentication failed, the proxy requires valid credentials")]
+    ProxyAuthentication,
+    /// Replay with no recorded response left for a call of the index
+    #[error("No {call} response of {index_name} left to replay")]
+    ReplayEnded { index_name: String, call: String },
+    /// Failure recorded along with the responses, replayed with its message
+    #[error("{0}")]
+    Replayed(String),
+    /// [`EventSink`](crate::EventSink) that failed to take an event, such as a webhook that can't be reached
+    #[error("{0}")]
+    Sink(String),
+    /// Option, config value or credential that can't be used
+    #[error("{0}")]
+    InvalidArgument(String),
+    /// File the monitor reads its settings from or writes its output to
+    #[error("Failed to access {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+impl MonitorError {
+    /// Reads the Algolia error message from the body of a failed response, masking a key it echoes
+    pub(crate) async fn from_response(response: Response) -> Self {
+        let status = response.status();
+        let message = response
+            .json::<Value>()
+            .await
+            .ok()
+            .and_then(|body| body.get("message")?.as_str().map(redact_keys))
+            .unwrap_or_else(|| status.canonical_reason().unwrap_or_default().to_string());
+
+        MonitorError::Api { status, message }
+    }
+
+    /// Errors that retrying will not fix: a rejected API key or proxy credentials, or a missing index
+    pub fn is_permanent(&self) -> bool {
+        matches!(
+            self,
+            MonitorError::Api {
+                status: StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN | StatusCode::NOT_FOUND,
+                ..
+            } | MonitorError::ProxyAuthentication
+        )
+    }
+
+    /// Errors of an index that doesn't exist, from the query endpoint or the list of indices
+    pub fn is_not_found(&self) -> bool {
+        matches!(
+            self,
+            MonitorError::Api {
+                status: StatusCode::NOT_FOUND,
+                ..
+            } | MonitorError::IndexNotFound(_)
+        )
+    }
+
+    /// Connection errors of hosts that don't resolve, such as the ones of a mistyped application ID
+    pub fn is_unresolved_host(&self) -> bool {
+        let MonitorError::Http(error) = self else {
+            return false;
+        };
+
+        let mut source = std::error::Error::source(error);
+        while let Some(error) = source {
+            if error.to_string().starts_with("dns error") {
+                return true;
+            }
+            source = error.source();
+        }
+        false
+    }
+
+    /// End of the recorded responses of a replay, after which the monitor of the index stops
+    pub fn is_replay_end(&self) -> bool {
+        matches!(self, MonitorError::ReplayEnded { .. })
+    }
+
+    /// Errors meaning the index can't be read, as opposed to a flaky connection
+    pub fn is_unreachable(&self) -> bool {
+        self.is_permanent() || matches!(self, MonitorError::MissingField(_) | MonitorError::IndexNotFound(_))
+    }
+}
@@ -0,0 +1,87 @@
+use algolia_monitor::notify::shell;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+/// Shell command printing the expected records count of an index, such as a `SELECT COUNT(*)` on the database
+/// the index is fed from, run before the polls
+///
+/// `ALGOLIA_APP_ID` and `ALGOLIA_INDEX` tell which index the count is for, and `ALGOLIA_SUBSET` the subset when
+/// one is monitored, so one command can serve all of them.
+pub struct ExpectedCommand {
+    command: String,
+    timeout: Duration,
+    /// Time between two runs, every poll when `None`
+    refresh: Option<Duration>,
+    ran_at: Option<Instant>,
+}
+
+impl ExpectedCommand {
+    pub fn new(command: String, timeout: Duration, refresh: Option<Duration>) -> Self {
+        ExpectedCommand {
+            command,
+            timeout,
+            refresh,
+            ran_at: None,
+        }
+    }
+
+    /// Whether the refresh interval went by since the last run
+    pub fn is_due(&self) -> bool {
+        match (self.ran_at, self.refresh) {
+            (Some(ran_at), Some(refresh)) => ran_at.elapsed() >= refresh,
+            _ => true,
+        }
+    }
+
+    /// Runs the command, returning the records count it printed or why there is none
+    pub async fn run(&mut self, app_id: &str, index_name: &str, subset: Option<&str>) -> Result<u64, String> {
+        self.ran_at = Some(Instant::now());
+
+        let mut process = shell(&self.command);
+        process
+            .env("ALGOLIA_APP_ID", app_id)
+            .env("ALGOLIA_INDEX", index_name)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(subset) = subset {
+            process.env("ALGOLIA_SUBSET", subset);
+        }
+
+        let child = process.spawn().map_err(|error| format!("failed to run `{}`: {}", self.command, error))?;
+        // Dropping the child when the timeout is reached kills it
+        let output = match tokio::time::timeout(self.timeout, child.wait_with_output()).await {
+            Ok(output) => output.map_err(|error| format!("`{}` failed: {}", self.command, error))?,
+            Err(_) => {
+                return Err(format!(
+                    "`{}` was killed after {}",
+                    self.command,
+                    humantime::format_duration(self.timeout)
+                ));
+            }
+        };
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!(
+                "`{}` {}{}",
+                self.command,
+                match output.status.code() {
+                    Some(code) => format!("exited with status {code}"),
+                    None => "was stopped by a signal".to_string(),
+                },
+                match stderr.trim() {
+                    "" => String::new(),
+                    stderr => format!(": {stderr}"),
+                }
+            ));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        stdout
+            .trim()
+            .parse()
+            .map_err(|_| format!("`{}` printed `{}` instead of a records count", self.command, stdout.trim()))
+    }
+}
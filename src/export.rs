@@ -0,0 +1,46 @@
+use algolia_monitor::output::TimelineEntry;
+use std::io::{self, Write};
+
+/// Format of the history written by the `export` subcommand
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// Comma separated values with a header line, for spreadsheets
+    Csv,
+    /// One JSON object per line
+    Json,
+}
+
+const CSV_HEADER: &str = "timestamp,index,count,delta_vs_baseline,event_type,detail";
+
+/// Writes the polls and events, leaving the fields an entry doesn't have empty in CSV and null in JSON
+pub fn write(out: &mut impl Write, format: ExportFormat, entries: &[TimelineEntry]) -> io::Result<()> {
+    if format == ExportFormat::Csv {
+        writeln!(out, "{CSV_HEADER}")?;
+    }
+
+    for entry in entries {
+        match format {
+            ExportFormat::Csv => writeln!(
+                out,
+                "{},{},{},{},{},{}",
+                csv_field(&entry.timestamp),
+                csv_field(&entry.index_name),
+                entry.records.map(|records| records.to_string()).unwrap_or_default(),
+                entry.delta.map(|delta| format!("{delta:+}")).unwrap_or_default(),
+                csv_field(entry.kind.as_deref().unwrap_or_default()),
+                csv_field(entry.message.as_deref().unwrap_or_default())
+            )?,
+            ExportFormat::Json => writeln!(out, "{}", serde_json::to_string(entry)?)?,
+        }
+    }
+
+    out.flush()
+}
+
+/// Field quoted when it holds a comma, a quote or a line break, with its quotes doubled
+fn csv_field(value: &str) -> String {
+    match value.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")),
+        false => value.to_string(),
+    }
+}
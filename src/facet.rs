@@ -0,0 +1,81 @@
+use crate::algolia::FacetValues;
+use crate::threshold::CountDelta;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Counts of the values of a facet attribute, remembered between polls to catch a value losing its records
+///
+/// A value keeps the count it had before it dropped by more than the delta, so it stays dropped until it
+/// recovers, while values showing up are adopted with their first count.
+#[derive(Debug, Clone)]
+pub struct FacetBaseline {
+    attribute: String,
+    delta: CountDelta,
+    counts: BTreeMap<String, u64>,
+}
+
+/// Records counts of the values of a facet attribute, with the values that dropped
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FacetCounts {
+    pub attribute: String,
+    pub counts: BTreeMap<String, u64>,
+    pub drops: Vec<FacetDrop>,
+}
+
+/// Facet value with a records count further below its baseline than the delta allows
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FacetDrop {
+    pub value: String,
+    pub expected_records: u64,
+    /// Zero when the value disappeared
+    pub current_records: u64,
+}
+
+impl FacetBaseline {
+    pub fn new(attribute: impl Into<String>, delta: CountDelta) -> Self {
+        FacetBaseline {
+            attribute: attribute.into(),
+            delta,
+            counts: BTreeMap::new(),
+        }
+    }
+
+    pub fn attribute(&self) -> &str {
+        &self.attribute
+    }
+
+    /// Compares the values to the baseline and records them in it
+    ///
+    /// Values missing from an incomplete list are left as they are, as they may just be past its end.
+    pub fn check(&mut self, values: FacetValues) -> FacetCounts {
+        let mut drops = vec![];
+
+        for (value, expected_records) in &mut self.counts {
+            let current_records = match values.counts.get(value) {
+                Some(count) => *count,
+                None if !values.complete => continue,
+                None => 0,
+            };
+
+            if current_records < *expected_records && self.delta.exceeded(*expected_records, current_records) {
+                drops.push(FacetDrop {
+                    value: value.clone(),
+                    expected_records: *expected_records,
+                    current_records,
+                });
+            } else {
+                *expected_records = current_records;
+            }
+        }
+
+        for (value, count) in &values.counts {
+            self.counts.entry(value.clone()).or_insert(*count);
+        }
+
+        FacetCounts {
+            attribute: self.attribute.clone(),
+            counts: values.counts,
+            drops,
+        }
+    }
+}
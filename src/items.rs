@@ -0,0 +1,55 @@
+use crate::algolia::IndexItems;
+use crate::threshold::CountDelta;
+use serde::Serialize;
+
+/// Count of the synonyms or the rules of an index, remembered between checks to catch a sudden drop such as
+/// a bad import wiping them
+///
+/// Like a facet value, the count is kept as it was before it dropped by more than the delta, so it stays
+/// dropped until it recovers, while growing counts are adopted.
+#[derive(Debug, Clone)]
+pub struct ItemsBaseline {
+    items: IndexItems,
+    delta: CountDelta,
+    expected: Option<u64>,
+}
+
+/// Synonyms or rules count of an index next to the count it is expected to have
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ItemsCount {
+    pub items: IndexItems,
+    pub expected: u64,
+    pub current: u64,
+    /// Whether the count is further below the expected one than the delta allows
+    pub dropped: bool,
+}
+
+impl ItemsBaseline {
+    pub fn new(items: IndexItems, delta: CountDelta) -> Self {
+        ItemsBaseline {
+            items,
+            delta,
+            expected: None,
+        }
+    }
+
+    pub fn items(&self) -> IndexItems {
+        self.items
+    }
+
+    /// Compares the count to the baseline and records it in it, the first count being the baseline
+    pub fn check(&mut self, current: u64) -> ItemsCount {
+        let expected = *self.expected.get_or_insert(current);
+        let dropped = current < expected && self.delta.exceeded(expected, current);
+        if !dropped {
+            self.expected = Some(current);
+        }
+
+        ItemsCount {
+            items: self.items,
+            expected,
+            current,
+            dropped,
+        }
+    }
+}
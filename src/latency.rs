@@ -0,0 +1,56 @@
+use std::collections::VecDeque;
+
+/// Latest search processing times in milliseconds, keeping at most `size` of them
+#[derive(Debug)]
+pub struct LatencyWindow {
+    samples: VecDeque<u64>,
+    size: usize,
+}
+
+/// Percentiles of the processing times in a [`LatencyWindow`], in milliseconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Percentiles {
+    pub p50: u64,
+    pub p95: u64,
+    pub p99: u64,
+    /// Number of samples the percentiles are taken from
+    pub samples: usize,
+}
+
+impl LatencyWindow {
+    pub fn new(size: usize) -> Self {
+        LatencyWindow {
+            samples: VecDeque::with_capacity(size + 1),
+            size: size.max(1),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn record(&mut self, processing_time_ms: u64) {
+        self.samples.push_back(processing_time_ms);
+        if self.samples.len() > self.size {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Nearest-rank percentiles of the samples, `None` before the first one
+    pub fn percentiles(&self) -> Option<Percentiles> {
+        if self.samples.is_empty() {
+            return None;
+        }
+
+        let mut sorted: Vec<_> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let rank = |percent: usize| sorted[(sorted.len() * percent).div_ceil(100).max(1) - 1];
+
+        Some(Percentiles {
+            p50: rank(50),
+            p95: rank(95),
+            p99: rank(99),
+            samples: sorted.len(),
+        })
+    }
+}
@@ -0,0 +1,94 @@
+//! Monitoring of the records count of Algolia indices
+//!
+//! [`AlgoliaClient`] reads the records count and logs of an index, [`Monitor`] polls them
+//! against a [`Baseline`] and reports threshold breaches through its callbacks. The monitor reads through a
+//! [`DataSource`], the [`RecordSource`] and [`LogSource`] of an index, which a [`Replay`] of the responses
+//! written by a [`Recorder`] stands in for, or with the `testing` feature a `MockSource` of scripted ones.
+//! [`Monitor::run`] polls and checks the index on its own until a [`RunEnd`], taking [`Control`]s between the
+//! polls. [`LogStream`] tails the logs of an index as a stream of the new entries, and [`EventSinks`] fan the
+//! events of a monitor out to several [`EventSink`]s.
+//!
+//! A [`Target`] is an index to monitor, read from a [`Config`] or given on the command line, whose
+//! [`MonitoredIndex`] prints, notifies and records the events of its monitor. The [`notify`] module holds the
+//! channels the alerts go to and the [`output`] module the destinations of everything else.
+
+mod alerting;
+mod algolia;
+mod api_key;
+mod attribution;
+mod baseline;
+mod builder;
+mod churn;
+mod config;
+mod digest;
+mod error;
+mod facet;
+mod items;
+mod latency;
+mod log_filter;
+mod log_stream;
+pub mod metrics;
+#[cfg(any(test, feature = "testing"))]
+mod mock;
+mod monitor;
+pub mod notify;
+pub mod output;
+mod pacing;
+mod poll_delay;
+mod quiet_hours;
+mod rate;
+mod replay;
+mod replica;
+#[cfg(feature = "sentry")]
+pub mod reporting;
+mod runner;
+mod settings;
+mod sink;
+mod source;
+mod summary;
+mod target;
+mod threshold;
+mod tls;
+
+use chrono::{SecondsFormat, Utc};
+
+pub use alerting::{report_destructive, AlertOptions, IndexAlerts, MonitoredIndex};
+pub use algolia::{
+    default_hosts, AlgoliaClient, AlgoliaLogEntry, BrowseCount, CountSource, DestructiveOperation, FacetValues,
+    IndexItems, IndexStats, KeyPermissions, LogType, ProxyConfig, SampleStrategy, MAX_FACET_VALUES,
+};
+pub use api_key::{redact_keys, ApiKey};
+pub use attribution::Attribution;
+pub use baseline::{Baseline, BaselineMode};
+pub use builder::MonitorBuilder;
+pub use churn::{Churn, ObjectSample};
+pub use config::{parse_delay, parse_duration, CompareConfig, Config, DurationValue, IndexConfig, SubsetConfig};
+pub use digest::DigestSink;
+pub use error::MonitorError;
+pub use facet::{FacetBaseline, FacetCounts, FacetDrop};
+pub use items::{ItemsBaseline, ItemsCount};
+pub use latency::{LatencyWindow, Percentiles};
+pub use log_filter::LogFilter;
+pub use log_stream::LogStream;
+#[cfg(any(test, feature = "testing"))]
+pub use mock::MockSource;
+pub use monitor::{Comparison, ErrorRate, Monitor, MonitorEvent, Poll, RecordsCount, TaskBacklog};
+pub use pacing::RequestPacer;
+pub use poll_delay::{Jitter, PollDelay};
+pub use quiet_hours::QuietHours;
+pub use rate::{RateWindow, Rates};
+pub use replay::{Recorder, RecordingSource, Replay, ReplaySource};
+pub use replica::ReplicaCount;
+pub use runner::{Check, Control, Failures, PollEnd, Reload, RunEnd};
+pub use settings::{SettingsBaseline, SettingsChange, SettingsDiff};
+pub use sink::{EventSink, EventSinks};
+pub use source::{DataSource, LogSource, RecordSource};
+pub use summary::{print_summaries, Summary};
+pub use target::{CompareTarget, ExpectedRecords, Subset, Target, TargetDefaults};
+pub use threshold::{CountDelta, ThresholdBreach, Thresholds};
+pub use tls::TlsConfig;
+
+/// Current time in the format of the timestamps the monitor prints
+pub fn now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
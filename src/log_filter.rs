@@ -0,0 +1,141 @@
+use crate::algolia::AlgoliaLogEntry;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+
+/// Conditions the log entries handed out by the monitor have to meet
+#[derive(Debug, Clone, Default)]
+pub struct LogFilter {
+    only_writes: bool,
+    answer_codes: AnswerCodes,
+    /// Prefixes of the client IPs to keep, any IP when empty
+    ips: Vec<String>,
+    /// Prefixes of the client IPs to leave out
+    excluded_ips: Vec<String>,
+    /// Lowercase parts of the user agents to keep, any user agent when empty
+    user_agents: Vec<String>,
+    /// Patterns one of which the JSON of the entry has to match, any entry when empty
+    patterns: Vec<Regex>,
+    /// Patterns the JSON of the entry must not match
+    excluded_patterns: Vec<Regex>,
+    /// Time after which entries are left out
+    until: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Default)]
+enum AnswerCodes {
+    #[default]
+    Any,
+    AtLeast(u16),
+    OneOf(Vec<u16>),
+}
+
+impl LogFilter {
+    /// Leaves out entries of operations that don't change records
+    pub fn with_only_writes(mut self, only_writes: bool) -> Self {
+        self.only_writes = only_writes;
+        self
+    }
+
+    /// Leaves out entries Algolia answered with a lower status code, such as 400 to keep only failures
+    pub fn with_min_answer_code(mut self, code: u16) -> Self {
+        self.answer_codes = AnswerCodes::AtLeast(code);
+        self
+    }
+
+    /// Keeps only entries Algolia answered with one of the status codes
+    pub fn with_answer_codes(mut self, codes: Vec<u16>) -> Self {
+        self.answer_codes = AnswerCodes::OneOf(codes);
+        self
+    }
+
+    /// Keeps only entries from client IPs starting with one of the prefixes, such as `10.0.` or `1.2.3.4`
+    pub fn with_ips(mut self, prefixes: Vec<String>) -> Self {
+        self.ips = prefixes;
+        self
+    }
+
+    /// Leaves out entries from client IPs starting with one of the prefixes
+    pub fn with_excluded_ips(mut self, prefixes: Vec<String>) -> Self {
+        self.excluded_ips = prefixes;
+        self
+    }
+
+    /// Keeps only entries with a user agent containing one of the texts, ignoring case
+    pub fn with_user_agents(mut self, parts: Vec<String>) -> Self {
+        self.user_agents = parts.iter().map(|part| part.to_lowercase()).collect();
+        self
+    }
+
+    /// Keeps only entries with JSON matching one of the patterns
+    pub fn with_patterns(mut self, patterns: Vec<Regex>) -> Self {
+        self.patterns = patterns;
+        self
+    }
+
+    /// Leaves out entries with JSON matching one of the patterns
+    pub fn with_excluded_patterns(mut self, patterns: Vec<Regex>) -> Self {
+        self.excluded_patterns = patterns;
+        self
+    }
+
+    /// Leaves out entries logged after the time
+    pub fn with_until(mut self, until: Option<DateTime<Utc>>) -> Self {
+        self.until = until;
+        self
+    }
+
+    /// Whether the entry meets every condition, entries without the field a condition checks don't meet it
+    pub fn matches(&self, log: &AlgoliaLogEntry) -> bool {
+        if self.only_writes && !log.is_write() {
+            return false;
+        }
+
+        if self.until.is_some_and(|until| log.timestamp > until) {
+            return false;
+        }
+
+        let ip = log.ip.as_deref();
+        let ip_matches = |prefixes: &[String]| {
+            ip.is_some_and(|ip| prefixes.iter().any(|prefix| ip.starts_with(prefix.as_str())))
+        };
+        if (!self.ips.is_empty() && !ip_matches(&self.ips)) || ip_matches(&self.excluded_ips) {
+            return false;
+        }
+
+        if !self.user_agents.is_empty() {
+            let user_agent = user_agent(log).map(str::to_lowercase);
+            let matched = user_agent.is_some_and(|user_agent| {
+                self.user_agents.iter().any(|part| user_agent.contains(part.as_str()))
+            });
+            if !matched {
+                return false;
+            }
+        }
+
+        let code = log.status_code();
+        let code_matches = match &self.answer_codes {
+            AnswerCodes::Any => true,
+            AnswerCodes::AtLeast(min) => code.is_some_and(|code| code >= *min),
+            AnswerCodes::OneOf(codes) => code.is_some_and(|code| codes.contains(&code)),
+        };
+        if !code_matches {
+            return false;
+        }
+
+        // Serializing is the most expensive check, so it runs last and only when there is a pattern
+        if self.patterns.is_empty() && self.excluded_patterns.is_empty() {
+            return true;
+        }
+        let json = log.to_json();
+        (self.patterns.is_empty() || self.patterns.iter().any(|pattern| pattern.is_match(&json)))
+            && !self.excluded_patterns.iter().any(|pattern| pattern.is_match(&json))
+    }
+}
+
+/// User agent from the request headers, which Algolia logs as a single string with one header per line
+fn user_agent(log: &AlgoliaLogEntry) -> Option<&str> {
+    log.query_headers.as_deref()?.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim().eq_ignore_ascii_case("user-agent").then_some(value.trim())
+    })
+}
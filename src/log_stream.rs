@@ -0,0 +1,264 @@
+use crate::algolia::{AlgoliaClient, AlgoliaLogEntry, LogType};
+use crate::error::MonitorError;
+use crate::source::LogSource;
+use chrono::{DateTime, Utc};
+use futures::stream::{self, Stream};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{sleep_until, Instant};
+
+/// Interval between the fetches of a stream unless set with [`LogStream::with_interval`]
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// New entries of the logs of an index, each fetch going on from where the previous one left off
+///
+/// The entries seen by a fetch are left out of the next ones, even those logged in the same millisecond that
+/// the next fetch returns again. [`LogStream::into_stream`] turns it into a [`Stream`] of the entries, while
+/// [`LogStream::next_batch`] hands out the ones of a single fetch.
+pub struct LogStream<S: ?Sized = dyn LogSource> {
+    source: Arc<S>,
+    index_name: String,
+    cursor: LogCursor,
+    interval: Duration,
+    page_size: Option<u32>,
+    fetched_at: Option<Instant>,
+}
+
+impl<S: LogSource + ?Sized> LogStream<S> {
+    /// Stream of the entries of the type, starting with the latest page of them
+    pub fn new(source: Arc<S>, index_name: impl Into<String>, log_type: LogType) -> Self {
+        Self::of(source, index_name, Some(log_type))
+    }
+
+    /// Stream of the entries of the type, or of the one the source is set up for without it
+    pub(crate) fn of(source: Arc<S>, index_name: impl Into<String>, log_type: Option<LogType>) -> Self {
+        LogStream {
+            source,
+            index_name: index_name.into(),
+            cursor: LogCursor::new(log_type, None),
+            interval: DEFAULT_INTERVAL,
+            page_size: None,
+            fetched_at: None,
+        }
+    }
+
+    /// Time from the start of a fetch to the start of the next one, the first fetch not waiting
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Entries requested at a time, instead of the length the source is set up with
+    pub fn with_page_size(mut self, page_size: u32) -> Self {
+        self.page_size = Some(page_size.max(1));
+        self
+    }
+
+    /// Hands out the entries from the time on, instead of starting with the latest page of them
+    pub fn with_since(mut self, since: Option<DateTime<Utc>>) -> Self {
+        self.cursor.timestamp = since;
+        self
+    }
+
+    /// Type of the fetched entries, `None` for the one the source is set up for
+    pub fn log_type(&self) -> Option<LogType> {
+        self.cursor.log_type
+    }
+
+    /// Timestamp of the newest entry handed out, or the one set with [`LogStream::with_since`]
+    pub fn since(&self) -> Option<DateTime<Utc>> {
+        self.cursor.timestamp
+    }
+
+    /// Waits for the interval from the previous fetch and fetches the entries that weren't seen, newest first
+    ///
+    /// Dropping the future before it is done leaves the position in the logs where it was, so the entries of
+    /// an interrupted fetch come with the next one.
+    pub async fn next_batch(&mut self) -> Result<Vec<AlgoliaLogEntry>, MonitorError> {
+        if let Some(fetched_at) = self.fetched_at {
+            sleep_until(fetched_at + self.interval).await;
+        }
+        self.fetched_at = Some(Instant::now());
+
+        let since = self.cursor.timestamp;
+        let logs = match (self.cursor.log_type, self.page_size) {
+            (Some(log_type), Some(page_size)) => {
+                self.source
+                    .logs_paged(&self.index_name, log_type, since, page_size)
+                    .await?
+            }
+            (Some(log_type), None) => self.source.logs_of_type(&self.index_name, log_type, since).await?,
+            (None, _) => self.source.logs(&self.index_name, since).await?,
+        };
+
+        Ok(self.cursor.advance(logs))
+    }
+}
+
+impl<S: LogSource + ?Sized + 'static> LogStream<S> {
+    /// Stream of the entries oldest first, along with the error of each failed fetch
+    ///
+    /// The next fetch only starts once the entries of the previous one are taken, so a slow consumer holds
+    /// back the fetches instead of having the entries pile up. Nothing runs apart from the stream, dropping it
+    /// stops the fetches, and a failed fetch is tried again after the interval from the same position.
+    pub fn into_stream(self) -> impl Stream<Item = Result<AlgoliaLogEntry, MonitorError>> + Send {
+        stream::unfold((self, VecDeque::new()), |(mut logs, mut fetched)| async move {
+            loop {
+                if let Some(log) = fetched.pop_back() {
+                    return Some((Ok(log), (logs, fetched)));
+                }
+
+                match logs.next_batch().await {
+                    Ok(batch) => fetched = VecDeque::from(batch),
+                    Err(error) => return Some((Err(error), (logs, fetched))),
+                }
+            }
+        })
+    }
+}
+
+impl AlgoliaClient {
+    /// Stream of the new entries of the type in the logs of the index, see [`LogStream`]
+    pub fn log_stream(self: &Arc<Self>, index_name: impl Into<String>, log_type: LogType) -> LogStream<Self> {
+        LogStream::new(self.clone(), index_name, log_type)
+    }
+}
+
+/// Position in the logs of one type, the newest timestamp seen along with the entries logged at it
+pub(crate) struct LogCursor {
+    pub(crate) log_type: Option<LogType>,
+    pub(crate) timestamp: Option<DateTime<Utc>>,
+    /// Identifiers of the seen entries logged at `timestamp`, which the next fetch returns again
+    ids: HashSet<u64>,
+}
+
+impl LogCursor {
+    pub(crate) fn new(log_type: Option<LogType>, timestamp: Option<DateTime<Utc>>) -> Self {
+        LogCursor {
+            log_type,
+            timestamp,
+            ids: HashSet::new(),
+        }
+    }
+
+    /// Leaves out the fetched entries that were seen before and moves past the rest
+    pub(crate) fn advance(&mut self, logs: Vec<AlgoliaLogEntry>) -> Vec<AlgoliaLogEntry> {
+        let logs: Vec<_> = logs
+            .into_iter()
+            .filter(|log| Some(log.timestamp) != self.timestamp || !self.ids.contains(&log_id(log)))
+            .collect();
+
+        if let Some(newest) = logs.iter().map(|log| log.timestamp).max() {
+            if self.timestamp != Some(newest) {
+                self.timestamp = Some(newest);
+                self.ids.clear();
+            }
+            self.ids.extend(logs.iter().filter(|log| log.timestamp == newest).map(log_id));
+        }
+
+        logs
+    }
+}
+
+/// Identifier of a log entry, its `sha1` when Algolia provides one or a hash of the whole entry
+fn log_id(log: &AlgoliaLogEntry) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    match log.extra.get("sha1").and_then(|sha1| sha1.as_str()) {
+        Some(sha1) => sha1.hash(&mut hasher),
+        None => log.to_json().hash(&mut hasher),
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn entry(timestamp: &str, sha1: Option<&str>, url: &str) -> AlgoliaLogEntry {
+        let mut entry = json!({ "timestamp": timestamp, "method": "POST", "url": url, "answer_code": "200" });
+        if let Some(sha1) = sha1 {
+            entry["sha1"] = Value::from(sha1);
+        }
+        serde_json::from_value(entry).unwrap()
+    }
+
+    fn urls(logs: &[AlgoliaLogEntry]) -> Vec<&str> {
+        logs.iter().map(|log| log.url.as_deref().unwrap()).collect()
+    }
+
+    #[test]
+    fn entries_of_the_same_timestamp_are_only_returned_once_across_fetches() {
+        let mut cursor = LogCursor::new(Some(LogType::Build), None);
+        let first = vec![
+            entry("2024-05-02T10:14:03Z", Some("a"), "/1/indexes/products/batch?a"),
+            entry("2024-05-02T10:14:03Z", Some("b"), "/1/indexes/products/batch?b"),
+            entry("2024-05-02T10:14:02Z", Some("c"), "/1/indexes/products/batch?c"),
+        ];
+        assert_eq!(urls(&cursor.advance(first)).len(), 3);
+
+        // The next fetch starts at the newest timestamp, with an entry logged in the same millisecond since
+        let second = vec![
+            entry("2024-05-02T10:14:03Z", Some("d"), "/1/indexes/products/batch?d"),
+            entry("2024-05-02T10:14:03Z", Some("a"), "/1/indexes/products/batch?a"),
+            entry("2024-05-02T10:14:03Z", Some("b"), "/1/indexes/products/batch?b"),
+        ];
+
+        assert_eq!(urls(&cursor.advance(second)), ["/1/indexes/products/batch?d"]);
+        assert_eq!(cursor.timestamp, Some("2024-05-02T10:14:03Z".parse().unwrap()));
+        assert_eq!(cursor.ids.len(), 3);
+    }
+
+    #[test]
+    fn entries_without_a_sha1_are_told_apart_by_their_content() {
+        let mut cursor = LogCursor::new(None, None);
+        let first = vec![
+            entry("2024-05-02T10:14:03Z", None, "/1/indexes/products/batch?a"),
+            entry("2024-05-02T10:14:03Z", Some("b"), "/1/indexes/products/batch?b"),
+        ];
+        cursor.advance(first);
+
+        let second = vec![
+            entry("2024-05-02T10:14:03Z", None, "/1/indexes/products/batch?a"),
+            entry("2024-05-02T10:14:03Z", None, "/1/indexes/products/batch?c"),
+            // Same sha1 as a seen entry, whatever the rest of it
+            entry("2024-05-02T10:14:03Z", Some("b"), "/1/indexes/products/clear"),
+        ];
+
+        assert_eq!(urls(&cursor.advance(second)), ["/1/indexes/products/batch?c"]);
+    }
+
+    #[test]
+    fn newer_timestamp_forgets_the_entries_of_the_previous_one() {
+        let mut cursor = LogCursor::new(None, None);
+        cursor.advance(vec![
+            entry("2024-05-02T10:14:03Z", Some("a"), "/1/indexes/products/batch?a"),
+            entry("2024-05-02T10:14:03Z", Some("b"), "/1/indexes/products/batch?b"),
+        ]);
+        assert_eq!(cursor.ids.len(), 2);
+
+        let second = vec![
+            entry("2024-05-02T10:14:05Z", Some("c"), "/1/indexes/products/batch?c"),
+            entry("2024-05-02T10:14:03Z", Some("b"), "/1/indexes/products/batch?b"),
+        ];
+
+        assert_eq!(urls(&cursor.advance(second)), ["/1/indexes/products/batch?c"]);
+        assert_eq!(cursor.timestamp, Some("2024-05-02T10:14:05Z".parse().unwrap()));
+        assert_eq!(cursor.ids, HashSet::from([log_id(&entry("2024-05-02T10:14:05Z", Some("c"), "/"))]));
+    }
+
+    #[test]
+    fn fetch_without_entries_keeps_the_position() {
+        let mut cursor = LogCursor::new(None, None);
+        cursor.advance(vec![entry("2024-05-02T10:14:03Z", Some("a"), "/1/indexes/products/batch?a")]);
+
+        assert!(cursor.advance(vec![]).is_empty());
+
+        assert_eq!(cursor.timestamp, Some("2024-05-02T10:14:03Z".parse().unwrap()));
+        let again = entry("2024-05-02T10:14:03Z", Some("a"), "/1/indexes/products/batch?a");
+        assert!(cursor.advance(vec![again]).is_empty());
+    }
+}
@@ -1,188 +1,3531 @@
-use clap::{arg, Parser};
-use serde_json::{to_string, Value};
-use std::time::Duration;
-use reqwest::Error;
-use tokio::time::sleep;
+mod diagnostics;
+mod expected_command;
+mod export;
+mod settings_file;
+mod status;
+mod tail;
+mod telemetry;
+mod validation;
+
+#[cfg(feature = "cloudwatch")]
+use algolia_monitor::metrics::CloudWatchMetrics;
+use algolia_monitor::metrics::{Metrics, OtlpMetrics, PrometheusMetrics, Pushgateway, StatsdMetrics};
+use algolia_monitor::notify::{Commands, Heartbeat, Notifier, PagerDuty, Slack, Telegram, Webhook, WebhookHeader};
+#[cfg(feature = "kafka")]
+use algolia_monitor::output::KafkaTarget;
+use algolia_monitor::output::{
+    ColorMode, ElasticsearchAuth, ElasticsearchTarget, HistoryReader, LokiTarget, MqttTarget, Output,
+    OutputOptions, OutputTarget,
+};
+#[cfg(feature = "sentry")]
+use algolia_monitor::reporting;
+use algolia_monitor::{
+    now, parse_delay, parse_duration, print_summaries, report_destructive, AlertOptions, AlgoliaClient, ApiKey,
+    Baseline, BaselineMode, BrowseCount, CompareTarget, Config, Control, CountDelta, CountSource,
+    DataSource, DigestSink, EventSinks, ExpectedRecords, Failures, IndexAlerts, IndexItems, Jitter, LogFilter,
+    LogType, Monitor, MonitorError, MonitorEvent, MonitoredIndex, PollDelay, ProxyConfig, QuietHours, Recorder,
+    Reload, Replay, RequestPacer, RunEnd, SampleStrategy, Subset, Summary, Target, TargetDefaults, Thresholds,
+    ThresholdBreach, TlsConfig,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use clap::parser::ValueSource;
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use clap::{ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
+use clap_complete::Shell;
+use expected_command::ExpectedCommand;
+use export::ExportFormat;
+use regex::{Regex, RegexBuilder};
+use settings_file::SettingsFile;
+use status::{StatusApi, StatusReport};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::fs::File;
+use std::io::{BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use telemetry::{OtlpProtocol, Telemetry};
+use tokio::sync::{mpsc, watch, Semaphore};
+use tokio::task::JoinSet;
+use tokio::time::sleep_until;
+use tracing::{debug, error, info, warn};
+use validation::Credentials;
 
 /// Algolia index size monitor
-#[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+///
+/// Deliberately not `Debug`, so the API key can't end up in debug output.
+#[derive(Parser)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Application ID
+    #[arg(required_unless_present_any = ["config", "generate_man"])]
+    app_id: Option<String>,
+
+    /// Algolia API key, left out when it comes from --key-file, --key-stdin or ALGOLIA_API_KEY
+    key: Option<String>,
+
+    /// Names of the indices to monitor, separated by spaces or commas
+    #[arg(value_delimiter = ',')]
+    index_name: Vec<String>,
+
+    /// Read the API key from the first line of a file
+    #[arg(long, conflicts_with = "key_stdin")]
+    key_file: Option<PathBuf>,
+
+    /// Read the API key from the first line of standard input
+    #[arg(long)]
+    key_stdin: bool,
+
+    /// TOML file with defaults for the options below and `[[index]]` tables of indices to monitor,
+    /// options given on the command line take precedence, read again on SIGHUP
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    #[arg(short, long, default_value = "false")]
+    all_logs: bool,
+
+    /// Check the records count once and exit with 0 when within the delta, 1 when breached
+    /// and 2 on Algolia API errors
+    #[arg(long)]
+    once: bool,
+
+    /// Show a dashboard with a chart of the records counts, the delta from the expected count and the latest
+    /// log entries instead of printing them, `r` polling right away, `/` filtering the log entries, `p`
+    /// pausing them and the arrows switching indices
+    #[arg(long, conflicts_with_all = ["once", "until"])]
+    tui: bool,
+
+    /// Print log entries from this time on, an RFC 3339 timestamp or a duration ago such as `2h` or `45m`
+    #[arg(long, value_parser = parse_time)]
+    since: Option<DateTime<Utc>>,
+
+    /// Print log entries up to this time, given like --since; the monitor exits once it has passed,
+    /// right after printing the entries when it is in the past
+    #[arg(long, value_parser = parse_time)]
+    until: Option<DateTime<Utc>>,
+
+    /// Stop monitoring after this long, such as `20m`, counting the time of the requests, and exit with 0
+    /// when no breach is ongoing and 1 otherwise; --once checks a single time instead
+    #[arg(long, value_parser = parse_duration, conflicts_with = "once")]
+    max_duration: Option<Duration>,
+
+    /// Stretch the delays between the polls once the Algolia API calls of the monitor would go over this many a
+    /// day, counting every request to Algolia, retries included, and going by the calls of the latest hour
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    max_daily_operations: Option<u64>,
+
+    /// Send one digest of each index per interval, such as `1h`, in place of the other alerts: the records
+    /// counts at the start and the end, their range, the write operations in the fetched logs and the
+    /// breaches with their durations; an index left with no records, deleted or cleared sends it right away
+    #[arg(long, value_parser = parse_duration, conflicts_with = "once")]
+    digest: Option<Duration>,
+
+    /// Stop monitoring each index after this many polls and exit like --max-duration, --once being a single
+    /// check
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..), conflicts_with = "once")]
+    max_iterations: Option<u64>,
+
+    /// Write every response the monitors get from Algolia to this file as JSON lines, for replaying them with
+    /// --replay
+    #[arg(long, value_name = "FILE", conflicts_with = "once")]
+    record: Option<PathBuf>,
+
+    /// Feed the monitors the responses of a file written by --record instead of calling Algolia, each index
+    /// stopping once its responses run out with the exit status of --max-duration; the indices are given like
+    /// when recording, the API key being left unused
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["once", "until", "record"])]
+    replay: Option<PathBuf>,
+
+    /// How many times faster than the delays the replay polls, such as `10x`
+    #[arg(long, value_parser = parse_replay_speed, default_value = "1x")]
+    replay_speed: f64,
+
+    /// Print the latest log entries when the single check breaches the delta
+    #[arg(long, requires = "once")]
+    with_logs: bool,
+
+    /// Only print log entries of operations that change records: batch, object, deleteBy and clear calls
+    #[arg(long)]
+    only_writes: bool,
+
+    /// Only print log entries Algolia answered with this status code or a higher one, such as 400 for failures
+    #[arg(long, conflicts_with = "answer_code")]
+    min_answer_code: Option<u16>,
+
+    /// Only print log entries Algolia answered with one of these status codes, such as 400,422,429
+    #[arg(long, value_delimiter = ',')]
+    answer_code: Vec<u16>,
+
+    /// Only print log entries from client IPs starting with this prefix, such as 10.0. [repeatable]
+    #[arg(long)]
+    ip: Vec<String>,
+
+    /// Leave out log entries from client IPs starting with this prefix, such as a known importer [repeatable]
+    #[arg(long)]
+    exclude_ip: Vec<String>,
+
+    /// Only print log entries with a user agent containing this text, ignoring case [repeatable]
+    #[arg(long)]
+    user_agent_contains: Vec<String>,
+
+    /// Only print log entries with JSON matching this regular expression [repeatable]
+    #[arg(long, value_parser = parse_regex)]
+    grep: Vec<Regex>,
+
+    /// Leave out log entries with JSON matching this regular expression [repeatable]
+    #[arg(long, value_parser = parse_regex)]
+    grep_exclude: Vec<Regex>,
+
+    /// Match --grep and --grep-exclude ignoring case
+    #[arg(long)]
+    grep_ignore_case: bool,
+
+    /// Tail the query, build and error logs in one stream ordered by timestamp, with the type of every entry
+    #[arg(long, conflicts_with_all = ["once", "log_type"])]
+    follow: bool,
+
+    /// Print only every Nth entry of the query logs while following
+    #[arg(long, default_value = "1", value_parser = clap::value_parser!(u32).range(1..), requires = "follow")]
+    sample: u32,
+
+    /// Type of the fetched log entries
+    #[arg(long, default_value = "build", value_parser = log_types())]
+    log_type: LogType,
+
+    /// Number of newest log entries to skip
+    #[arg(long, default_value = "0")]
+    log_offset: u32,
+
+    /// Number of log entries to fetch per poll, at most 1000
+    #[arg(long, default_value = "1000", value_parser = clap::value_parser!(u32).range(1..=1000))]
+    log_length: u32,
+
+    /// Most pages of log entries fetched per poll to catch up with the last seen entry
+    #[arg(long, default_value = "10", value_parser = clap::value_parser!(u32).range(1..))]
+    max_log_pages: u32,
+
+    /// Where the events go, each given once: the log entries on stdout as `json` or `pretty` (`text`), or
+    /// every event as a line of `json-file=<PATH>` or `text-file=<PATH>` or POSTed to `webhook=<URL>`; stdout
+    /// is left out unless given
+    #[arg(long, default_value = "json")]
+    output: Vec<OutputTarget>,
+
+    /// Turn an --output off after it failed this many times in a row, kept on by default
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    output_max_errors: Option<u32>,
+
+    /// When to color the output
+    #[arg(long, value_enum, default_value = "auto")]
+    color: ColorMode,
+
+    /// Latest records counts of each index shown as a sparkline after its count in text output, 0 leaving it
+    /// out
+    #[arg(long, value_name = "POLLS", default_value_t = 30)]
+    sparkline_window: usize,
+
+    /// Draw the sparklines with ASCII characters, for terminals without unicode
+    #[arg(long)]
+    ascii: bool,
+
+    /// Also append the printed log entries to this file
+    #[arg(long)]
+    log_file: Option<PathBuf>,
+
+    /// Size at which the log file is rotated, in bytes or with a K, M or G suffix
+    #[arg(long, default_value = "10M", value_parser = parse_size)]
+    log_file_max_size: u64,
+
+    /// Number of rotated log files to keep
+    #[arg(long, default_value = "5")]
+    log_file_keep: usize,
+
+    /// Grafana Loki to push the fetched log entries to, labeled with the job `algolia-monitor`, the index
+    /// and the log type, such as `http://loki:3100`
+    #[arg(long)]
+    loki_url: Option<String>,
+
+    /// User name for the basic authentication of --loki-url, which may also come with the URL
+    #[arg(long, requires = "loki_url")]
+    loki_user: Option<String>,
+
+    /// Password for the basic authentication of --loki-url
+    #[arg(long, requires = "loki_user")]
+    loki_password: Option<String>,
+
+    /// Tenant of a multi-tenant Loki, sent as the X-Scope-OrgID header
+    #[arg(long, requires = "loki_url")]
+    loki_tenant: Option<String>,
+
+    /// Elasticsearch or OpenSearch cluster to index the fetched log entries in with the bulk API, such as
+    /// `https://elasticsearch:9200`
+    #[arg(long)]
+    elasticsearch_url: Option<String>,
+
+    /// Prefix of the indices of --elasticsearch-url, followed by the month of each entry
+    #[arg(long, default_value = "algolia-logs", requires = "elasticsearch_url")]
+    elasticsearch_index: String,
+
+    /// User name for the basic authentication of --elasticsearch-url, which may also come with the URL
+    #[arg(long, requires = "elasticsearch_url", conflicts_with = "elasticsearch_api_key")]
+    elasticsearch_user: Option<String>,
+
+    /// Password for the basic authentication of --elasticsearch-url
+    #[arg(long, requires = "elasticsearch_user")]
+    elasticsearch_password: Option<String>,
+
+    /// Encoded API key for --elasticsearch-url, sent as `Authorization: ApiKey <key>`
+    #[arg(long, requires = "elasticsearch_url")]
+    elasticsearch_api_key: Option<String>,
+
+    /// Number of log entries indexed with a single bulk request
+    #[arg(long, default_value = "500", value_parser = clap::value_parser!(u32).range(1..))]
+    elasticsearch_batch_size: u32,
+
+    /// Time between bulk requests, unless a batch fills up before
+    #[arg(long, default_value = "5s", value_parser = parse_duration)]
+    elasticsearch_flush_interval: Duration,
+
+    /// Kafka brokers to publish the records counts, events and log entries to as JSON messages keyed by the
+    /// index name, such as `kafka-1:9092,kafka-2:9092`
+    #[cfg(feature = "kafka")]
+    #[arg(long)]
+    kafka_brokers: Option<String>,
+
+    /// Topic of the messages published to --kafka-brokers
+    #[cfg(feature = "kafka")]
+    #[arg(long, default_value = "algolia-monitor", requires = "kafka_brokers")]
+    kafka_topic: String,
+
+    /// librdkafka setting of the producer as KEY=VALUE, such as `security.protocol=SASL_SSL` or
+    /// `sasl.username=monitor`, can be repeated
+    #[cfg(feature = "kafka")]
+    #[arg(long, value_name = "KEY=VALUE", value_parser = parse_kafka_config, requires = "kafka_brokers")]
+    kafka_config: Vec<(String, String)>,
+
+    /// MQTT broker to publish the records count of each poll to, retained on `<prefix>/<index>/records`, and
+    /// the breaches and recoveries on `<prefix>/<index>/events`, such as `mqtt://broker:1883` or
+    /// `mqtts://broker:8883` for TLS
+    #[arg(long)]
+    mqtt_url: Option<String>,
+
+    /// Prefix of the topics published to --mqtt-url
+    #[arg(long, default_value = "algolia-monitor", requires = "mqtt_url")]
+    mqtt_topic_prefix: String,
+
+    /// User name for --mqtt-url, which may also come with the URL
+    #[arg(long, requires = "mqtt_url")]
+    mqtt_user: Option<String>,
+
+    /// Password for --mqtt-user
+    #[arg(long, requires = "mqtt_user")]
+    mqtt_password: Option<String>,
+
+    /// SQLite database recording the records count of every poll and the breaches, recoveries, errors and
+    /// destructive operations, created when missing
+    #[arg(long)]
+    history_db: Option<PathBuf>,
+
+    /// Expected records count for every index or per index as `index=count`,
+    /// detected at startup when omitted or 0
+    #[arg(short, long, value_delimiter = ',')]
+    expected_records: Vec<ExpectedRecords>,
+
+    /// Expected records count strategy: `fixed` count or the moving average of the last N polls
+    /// within threshold as `rolling:<N>`, which doesn't alert before N polls unless the count hits 0
+    #[arg(long, default_value = "fixed")]
+    baseline: BaselineMode,
+
+    /// Interval after which a records count within threshold becomes the new expected count,
+    /// such as `24h`; applies to explicit --expected-records as well
+    #[arg(long, value_parser = parse_duration)]
+    baseline_refresh: Option<Duration>,
+
+    /// Shell command printing the expected records count, such as a count of the products in the database, run
+    /// before every poll or every --expected-refresh, a failure keeping the previous count; ALGOLIA_APP_ID,
+    /// ALGOLIA_INDEX and ALGOLIA_SUBSET tell which index it is for
+    #[arg(long, conflicts_with_all = ["baseline", "baseline_refresh"])]
+    expected_cmd: Option<String>,
+
+    /// Interval between the runs of --expected-cmd, such as `10m`
+    #[arg(long, value_parser = parse_duration, requires = "expected_cmd")]
+    expected_refresh: Option<Duration>,
+
+    /// Interval between polls, such as `90s` or `5m`; bare numbers are seconds
+    #[arg(short, long, default_value = "30s", value_parser = parse_delay)]
+    delay: Duration,
+
+    /// Randomizes each delay by up to a percentage of it or a duration either way, such as `10%` or `3s`, so
+    /// monitors started together don't poll at the same time
+    #[arg(long)]
+    jitter: Option<Jitter>,
+
+    /// Backs the delay off while the records count stays the same and within the thresholds, going back to
+    /// --delay as soon as it changes or a poll fails
+    #[arg(long)]
+    adaptive_delay: bool,
+
+    /// Longest delay --adaptive-delay backs off to
+    #[arg(long, default_value = "5m", value_parser = parse_delay, requires = "adaptive_delay")]
+    max_delay: Duration,
+
+    /// Records count difference to alert on, a drop when negative and growth when positive,
+    /// kept for compatibility with --min-delta and --max-delta
+    /// [default: -1000 unless another threshold is given]
+    #[arg(long, allow_negative_numbers = true, conflicts_with_all = ["min_delta", "max_delta"])]
+    delta: Option<i64>,
+
+    /// Lowest allowed records count difference, such as -500 to alert when losing more than 500 records
+    #[arg(long, allow_negative_numbers = true, value_parser = parse_min_delta)]
+    min_delta: Option<i64>,
+
+    /// Highest allowed records count difference, such as 10000 to alert when gaining more than 10000 records
+    #[arg(long, value_parser = parse_max_delta)]
+    max_delta: Option<i64>,
+
+    /// Records count difference in percent of the expected count to alert on, in either direction
+    #[arg(long, value_parser = parse_percent)]
+    delta_percent: Option<f64>,
+
+    /// Source of the records count: `query` counts the hits of an empty query, `list-indexes` reads
+    /// the entries from the list of indices without a search operation and also reports the data size
+    #[arg(long, default_value = "query", value_parser = count_sources())]
+    count_source: CountSource,
+
+    /// Count the records exactly by browsing the index when a breach starts, telling whether `nbHits` was off;
+    /// needs the `browse` ACL
+    #[arg(long)]
+    verify_with_browse: bool,
+
+    /// Most records counted by a browse, which costs a request for every 1000 of them
+    #[arg(long, default_value = "1000000")]
+    browse_max_records: u64,
+
+    /// Pages of 1000 records between the progress lines of a browse
+    #[arg(long, default_value = "10", value_parser = clap::value_parser!(u64).range(1..))]
+    browse_progress: u64,
+
+    /// Sample this many objectIDs on every poll and alert when more than --max-churn-percent of the previous
+    /// sample disappeared, such as records replaced by different ones while the count stays the same
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..=1000))]
+    sample_object_ids: Option<u64>,
+
+    /// How the objectIDs are sampled: `search` takes the first hits of an empty query, `browse` the first page
+    /// of the index, which needs the `browse` ACL and costs no search operation
+    #[arg(long, default_value = "search", value_parser = sample_strategies(), requires = "sample_object_ids")]
+    sample_strategy: SampleStrategy,
+
+    /// Largest share of the objectID sample allowed to disappear between two polls
+    #[arg(long, default_value = "10%", value_parser = parse_percent, requires = "sample_object_ids")]
+    max_churn_percent: f64,
+
+    /// Largest allowed index data size, in bytes or with a K, M or G suffix; needs --count-source list-indexes
+    #[arg(long, value_parser = parse_size)]
+    max_data_size: Option<u64>,
+
+    /// Number of latest search processing times the latency percentiles are taken from
+    #[arg(long, default_value = "100", value_parser = clap::value_parser!(u64).range(1..))]
+    latency_window: u64,
+
+    /// Highest allowed 95th percentile of the search processing times, in milliseconds
+    #[arg(long)]
+    max_p95_ms: Option<u64>,
+
+    /// Number of polls the rate of change of the records count is taken over, one rate between each two
+    #[arg(long, default_value = "5", value_parser = clap::value_parser!(u64).range(1..))]
+    rate_window: u64,
+
+    /// Highest allowed loss of records per minute, alerting when it is faster between each poll of
+    /// --rate-window
+    #[arg(long)]
+    max_loss_rate: Option<f64>,
+
+    /// Facet attribute with the records of each value counted on every poll, alerting when a value drops
+    /// by more than --facet-delta or disappears, can be repeated
+    #[arg(long = "facet", value_name = "ATTRIBUTE")]
+    facets: Vec<String>,
+
+    /// Largest allowed drop of the records count of a facet value, such as `500` or `10%`
+    #[arg(long, default_value = "10%")]
+    facet_delta: CountDelta,
+
+    /// Subset of the records to watch with its own baseline, given as `NAME=FILTERS` with Algolia filters
+    /// such as `store_1=visibility:search AND store_id:1`, can be repeated
+    #[arg(long = "subset", value_name = "NAME=FILTERS")]
+    subsets: Vec<Subset>,
+
+    /// Replica of the index to compare its records count to on every poll, can be repeated;
+    /// needs a single index
+    #[arg(long = "replica", value_name = "NAME", conflicts_with = "once")]
+    replicas: Vec<String>,
+
+    /// Largest allowed difference of a replica's records count from the index's, such as `500` or `1%`
+    #[arg(long, default_value = "0")]
+    replica_delta: CountDelta,
+
+    /// Index in another application to compare the records count to on every poll, such as the staging
+    /// index before a go-live; needs a single index
+    #[arg(
+        long,
+        value_name = "APP_ID:KEY:INDEX_NAME",
+        conflicts_with = "once",
+        value_parser = CompareTargetParser
+    )]
+    compare: Option<CompareTarget>,
+
+    /// Largest allowed difference of the compared index's records count from the index's,
+    /// such as `500` or `1%`
+    #[arg(long, default_value = "0")]
+    compare_delta: CountDelta,
+
+    /// Resets the expected records count to the count of a recreated index, instead of comparing it
+    /// to the count of the deleted one
+    #[arg(long)]
+    rebaseline_on_recreate: bool,
+
+    /// Treats a records count of 0 like any other count, instead of alerting right away whatever the
+    /// thresholds and the alert cooldown
+    #[arg(long)]
+    allow_empty: bool,
+
+    /// Number of failed polls in a row after which the monitor gives up
+    #[arg(long, default_value = "10")]
+    max_consecutive_errors: u32,
+
+    /// Number of times a failed Algolia request is retried before giving up on the poll
+    #[arg(long, default_value = "3")]
+    retry_attempts: u32,
+
+    /// Base delay in milliseconds for exponential backoff between retries
+    #[arg(long, default_value = "200")]
+    retry_base_ms: u64,
+
+    /// Total time a request to Algolia may take, such as `10s`
+    #[arg(long, default_value = "10s", value_parser = parse_duration)]
+    timeout: Duration,
+
+    /// Time to wait for the connection to an Algolia host before moving on to the next one
+    #[arg(long, default_value = "5s", value_parser = parse_duration)]
+    connect_timeout: Duration,
+
+    /// Most Algolia requests in flight at once across all the indices and applications, the others waiting
+    /// for their turn
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    max_concurrent_requests: Option<u32>,
+
+    /// Most Algolia requests sent every second to each application, such as `5` or `0.5`, a poll the pacing
+    /// would hold past the next one being skipped
+    #[arg(long, value_parser = parse_requests_per_second)]
+    max_requests_per_second: Option<f64>,
+
+    /// Proxy to send the Algolia requests through instead of the one of the HTTPS_PROXY and HTTP_PROXY
+    /// environment variables, such as `http://proxy.internal:3128`, for the hosts NO_PROXY leaves in
+    #[arg(long, env = "ALGOLIA_MONITOR_PROXY", value_parser = parse_proxy, conflicts_with = "no_proxy")]
+    proxy: Option<String>,
+
+    /// User and password to authenticate to the --proxy with, given as `USER:PASSWORD`
+    #[arg(long, env = "ALGOLIA_MONITOR_PROXY_AUTH", value_parser = parse_proxy_auth, requires = "proxy")]
+    proxy_auth: Option<(String, ApiKey)>,
+
+    /// Send the Algolia requests straight to the hosts, ignoring the proxy of the environment variables, such
+    /// as to tell whether the proxy is the one failing
+    #[arg(long)]
+    no_proxy: bool,
+
+    /// PEM file of a certificate authority to trust on top of the ones of the system, such as the one of a
+    /// proxy intercepting TLS, for the requests to Algolia, the notifications, Loki, Elasticsearch and the
+    /// Pushgateway; may be repeated
+    #[arg(long)]
+    ca_cert: Vec<PathBuf>,
+
+    /// Accept any TLS certificate, leaving the requests open to interception; only a last resort for lab
+    /// environments
+    #[arg(long)]
+    tls_insecure: bool,
+
+    /// Algolia hosts to query in order of preference, instead of the DSN host and its fallbacks
+    #[arg(long, value_delimiter = ',')]
+    hosts: Vec<String>,
+
+    /// API base URLs in order of preference, such as `https://my-cluster.algolia.net/1/` for dedicated clusters,
+    /// replacing the hosts derived from the application ID
+    #[arg(long, value_delimiter = ',', value_parser = parse_base_url, conflicts_with = "hosts")]
+    base_url: Vec<String>,
+
+    /// Start without checking that the API key has the needed ACLs and that the indices exist, such as for
+    /// secured API keys that can't read their own permissions
+    #[arg(long)]
+    no_validate: bool,
+
+    /// URL receiving a JSON POST request whenever the records count breaches the delta
+    #[arg(long)]
+    webhook_url: Option<String>,
+
+    /// Header sent with webhook requests as `Name: value`, can be repeated
+    #[arg(long)]
+    webhook_header: Vec<WebhookHeader>,
+
+    /// Slack incoming webhook URL notified about breaches and recoveries
+    #[arg(long)]
+    slack_webhook: Option<String>,
+
+    /// Maximum number of write operation log lines included in a Slack or Telegram message
+    #[arg(long, default_value = "5")]
+    slack_log_lines: usize,
+
+    /// Token of the Telegram bot sending breaches, recoveries and destructive operations to --telegram-chat-id
+    #[arg(long)]
+    telegram_token: Option<String>,
+
+    /// Chat the Telegram bot sends messages to, such as `-1001234567890` for a group
+    #[arg(long)]
+    telegram_chat_id: Option<String>,
+
+    /// Routing key of the PagerDuty service that gets an incident for each breached index, resolved on
+    /// recovery, and for destructive operations
+    #[arg(long)]
+    pagerduty_routing_key: Option<String>,
+
+    /// URL of a dead man's switch, such as a healthchecks.io check, pinged with a POST request after each
+    /// successful poll, so it alerts when the monitor stops
+    #[arg(long)]
+    heartbeat_url: Option<String>,
+
+    /// URL pinged after each failed poll instead of --heartbeat-url, `<heartbeat-url>/fail` by default
+    #[arg(long, requires = "heartbeat_url")]
+    heartbeat_fail_url: Option<String>,
+
+    /// Shell command run on breaches, following --alert-cooldown, with ALGOLIA_INDEX, EXPECTED_RECORDS,
+    /// CURRENT_RECORDS, DELTA and EVENT set and ALGOLIA_LOGS pointing to a file with the new log entries
+    #[arg(long)]
+    on_breach_cmd: Option<String>,
+
+    /// Shell command run when the records count is back within the thresholds, with the variables of
+    /// --on-breach-cmd
+    #[arg(long)]
+    on_recovery_cmd: Option<String>,
+
+    /// Time after which the --on-breach-cmd, --on-recovery-cmd and --expected-cmd commands are killed
+    #[arg(long, default_value = "30s", value_parser = parse_duration)]
+    cmd_timeout: Duration,
+
+    /// Notify the alert channels when an index can't be read, such as after losing search rights
+    #[arg(long)]
+    alert_on_error: bool,
+
+    /// Print the alerts with the channels they would go to instead of notifying them, such as to try out the
+    /// thresholds with --replay
+    #[arg(long)]
+    notify_dry_run: bool,
+
+    /// Fetch the logs on every poll and alert right away when the index gets cleared or deleted
+    #[arg(long)]
+    alert_on_destructive: bool,
+
+    /// Check the error logs on every poll and alert when searches fail with server errors
+    /// more often than this many times per minute
+    #[arg(long, value_parser = parse_rate)]
+    error_rate_threshold: Option<f64>,
+
+    /// Check the indexing tasks on every poll and alert when more than this many wait to be published, read
+    /// from the list of indices with the `listIndexes` ACL
+    #[arg(long)]
+    max_pending_tasks: Option<u64>,
+
+    /// Check the indexing tasks on every poll and alert when the oldest task of the build logs has waited
+    /// longer than this to be published, such as `10m`; needs the `logs` ACL as well
+    #[arg(long, value_parser = parse_duration)]
+    max_task_age: Option<Duration>,
+
+    /// Check the settings of the indices every --settings-interval and alert when they change, such as a deploy
+    /// dropping a searchable attribute; needs the `settings` ACL
+    #[arg(long)]
+    watch_settings: bool,
+
+    /// Time between the checks of --watch-settings, --watch-synonyms and --watch-rules
+    #[arg(long, default_value = "5m", value_parser = parse_duration)]
+    settings_interval: Duration,
+
+    /// Settings key left out of --watch-settings, such as `customRanking` when a job tunes it. Repeatable
+    #[arg(long, requires = "watch_settings")]
+    settings_ignore: Vec<String>,
+
+    /// Directory keeping the settings each index was last seen with, so --watch-settings catches the changes
+    /// made while the monitor was stopped
+    #[arg(long, requires = "watch_settings")]
+    settings_dir: Option<PathBuf>,
+
+    /// Check the synonyms count of the indices every --settings-interval and alert when it drops, such as after
+    /// a bad import wiped them; needs the `settings` ACL
+    #[arg(long)]
+    watch_synonyms: bool,
+
+    /// Largest allowed drop of the synonyms count, such as `50` or `10%`
+    #[arg(long, default_value = "10%")]
+    synonyms_delta: CountDelta,
+
+    /// Check the rules count of the indices every --settings-interval and alert when it drops; needs the
+    /// `settings` ACL
+    #[arg(long)]
+    watch_rules: bool,
+
+    /// Largest allowed drop of the rules count, such as `5` or `10%`
+    #[arg(long, default_value = "10%")]
+    rules_delta: CountDelta,
+
+    /// Time after which an ongoing breach is printed and notified again, such as `15m` or `1h`,
+    /// in between it is only reported when it starts
+    #[arg(long, visible_alias = "renotify-interval", default_value = "15m", value_parser = parse_duration)]
+    alert_cooldown: Duration,
+
+    /// Daily window during which threshold breaches are only printed and recorded, such as
+    /// `02:30-04:30` around a nightly rebuild; a breach still going on at its end is alerted then. Repeatable
+    #[arg(long)]
+    quiet_hours: Vec<QuietHours>,
+
+    /// IANA timezone of --quiet-hours, such as `Europe/Amsterdam`, the local time when omitted
+    #[arg(long, requires = "quiet_hours")]
+    timezone: Option<chrono_tz::Tz>,
+
+    /// Address to serve Prometheus metrics on at `/metrics`, such as `0.0.0.0:9184`
+    #[arg(long)]
+    metrics_addr: Option<SocketAddr>,
+
+    /// Address to serve the state of the indices on at `/status` as JSON, and at `/healthz` for liveness
+    /// probes, which fails once an index went three of its delays without a successful poll, such as
+    /// `127.0.0.1:8080`; `/state` tells the state printed on SIGUSR1 and a POST to `/poll` polls right away
+    #[arg(long, env = "ALGOLIA_MONITOR_STATUS_ADDR")]
+    status_addr: Option<SocketAddr>,
+
+    /// File to write the state of the indices to after each poll, as served at `/status`, for the
+    /// `healthcheck` subcommand to read without --status-addr
+    #[arg(long, env = "ALGOLIA_MONITOR_STATE_FILE")]
+    state_file: Option<PathBuf>,
+
+    /// Prometheus Pushgateway to push the Prometheus metrics to, such as `http://pushgateway:9091`,
+    /// grouped by the `algolia_monitor` job and the application and removed when the monitor stops
+    #[arg(long)]
+    pushgateway_url: Option<String>,
+
+    /// Time between pushes to --pushgateway-url, --delay by default
+    #[arg(long, requires = "pushgateway_url", value_parser = parse_duration)]
+    push_interval: Option<Duration>,
+
+    /// User name for the basic authentication of --pushgateway-url, which may also come with the URL
+    #[arg(long, requires = "pushgateway_url")]
+    pushgateway_user: Option<String>,
+
+    /// Password for the basic authentication of --pushgateway-url
+    #[arg(long, requires = "pushgateway_user")]
+    pushgateway_password: Option<String>,
+
+    /// OpenTelemetry collector to export the metrics to over OTLP, such as `http://localhost:4317`
+    #[arg(long)]
+    otlp_endpoint: Option<String>,
+
+    /// Transport of the exports to --otlp-endpoint
+    #[arg(long, value_enum, default_value = "grpc", requires = "otlp_endpoint")]
+    otlp_protocol: OtlpProtocol,
+
+    /// Also export a span for each poll to --otlp-endpoint, with the index and the outcome of the poll
+    #[arg(long, requires = "otlp_endpoint")]
+    otlp_traces: bool,
+
+    /// CloudWatch namespace the RecordCount, RecordDelta and PollErrors metrics are published to after each
+    /// poll cycle, with the credentials of the default AWS provider chain
+    #[cfg(feature = "cloudwatch")]
+    #[arg(long)]
+    cloudwatch_namespace: Option<String>,
+
+    /// AWS region of --cloudwatch-namespace, the one of the AWS configuration by default
+    #[cfg(feature = "cloudwatch")]
+    #[arg(long, requires = "cloudwatch_namespace")]
+    aws_region: Option<String>,
+
+    /// StatsD server to push metrics to after each poll, such as `127.0.0.1:8125`
+    #[arg(long)]
+    statsd_addr: Option<String>,
+
+    /// Prefix of the metric names pushed to StatsD
+    #[arg(long, default_value = "algolia")]
+    statsd_prefix: String,
+
+    /// Sentry project to report the errors the monitor of an index gives up on and the panics to, tagged with a
+    /// hash of the application id, the index name and the poll
+    #[cfg(feature = "sentry")]
+    #[arg(long)]
+    sentry_dsn: Option<String>,
+
+    /// Also report the breaches to --sentry-dsn, as warning messages
+    #[cfg(feature = "sentry")]
+    #[arg(long, requires = "sentry_dsn")]
+    sentry_report_breaches: bool,
+
+    /// Print diagnostic messages, such as request retries and the requested URLs, twice to also print the
+    /// response bodies; RUST_LOG takes precedence, such as `algolia_monitor=debug,reqwest=trace`
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
+
+    /// Only print the breaches and the errors on stderr, twice for the errors only
+    #[arg(short, long, action = ArgAction::Count, conflicts_with = "verbose")]
+    quiet: u8,
+
+    /// Write the man page to stdout, or the pages of the command and its subcommands to a directory
+    #[arg(long, hide = true, value_name = "DIR", num_args = 0..=1)]
+    generate_man: Option<Option<PathBuf>>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the records counts recorded in a --history-db database
+    History(HistoryArgs),
+    /// Write the polls and events recorded in a --history-db database as CSV or JSON, such as for sharing the
+    /// timeline of an incident
+    Export(ExportArgs),
+    /// Check the state of a running monitor, such as for `HEALTHCHECK CMD algolia-monitor healthcheck`
+    ///
+    /// Exits with 1 and the reason when the last successful poll read from --status-addr or --state-file is
+    /// too old, it failed or a breach left an index without records.
+    Healthcheck(HealthcheckArgs),
+    /// Wait for Algolia to publish an indexing task, such as to hold a deployment until a reindex is searchable
+    ///
+    /// Exits with 0 once the task is published, 2 when it doesn't exist or can't be read and 3 when --timeout
+    /// expires first. With --expected-records the records count is checked next, exiting with 1 when breached.
+    WaitTask(WaitTaskArgs),
+    /// Print the records count of an index, `nbHits` of an empty query, or with --exact the number of records
+    /// counted by browsing it next to it
+    ///
+    /// Exits with 0 once counted and 2 when the index can't be read, such as with a key lacking the `browse`
+    /// ACL.
+    Count(CountArgs),
+    /// Print the completion script of a shell, such as for `source <(algolia-monitor completions bash)`
+    Completions(CompletionsArgs),
+}
+
+#[derive(clap::Args)]
+struct HistoryArgs {
+    /// Database written by --history-db
+    #[arg(long)]
+    history_db: PathBuf,
+
+    /// Index to print the records counts of, `index:subset` for a subset
+    index_name: String,
+
+    /// Leave out the polls before this time, an RFC 3339 timestamp or a duration ago such as `14d`
+    #[arg(long, value_parser = parse_time)]
+    since: Option<DateTime<Utc>>,
+
+    /// Leave out the polls after this time, an RFC 3339 timestamp or a duration ago such as `1h`
+    #[arg(long, value_parser = parse_time)]
+    until: Option<DateTime<Utc>>,
+
+    /// Print the lowest, highest and average records count of each day instead of the polls
+    #[arg(long)]
+    daily: bool,
+
+    /// Number of latest polls to print
+    #[arg(long, default_value = "50")]
+    limit: u32,
+}
+
+#[derive(clap::Args)]
+struct HealthcheckArgs {
+    /// --status-addr of the monitor, the status is fetched from its `/status` endpoint
+    #[arg(long, env = "ALGOLIA_MONITOR_STATUS_ADDR")]
+    status_addr: Option<SocketAddr>,
+
+    /// --state-file of the monitor, read when there is no --status-addr
+    #[arg(long, env = "ALGOLIA_MONITOR_STATE_FILE")]
+    state_file: Option<PathBuf>,
+}
+
+#[derive(clap::Args)]
+struct WaitTaskArgs {
+    /// Application ID
+    app_id: String,
+
+    /// Algolia API key
+    key: ApiKey,
+
+    /// Index the task belongs to
+    index_name: String,
+
+    /// `taskID` of the indexing response
+    task_id: u64,
+
+    /// Time after which the wait gives up
+    #[arg(long, default_value = "10m", value_parser = parse_duration)]
+    timeout: Duration,
+
+    /// Records count the index is expected to have once the task is published
+    #[arg(long)]
+    expected_records: Option<u64>,
+
+    /// Records count difference to fail the check on, a drop when negative and growth when positive
+    #[arg(long, default_value = "-1000", allow_negative_numbers = true, requires = "expected_records")]
+    delta: i64,
+
+    #[command(flatten)]
+    client: ClientArgs,
+}
+
+#[derive(clap::Args)]
+struct CountArgs {
     /// Application ID
     app_id: String,
 
-    /// Algolia API key
-    key: String,
+    /// Algolia API key
+    key: ApiKey,
+
+    /// Index to count the records of
+    index_name: String,
+
+    /// Count the records by browsing the index as well, which unlike `nbHits` isn't an estimate
+    #[arg(long)]
+    exact: bool,
+
+    /// Most records counted by browsing, which costs a request for every 1000 of them
+    #[arg(long, default_value = "1000000", requires = "exact")]
+    browse_max_records: u64,
+
+    /// Pages of 1000 records between the progress lines of the browse
+    #[arg(long, default_value = "10", value_parser = clap::value_parser!(u64).range(1..), requires = "exact")]
+    browse_progress: u64,
+
+    #[command(flatten)]
+    client: ClientArgs,
+}
+
+/// Requests to Algolia of the `wait-task` and `count` subcommands, the flags of the monitor of the same name
+#[derive(clap::Args)]
+struct ClientArgs {
+    /// Number of times a failed Algolia request is retried before giving up on it
+    #[arg(long, default_value = "3")]
+    retry_attempts: u32,
+
+    /// Base delay in milliseconds for exponential backoff between retries
+    #[arg(long, default_value = "200")]
+    retry_base_ms: u64,
+
+    /// Total time a request to Algolia may take, such as `10s`, the --timeout of the monitor
+    #[arg(long, default_value = "10s", value_parser = parse_duration)]
+    request_timeout: Duration,
+
+    /// Time to wait for the connection to an Algolia host before moving on to the next one
+    #[arg(long, default_value = "5s", value_parser = parse_duration)]
+    connect_timeout: Duration,
+
+    /// Proxy to send the Algolia requests through instead of the one of the HTTPS_PROXY and HTTP_PROXY
+    /// environment variables
+    #[arg(long, env = "ALGOLIA_MONITOR_PROXY", value_parser = parse_proxy, conflicts_with = "no_proxy")]
+    proxy: Option<String>,
+
+    /// User and password to authenticate to the --proxy with, given as `USER:PASSWORD`
+    #[arg(long, env = "ALGOLIA_MONITOR_PROXY_AUTH", value_parser = parse_proxy_auth, requires = "proxy")]
+    proxy_auth: Option<(String, ApiKey)>,
+
+    /// Send the Algolia requests straight to the hosts, ignoring the proxy of the environment variables
+    #[arg(long)]
+    no_proxy: bool,
+
+    /// API base URLs in order of preference, replacing the hosts derived from the application ID
+    #[arg(long, value_delimiter = ',', value_parser = parse_base_url)]
+    base_url: Vec<String>,
+
+    /// PEM file of a certificate authority to trust on top of the ones of the system; may be repeated
+    #[arg(long)]
+    ca_cert: Vec<PathBuf>,
+
+    /// Accept any TLS certificate, leaving the requests open to interception
+    #[arg(long)]
+    tls_insecure: bool,
+}
+
+impl ClientArgs {
+    fn proxy_config(&self) -> ProxyConfig {
+        match (&self.proxy, self.no_proxy) {
+            (Some(url), _) => ProxyConfig::Url {
+                url: url.clone(),
+                auth: self.proxy_auth.clone(),
+            },
+            (None, true) => ProxyConfig::Disabled,
+            (None, false) => ProxyConfig::Environment,
+        }
+    }
+
+    /// Client of the requests to Algolia, with the timeouts, retries, proxy and certificates of the flags
+    fn create_client(&self, app_id: &str, key: &ApiKey) -> Result<AlgoliaClient, MonitorError> {
+        let client = AlgoliaClient::new(app_id, key)?
+            .with_timeouts(self.request_timeout, self.connect_timeout)?
+            .with_retries(self.retry_attempts, Duration::from_millis(self.retry_base_ms))
+            .with_proxy(self.proxy_config())?
+            .with_tls(TlsConfig::load(&self.ca_cert, self.tls_insecure)?)?;
+
+        Ok(match self.base_url.is_empty() {
+            true => client,
+            false => client.with_base_urls(self.base_url.clone()),
+        })
+    }
+}
+
+#[derive(clap::Args)]
+struct CompletionsArgs {
+    shell: Shell,
+}
+
+#[derive(clap::Args)]
+struct ExportArgs {
+    /// Database written by --history-db
+    #[arg(long)]
+    history_db: PathBuf,
+
+    /// Index to export the polls and events of, every index when omitted
+    index_name: Option<String>,
+
+    /// Leave out the entries before this time, an RFC 3339 timestamp or a duration ago such as `2h`
+    #[arg(long, value_parser = parse_time)]
+    since: Option<DateTime<Utc>>,
+
+    /// Leave out the entries after this time, an RFC 3339 timestamp or a duration ago such as `1h`
+    #[arg(long, value_parser = parse_time)]
+    until: Option<DateTime<Utc>>,
+
+    /// Format of the export
+    #[arg(long, value_enum, default_value = "csv")]
+    format: ExportFormat,
+
+    /// Write to this file instead of stdout
+    #[arg(long)]
+    output_file: Option<PathBuf>,
+}
+
+fn parse_min_delta(value: &str) -> Result<i64, String> {
+    match value.parse::<i64>() {
+        Ok(delta) if delta <= 0 => Ok(delta),
+        Ok(_) => Err("the minimum delta must be zero or negative".to_string()),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+fn parse_max_delta(value: &str) -> Result<i64, String> {
+    match value.parse::<i64>() {
+        Ok(delta) if delta >= 0 => Ok(delta),
+        Ok(_) => Err("the maximum delta must be zero or positive".to_string()),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+/// librdkafka setting such as `sasl.mechanism=PLAIN`, the value may hold more `=`
+#[cfg(feature = "kafka")]
+fn parse_kafka_config(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => Ok((key.trim().to_string(), value.to_string())),
+        _ => Err(format!("invalid Kafka setting `{value}`, expected KEY=VALUE")),
+    }
+}
+
+/// RFC 3339 timestamp, or a duration such as `2h` that is taken as that long ago
+fn parse_time(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+
+    humantime::parse_duration(value)
+        .ok()
+        .and_then(|ago| chrono::Duration::from_std(ago).ok())
+        .map(|ago| Utc::now() - ago)
+        .ok_or_else(|| format!("invalid time `{value}`, expected an RFC 3339 timestamp or a duration such as `2h`"))
+}
+
+/// Values of --log-type, listed in the help and the shell completions
+fn log_types() -> impl TypedValueParser<Value = LogType> {
+    PossibleValuesParser::new(["all", "query", "build", "error"]).try_map(|value| value.parse::<LogType>())
+}
+
+/// Values of --count-source, listed in the help and the shell completions
+fn count_sources() -> impl TypedValueParser<Value = CountSource> {
+    PossibleValuesParser::new(["query", "list-indexes"]).try_map(|value| value.parse::<CountSource>())
+}
+
+fn sample_strategies() -> impl TypedValueParser<Value = SampleStrategy> {
+    PossibleValuesParser::new(["search", "browse"]).try_map(|value| value.parse::<SampleStrategy>())
+}
+
+/// HTTPS URL with a trailing slash, or a plain HTTP one on the loopback interface for local mocks,
+/// getting the API version path when it has none
+fn parse_base_url(value: &str) -> Result<String, String> {
+    let mut url = reqwest::Url::parse(value.trim()).map_err(|error| format!("invalid URL `{value}`: {error}"))?;
+
+    let local = matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]"));
+    match url.scheme() {
+        "https" => {}
+        "http" if local => {}
+        _ => return Err(format!("`{value}` is not an HTTPS URL")),
+    }
+
+    match url.path() {
+        "/" => url.set_path("/1/"),
+        path if !path.ends_with('/') => url.set_path(&format!("{path}/")),
+        _ => {}
+    }
+
+    Ok(url.to_string())
+}
+
+/// Regular expression of `--grep` or `--grep-exclude`, the error telling where it is invalid
+fn parse_regex(value: &str) -> Result<Regex, String> {
+    Regex::new(value).map_err(|error| error.to_string())
+}
+
+/// Size in bytes, optionally with a `K`, `M` or `G` suffix
+fn parse_size(value: &str) -> Result<u64, String> {
+    let (number, multiplier) = match value.trim().to_ascii_uppercase() {
+        value if value.ends_with('K') => (value.trim_end_matches('K').to_string(), 1 << 10),
+        value if value.ends_with('M') => (value.trim_end_matches('M').to_string(), 1 << 20),
+        value if value.ends_with('G') => (value.trim_end_matches('G').to_string(), 1 << 30),
+        value => (value, 1),
+    };
+
+    match number.parse::<u64>() {
+        Ok(size) if size > 0 => size
+            .checked_mul(multiplier)
+            .ok_or_else(|| format!("size `{value}` is too large")),
+        _ => Err(format!("invalid size `{value}`")),
+    }
+}
+
+fn parse_percent(value: &str) -> Result<f64, String> {
+    match value.trim_end_matches('%').parse::<f64>() {
+        Ok(percent) if percent.is_finite() && percent >= 0.0 => Ok(percent),
+        _ => Err(format!("invalid percentage `{value}`")),
+    }
+}
+
+fn parse_rate(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate >= 0.0 => Ok(rate),
+        _ => Err(format!("invalid rate `{value}`, expected a number of errors per minute")),
+    }
+}
+
+fn parse_proxy(value: &str) -> Result<String, String> {
+    // The value isn't quoted, as it may hold a password
+    let url = reqwest::Url::parse(value).map_err(|error| format!("invalid proxy URL: {error}"))?;
+    match url.scheme() {
+        "http" | "https" => Ok(value.to_string()),
+        scheme => Err(format!("unsupported proxy scheme `{scheme}`, expected `http` or `https`")),
+    }
+}
+
+fn parse_proxy_auth(value: &str) -> Result<(String, ApiKey), String> {
+    match value.split_once(':') {
+        Some((user, password)) if !user.is_empty() => Ok((user.to_string(), ApiKey::new(password))),
+        _ => Err("expected `USER:PASSWORD`".to_string()),
+    }
+}
+
+/// Speed of a replay such as `10x`, the `x` being optional
+fn parse_replay_speed(value: &str) -> Result<f64, String> {
+    match value.trim().trim_end_matches(['x', 'X']).parse::<f64>() {
+        Ok(speed) if speed.is_finite() && speed > 0.0 => Ok(speed),
+        _ => Err(format!("invalid replay speed `{value}`, expected a factor above 0 such as `10x`")),
+    }
+}
+
+fn parse_requests_per_second(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate > 0.0 => Ok(rate),
+        _ => Err(format!("invalid rate `{value}`, expected a number of requests per second above 0")),
+    }
+}
+
+/// Parses `--compare` without quoting the value in its error, as clap does, since it holds a key
+#[derive(Clone)]
+struct CompareTargetParser;
+
+impl TypedValueParser for CompareTargetParser {
+    type Value = CompareTarget;
+
+    fn parse_ref(
+        &self,
+        command: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<CompareTarget, clap::Error> {
+        let invalid = |error: String| {
+            let arg = arg.map_or_else(|| "--compare".to_string(), ToString::to_string);
+            let message = format!("invalid value for '{arg}': {error}\n");
+            clap::Error::raw(clap::error::ErrorKind::ValueValidation, message).with_cmd(command)
+        };
+        value.to_str().ok_or_else(|| invalid("not valid UTF-8".to_string()))?.parse().map_err(invalid)
+    }
+}
+
+impl Args {
+    /// Whether the logs are printed on every poll instead of watching the records count
+    fn tails_logs(&self) -> bool {
+        self.all_logs || self.follow
+    }
+
+    fn log_filter(&self) -> LogFilter {
+        let log_filter = LogFilter::default()
+            .with_only_writes(self.only_writes)
+            .with_ips(self.ip.clone())
+            .with_excluded_ips(self.exclude_ip.clone())
+            .with_user_agents(self.user_agent_contains.clone())
+            .with_patterns(self.patterns(&self.grep))
+            .with_excluded_patterns(self.patterns(&self.grep_exclude))
+            .with_until(self.until);
+
+        match (self.min_answer_code, self.answer_code.is_empty()) {
+            (Some(code), _) => log_filter.with_min_answer_code(code),
+            (None, false) => log_filter.with_answer_codes(self.answer_code.clone()),
+            (None, true) => log_filter,
+        }
+    }
+
+    fn patterns(&self, patterns: &[Regex]) -> Vec<Regex> {
+        match self.grep_ignore_case {
+            true => patterns
+                .iter()
+                .map(|pattern| {
+                    RegexBuilder::new(pattern.as_str())
+                        .case_insensitive(true)
+                        .build()
+                        .expect("pattern is valid")
+                })
+                .collect(),
+            false => patterns.to_vec(),
+        }
+    }
+
+    /// Thresholds of the limits given, a drop of 1000 records without any
+    fn thresholds(&self) -> Thresholds {
+        // clap rejects a delta given with the other limits and the value parsers the out of range ones, the
+        // limits of the config file being checked when it is applied
+        Thresholds::from_limits(self.delta, self.min_delta, self.max_delta, self.delta_percent)
+            .expect("the limits are validated by the value parsers and the config file")
+            .unwrap_or_else(|| Thresholds::from_delta(-1000))
+    }
+
+    /// Options of the command line the targets fall back on
+    fn target_defaults(&self) -> TargetDefaults {
+        TargetDefaults {
+            thresholds: self.thresholds(),
+            delay: self.delay,
+            max_delay: self.adaptive_delay.then_some(self.max_delay),
+            jitter: self.jitter,
+            speed: self.replay.as_ref().map_or(1.0, |_| self.replay_speed),
+            all_logs: self.all_logs,
+            follow: self.follow,
+            only_writes: self.only_writes,
+            log_filter: self.log_filter(),
+            expected_records: self.expected_records.clone(),
+        }
+    }
+
+    /// Limits and cooldown of the alerts of the monitors, from the arguments
+    fn alert_options(&self) -> AlertOptions {
+        AlertOptions {
+            alert_cooldown: self.alert_cooldown,
+            alert_on_error: self.alert_on_error,
+            max_consecutive_errors: self.max_consecutive_errors,
+            max_pending_tasks: self.max_pending_tasks,
+            max_task_age: self.max_task_age,
+            max_churn_percent: self.max_churn_percent,
+            error_rate_threshold: self.error_rate_threshold,
+            expected_by_command: self.expected_cmd.is_some(),
+            rebaseline_on_recreate: self.rebaseline_on_recreate,
+            quiet_hours: self.quiet_hours.clone(),
+            timezone: self.timezone,
+            #[cfg(feature = "sentry")]
+            sentry_report_breaches: self.sentry_report_breaches,
+        }
+    }
+
+    /// Indices given on the command line and the ones from the config file,
+    /// whose defaults fill in the options that weren't given on the command line
+    fn load_targets(&mut self, matches: &ArgMatches) -> Result<(Vec<Target>, Vec<Target>), MonitorError> {
+        let mut targets = vec![];
+
+        if let (Some(since), Some(until)) = (self.since, self.until) {
+            if since >= until {
+                return Err(MonitorError::InvalidArgument("--since must be before --until".to_string()));
+            }
+        }
+        if !self.replicas.is_empty() && (self.config.is_some() || self.index_name.len() > 1) {
+            return Err(MonitorError::InvalidArgument("--replica needs a single INDEX_NAME".to_string()));
+        }
+        if self.compare.is_some() && (self.config.is_some() || self.index_name.len() > 1) {
+            return Err(MonitorError::InvalidArgument(
+                "--compare needs a single INDEX_NAME, set `compare` per index in a config file".to_string(),
+            ));
+        }
+        if self.output.iter().filter(|output| matches!(output, OutputTarget::Stdout(_))).count() > 1 {
+            return Err(MonitorError::InvalidArgument(
+                "--output takes only one of `json`, `pretty` and `text`, which all print on stdout".to_string(),
+            ));
+        }
+        if self.max_data_size.is_some() && self.count_source != CountSource::ListIndexes {
+            return Err(MonitorError::InvalidArgument(
+                "--max-data-size needs --count-source list-indexes".to_string(),
+            ));
+        }
+
+        if let Some(app_id) = self.app_id.clone() {
+            let key = self.api_key()?;
+            if self.index_name.is_empty() {
+                return Err(MonitorError::InvalidArgument(
+                    "INDEX_NAME is required along with APP_ID".to_string(),
+                ));
+            }
+
+            for index_name in &self.index_name {
+                let target = Target {
+                    compare: self.compare.clone(),
+                    ..Target::new(app_id.clone(), key.clone(), index_name.clone())
+                };
+                let subsets: Vec<_> = self
+                    .subsets
+                    .iter()
+                    .map(|subset| target.with_subset(subset.clone(), None, None))
+                    .collect();
+                targets.push(target);
+                targets.extend(subsets);
+            }
+        }
+
+        let Some(path) = self.config.clone() else {
+            return Ok((targets, vec![]));
+        };
+
+        let config = Config::load(&path)?;
+        self.apply_config(&config, matches)?;
+        let config_targets = Target::from_config(&config)?;
+
+        if targets.is_empty() && config_targets.is_empty() {
+            return Err(MonitorError::InvalidArgument(format!(
+                "No indices to monitor in config file {}",
+                path.display()
+            )));
+        }
+
+        Ok((targets, config_targets))
+    }
+
+    /// Destinations of the output besides the terminal, from `--output`, `--tui`, `--history-db` and the
+    /// Loki, Elasticsearch, MQTT and Kafka flags
+    fn output_options(&self) -> OutputOptions {
+        OutputOptions {
+            outputs: self.output.clone(),
+            color: self.color,
+            sparkline_window: self.sparkline_window,
+            ascii: self.ascii,
+            max_daily_operations: self.max_daily_operations,
+            log_file: self.log_file.clone(),
+            log_file_max_size: self.log_file_max_size,
+            log_file_keep: self.log_file_keep,
+            output_max_errors: self.output_max_errors,
+            dashboard: self.tui,
+            history_db: self.history_db.clone(),
+            loki: self.loki_url.clone().map(|url| LokiTarget {
+                url,
+                basic_auth: self
+                    .loki_user
+                    .clone()
+                    .map(|user| (user, self.loki_password.clone())),
+                tenant: self.loki_tenant.clone(),
+            }),
+            elasticsearch: self.elasticsearch_url.clone().map(|url| ElasticsearchTarget {
+                url,
+                index_prefix: self.elasticsearch_index.clone(),
+                auth: match (&self.elasticsearch_user, &self.elasticsearch_api_key) {
+                    (Some(user), _) => {
+                        Some(ElasticsearchAuth::Basic(user.clone(), self.elasticsearch_password.clone()))
+                    }
+                    (None, Some(key)) => Some(ElasticsearchAuth::ApiKey(key.clone())),
+                    (None, None) => None,
+                },
+                batch_size: self.elasticsearch_batch_size as usize,
+                flush_interval: self.elasticsearch_flush_interval,
+            }),
+            mqtt: self.mqtt_url.clone().map(|url| MqttTarget {
+                url,
+                topic_prefix: self.mqtt_topic_prefix.clone(),
+                credentials: self
+                    .mqtt_user
+                    .clone()
+                    .map(|user| (user, self.mqtt_password.clone().unwrap_or_default())),
+            }),
+            #[cfg(feature = "kafka")]
+            kafka: self.kafka_brokers.clone().map(|brokers| KafkaTarget {
+                brokers,
+                topic: self.kafka_topic.clone(),
+                config: self.kafka_config.clone(),
+            }),
+        }
+    }
+
+    /// Key from `--key-file`, `--key-stdin`, the KEY argument or `ALGOLIA_API_KEY`, in that order
+    ///
+    /// When the key comes from elsewhere than the KEY argument, the argument holds the first index names.
+    fn api_key(&mut self) -> Result<ApiKey, MonitorError> {
+        let external_key = match (&self.key_file, self.key_stdin) {
+            (Some(path), _) => Some(std::fs::read_to_string(path).map_err(|source| MonitorError::Io {
+                path: path.clone(),
+                source,
+            })?),
+            (None, true) => {
+                let mut key = String::new();
+                std::io::stdin()
+                    .read_line(&mut key)
+                    .map_err(|source| MonitorError::Io {
+                        path: "stdin".into(),
+                        source,
+                    })?;
+                Some(key)
+            }
+            (None, false) if self.index_name.is_empty() => std::env::var("ALGOLIA_API_KEY").ok(),
+            (None, false) => None,
+        };
+
+        let key = match external_key {
+            Some(key) => {
+                if let Some(index_names) = self.key.take() {
+                    let index_names = index_names.split(',').map(str::to_string);
+                    self.index_name.splice(0..0, index_names);
+                }
+                key.lines().next().unwrap_or_default().trim().to_string()
+            }
+            None if self.index_name.is_empty() => {
+                return Err(MonitorError::InvalidArgument(
+                    "Missing API key, pass it as KEY argument, with --key-file, --key-stdin or in ALGOLIA_API_KEY"
+                        .to_string(),
+                ))
+            }
+            None => self.key.clone().unwrap_or_default(),
+        };
+
+        match key.is_empty() {
+            true => Err(MonitorError::InvalidArgument("API key is empty".to_string())),
+            false => Ok(ApiKey::new(key)),
+        }
+    }
+
+    fn apply_config(&mut self, config: &Config, matches: &ArgMatches) -> Result<(), MonitorError> {
+        let from_cli = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+
+        let cli_thresholds = ["delta", "min_delta", "max_delta", "delta_percent"]
+            .iter()
+            .any(|id| from_cli(id));
+        if let (false, Some(thresholds)) = (cli_thresholds, config.thresholds()?) {
+            self.delta = None;
+            self.min_delta = thresholds.min_delta;
+            self.max_delta = thresholds.max_delta;
+            self.delta_percent = thresholds.delta_percent;
+        }
+
+        if let (false, Some(delay)) = (from_cli("delay"), &config.delay) {
+            self.delay = parse_delay(&delay.to_string())
+                .map_err(|error| MonitorError::InvalidArgument(format!("Invalid delay in config file: {error}")))?;
+        }
+        if let (false, Some(all_logs)) = (from_cli("all_logs"), config.all_logs) {
+            self.all_logs = all_logs;
+        }
+        if let (false, Some(only_writes)) = (from_cli("only_writes"), config.only_writes) {
+            self.only_writes = only_writes;
+        }
+        if let (false, Some(max_consecutive_errors)) =
+            (from_cli("max_consecutive_errors"), config.max_consecutive_errors)
+        {
+            self.max_consecutive_errors = max_consecutive_errors;
+        }
+        if let (false, Some(alert_cooldown)) = (from_cli("alert_cooldown"), &config.alert_cooldown) {
+            self.alert_cooldown = parse_duration(&alert_cooldown.to_string()).map_err(|error| {
+                MonitorError::InvalidArgument(format!("Invalid alert_cooldown in config file: {error}"))
+            })?;
+        }
+        if let (true, true, Some(base_url)) = (self.base_url.is_empty(), self.hosts.is_empty(), &config.base_url) {
+            self.base_url = base_url
+                .split(',')
+                .map(parse_base_url)
+                .collect::<Result<_, _>>()
+                .map_err(|error| MonitorError::InvalidArgument(format!("Invalid base_url in config file: {error}")))?;
+        }
+        if self.webhook_url.is_none() {
+            self.webhook_url = config.webhook_url.clone();
+        }
+        if self.slack_webhook.is_none() {
+            self.slack_webhook = config.slack_webhook.clone();
+        }
+        if self.telegram_token.is_none() {
+            self.telegram_token = config.telegram_token.clone();
+        }
+        if self.telegram_chat_id.is_none() {
+            self.telegram_chat_id = config.telegram_chat_id.clone();
+        }
+        if self.pagerduty_routing_key.is_none() {
+            self.pagerduty_routing_key = config.pagerduty_routing_key.clone();
+        }
+        if self.heartbeat_url.is_none() {
+            self.heartbeat_url = config.heartbeat_url.clone();
+        }
+        if self.heartbeat_fail_url.is_none() {
+            self.heartbeat_fail_url = config.heartbeat_fail_url.clone();
+        }
+
+        Ok(())
+    }
+
+    /// Enabled metrics backends, with the Prometheus endpoint already serving, and the Pushgateway to push
+    /// the Prometheus metrics to
+    async fn create_metrics(&self) -> Result<(Metrics, Option<Pushgateway>), MonitorError> {
+        let mut metrics = Metrics::default();
+        let mut pushgateway = None;
+
+        if self.metrics_addr.is_some() || self.pushgateway_url.is_some() {
+            let prometheus = PrometheusMetrics::default();
+            if let Some(address) = self.metrics_addr {
+                prometheus
+                    .clone()
+                    .serve(address)
+                    .await
+                    .map_err(|error| {
+                        MonitorError::InvalidArgument(format!("Failed to serve metrics on {address}: {error}"))
+                    })?;
+            }
+            if let Some(url) = &self.pushgateway_url {
+                let mut gateway = Pushgateway::new(url, self.app_id.as_deref(), prometheus.clone())
+                    .with_client(self.http_client()?);
+                if let Some(user) = &self.pushgateway_user {
+                    gateway = gateway.with_basic_auth(user.clone(), self.pushgateway_password.clone());
+                }
+                pushgateway = Some(gateway);
+            }
+            metrics = metrics.with_backend(prometheus);
+        }
+
+        if let Some(address) = &self.statsd_addr {
+            let statsd = StatsdMetrics::connect(address, &self.statsd_prefix, self.app_id.as_deref())
+                .map_err(|error| {
+                    MonitorError::InvalidArgument(format!("Failed to connect to StatsD at {address}: {error}"))
+                })?;
+            metrics = metrics.with_backend(statsd);
+        }
+
+        Ok((metrics, pushgateway))
+    }
+
+    /// OpenTelemetry providers exporting to --otlp-endpoint, with the metrics backend recording to them
+    fn start_telemetry(&self) -> Result<Option<(Telemetry, OtlpMetrics)>, MonitorError> {
+        let Some(endpoint) = &self.otlp_endpoint else {
+            return Ok(None);
+        };
+
+        Telemetry::start(endpoint, self.otlp_protocol, self.app_id.as_deref(), self.otlp_traces)
+            .map(Some)
+            .map_err(|error| {
+                MonitorError::InvalidArgument(format!(
+                    "Failed to export OpenTelemetry data to {endpoint}: {error}"
+                ))
+            })
+    }
+
+    /// Notification channels, using a client without the Algolia credentials
+    fn create_notifier(&self) -> Result<Notifier, MonitorError> {
+        if self.telegram_token.is_some() != self.telegram_chat_id.is_some() {
+            return Err(MonitorError::InvalidArgument(
+                "--telegram-token and --telegram-chat-id have to be given together".to_string(),
+            ));
+        }
+
+        let client = self.http_client()?;
+        let mut notifier = Notifier::default()
+            .with_dry_run(self.notify_dry_run)
+            .with_digest(self.digest.is_some());
+
+        if let Some(url) = &self.webhook_url {
+            notifier = notifier.with_webhook(Webhook::new(
+                client.clone(),
+                url.clone(),
+                &self.webhook_header,
+            ));
+        }
+
+        if let Some(url) = &self.slack_webhook {
+            notifier = notifier.with_slack(Slack::new(client.clone(), url.clone(), self.slack_log_lines));
+        }
+
+        if let (Some(token), Some(chat_id)) = (&self.telegram_token, &self.telegram_chat_id) {
+            notifier = notifier.with_telegram(Telegram::new(
+                client.clone(),
+                token,
+                chat_id.clone(),
+                self.slack_log_lines,
+            ));
+        }
+
+        if let Some(routing_key) = &self.pagerduty_routing_key {
+            notifier = notifier.with_pagerduty(PagerDuty::new(client.clone(), routing_key.clone()));
+        }
+
+        if let Some(url) = &self.heartbeat_url {
+            let fail_url = self.heartbeat_fail_url.clone();
+            notifier = notifier.with_heartbeat(Heartbeat::new(client, url.clone(), fail_url));
+        }
+
+        if self.on_breach_cmd.is_some() || self.on_recovery_cmd.is_some() {
+            notifier = notifier.with_commands(Commands::new(
+                self.on_breach_cmd.clone(),
+                self.on_recovery_cmd.clone(),
+                self.cmd_timeout,
+            ));
+        }
+
+        Ok(notifier)
+    }
+
+    /// Certificates of --ca-cert and --tls-insecure, read again for every client so a reload picks up a renewed
+    /// CA
+    fn tls(&self) -> Result<TlsConfig, MonitorError> {
+        TlsConfig::load(&self.ca_cert, self.tls_insecure)
+    }
+
+    /// Client of the requests that don't go to Algolia, trusting the same certificates
+    fn http_client(&self) -> Result<reqwest::Client, MonitorError> {
+        self.tls()?.client()
+    }
+
+    /// Flags of the requests to Algolia, the ones the subcommands take as well
+    fn client_args(&self) -> ClientArgs {
+        ClientArgs {
+            retry_attempts: self.retry_attempts,
+            retry_base_ms: self.retry_base_ms,
+            request_timeout: self.timeout,
+            connect_timeout: self.connect_timeout,
+            proxy: self.proxy.clone(),
+            proxy_auth: self.proxy_auth.clone(),
+            no_proxy: self.no_proxy,
+            base_url: self.base_url.clone(),
+            ca_cert: self.ca_cert.clone(),
+            tls_insecure: self.tls_insecure,
+        }
+    }
+
+    fn create_client(&self, app_id: &str, key: &ApiKey, metrics: Metrics) -> Result<AlgoliaClient, MonitorError> {
+        let mut client = self
+            .client_args()
+            .create_client(app_id, key)?
+            .with_metrics(metrics)
+            .with_logs(self.log_type, self.log_offset, self.log_length)
+            .with_max_log_pages(self.max_log_pages)
+            .with_count_source(self.count_source);
+
+        if self.base_url.is_empty() && !self.hosts.is_empty() {
+            client = client.with_hosts(&self.hosts);
+        }
+
+        Ok(client)
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let started = Instant::now();
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+    diagnostics::init(args.verbose, args.quiet);
+    match &args.command {
+        Some(Command::History(history)) => return print_history(history),
+        Some(Command::Export(export)) => return export_history(export),
+        Some(Command::Healthcheck(healthcheck)) => return check_health(healthcheck).await,
+        Some(Command::WaitTask(wait_task)) => return wait_for_task(wait_task).await,
+        Some(Command::Count(count)) => return count_records(count).await,
+        Some(Command::Completions(completions)) => return print_completions(completions),
+        None => {}
+    }
+    if let Some(directory) = &args.generate_man {
+        return generate_man(directory.as_deref());
+    }
+    let (command_line_targets, config_targets) = match args.load_targets(&matches) {
+        Ok(targets) => targets,
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::from(2);
+        }
+    };
+    let mut targets = [command_line_targets.as_slice(), &config_targets].concat();
+    Target::name_applications(&mut targets);
+    let http_client = match args.http_client() {
+        Ok(client) => client,
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::from(2);
+        }
+    };
+    if args.tls_insecure {
+        warn!("[{}] --tls-insecure accepts any TLS certificate, the requests can be intercepted", now());
+    }
+    // Subsets share the logs of their index, which would be printed once per subset
+    if args.tails_logs() || args.until.is_some_and(|until| until <= Utc::now()) {
+        targets.retain(|target| target.subset.is_none());
+    }
+    // Kept until the end, so the last reports are sent before exiting
+    #[cfg(feature = "sentry")]
+    let _sentry = match args.sentry_dsn.as_deref().map(reporting::init).transpose() {
+        Ok(guard) => guard,
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::from(2);
+        }
+    };
+    let (output, mut output_tasks) = match Output::open(args.output_options(), http_client) {
+        Ok(output) => output,
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::from(2);
+        }
+    };
+    let defaults = args.target_defaults();
+    let args = Arc::new(args);
+
+    let (mut metrics, pushgateway) = match args.create_metrics().await {
+        Ok(metrics) => metrics,
+        Err(error) => {
+            eprintln!("[{}] {}", now(), error);
+            return ExitCode::FAILURE;
+        }
+    };
+    if output.dashboard.is_active() {
+        metrics = metrics.with_backend(output.dashboard.clone());
+    }
+    metrics = metrics.with_backend(output.state.clone()).with_backend(output.usage.clone());
+    output.state.listen();
+    let status = (args.status_addr.is_some() || args.state_file.is_some()).then(|| {
+        let delays = targets.iter().map(|target| (target.name(), target.longest_delay(&defaults)));
+        StatusApi::new(delays, args.state_file.clone())
+    });
+    if let Some(status) = &status {
+        if let Some(address) = args.status_addr {
+            if let Err(error) = status.clone().serve(address, output.state.clone()).await {
+                eprintln!("Failed to serve the status on {address}: {error}");
+                return ExitCode::from(2);
+            }
+        }
+        metrics = metrics.with_backend(status.clone());
+    }
+    let telemetry = match args.start_telemetry() {
+        Ok(Some((telemetry, otlp))) => {
+            metrics = metrics.with_backend(otlp);
+            Some(telemetry)
+        }
+        Ok(None) => None,
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::from(2);
+        }
+    };
+    #[cfg(feature = "cloudwatch")]
+    let cloudwatch = match &args.cloudwatch_namespace {
+        Some(namespace) => {
+            let cloudwatch =
+                CloudWatchMetrics::connect(namespace, args.aws_region.as_deref(), args.app_id.as_deref()).await;
+            metrics = metrics.with_backend(cloudwatch.clone());
+            Some(cloudwatch)
+        }
+        None => None,
+    };
+    let notifier = match args.create_notifier() {
+        Ok(notifier) => notifier,
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut clients = HashMap::new();
+    if let Err(error) = connect_clients(&args, &targets, &metrics, &mut clients).await {
+        eprintln!("{error}");
+        return ExitCode::from(2);
+    }
+    let recorder = args.record.as_deref().map(Recorder::create).transpose();
+    let replay = args.replay.as_deref().map(Replay::load).transpose();
+    let (recorder, replay) = match (recorder, replay) {
+        (Ok(recorder), Ok(replay)) => (recorder, replay),
+        (Err(error), _) | (_, Err(error)) => {
+            eprintln!("{error}");
+            return ExitCode::from(2);
+        }
+    };
+    if let Err(error) = validate_targets(&args, &targets, &clients).await {
+        eprintln!("{error}");
+        return ExitCode::from(2);
+    }
+    let client_for = |app_id: &String, key: &ApiKey| clients[&(app_id.clone(), key.clone())].clone();
+
+    if args.once {
+        let checks: Vec<_> = targets
+            .iter()
+            .map(|target| {
+                let client = client_for(&target.app_id, &target.key);
+                tokio::spawn(check_index(client, args.clone(), target.clone(), output.clone()))
+            })
+            .collect();
+
+        let mut status = CHECK_OK;
+        for check in checks {
+            status = status.max(check.await.unwrap_or(CHECK_ERROR));
+        }
+        return ExitCode::from(status);
+    }
+
+    if args.until.is_some_and(|until| until <= Utc::now()) {
+        let prints: Vec<_> = targets
+            .iter()
+            .map(|target| {
+                tokio::spawn(print_logs(
+                    client_for(&target.app_id, &target.key),
+                    args.clone(),
+                    target.clone(),
+                    output.with_prefix(match targets.len() {
+                        1 => String::new(),
+                        _ => format!("[{}] ", target.name()),
+                    }),
+                ))
+            })
+            .collect();
+
+        let mut status = CHECK_OK;
+        for print in prints {
+            status = status.max(print.await.unwrap_or(CHECK_ERROR));
+        }
+        return ExitCode::from(status);
+    }
+
+    let (shutdown_sender, shutdown) = watch::channel(false);
+    let quit_sender = shutdown_sender.clone();
+    // Reaching --max-duration stops the monitors like an interrupt, once the requests in flight finish
+    if let Some(max_duration) = args.max_duration {
+        let deadline_sender = shutdown_sender.clone();
+        tokio::spawn(async move {
+            sleep_until(tokio::time::Instant::from_std(started + max_duration)).await;
+            let max_duration = humantime::format_duration(max_duration);
+            info!("[{}] Stopping, --max-duration of {} reached", now(), max_duration);
+            let _ = deadline_sender.send(true);
+        });
+    }
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        info!(
+            "[{}] Shutting down once the requests in flight finish, interrupt again to exit right away",
+            now()
+        );
+        let _ = shutdown_sender.send(true);
+
+        shutdown_signal().await;
+        std::process::exit(130);
+    });
+    // Quitting the dashboard shuts the monitors down like an interrupt
+    let tui = output_tasks.dashboard.take().map(|ui| {
+        ui.start(move || {
+            let _ = quit_sender.send(true);
+        })
+    });
+    let tui = match tui.transpose() {
+        Ok(tui) => tui,
+        Err(error) => {
+            eprintln!("Failed to start the dashboard: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let pushes = pushgateway
+        .clone()
+        .map(|pushgateway| tokio::spawn(push_metrics(pushgateway, args.push_interval.unwrap_or(args.delay))));
+    #[cfg(feature = "cloudwatch")]
+    let publishes = cloudwatch
+        .clone()
+        .map(|cloudwatch| tokio::spawn(publish_cloudwatch(cloudwatch, args.delay)));
+
+    let (settings_sender, settings) = watch::channel(Arc::new(Settings {
+        args: args.clone(),
+        defaults,
+        alert_options: args.alert_options(),
+        notifier,
+        targets: targets.clone(),
+    }));
+    // Summaries are printed in the order the monitors started, which is the one of the targets
+    let mut monitors = JoinSet::new();
+    let mut started_monitors = 0;
+    let mut start_monitor = |monitors: &mut JoinSet<_>, clients: &Clients, target: &Target| {
+        let client_for = |app_id: &String, key: &ApiKey| clients[&(app_id.clone(), key.clone())].clone();
+        let source_for = |app_id: &String, key: &ApiKey| -> Arc<dyn DataSource> {
+            match (&replay, &recorder) {
+                (Some(replay), _) => Arc::new(replay.source(app_id)),
+                (None, Some(recorder)) => Arc::new(recorder.source(app_id, client_for(app_id, key))),
+                (None, None) => client_for(app_id, key),
+            }
+        };
+        let clients = IndexClients {
+            index: client_for(&target.app_id, &target.key),
+            source: source_for(&target.app_id, &target.key),
+            compared: target.compare.as_ref().map(|compare| source_for(&compare.app_id, &compare.key)),
+        };
+        let monitor = monitor_index(
+            clients,
+            settings.clone(),
+            metrics.clone(),
+            target.clone(),
+            output.with_prefix(match targets.len() {
+                1 => String::new(),
+                _ => format!("[{}] ", target.name()),
+            }),
+            shutdown.clone(),
+        );
+        let position = started_monitors;
+        started_monitors += 1;
+        monitors.spawn(async move { (position, monitor.await) });
+    };
+    for target in &targets {
+        start_monitor(&mut monitors, &clients, target);
+    }
+
+    let mut hangups = Hangups::listen(args.config.is_some());
+    let mut summaries = vec![];
+    loop {
+        tokio::select! {
+            monitor = monitors.join_next() => match monitor {
+                Some(Ok(summary)) => summaries.push(summary),
+                Some(Err(_)) => {}
+                None => break,
+            },
+            _ = hangups.recv(), if !*shutdown.borrow() => {
+                let Some(path) = &args.config else {
+                    continue;
+                };
+                let current = settings_sender.borrow().clone();
+                let targets = &command_line_targets;
+                let (reloaded, changes) =
+                    match reload_settings(&matches, path, &current, targets, &mut clients, &metrics).await {
+                        Ok(reloaded) => reloaded,
+                        Err(error) => {
+                            error!(
+                                "[{}] Keeping the current settings, failed to reload {}: {}",
+                                now(),
+                                path.display(),
+                                error
+                            );
+                            continue;
+                        }
+                    };
+
+                match changes.is_empty() {
+                    true => info!("[{}] Reloaded {}, nothing changed", now(), path.display()),
+                    false => info!("[{}] Reloaded {}: {}", now(), path.display(), changes.join(", ")),
+                }
+                if let Some(status) = &status {
+                    let delays = reloaded
+                        .targets
+                        .iter()
+                        .map(|target| (target.name(), target.longest_delay(&reloaded.defaults)));
+                    status.set_indices(delays);
+                }
+                let added: Vec<_> = reloaded
+                    .targets
+                    .iter()
+                    .filter(|target| !current.targets.iter().any(|running| running.same_monitor(target)))
+                    .cloned()
+                    .collect();
+                // The monitors going on pick up the new settings, the ones left out stop
+                settings_sender.send_replace(Arc::new(reloaded));
+                for target in &added {
+                    start_monitor(&mut monitors, &clients, target);
+                }
+            }
+        }
+    }
+    summaries.sort_by_key(|(position, _)| *position);
+    let summaries: Vec<_> = summaries.into_iter().map(|(_, summary)| summary).collect();
+    if let Some(tui) = tui {
+        tui.finish();
+    }
+    print_summaries(&summaries, started.elapsed(), &output.usage);
+
+    if let (Some(pushgateway), Some(pushes)) = (pushgateway, pushes) {
+        pushes.abort();
+        if let Err(error) = pushgateway.delete().await {
+            error!("[{}] Failed to remove the metrics from the Pushgateway: {}", now(), error);
+        }
+    }
+    if let Some(telemetry) = telemetry {
+        telemetry.shutdown().await;
+    }
+    #[cfg(feature = "cloudwatch")]
+    if let (Some(cloudwatch), Some(publishes)) = (cloudwatch, publishes) {
+        publishes.abort();
+        if let Err(error) = cloudwatch.publish().await {
+            error!(
+                "[{}] Failed to publish the last metrics to CloudWatch: {}",
+                now(),
+                aws_sdk_cloudwatch::error::DisplayErrorContext(&error)
+            );
+        }
+    }
+
+    // The monitors dropped their handles when they stopped, so the writer has every entry once this one goes
+    drop(output);
+    output_tasks.finish().await;
+
+    // Reaching a limit or the end of a replay fails the run on the breaches still going on, so a CI job can
+    // fail on it
+    let limited = args.replay.is_some()
+        || args.max_duration.is_some_and(|max_duration| started.elapsed() >= max_duration)
+        || args.max_iterations.is_some_and(|max_iterations| {
+            !summaries.is_empty() && summaries.iter().all(|(_, summary)| summary.polls >= max_iterations)
+        });
+    if limited {
+        return match summaries.iter().any(|(_, summary)| summary.breached) {
+            true => ExitCode::FAILURE,
+            false => ExitCode::SUCCESS,
+        };
+    }
+
+    // Without a shutdown request or reaching --until the monitors only stop when they give up
+    let finished = *shutdown.borrow() || args.until.is_some_and(|until| until < Utc::now());
+    match finished {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::FAILURE,
+    }
+}
+
+/// Pushes the metrics to the Pushgateway every interval until aborted, reporting a failure once until a push
+/// succeeds again
+async fn push_metrics(pushgateway: Pushgateway, interval: Duration) {
+    let mut pushes = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+    let mut failed = false;
+
+    loop {
+        pushes.tick().await;
+        match pushgateway.push().await {
+            Ok(()) if failed => {
+                failed = false;
+                info!("[{}] Pushing metrics to the Pushgateway again", now());
+            }
+            Ok(()) => {}
+            Err(error) if !failed => {
+                failed = true;
+                error!("[{}] Failed to push metrics to the Pushgateway: {}", now(), error);
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+/// Publishes the datapoints of each poll cycle to CloudWatch until aborted, reporting a failure once until
+/// publishing succeeds again
+#[cfg(feature = "cloudwatch")]
+async fn publish_cloudwatch(cloudwatch: CloudWatchMetrics, interval: Duration) {
+    let mut publishes = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+    let mut failed = false;
+
+    loop {
+        publishes.tick().await;
+        match cloudwatch.publish().await {
+            Ok(()) if failed => {
+                failed = false;
+                info!("[{}] Publishing metrics to CloudWatch again", now());
+            }
+            Ok(()) => {}
+            Err(error) if !failed => {
+                failed = true;
+                error!(
+                    "[{}] Failed to publish metrics to CloudWatch: {}",
+                    now(),
+                    aws_sdk_cloudwatch::error::DisplayErrorContext(&error)
+                );
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+/// Prints the polls or the daily records counts of an index from the history database
+fn print_history(args: &HistoryArgs) -> ExitCode {
+    let history = match open_history_reader(&args.history_db) {
+        Ok(history) => history,
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let printed = match args.daily {
+        true => history.days(&args.index_name, args.since, args.until).map(|days| {
+            for day in days {
+                println!(
+                    "{}  min {} / max {} / avg {:.0} over {} polls",
+                    day.date, day.min_records, day.max_records, day.avg_records, day.samples
+                );
+            }
+        }),
+        false => history.samples(&args.index_name, args.since, args.until, args.limit).map(|samples| {
+            for sample in samples {
+                println!(
+                    "{}  {} records, expected {} ({:+}){}",
+                    sample.timestamp,
+                    sample.records,
+                    sample.expected_records,
+                    sample.records as i64 - sample.expected_records as i64,
+                    sample
+                        .processing_time_ms
+                        .map(|processing_time_ms| format!(", {processing_time_ms}ms"))
+                        .unwrap_or_default()
+                );
+            }
+        }),
+    };
+
+    match printed {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Failed to read history database {}: {}", args.history_db.display(), error);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Writes the polls and events of the history database to stdout or a file
+fn export_history(args: &ExportArgs) -> ExitCode {
+    let entries = open_history_reader(&args.history_db).and_then(|history| {
+        history
+            .timeline(args.index_name.as_deref(), args.since, args.until)
+            .map_err(|error| format!("Failed to read history database {}: {}", args.history_db.display(), error))
+    });
+    let entries = match entries {
+        Ok(entries) => entries,
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let written = match &args.output_file {
+        Some(path) => File::create(path)
+            .and_then(|file| export::write(&mut BufWriter::new(file), args.format, &entries))
+            .map_err(|error| format!("Failed to write {}: {}", path.display(), error)),
+        None => export::write(&mut std::io::stdout().lock(), args.format, &entries)
+            .map_err(|error| format!("Failed to write the export: {error}")),
+    };
+
+    match written {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{error}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Prints the completion script of the shell to stdout
+fn print_completions(args: &CompletionsArgs) -> ExitCode {
+    let mut command = Args::command();
+    let name = command.get_name().to_string();
+    let mut script = Vec::new();
+    clap_complete::generate(args.shell, &mut command, name, &mut script);
+
+    match write_output(&mut std::io::stdout().lock(), &script) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Failed to write the completion script: {error}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Writes the man page to stdout, or one page per command to the directory, such as `algolia-monitor-export.1`
+fn generate_man(directory: Option<&Path>) -> ExitCode {
+    let written = match directory {
+        Some(directory) => {
+            std::fs::create_dir_all(directory).and_then(|()| clap_mangen::generate_to(Args::command(), directory))
+        }
+        None => {
+            let mut page = Vec::new();
+            clap_mangen::Man::new(Args::command())
+                .render(&mut page)
+                .and_then(|()| write_output(&mut std::io::stdout().lock(), &page))
+        }
+    };
+
+    match written {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Failed to write the man page: {error}");
+            ExitCode::from(2)
+        }
+    }
+}
 
-    /// Name of the index to monitor
-    index_name: String,
+/// Writes generated output such as a completion script, a reader closing the pipe early like `head` does not
+/// being a failure
+fn write_output(writer: &mut impl Write, bytes: &[u8]) -> std::io::Result<()> {
+    match writer.write_all(bytes).and_then(|()| writer.flush()) {
+        Err(error) if error.kind() == ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    }
+}
 
-    #[arg(short, long, default_value = "false")]
-    all_logs: bool,
+/// Prints `ok`, or the reason the monitor is unhealthy and exits with 1, which is also how a monitor that
+/// can't be reached is reported
+async fn check_health(args: &HealthcheckArgs) -> ExitCode {
+    let report = match (&args.status_addr, &args.state_file) {
+        (Some(address), _) => fetch_status(*address).await,
+        (None, Some(path)) => std::fs::read(path)
+            .map_err(|error| format!("Failed to read the state file {}: {}", path.display(), error))
+            .and_then(|content| {
+                serde_json::from_slice::<StatusReport>(&content)
+                    .map_err(|error| format!("Invalid state file {}: {}", path.display(), error))
+            }),
+        (None, None) => Err("Missing --status-addr or --state-file, ALGOLIA_MONITOR_STATUS_ADDR or \
+            ALGOLIA_MONITOR_STATE_FILE in the environment"
+            .to_string()),
+    };
 
-    #[arg(short, long, default_value = "0")]
-    expected_records: u64,
+    match report.and_then(|report| report.check().map_err(|reason| format!("unhealthy: {reason}"))) {
+        Ok(()) => {
+            println!("ok");
+            ExitCode::SUCCESS
+        }
+        Err(reason) => {
+            println!("{reason}");
+            ExitCode::from(1)
+        }
+    }
+}
 
-    #[arg(short, long, default_value = "30")]
-    delay: u64,
+/// Waits for the task with polls backing off from [`TASK_MIN_DELAY`] to [`TASK_MAX_DELAY`], then checks the
+/// records count when there is an expected one
+async fn wait_for_task(args: &WaitTaskArgs) -> ExitCode {
+    let client = match args.client.create_client(&args.app_id, &args.key) {
+        Ok(client) => client,
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::from(CHECK_ERROR);
+        }
+    };
+    let (index_name, task_id) = (&args.index_name, args.task_id);
+    let started = Instant::now();
+    let mut delay = PollDelay::new(TASK_MIN_DELAY, Some(TASK_MAX_DELAY), None);
 
-    #[arg(long, default_value = "-1000")]
-    delta: i64,
+    loop {
+        match client.is_task_published(index_name, task_id).await {
+            Ok(true) => break,
+            Ok(false) => {}
+            Err(error) if error.is_not_found() => {
+                println!("ERROR {index_name}: task {task_id} doesn't exist: {error}");
+                return ExitCode::from(CHECK_ERROR);
+            }
+            Err(error) if error.is_permanent() => {
+                println!("ERROR {index_name}: {error}");
+                return ExitCode::from(CHECK_ERROR);
+            }
+            // Failed requests were already retried, the task is asked for again after the delay
+            Err(error) => warn!("[{}] Failed to read task {} of {}: {}", now(), task_id, index_name, error),
+        }
+
+        let remaining = args.timeout.saturating_sub(started.elapsed());
+        if remaining.is_zero() {
+            println!(
+                "TIMEOUT {index_name}: task {task_id} not published after {}",
+                humantime::format_duration(args.timeout)
+            );
+            return ExitCode::from(CHECK_TIMEOUT);
+        }
+        let wait = delay.next().min(remaining);
+        debug!(
+            "[{}] Task {} of {} not published yet, asking again in {}",
+            now(),
+            task_id,
+            index_name,
+            humantime::format_duration(Duration::from_millis(wait.as_millis() as u64))
+        );
+        tokio::time::sleep(wait).await;
+        delay.stable();
+    }
+
+    let waited = humantime::format_duration(Duration::from_secs(started.elapsed().as_secs()));
+    let Some(expected_records) = args.expected_records else {
+        println!("OK {index_name}: task {task_id} published after {waited}");
+        return ExitCode::from(CHECK_OK);
+    };
+
+    let records = match client.total_records(index_name).await {
+        Ok(records) => records,
+        Err(error) => {
+            println!("ERROR {index_name}: task {task_id} published after {waited}, {error}");
+            return ExitCode::from(CHECK_ERROR);
+        }
+    };
+    let delta = records as i64 - expected_records as i64;
+    match Thresholds::from_delta(args.delta).check(expected_records, records) {
+        None => {
+            println!(
+                "OK {index_name}: task {task_id} published after {waited}, {records} records, expected \
+                 {expected_records} ({delta:+})"
+            );
+            ExitCode::from(CHECK_OK)
+        }
+        Some(breach) => {
+            println!(
+                "BREACHED {index_name}: task {task_id} published after {waited}, {records} records, expected \
+                 {expected_records}, {breach}"
+            );
+            ExitCode::from(CHECK_BREACHED)
+        }
+    }
 }
 
-impl Args {
-    fn create_client(&self) -> AlgoliaClient {
-        let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert("x-algolia-application-id", self.app_id.parse().unwrap());
-        headers.insert("x-algolia-api-key", self.key.parse().unwrap());
-        headers.insert("content-type", "application/json".parse().unwrap());
-        headers.insert("accept", "application/json".parse().unwrap());
+async fn fetch_status(address: SocketAddr) -> Result<StatusReport, String> {
+    let url = format!("http://{address}/status");
+    let response = reqwest::Client::new()
+        .get(&url)
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|error| format!("Failed to fetch {url}: {error}"))?;
+
+    response.json().await.map_err(|error| format!("Invalid status from {url}: {error}"))
+}
+
+/// Opens the history database for reading, explaining how to record one when it doesn't exist
+fn open_history_reader(path: &Path) -> Result<HistoryReader, String> {
+    if !path.exists() {
+        return Err(format!(
+            "No history database at {}, run the monitor with --history-db {} to record one",
+            path.display(),
+            path.display()
+        ));
+    }
+
+    HistoryReader::open(path)
+        .map_err(|error| format!("Failed to open history database {}: {}", path.display(), error))
+}
 
-        let client = reqwest::Client::builder()
-            .default_headers(headers)
-            .build()
-            .unwrap();
+/// Resolves on Ctrl-C, on SIGTERM on unix, and on Ctrl-Break or the console closing or the system shutting down
+/// on Windows
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
 
-        AlgoliaClient {
-            client,
-            base_url: format!("https://{}-dsn.algolia.net/1/", self.app_id),
-            index_name: self.index_name.clone(),
+        let mut terminate = signal(SignalKind::terminate()).expect("failed to listen for SIGTERM");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
         }
     }
+
+    #[cfg(windows)]
+    {
+        use tokio::signal::windows::{ctrl_break, ctrl_close, ctrl_shutdown};
+
+        let mut ctrl_break = ctrl_break().expect("failed to listen for Ctrl-Break");
+        let mut close = ctrl_close().expect("failed to listen for the console closing");
+        let mut shutdown = ctrl_shutdown().expect("failed to listen for the system shutting down");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = ctrl_break.recv() => {}
+            _ = close.recv() => {}
+            _ = shutdown.recv() => {}
+        }
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    let _ = tokio::signal::ctrl_c().await;
 }
 
-struct AlgoliaClient {
-    client: reqwest::Client,
-    base_url: String,
-    index_name: String,
+/// SIGHUP listener asking to reload the config file, never firing without one or where there are no signals
+struct Hangups {
+    #[cfg(unix)]
+    signal: Option<tokio::signal::unix::Signal>,
 }
 
-struct AlgoliaLog {
-    timestamp: String,
-    message: String
+impl Hangups {
+    fn listen(enabled: bool) -> Self {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+
+            Hangups {
+                signal: enabled.then(|| signal(SignalKind::hangup()).expect("failed to listen for SIGHUP")),
+            }
+        }
+
+        #[cfg(not(unix))]
+        {
+            let _ = enabled;
+            Hangups {}
+        }
+    }
+
+    async fn recv(&mut self) {
+        #[cfg(unix)]
+        if let Some(signal) = &mut self.signal {
+            if signal.recv().await.is_some() {
+                return;
+            }
+        }
+
+        std::future::pending().await
+    }
 }
 
-impl AlgoliaLog {
-    fn from_json(json: &Value) -> AlgoliaLog {
-        AlgoliaLog {
-            timestamp: json["timestamp"].as_str().unwrap().to_string(),
-            message: to_string(json).unwrap(),
+/// Prints the records count of an index, counted by browsing it as well with `--exact`
+async fn count_records(args: &CountArgs) -> ExitCode {
+    let client = match args.client.create_client(&args.app_id, &args.key) {
+        Ok(client) => client,
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::from(CHECK_ERROR);
+        }
+    };
+    let index_name = &args.index_name;
+
+    let records = match client.total_records(index_name).await {
+        Ok(records) => records,
+        Err(error) => {
+            println!("ERROR {index_name}: {error}");
+            return ExitCode::from(CHECK_ERROR);
+        }
+    };
+    if !args.exact {
+        println!("{index_name}: {records} records");
+        return ExitCode::from(CHECK_OK);
+    }
+
+    let progress = |count: &BrowseCount| browse_progress(index_name, count, args.browse_progress);
+    match client.browse_count(index_name, None, args.browse_max_records, progress).await {
+        Ok(count) if count.complete => {
+            let delta = records as i64 - count.records as i64;
+            println!("{index_name}: {} records by browsing, nbHits says {records} ({delta:+})", count.records);
+            ExitCode::from(CHECK_OK)
+        }
+        Ok(count) => {
+            println!(
+                "{index_name}: at least {} records by browsing, stopped at --browse-max-records, nbHits says \
+                 {records}",
+                count.records
+            );
+            ExitCode::from(CHECK_OK)
+        }
+        Err(error) => {
+            println!("ERROR {index_name}: {records} records by nbHits, browsing failed: {error}");
+            ExitCode::from(CHECK_ERROR)
         }
     }
+}
 
-    fn is_newer(&self, timestamp: &String) -> bool {
-        self.timestamp.gt(timestamp)
+/// Logs how far a browse went every so many pages
+fn browse_progress(index_name: &str, count: &BrowseCount, every: u64) {
+    if count.pages.is_multiple_of(every) && !count.complete {
+        let (records, pages) = (count.records, count.pages);
+        info!("[{}] Browsing {}: {} records over {} pages so far", now(), index_name, records, pages);
     }
 }
 
-impl AlgoliaClient {
-    async fn total_records(&self) -> Result<u64, reqwest::Error> {
-        let request = self
-            .client
-            .post(format!(
-                "{}indexes/{}/query",
-                self.base_url, self.index_name
+const CHECK_OK: u8 = 0;
+const CHECK_BREACHED: u8 = 1;
+const CHECK_ERROR: u8 = 2;
+/// Exit status of `wait-task` when the task isn't published before --timeout
+const CHECK_TIMEOUT: u8 = 3;
+
+/// First delay between the polls of `wait-task`, most tasks being published within a few seconds
+const TASK_MIN_DELAY: Duration = Duration::from_secs(1);
+
+/// Longest delay `wait-task` backs off to
+const TASK_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Checks the records count of an index once, returning the exit status of the check
+async fn check_index(client: Arc<AlgoliaClient>, args: Arc<Args>, target: Target, output: Output) -> u8 {
+    let index_name = &target.name();
+    let defaults = args.target_defaults();
+    let expected_records = target.expected_records(&defaults);
+    let stats = match target.stats(client.as_ref()).await {
+        Ok(stats) => stats,
+        Err(error) => {
+            println!("{}", output.stdout.error(format_args!("ERROR {index_name}: {error}")));
+            return CHECK_ERROR;
+        }
+    };
+
+    let total_records = stats.records;
+    let changed_records = total_records as i64 - expected_records as i64;
+    let thresholds = target.thresholds(&defaults);
+    let breach = thresholds.check(expected_records, total_records).or(match (args.max_data_size, stats.data_size) {
+        (Some(limit), Some(data_size)) if data_size > limit => Some(ThresholdBreach::DataSize { limit, data_size }),
+        _ => None,
+    });
+    let Some(breach) = breach else {
+        println!(
+            "{}",
+            output.stdout.recovery(format_args!(
+                "OK {index_name}: {total_records} records, expected {expected_records} ({changed_records:+})"
             ))
-            .body(r#"{"params":"hitsPerPage=0&getRankingInfo=0&query=*"}"#)
-            .build()?;
+        );
+        return CHECK_OK;
+    };
 
-        let response = self.client.execute(request).await?;
-        let response: Value = response.json().await?;
+    println!(
+        "{}",
+        output.stdout.breach(format_args!(
+            "BREACHED {index_name}: {total_records} records, expected {expected_records}, {breach}"
+        ))
+    );
+
+    if args.with_logs {
+        match client.get_logs(&target.index_name, args.since).await {
+            Ok(logs) => {
+                let log_filter = target.log_filter(&defaults);
+                for log in logs.iter().filter(|log| log_filter.matches(log)) {
+                    output.print_log(log);
+                }
+            }
+            Err(error) => {
+                println!("{}", output.stdout.error(format_args!("ERROR {index_name}: {error}")));
+                return CHECK_ERROR;
+            }
+        }
+    }
 
-        let value = response
-            .get("nbHits")
-            .map(|v| v.as_u64().unwrap_or(0))
-            .unwrap_or(0);
+    CHECK_BREACHED
+}
 
-        Ok(value)
+/// Prints the log entries between --since and an --until in the past, returning the exit status
+async fn print_logs(client: Arc<AlgoliaClient>, args: Arc<Args>, target: Target, output: Output) -> u8 {
+    let index_name = &target.index_name;
+    match client.get_logs(index_name, args.since).await {
+        Ok(logs) => {
+            let log_filter = target.log_filter(&args.target_defaults());
+            for log in logs.iter().filter(|log| log_filter.matches(log)) {
+                output.print_log(log);
+            }
+            CHECK_OK
+        }
+        Err(error) => {
+            error!(
+                "{}",
+                output.stderr.error(format_args!(
+                    "[{}] Failed to fetch logs of {}: {}",
+                    now(),
+                    index_name,
+                    error
+                ))
+            );
+            CHECK_ERROR
+        }
     }
+}
 
-    async fn get_logs(&self) -> Result<Vec<AlgoliaLog>, reqwest::Error> {
-        let url = format!(
-            "{}logs?indexName={}&type={}&offset=1&length=1000",
-            self.base_url, self.index_name, "build"
-        );
+/// One client per application and key, shared by all of its indices and the ones compared to them
+type Clients = HashMap<(String, ApiKey), Arc<AlgoliaClient>>;
 
-        let request = self.client.get(url).build()?;
+/// Adds the clients of the applications and keys of the targets that have none yet
+async fn connect_clients(
+    args: &Args,
+    targets: &[Target],
+    metrics: &Metrics,
+    clients: &mut Clients,
+) -> Result<(), MonitorError> {
+    let credentials = targets.iter().flat_map(|target| {
+        let compared = target.compare.as_ref().map(|compare| (&compare.app_id, &compare.key));
+        [Some((&target.app_id, &target.key)), compared].into_iter().flatten()
+    });
+    for (app_id, key) in credentials {
+        let credentials = (app_id.clone(), key.clone());
+        if clients.contains_key(&credentials) {
+            continue;
+        }
 
-        let response = self.client.execute(request).await?;
-        let response: Value = response.json().await?;
+        // The applications connect to hosts of their own anyway, so they can share the connection pool
+        let mut client = args.create_client(app_id, key, metrics.clone())?;
+        match clients.values().next() {
+            Some(shared) => client = client.with_connections_of(shared),
+            None => {
+                if let Some(permits) = args.max_concurrent_requests {
+                    client = client.with_concurrency_limit(Arc::new(Semaphore::new(permits as usize)));
+                }
+            }
+        }
+        // The keys of an application share its pace, as Algolia limits the requests of the application
+        let pacer = clients
+            .iter()
+            .find(|((other_app_id, _), _)| other_app_id == app_id)
+            .and_then(|(_, other)| other.pacer().cloned())
+            .or_else(|| args.max_requests_per_second.map(|per_second| Arc::new(RequestPacer::new(per_second))));
+        if let Some(pacer) = pacer {
+            client = client.with_pacer(pacer);
+        }
+        clients.insert(credentials, Arc::new(client));
+    }
 
-        let logs = match response.get("logs") {
-            Some(Value::Array(logs)) => logs,
-            _ => return Ok(vec![]),
-        };
+    Ok(())
+}
+
+/// Checks the credentials and the indices of the targets unless `--no-validate` is given or replaying
+///
+/// The compared indices only add to the monitoring, so a problem with their credentials is only a warning.
+async fn validate_targets(args: &Args, targets: &[Target], clients: &Clients) -> Result<(), MonitorError> {
+    if args.no_validate || args.replay.is_some() {
+        return Ok(());
+    }
+
+    let count_acl = match args.count_source {
+        CountSource::Query => "search",
+        CountSource::ListIndexes => "listIndexes",
+    };
+    // Credentials of the applications, with whether they are only used for compared indices
+    let mut all_credentials: Vec<(Credentials, bool)> = vec![];
+    for target in targets {
+        let mut index_names = vec![target.index_name.as_str()];
+        let mut acls = vec![count_acl, "logs"];
+        match target.subset {
+            None => index_names.extend(args.replicas.iter().map(String::as_str)),
+            Some(_) => acls.push("search"),
+        }
+        if !args.facets.is_empty() {
+            acls.push("search");
+        }
+        if args.watch_settings && target.subset.is_none() {
+            acls.push("settings");
+        }
+        let browses_sample = args.sample_object_ids.is_some() && args.sample_strategy == SampleStrategy::Browse;
+        if args.verify_with_browse || browses_sample {
+            acls.push("browse");
+        }
+        let compared = target.compare.as_ref().map(|compare| {
+            (&compare.app_id, &compare.key, vec![compare.index_name.as_str()], vec![count_acl], true)
+        });
+
+        for (app_id, key, index_names, acls, compared) in
+            [Some((&target.app_id, &target.key, index_names, acls, false)), compared].into_iter().flatten()
+        {
+            let position = all_credentials
+                .iter()
+                .position(|(credentials, _)| credentials.app_id == app_id && credentials.key == key);
+            let (credentials, compared_only) = match position {
+                Some(position) => &mut all_credentials[position],
+                None => {
+                    let credentials = Credentials {
+                        app_id,
+                        key,
+                        client: &clients[&(app_id.clone(), key.clone())],
+                        acls: vec![],
+                        index_names: vec![],
+                    };
+                    all_credentials.push((credentials, true));
+                    all_credentials.last_mut().unwrap()
+                }
+            };
+            *compared_only &= compared;
+            for acl in acls {
+                if !credentials.acls.contains(&acl) {
+                    credentials.acls.push(acl);
+                }
+            }
+            for index_name in index_names {
+                if !credentials.index_names.contains(&index_name) {
+                    credentials.index_names.push(index_name);
+                }
+            }
+        }
+    }
 
-        Ok(logs.iter().map(AlgoliaLog::from_json).collect())
+    for (credentials, compared_only) in &all_credentials {
+        match validation::validate(credentials).await {
+            Err(error) if *compared_only => warn!("[{}] {}, monitoring goes on without it", now(), error),
+            result => result?,
+        }
     }
+
+    Ok(())
 }
 
-#[tokio::main]
-async fn main() -> Result<(), reqwest::Error> {
-    let args = Args::parse();
-    let client = args.create_client();
-    let mut last_log_timestamp = "0000-00-00T00:00:00.000Z".to_string();
-    let expected_records = match args.expected_records {
-        0 => client.total_records().await?,
-        _ => args.expected_records,
-    };
-
-    if !args.all_logs {
-        eprintln!(
-            "Monitoring for record count changes, started with expected value of {expected_records}"
-        );
+/// Settings the monitors follow, replaced when the config file is reloaded on SIGHUP
+struct Settings {
+    args: Arc<Args>,
+    /// Options of the command line the targets fall back on, from the arguments
+    defaults: TargetDefaults,
+    /// Limits and cooldown of the alerts, from the arguments
+    alert_options: AlertOptions,
+    notifier: Notifier,
+    targets: Vec<Target>,
+}
+
+/// Reads the config file again, returning the settings to follow and what changed in them
+///
+/// The clients are created once with the base URLs and `all_logs` switches between polling the records and
+/// tailing the logs, so changes to them wait for a restart. An index with other credentials gets a new client,
+/// and a new monitor like the ones with changed expected records, comparison or filters.
+async fn reload_settings(
+    matches: &ArgMatches,
+    path: &Path,
+    current: &Settings,
+    command_line_targets: &[Target],
+    clients: &mut Clients,
+    metrics: &Metrics,
+) -> Result<(Settings, Vec<String>), MonitorError> {
+    let mut args =
+        Args::from_arg_matches(matches).map_err(|error| MonitorError::InvalidArgument(error.to_string()))?;
+    let config = Config::load(path)?;
+    args.apply_config(&config, matches)?;
+    let mut targets = [command_line_targets, &Target::from_config(&config)?].concat();
+    Target::name_applications(&mut targets);
+    if args.tails_logs() {
+        targets.retain(|target| target.subset.is_none());
+    }
+    if targets.is_empty() {
+        return Err(MonitorError::InvalidArgument(format!(
+            "No indices to monitor in config file {}",
+            path.display()
+        )));
+    }
+
+    let mut kept = vec![];
+    if args.base_url != current.args.base_url {
+        kept.push("base_url");
+        args.base_url = current.args.base_url.clone();
+    }
+    if args.all_logs != current.args.all_logs {
+        kept.push("all_logs");
+        args.all_logs = current.args.all_logs;
+    }
+    if !kept.is_empty() {
+        warn!("[{}] Keeping the current {}, changing it needs a restart", now(), kept.join(" and "));
+    }
+
+    let mut changes = vec![];
+    let notifier = match notifications(&args) == notifications(&current.args) {
+        true => current.notifier.clone(),
+        false => {
+            changes.push("notifications".to_string());
+            args.create_notifier()?
+        }
+    };
+    connect_clients(&args, &targets, metrics, clients).await?;
+    validate_targets(&args, &targets, clients).await?;
+
+    if args.delay != current.args.delay {
+        changes.push(format!(
+            "delay {} -> {}",
+            humantime::format_duration(current.args.delay),
+            humantime::format_duration(args.delay)
+        ));
+    }
+    if args.max_consecutive_errors != current.args.max_consecutive_errors {
+        changes.push(format!(
+            "max_consecutive_errors {} -> {}",
+            current.args.max_consecutive_errors, args.max_consecutive_errors
+        ));
+    }
+    if args.alert_cooldown != current.args.alert_cooldown {
+        changes.push(format!(
+            "alert_cooldown {} -> {}",
+            humantime::format_duration(current.args.alert_cooldown),
+            humantime::format_duration(args.alert_cooldown)
+        ));
+    }
+    if args.only_writes != current.args.only_writes {
+        changes.push(format!("only_writes {} -> {}", current.args.only_writes, args.only_writes));
+    }
+    let defaults = args.target_defaults();
+    for target in &targets {
+        match current.targets.iter().find(|running| running.name() == target.name()) {
+            None => changes.push(format!("added {}", target.name())),
+            Some(running) if !running.same_monitor(target) => {
+                changes.push(format!("restarted {}", target.name()))
+            }
+            Some(running) if running.thresholds(&current.defaults) != target.thresholds(&defaults) => {
+                changes.push(format!("thresholds of {}", target.name()))
+            }
+            Some(running) if (running.delay, running.only_writes) != (target.delay, target.only_writes) => {
+                changes.push(format!("delay and log filter of {}", target.name()))
+            }
+            Some(_) => {}
+        }
+    }
+    for running in &current.targets {
+        if !targets.iter().any(|target| target.name() == running.name()) {
+            changes.push(format!("removed {}", running.name()));
+        }
+    }
+
+    let settings = Settings {
+        alert_options: args.alert_options(),
+        args: Arc::new(args),
+        defaults,
+        notifier,
+        targets,
+    };
+    Ok((settings, changes))
+}
+
+/// Settings the notifier is created from, which the config file can change
+fn notifications(args: &Args) -> [&Option<String>; 7] {
+    [
+        &args.webhook_url,
+        &args.slack_webhook,
+        &args.telegram_token,
+        &args.telegram_chat_id,
+        &args.pagerduty_routing_key,
+        &args.heartbeat_url,
+        &args.heartbeat_fail_url,
+    ]
+}
+
+/// Clients of a monitored index and of the index it is compared to
+struct IndexClients {
+    index: Arc<AlgoliaClient>,
+    /// Where the monitor reads the index from, the client itself unless recording or replaying
+    source: Arc<dyn DataSource>,
+    compared: Option<Arc<dyn DataSource>>,
+}
+
+/// Runs the monitor of a single index until shutdown, a permanent error, too many consecutive failures or a
+/// reload leaving it out, returning the statistics of the run
+///
+/// The polls, their checks, the pacing and the reloads go on in [`Monitor::run`], this handing its events to the
+/// outputs and the controls to it.
+async fn monitor_index(
+    clients: IndexClients,
+    mut settings: watch::Receiver<Arc<Settings>>,
+    metrics: Metrics,
+    target: Target,
+    output: Output,
+    mut shutdown: watch::Receiver<bool>,
+) -> (String, Summary) {
+    // A change of all_logs restarts the monitor, so it stays the same
+    if target.tails_logs(&settings.borrow().defaults) {
+        return tail::tail_index(clients, settings, metrics, target, output, shutdown).await;
+    }
+
+    let current = settings.borrow_and_update().clone();
+    let args = current.args.clone();
+    let defaults = &current.defaults;
+    let index_name = target.name();
+    let whole_index = target.subset.is_none();
+    let check_tasks = whole_index && (args.max_pending_tasks.is_some() || args.max_task_age.is_some());
+    let check_settings = whole_index && args.watch_settings;
+    let watches = [(IndexItems::Synonyms, args.watch_synonyms), (IndexItems::Rules, args.watch_rules)];
+    let settings_file = args
+        .settings_dir
+        .as_deref()
+        .filter(|_| check_settings)
+        .map(|directory| SettingsFile::new(directory, &target.app_id, &target.index_name));
+    let mut expected_command = args
+        .expected_cmd
+        .clone()
+        .map(|command| ExpectedCommand::new(command, args.cmd_timeout, args.expected_refresh));
+    let baseline = match args.baseline {
+        BaselineMode::Rolling(size) => {
+            info!(
+                "Monitoring {index_name} for record count changes, compared to the average of the last {size} polls"
+            );
+            Baseline::rolling(size)
+        }
+        BaselineMode::Fixed => {
+            let external = match &mut expected_command {
+                Some(command) => {
+                    let subset = target.subset.as_ref().map(|subset| subset.name.as_str());
+                    match command.run(&target.app_id, &target.index_name, subset).await {
+                        Ok(expected_records) => Some(expected_records),
+                        Err(error) => {
+                            warn!("[{}] Expected records count of {} not read: {}", now(), index_name, error);
+                            None
+                        }
+                    }
+                }
+                None => None,
+            };
+            let expected_records = match external.unwrap_or_else(|| target.expected_records(defaults)) {
+                0 if external.is_none() => {
+                    let source = clients.source.as_ref();
+                    match initial_records(&target, &current, source, &output, &mut shutdown).await {
+                        Some(records) => records,
+                        None => return (index_name, Summary::default()),
+                    }
+                }
+                expected_records => expected_records,
+            };
+
+            info!(
+                "Monitoring {index_name} for record count changes, started with expected value of {expected_records}"
+            );
+            Baseline::fixed(expected_records, args.baseline_refresh)
+        }
+    };
+
+    let mut monitor = Monitor::builder(clients.source.clone(), target.index_name.clone(), baseline)
+        .with_thresholds(target.thresholds(defaults))
+        .with_max_data_size(args.max_data_size)
+        .with_latency_window(args.latency_window as usize)
+        .with_max_p95_ms(args.max_p95_ms.filter(|_| whole_index))
+        .with_rate_window(args.rate_window as usize)
+        .with_max_loss_rate(args.max_loss_rate)
+        .with_replicas(if whole_index { args.replicas.clone() } else { vec![] }, args.replica_delta)
+        .with_facets(if whole_index { args.facets.clone() } else { vec![] }, args.facet_delta)
+        .with_log_filter(target.log_filter(defaults))
+        .with_logs_since(args.since)
+        .with_destructive_watch(args.alert_on_destructive && whole_index)
+        .with_rebaseline_on_recreate(args.rebaseline_on_recreate)
+        .with_zero_guard(!args.allow_empty)
+        .with_object_sample(args.sample_strategy, args.sample_object_ids);
+    if let Some(subset) = &target.subset {
+        monitor = monitor.with_filters(subset.filters.clone());
+    }
+    if let (Some(compare), Some(client)) = (&target.compare, clients.compared) {
+        monitor = monitor.with_comparison(client, compare.index_name.clone(), args.compare_delta);
+    }
+    for (items, _) in watches.into_iter().filter(|(_, watch)| *watch && whole_index) {
+        let delta = match items {
+            IndexItems::Synonyms => args.synonyms_delta,
+            IndexItems::Rules => args.rules_delta,
+        };
+        monitor = monitor.with_items_watch(items, delta);
+    }
+    if check_settings {
+        let snapshot = settings_file.as_ref().and_then(|file| match file.load() {
+            Ok(snapshot) => snapshot,
+            Err(error) => {
+                let path = file.path().display();
+                warn!("[{}] Settings of {} not read from {}: {}", now(), index_name, path, error);
+                None
+            }
+        });
+        monitor = monitor.with_settings_watch(args.settings_ignore.clone(), snapshot);
     }
 
+    if args.alert_on_destructive && whole_index {
+        let output = output.clone();
+        let settings = settings.clone();
+        let index_name = index_name.clone();
+        let app_id = target.app_id.clone();
+        monitor = monitor.on_destructive(move |log, operation| {
+            report_destructive(&output, &settings.borrow().notifier, &app_id, &index_name, log, operation)
+        });
+    }
+
+    // The outputs get the events one by one, the shippers the log entries in the batches of the polls
+    let (sinks, digest) = index_sinks(&target, &args, &settings, &output);
+    let (events, mut received) = mpsc::unbounded_channel();
+    let (controls, control) = mpsc::unbounded_channel();
+    let usage = output.usage.clone();
+    let client = clients.index.clone();
+    let mut monitor = monitor
+        .with_poll_delay(target.poll_delay(defaults))
+        .with_error_check(args.error_rate_threshold.is_some() && whole_index)
+        .with_task_check(check_tasks)
+        .with_settings_interval(args.settings_interval)
+        .with_browse_verification(args.verify_with_browse.then_some(args.browse_max_records))
+        .with_max_consecutive_errors(Some(args.max_consecutive_errors))
+        .with_max_polls(args.max_iterations)
+        .with_until(args.until)
+        .with_delay_stretch(move |delay| usage.stretch(delay))
+        .with_pacing(move || client.pacing_backlog())
+        .with_controls(control)
+        .with_event_channel(events)
+        .on_logs({
+            let output = output.clone();
+            let name = index_name.clone();
+            let index_name = target.index_name.clone();
+            let log_type = args.log_type;
+            move |logs| output.ship_logs(&name, &index_name, log_type, logs)
+        })
+        .build();
+
+    let mut run = IndexRun {
+        settings: current.clone(),
+        target,
+        name: index_name.clone(),
+        metrics,
+        output: output.clone(),
+        sinks,
+        digest,
+        settings_file,
+        expected_command,
+        expected_records: monitor.expected_records(),
+        summary: Summary::default(),
+        alerts: IndexAlerts::default(),
+    };
+    // The terminal, the notifications and the metrics get the events as the run goes, the shutdown, the polls
+    // asked for and the reloads going to the run as controls
+    let end = {
+        let polls = monitor.run();
+        tokio::pin!(polls);
+        let mut stopping = false;
+        loop {
+            // The event is handled after the select, which holds on to the futures of the other branches
+            let event = tokio::select! {
+                end = &mut polls => break end,
+                Some(event) = received.recv() => event,
+                _ = output.dashboard.poll_requested() => {
+                    let _ = controls.send(Control::PollNow);
+                    continue;
+                }
+                _ = output.state.poll_requested() => {
+                    let _ = controls.send(Control::PollNow);
+                    continue;
+                }
+                _ = shutdown.wait_for(|&shutdown| shutdown), if !stopping => {
+                    stopping = true;
+                    let _ = controls.send(Control::Stop);
+                    continue;
+                }
+                Ok(()) = settings.changed(), if !stopping => {
+                    let current = settings.borrow_and_update().clone();
+                    stopping = !run.reload(&current, &controls);
+                    continue;
+                }
+            };
+            run.handle(event, &controls).await;
+        }
+    };
+    while let Ok(event) = received.try_recv() {
+        run.handle(event, &controls).await;
+    }
+    if let RunEnd::ReplayEnded(error) = end {
+        info!("[{}] Replay of {} finished: {}", now(), index_name, error);
+    }
+
+    (index_name, run.finish())
+}
+
+/// Fetches the records count the fixed baseline of the index starts from, trying again after the poll delay
+/// while the failures don't give up on the index like the ones of the polls would, `None` once they do or on
+/// shutdown
+async fn initial_records(
+    target: &Target,
+    settings: &Settings,
+    source: &dyn DataSource,
+    output: &Output,
+    shutdown: &mut watch::Receiver<bool>,
+) -> Option<u64> {
+    let index_name = target.name();
+    let max_consecutive_errors = settings.args.max_consecutive_errors;
+    let mut failures = Failures::new(Some(max_consecutive_errors));
     loop {
-        if args.all_logs {
-            print_all_logs(&client, &mut last_log_timestamp).await?;
-        } else {
-            print_logs_when_records_change(&client, expected_records, args.delta, &mut last_log_timestamp).await?;
+        let error = match target.stats(source).await {
+            Ok(stats) => return Some(stats.records),
+            Err(error) => error,
+        };
+        let gives_up = failures.failed(&error);
+        error!(
+            "{}",
+            output.stderr.error(format_args!(
+                "[{}] Failed to fetch initial records count of {} ({}/{}){}: {}",
+                now(),
+                index_name,
+                failures.consecutive(),
+                max_consecutive_errors,
+                if gives_up { ", giving up" } else { "" },
+                error
+            ))
+        );
+        if gives_up {
+            #[cfg(feature = "sentry")]
+            reporting::capture_error(&target.app_id, &index_name, 0, &error);
+            return None;
+        }
+
+        let delay = target.poll_delay(&settings.defaults).next();
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = shutdown.wait_for(|&shutdown| shutdown) => return None,
         }
-        sleep(Duration::from_secs(args.delay)).await;
     }
 }
 
-async fn print_logs_when_records_change(
-    client: &AlgoliaClient,
+/// Monitor of the records count of an index as its run goes, with the alert states and the outputs the events
+/// of the run go to
+struct IndexRun {
+    target: Target,
+    /// Settings of the latest reload of the config file, or of the start without one
+    settings: Arc<Settings>,
+    /// Name of the index in the output and the metrics, along with the subset
+    name: String,
+    metrics: Metrics,
+    output: Output,
+    sinks: EventSinks,
+    digest: Option<DigestSink>,
+    settings_file: Option<SettingsFile>,
+    expected_command: Option<ExpectedCommand>,
+    /// Expected records count of the latest poll, or the one the run was told to go by since
     expected_records: u64,
-    delta: i64,
-    last_log_timestamp: &mut String,
-) -> Result<(), Error> {
-    let total_records = client.total_records().await?;
-    let changed_records = total_records as i64 - expected_records as i64;
-    if (delta < 0 && changed_records < delta) || (delta > 0 && changed_records > delta) {
-        eprintln!(
-            "Records count difference is more than {} ({}), waiting for logs...",
-            delta,
-            changed_records
-        );
-        print_algolia_logs(client, last_log_timestamp).await?;
+    summary: Summary,
+    alerts: IndexAlerts,
+}
+
+impl IndexRun {
+    /// Hands the event to the outputs, then to the terminal, the notifications and the metrics
+    async fn handle(&mut self, event: MonitorEvent, controls: &mpsc::UnboundedSender<Control>) {
+        self.sinks.handle(&event);
+        let name = &self.name;
+        match &event {
+            MonitorEvent::Polled(poll) => self.expected_records = poll.records.expected_records,
+            MonitorEvent::SettingsChecked { settings, .. } => {
+                if let Some(file) = &self.settings_file {
+                    if let Err(error) = file.save(settings) {
+                        let path = file.path().display();
+                        warn!("[{}] Settings of {} not written to {}: {}", now(), name, path, error);
+                    }
+                }
+            }
+            MonitorEvent::Browsing(count) => browse_progress(name, count, self.settings.args.browse_progress),
+            MonitorEvent::NextPoll(delay) => {
+                let next_poll = tokio::time::Instant::now() + *delay;
+                report_next_poll(&self.metrics, name, self.target.delay(&self.settings.defaults), next_poll);
+            }
+            _ => {}
+        }
+        let poll_ended = matches!(event, MonitorEvent::PollEnded(_));
+        let index = MonitoredIndex {
+            options: &self.settings.alert_options,
+            notifier: &self.settings.notifier,
+            metrics: &self.metrics,
+            output: &self.output,
+            index_name: &self.target.index_name,
+            name: &self.name,
+            subset: self.target.subset.as_ref().map(|subset| subset.name.as_str()),
+            app_id: &self.target.app_id,
+            compare: self.target.compare.as_ref(),
+        };
+        index.report_event(event, &mut self.alerts, &mut self.summary, self.digest.as_ref()).await;
+        if poll_ended {
+            self.refresh_expected(controls).await;
+        }
     }
 
-    Ok(())
+    /// Runs the expected records command when its refresh is due, for the next polls to go by its count
+    async fn refresh_expected(&mut self, controls: &mpsc::UnboundedSender<Control>) {
+        let Some(command) = self.expected_command.as_mut().filter(|command| command.is_due()) else {
+            return;
+        };
+        let subset = self.target.subset.as_ref().map(|subset| subset.name.as_str());
+        match command.run(&self.target.app_id, &self.target.index_name, subset).await {
+            Ok(expected_records) => {
+                self.expected_records = expected_records;
+                let _ = controls.send(Control::ExpectedRecords(expected_records));
+            }
+            Err(error) => warn!(
+                "[{}] Keeping the expected records count of {} at {}: {}",
+                now(),
+                self.name,
+                self.expected_records,
+                error
+            ),
+        }
+    }
+
+    /// Goes on with the settings of a reload of the config file, returning `false` when the run stops instead
+    /// as the reload left the monitor out or has to start it again
+    fn reload(&mut self, current: &Arc<Settings>, controls: &mpsc::UnboundedSender<Control>) -> bool {
+        let Some(reloaded) = reloaded_target(current, &self.target) else {
+            let _ = controls.send(Control::Stop);
+            return false;
+        };
+        let _ = controls.send(Control::Reload(Box::new(Reload {
+            thresholds: reloaded.thresholds(&current.defaults),
+            log_filter: reloaded.log_filter(&current.defaults),
+            poll_delay: reloaded.poll_delay(&current.defaults),
+        })));
+        self.target = reloaded;
+        self.settings = current.clone();
+        true
+    }
+
+    /// Statistics of the run once it ended, breached when an alert of any of its checks was
+    fn finish(self) -> Summary {
+        Summary { breached: self.alerts.breached(), ..self.summary }
+    }
+}
+
+/// Target of the monitor in the settings of a reload of the config file, `None` when the reload left it out or
+/// changed it so it has to start again
+fn reloaded_target(current: &Settings, target: &Target) -> Option<Target> {
+    if let Some(reloaded) = current.targets.iter().find(|other| other.same_monitor(target)) {
+        return Some(reloaded.clone());
+    }
+    let index_name = target.name();
+    let restarted = current.targets.iter().any(|other| other.name() == index_name);
+    info!(
+        "[{}] {}",
+        now(),
+        match restarted {
+            true => format!("Restarting the monitor of {index_name} with its new settings"),
+            false => format!("Stopped monitoring {index_name}, it left the config file"),
+        }
+    );
+    None
+}
+
+/// Outputs the events of the monitor of the index go to, along with the digest among them
+fn index_sinks(
+    target: &Target,
+    args: &Args,
+    settings: &watch::Receiver<Arc<Settings>>,
+    output: &Output,
+) -> (EventSinks, Option<DigestSink>) {
+    let mut sinks = output.sinks.for_index(&target.name(), output);
+    let digest = args.digest.map(|interval| {
+        DigestSink::new(
+            target.app_id.clone(),
+            target.index_name.clone(),
+            target.subset.as_ref().map(|subset| subset.name.clone()),
+            interval,
+            {
+                let settings = settings.clone();
+                move || settings.borrow().notifier.clone()
+            },
+            output.clone(),
+        )
+    });
+    if let Some(digest) = &digest {
+        sinks = sinks.with_sink(digest.clone());
+    }
+    (sinks, digest)
 }
 
+/// What ended the wait for the next poll of an index
+enum Wake {
+    Poll,
+    Stop,
+    /// The config file was reloaded with new settings for the monitor, which goes on with them
+    Reload(Arc<Settings>, Box<Target>),
+}
 
-async fn print_all_logs(
+/// Waits for the next poll, or for a poll to be asked for, skipping the polls the pacing of the requests of the
+/// application would hold past the next one
+///
+/// A reload leaving the monitor out of the config file, or changing it so it has to start again, stops it.
+async fn wait_for_poll(
+    next_poll: &mut tokio::time::Instant,
     client: &AlgoliaClient,
-    last_log_timestamp: &mut String,
-) -> Result<(), reqwest::Error> {
-    print_algolia_logs(client, last_log_timestamp).await?;
-    Ok(())
+    target: &Target,
+    defaults: &TargetDefaults,
+    output: &Output,
+    settings: &mut watch::Receiver<Arc<Settings>>,
+    shutdown: &mut watch::Receiver<bool>,
+) -> Wake {
+    let index_name = target.name();
+    loop {
+        tokio::select! {
+            _ = sleep_until(*next_poll) => {
+                let backlog = client.pacing_backlog();
+                let delay = target.delay(defaults);
+                if backlog <= delay {
+                    return Wake::Poll;
+                }
+                debug!(
+                    "[{}] Skipping a poll of {}, the requests of {} are paced {} behind",
+                    now(),
+                    index_name,
+                    target.app_id,
+                    humantime::format_duration(Duration::from_millis(backlog.as_millis() as u64))
+                );
+                *next_poll += delay;
+            }
+            _ = output.dashboard.poll_requested() => return Wake::Poll,
+            _ = output.state.poll_requested() => return Wake::Poll,
+            _ = shutdown.wait_for(|&shutdown| shutdown) => return Wake::Stop,
+            Ok(()) = settings.changed() => {
+                let current = settings.borrow_and_update().clone();
+                return match reloaded_target(&current, target) {
+                    Some(reloaded) => Wake::Reload(current, Box::new(reloaded)),
+                    None => Wake::Stop,
+                };
+            }
+        }
+    }
+}
+
+/// Logs when the next poll of the index is due and records it in the metrics along with the configured delay
+fn report_next_poll(metrics: &Metrics, index_name: &str, interval: Duration, next_poll: tokio::time::Instant) {
+    let delay = next_poll.saturating_duration_since(tokio::time::Instant::now());
+    let next_poll_at = Utc::now() + delay;
+    debug!(
+        "[{}] Next poll of {} in {} at {}",
+        now(),
+        index_name,
+        humantime::format_duration(Duration::from_millis(delay.as_millis() as u64)),
+        next_poll_at.to_rfc3339_opts(SecondsFormat::Secs, true)
+    );
+    metrics.next_poll(index_name, interval, next_poll_at);
 }
 
-async fn print_algolia_logs(client: &AlgoliaClient, last_log_timestamp: &mut String) -> Result<(), Error> {
-    let logs = client.get_logs().await?;
-    for log in &logs {
-        if log.is_newer(last_log_timestamp) {
-            println!("{}", log.message);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use algolia_monitor::IndexStats;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Config files written so far, numbering the next one so the tests running at once don't share one
+    static CONFIG_FILES: AtomicUsize = AtomicUsize::new(0);
+
+    /// Arguments of the command line after applying the config file, along with the indices of both
+    fn try_load(command_line: &[&str], config: &str) -> Result<(Args, Vec<Target>, Vec<Target>), MonitorError> {
+        let number = CONFIG_FILES.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("algolia-monitor-{}-{}.toml", std::process::id(), number));
+        std::fs::write(&path, config).unwrap();
+        let mut command_line = command_line.to_vec();
+        let config_arg = format!("--config={}", path.display());
+        command_line.insert(1, &config_arg);
+
+        let matches = Args::command().try_get_matches_from(command_line).unwrap();
+        let mut args = Args::from_arg_matches(&matches).unwrap();
+        let loaded = args.load_targets(&matches);
+        std::fs::remove_file(&path).unwrap();
+        let (command_line_targets, config_targets) = loaded?;
+
+        Ok((args, command_line_targets, config_targets))
+    }
+
+    fn load(command_line: &[&str], config: &str) -> (Args, Vec<Target>, Vec<Target>) {
+        try_load(command_line, config).unwrap()
+    }
+
+    const DEFAULTS: &str = r#"
+        delay = "5m"
+        min_delta = -50
+        max_delta = 200
+        all_logs = true
+        max_consecutive_errors = 3
+        alert_cooldown = 3600
+        base_url = "http://localhost:8080/1/"
+        webhook_url = "https://config.example.com/hook"
+    "#;
+
+    #[test]
+    fn config_file_fills_in_the_options_left_out_of_the_command_line() {
+        let (args, targets, _) = load(&["algolia-monitor", "APPID", "key", "products"], DEFAULTS);
+
+        assert_eq!(args.delay, Duration::from_secs(300));
+        assert_eq!(args.delta, None);
+        let thresholds = Thresholds::from_limits(None, Some(-50), Some(200), None).unwrap().unwrap();
+        assert_eq!(args.thresholds(), thresholds);
+        assert!(args.all_logs);
+        assert_eq!(args.max_consecutive_errors, 3);
+        assert_eq!(args.alert_cooldown, Duration::from_secs(3600));
+        assert_eq!(args.base_url, ["http://localhost:8080/1/"]);
+        assert_eq!(args.webhook_url.as_deref(), Some("https://config.example.com/hook"));
+        assert_eq!(targets[0].thresholds(&args.target_defaults()), args.thresholds());
+    }
+
+    #[test]
+    fn command_line_overrides_the_config_file() {
+        let command_line = [
+            "algolia-monitor",
+            "--delay=10s",
+            "--max-consecutive-errors=7",
+            "--alert-cooldown=1m",
+            "--base-url=http://localhost:9090/1/",
+            "--webhook-url=https://cli.example.com/hook",
+            "APPID",
+            "key",
+            "products",
+        ];
+        let (args, _, _) = load(&command_line, DEFAULTS);
+
+        assert_eq!(args.delay, Duration::from_secs(10));
+        assert_eq!(args.max_consecutive_errors, 7);
+        assert_eq!(args.alert_cooldown, Duration::from_secs(60));
+        assert_eq!(args.base_url, ["http://localhost:9090/1/"]);
+        assert_eq!(args.webhook_url.as_deref(), Some("https://cli.example.com/hook"));
+        // Left out of the command line, so still from the config file
+        assert!(args.all_logs);
+    }
+
+    #[test]
+    fn command_line_value_equal_to_the_default_overrides_the_config_file() {
+        let (args, _, _) = load(&["algolia-monitor", "--delay=30s", "APPID", "key", "products"], "delay = 90");
+        let (defaults, _, _) = load(&["algolia-monitor", "APPID", "key", "products"], "delay = 90");
+
+        assert_eq!(args.delay, Duration::from_secs(30));
+        assert_eq!(defaults.delay, Duration::from_secs(90));
+    }
+
+    #[test]
+    fn one_threshold_on_the_command_line_replaces_all_the_ones_of_the_config_file() {
+        for threshold in ["--delta=-10", "--min-delta=-10", "--max-delta=10", "--delta-percent=5"] {
+            let (args, _, _) = load(&["algolia-monitor", threshold, "APPID", "key", "products"], DEFAULTS);
+
+            let expected = match threshold {
+                "--delta=-10" => Thresholds::from_delta(-10),
+                "--min-delta=-10" => Thresholds::from_limits(None, Some(-10), None, None).unwrap().unwrap(),
+                "--max-delta=10" => Thresholds::from_limits(None, None, Some(10), None).unwrap().unwrap(),
+                _ => Thresholds::from_limits(None, None, None, Some(5.0)).unwrap().unwrap(),
+            };
+            assert_eq!(args.thresholds(), expected, "{threshold}");
         }
     }
-    for log in &logs {
-        if log.is_newer(last_log_timestamp) {
-            let _ = std::mem::replace(last_log_timestamp, log.timestamp.clone());
+
+    /// Reader of the output that went away, like `head` once it has its lines
+    struct ClosedPipe;
+
+    impl Write for ClosedPipe {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
         }
     }
-    Ok(())
+
+    #[test]
+    fn output_to_a_closed_pipe_is_written_without_an_error() {
+        assert!(write_output(&mut ClosedPipe, b"complete -F _algolia-monitor algolia-monitor").is_ok());
+        assert!(write_output(&mut Vec::new(), b"script").is_ok());
+    }
+
+    #[test]
+    fn delta_with_another_limit_is_rejected_before_the_thresholds() {
+        for limit in ["--min-delta=-10", "--max-delta=10"] {
+            let command_line = ["algolia-monitor", "APPID", "key", "products", "--delta=-100", limit];
+
+            assert!(Args::command().try_get_matches_from(command_line).is_err(), "{limit}");
+        }
+        let config = "delta = -100\nmin_delta = -10";
+
+        assert!(try_load(&["algolia-monitor", "APPID", "key", "products"], config).is_err());
+    }
+
+    #[test]
+    fn thresholds_of_the_config_file_replace_the_default_delta() {
+        let (args, _, _) = load(&["algolia-monitor", "APPID", "key", "products"], "delta_percent = 2.5");
+
+        assert_eq!(args.thresholds(), Thresholds::from_limits(None, None, None, Some(2.5)).unwrap().unwrap());
+    }
+
+    #[test]
+    fn index_of_the_config_file_overrides_the_command_line() {
+        let config = r#"
+            [[index]]
+            app_id = "APPID"
+            key = "config-key"
+            name = "categories"
+            delay = "1m"
+            min_delta = -5
+            all_logs = false
+
+            [[index.subset]]
+            name = "store_1"
+            filters = "store_id:1"
+        "#;
+        let command_line =
+            ["algolia-monitor", "--delay=10s", "--min-delta=-100", "--all-logs", "APPID", "key", "products"];
+        let (args, command_line_targets, config_targets) = load(&command_line, config);
+
+        let index_thresholds = Thresholds::from_limits(None, Some(-5), None, None).unwrap().unwrap();
+        assert_eq!(command_line_targets[0].delay(&args.target_defaults()), Duration::from_secs(10));
+        assert_eq!(config_targets[0].delay(&args.target_defaults()), Duration::from_secs(60));
+        assert_eq!(config_targets[0].thresholds(&args.target_defaults()), index_thresholds);
+        assert!(command_line_targets[0].tails_logs(&args.target_defaults()));
+        assert!(!config_targets[0].tails_logs(&args.target_defaults()));
+        // The subset falls back to the thresholds of its index rather than the ones of the command line
+        assert_eq!(config_targets[1].name(), "categories:store_1");
+        assert_eq!(config_targets[1].thresholds(&args.target_defaults()), index_thresholds);
+    }
+
+    #[test]
+    fn key_of_an_index_is_read_from_the_environment_variable_it_names() {
+        std::env::set_var("ALGOLIA_MONITOR_TEST_CONFIG_KEY", "key-from-the-environment");
+        let config = r#"
+            [[index]]
+            app_id = "APPID"
+            key_env = "ALGOLIA_MONITOR_TEST_CONFIG_KEY"
+            name = "products"
+        "#;
+
+        let (_, _, targets) = load(&["algolia-monitor"], config);
+
+        assert_eq!(targets[0].key.expose(), "key-from-the-environment");
+    }
+
+    #[test]
+    fn index_with_a_missing_environment_variable_is_an_error() {
+        let config = r#"
+            [[index]]
+            app_id = "APPID"
+            key_env = "ALGOLIA_MONITOR_TEST_UNSET_KEY"
+            name = "products"
+        "#;
+
+        let error = try_load(&["algolia-monitor"], config).err().unwrap();
+
+        assert!(error.to_string().contains("ALGOLIA_MONITOR_TEST_UNSET_KEY"), "{error}");
+    }
+
+    #[test]
+    fn key_argument_takes_precedence_over_the_environment() {
+        std::env::set_var("ALGOLIA_API_KEY", "key-from-the-environment");
+        let matches = Args::command().try_get_matches_from(["algolia-monitor", "APPID", "cli-key", "products"]);
+        let mut args = Args::from_arg_matches(&matches.unwrap()).unwrap();
+
+        assert_eq!(args.api_key().unwrap().expose(), "cli-key");
+        assert_eq!(args.index_name, ["products"]);
+
+        let matches = Args::command().try_get_matches_from(["algolia-monitor", "APPID", "products,categories"]);
+        let mut args = Args::from_arg_matches(&matches.unwrap()).unwrap();
+
+        assert_eq!(args.api_key().unwrap().expose(), "key-from-the-environment");
+        assert_eq!(args.index_name, ["products", "categories"]);
+    }
+
+    fn stats(records: u64) -> Result<IndexStats, MonitorError> {
+        Ok(IndexStats {
+            records,
+            data_size: None,
+            created_at: None,
+            updated_at: None,
+            processing_time_ms: None,
+        })
+    }
+
+    /// Settings the monitors follow when started with the arguments alone
+    fn settings(args: Args) -> Settings {
+        Settings {
+            defaults: args.target_defaults(),
+            alert_options: args.alert_options(),
+            args: Arc::new(args),
+            notifier: Notifier::default(),
+            targets: vec![],
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn initial_records_count_is_fetched_again_after_a_failure() {
+        let (args, targets, _) = load(&["algolia-monitor", "APPID", "key", "products"], DEFAULTS);
+        let timeout = Err(MonitorError::Timeout(Duration::from_secs(5)));
+        let source = algolia_monitor::MockSource::new().with_stats("products", [timeout, stats(1000)]);
+        let output = Output::new(None, ColorMode::Never);
+        let (_shutdown, mut shutdown) = watch::channel(false);
+
+        let records = initial_records(&targets[0], &settings(args), &source, &output, &mut shutdown).await;
+
+        assert_eq!(records, Some(1000));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn initial_records_count_gives_up_on_the_index_after_too_many_failures() {
+        let (args, targets, _) = load(&["algolia-monitor", "APPID", "key", "products"], DEFAULTS);
+        let timeouts = (0..3).map(|_| Err(MonitorError::Timeout(Duration::from_secs(5))));
+        let source = algolia_monitor::MockSource::new().with_stats("products", timeouts.chain([stats(1000)]));
+        let output = Output::new(None, ColorMode::Never);
+        let (_shutdown, mut shutdown) = watch::channel(false);
+
+        let records = initial_records(&targets[0], &settings(args), &source, &output, &mut shutdown).await;
+
+        assert_eq!(records, None);
+    }
 }
@@ -0,0 +1,141 @@
+#[cfg(feature = "cloudwatch")]
+mod cloudwatch;
+mod otlp;
+mod prometheus;
+mod pushgateway;
+mod statsd;
+
+use crate::latency::Percentiles;
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(feature = "cloudwatch")]
+pub use self::cloudwatch::CloudWatchMetrics;
+pub use self::otlp::OtlpMetrics;
+pub use self::prometheus::PrometheusMetrics;
+pub use self::pushgateway::Pushgateway;
+pub use self::statsd::StatsdMetrics;
+
+/// Kind of the Algolia API call to an endpoint, telling what it costs: `query` and `browse` for the search
+/// operations, `logs`, `settings` along with the synonyms and rules, and `other`
+pub fn api_call_type(endpoint: &str) -> &'static str {
+    match endpoint {
+        "query" => "query",
+        "browse" => "browse",
+        "logs" => "logs",
+        "settings" | "synonyms" | "rules" => "settings",
+        _ => "other",
+    }
+}
+
+/// Destination of the values collected by the polling loop
+pub trait MetricsBackend: Send + Sync {
+    fn records(&self, index_name: &str, records: u64, expected_records: u64);
+
+    fn data_size(&self, index_name: &str, data_size: u64);
+
+    fn pending_tasks(&self, index_name: &str, pending_tasks: u64);
+
+    fn latency(&self, index_name: &str, latency: &Percentiles);
+
+    fn poll_error(&self, index_name: &str);
+
+    fn threshold_breach(&self, index_name: &str);
+
+    fn api_response(&self, endpoint: &str, duration: Duration);
+
+    /// Time a request waited for the pacing and the limit of concurrent requests before it was sent, ignored by
+    /// backends that only keep the values of the polls
+    fn request_wait(&self, _endpoint: &str, _wait: Duration) {}
+
+    /// Size of a response body once decompressed, ignored by backends that only keep the values of the polls
+    fn response_size(&self, _endpoint: &str, _bytes: u64) {}
+
+    /// Time a poll of the index took, with the error it failed with, ignored by backends that only keep the
+    /// values of the polls
+    fn poll(&self, _index_name: &str, _duration: Duration, _error: Option<&str>) {}
+
+    /// Delay between the polls of the index and the time of the next one, ignored by backends that don't show
+    /// the schedule
+    fn next_poll(&self, _index_name: &str, _delay: Duration, _next_poll: DateTime<Utc>) {}
+}
+
+/// Forwards every update to all enabled backends
+#[derive(Clone, Default)]
+pub struct Metrics {
+    backends: Vec<Arc<dyn MetricsBackend>>,
+}
+
+impl Metrics {
+    pub fn with_backend(mut self, backend: impl MetricsBackend + 'static) -> Self {
+        self.backends.push(Arc::new(backend));
+        self
+    }
+
+    pub fn records(&self, index_name: &str, records: u64, expected_records: u64) {
+        for backend in &self.backends {
+            backend.records(index_name, records, expected_records);
+        }
+    }
+
+    pub fn data_size(&self, index_name: &str, data_size: u64) {
+        for backend in &self.backends {
+            backend.data_size(index_name, data_size);
+        }
+    }
+
+    pub fn pending_tasks(&self, index_name: &str, pending_tasks: u64) {
+        for backend in &self.backends {
+            backend.pending_tasks(index_name, pending_tasks);
+        }
+    }
+
+    pub fn latency(&self, index_name: &str, latency: &Percentiles) {
+        for backend in &self.backends {
+            backend.latency(index_name, latency);
+        }
+    }
+
+    pub fn poll_error(&self, index_name: &str) {
+        for backend in &self.backends {
+            backend.poll_error(index_name);
+        }
+    }
+
+    pub fn threshold_breach(&self, index_name: &str) {
+        for backend in &self.backends {
+            backend.threshold_breach(index_name);
+        }
+    }
+
+    pub fn api_response(&self, endpoint: &str, duration: Duration) {
+        for backend in &self.backends {
+            backend.api_response(endpoint, duration);
+        }
+    }
+
+    pub fn request_wait(&self, endpoint: &str, wait: Duration) {
+        for backend in &self.backends {
+            backend.request_wait(endpoint, wait);
+        }
+    }
+
+    pub fn response_size(&self, endpoint: &str, bytes: u64) {
+        for backend in &self.backends {
+            backend.response_size(endpoint, bytes);
+        }
+    }
+
+    pub fn poll(&self, index_name: &str, duration: Duration, error: Option<&str>) {
+        for backend in &self.backends {
+            backend.poll(index_name, duration, error);
+        }
+    }
+
+    pub fn next_poll(&self, index_name: &str, delay: Duration, next_poll: DateTime<Utc>) {
+        for backend in &self.backends {
+            backend.next_poll(index_name, delay, next_poll);
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use super::MetricsBackend;
+use crate::latency::Percentiles;
+use aws_config::{BehaviorVersion, Region};
+use aws_sdk_cloudwatch::error::ProvideErrorMetadata;
+use aws_sdk_cloudwatch::types::{Dimension, MetricDatum, StandardUnit};
+use aws_sdk_cloudwatch::Client;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// Number of datapoints sent with a single PutMetricData request
+const MAX_BATCH_SIZE: usize = 20;
+
+/// Number of times a throttled request is sent again, waiting twice as long each time
+const THROTTLING_RETRIES: u32 = 4;
+
+/// CloudWatch metrics with the `AppId` and `IndexName` dimensions, collected between calls to
+/// [`CloudWatchMetrics::publish`] so the datapoints of every index go out together
+///
+/// Only `RecordCount`, `RecordDelta` and `PollErrors` are published, the latter being 0 for a successful
+/// poll so alarms have a datapoint for each one.
+#[derive(Clone)]
+pub struct CloudWatchMetrics {
+    client: Client,
+    namespace: String,
+    app_id: Option<String>,
+    datapoints: Arc<Mutex<Vec<MetricDatum>>>,
+}
+
+impl CloudWatchMetrics {
+    /// Loads the credentials and the region from the default provider chain, the region being replaced by
+    /// `region` when given
+    pub async fn connect(namespace: &str, region: Option<&str>, app_id: Option<&str>) -> Self {
+        let mut loader = aws_config::defaults(BehaviorVersion::latest());
+        if let Some(region) = region {
+            loader = loader.region(Region::new(region.to_string()));
+        }
+
+        CloudWatchMetrics {
+            client: Client::new(&loader.load().await),
+            namespace: namespace.to_string(),
+            app_id: app_id.map(str::to_string),
+            datapoints: Arc::default(),
+        }
+    }
+
+    /// Publishes the datapoints collected since the previous call, retrying the requests CloudWatch throttles
+    pub async fn publish(&self) -> Result<(), aws_sdk_cloudwatch::Error> {
+        let datapoints = std::mem::take(&mut *self.datapoints.lock().unwrap());
+        for batch in datapoints.chunks(MAX_BATCH_SIZE) {
+            self.put(batch).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn put(&self, batch: &[MetricDatum]) -> Result<(), aws_sdk_cloudwatch::Error> {
+        let mut attempt = 0;
+        loop {
+            let result = self
+                .client
+                .put_metric_data()
+                .namespace(&self.namespace)
+                .set_metric_data(Some(batch.to_vec()))
+                .send()
+                .await;
+
+            match result {
+                Ok(_) => return Ok(()),
+                Err(error) if error.code() == Some("Throttling") && attempt < THROTTLING_RETRIES => {
+                    tokio::time::sleep(Duration::from_secs(1 << attempt)).await;
+                    attempt += 1;
+                }
+                Err(error) => return Err(error.into()),
+            }
+        }
+    }
+
+    fn record(&self, name: &str, index_name: &str, value: f64, unit: StandardUnit) {
+        let app_id = self
+            .app_id
+            .as_deref()
+            .map(|app_id| Dimension::builder().name("AppId").value(app_id).build());
+        let index = Dimension::builder().name("IndexName").value(index_name).build();
+
+        let datapoint = MetricDatum::builder()
+            .metric_name(name)
+            .set_dimensions(Some(app_id.into_iter().chain([index]).collect()))
+            .value(value)
+            .unit(unit)
+            .timestamp(SystemTime::now().into())
+            .build();
+        self.datapoints.lock().unwrap().push(datapoint);
+    }
+}
+
+impl MetricsBackend for CloudWatchMetrics {
+    fn records(&self, index_name: &str, records: u64, expected_records: u64) {
+        self.record("RecordCount", index_name, records as f64, StandardUnit::Count);
+        self.record(
+            "RecordDelta",
+            index_name,
+            records as f64 - expected_records as f64,
+            StandardUnit::Count,
+        );
+    }
+
+    fn data_size(&self, _index_name: &str, _data_size: u64) {}
+
+    fn pending_tasks(&self, _index_name: &str, _pending_tasks: u64) {}
+
+    fn latency(&self, _index_name: &str, _latency: &Percentiles) {}
+
+    fn poll_error(&self, _index_name: &str) {}
+
+    fn threshold_breach(&self, _index_name: &str) {}
+
+    fn api_response(&self, _endpoint: &str, _duration: Duration) {}
+
+    fn poll(&self, index_name: &str, _duration: Duration, error: Option<&str>) {
+        let errors = match error {
+            Some(_) => 1.0,
+            None => 0.0,
+        };
+        self.record("PollErrors", index_name, errors, StandardUnit::Count);
+    }
+}
@@ -0,0 +1,147 @@
+use super::MetricsBackend;
+use crate::latency::Percentiles;
+use opentelemetry::metrics::{Counter, Gauge, Histogram, Meter};
+use opentelemetry::trace::{Span, Status, Tracer};
+use opentelemetry::KeyValue;
+use opentelemetry_sdk::trace::SdkTracer;
+use std::time::{Duration, SystemTime};
+
+/// OpenTelemetry instruments exported over OTLP, along with a span for each poll when tracing is enabled
+pub struct OtlpMetrics {
+    records: Gauge<u64>,
+    expected_records: Gauge<u64>,
+    records_delta: Gauge<i64>,
+    data_size: Gauge<u64>,
+    pending_tasks: Gauge<u64>,
+    search_latency: Gauge<u64>,
+    poll_duration: Histogram<f64>,
+    poll_errors: Counter<u64>,
+    threshold_breaches: Counter<u64>,
+    api_response_time: Histogram<f64>,
+    tracer: Option<SdkTracer>,
+}
+
+impl OtlpMetrics {
+    pub fn new(meter: &Meter) -> Self {
+        OtlpMetrics {
+            records: meter
+                .u64_gauge("algolia.index.records")
+                .with_description("Records count reported by Algolia")
+                .build(),
+            expected_records: meter
+                .u64_gauge("algolia.index.expected_records")
+                .with_description("Expected records count of the index")
+                .build(),
+            records_delta: meter
+                .i64_gauge("algolia.records.delta")
+                .with_description("Difference between current and expected records count")
+                .build(),
+            data_size: meter
+                .u64_gauge("algolia.index.data_size")
+                .with_description("Size of the index data reported by Algolia")
+                .with_unit("By")
+                .build(),
+            pending_tasks: meter
+                .u64_gauge("algolia.index.pending_tasks")
+                .with_description("Indexing tasks of the index Algolia didn't publish yet")
+                .build(),
+            search_latency: meter
+                .u64_gauge("algolia.search.latency")
+                .with_description("Percentiles of the latest search processing times")
+                .with_unit("ms")
+                .build(),
+            poll_duration: meter
+                .f64_histogram("algolia.poll.duration")
+                .with_description("Time a poll of the index took")
+                .with_unit("s")
+                .build(),
+            poll_errors: meter
+                .u64_counter("algolia.poll.errors")
+                .with_description("Number of failed polls")
+                .build(),
+            threshold_breaches: meter
+                .u64_counter("algolia.threshold.breaches")
+                .with_description("Number of polls with the records count past the delta")
+                .build(),
+            api_response_time: meter
+                .f64_histogram("algolia.api.response_time")
+                .with_description("Response time of Algolia API requests")
+                .with_unit("s")
+                .build(),
+            tracer: None,
+        }
+    }
+
+    /// Starts a span for each poll, named `poll` and carrying the index and the outcome of the poll
+    pub fn with_tracer(mut self, tracer: SdkTracer) -> Self {
+        self.tracer = Some(tracer);
+        self
+    }
+}
+
+impl MetricsBackend for OtlpMetrics {
+    fn records(&self, index_name: &str, records: u64, expected_records: u64) {
+        let attributes = [KeyValue::new("index", index_name.to_string())];
+        self.records.record(records, &attributes);
+        self.expected_records.record(expected_records, &attributes);
+        self.records_delta
+            .record(records as i64 - expected_records as i64, &attributes);
+    }
+
+    fn data_size(&self, index_name: &str, data_size: u64) {
+        self.data_size
+            .record(data_size, &[KeyValue::new("index", index_name.to_string())]);
+    }
+
+    fn pending_tasks(&self, index_name: &str, pending_tasks: u64) {
+        self.pending_tasks
+            .record(pending_tasks, &[KeyValue::new("index", index_name.to_string())]);
+    }
+
+    fn latency(&self, index_name: &str, latency: &Percentiles) {
+        for (quantile, value) in [("0.5", latency.p50), ("0.95", latency.p95), ("0.99", latency.p99)] {
+            self.search_latency.record(
+                value,
+                &[KeyValue::new("index", index_name.to_string()), KeyValue::new("quantile", quantile)],
+            );
+        }
+    }
+
+    fn poll_error(&self, index_name: &str) {
+        self.poll_errors.add(1, &[KeyValue::new("index", index_name.to_string())]);
+    }
+
+    fn threshold_breach(&self, index_name: &str) {
+        self.threshold_breaches
+            .add(1, &[KeyValue::new("index", index_name.to_string())]);
+    }
+
+    fn api_response(&self, endpoint: &str, duration: Duration) {
+        self.api_response_time
+            .record(duration.as_secs_f64(), &[KeyValue::new("endpoint", endpoint.to_string())]);
+    }
+
+    fn poll(&self, index_name: &str, duration: Duration, error: Option<&str>) {
+        let outcome = match error {
+            Some(_) => "error",
+            None => "success",
+        };
+        self.poll_duration.record(
+            duration.as_secs_f64(),
+            &[KeyValue::new("index", index_name.to_string()), KeyValue::new("outcome", outcome)],
+        );
+
+        let Some(tracer) = &self.tracer else {
+            return;
+        };
+        let mut span = tracer
+            .span_builder("poll")
+            .with_start_time(SystemTime::now() - duration)
+            .with_attributes([KeyValue::new("index", index_name.to_string()), KeyValue::new("outcome", outcome)])
+            .start(tracer);
+        if let Some(error) = error {
+            span.set_status(Status::error(error.to_string()));
+        }
+        span.end();
+    }
+}
@@ -0,0 +1,234 @@
+use super::{api_call_type, MetricsBackend};
+use crate::latency::Percentiles;
+use axum::{extract::State, http::header, response::IntoResponse, routing::get, Router};
+use chrono::{DateTime, Utc};
+use prometheus::{
+    exponential_buckets, Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+/// Prometheus metrics scraped from the `/metrics` endpoint
+#[derive(Clone)]
+pub struct PrometheusMetrics {
+    registry: Registry,
+    records: IntGaugeVec,
+    expected_records: IntGaugeVec,
+    records_delta: IntGaugeVec,
+    data_size: IntGaugeVec,
+    pending_tasks: IntGaugeVec,
+    search_latency: IntGaugeVec,
+    poll_errors: IntCounterVec,
+    threshold_breaches: IntCounterVec,
+    api_response_time: HistogramVec,
+    api_calls: IntCounterVec,
+    request_wait: HistogramVec,
+    response_size: HistogramVec,
+    poll_interval: IntGaugeVec,
+    next_poll: IntGaugeVec,
+}
+
+impl Default for PrometheusMetrics {
+    fn default() -> Self {
+        let records = IntGaugeVec::new(
+            Opts::new("algolia_index_records", "Records count reported by Algolia"),
+            &["index"],
+        )
+        .unwrap();
+        let expected_records = IntGaugeVec::new(
+            Opts::new("algolia_index_expected_records", "Expected records count of the index"),
+            &["index"],
+        )
+        .unwrap();
+        let records_delta = IntGaugeVec::new(
+            Opts::new("algolia_records_delta", "Difference between current and expected records count"),
+            &["index"],
+        )
+        .unwrap();
+        let data_size = IntGaugeVec::new(
+            Opts::new("algolia_index_data_size_bytes", "Size of the index data reported by Algolia"),
+            &["index"],
+        )
+        .unwrap();
+        let pending_tasks = IntGaugeVec::new(
+            Opts::new("algolia_index_pending_tasks", "Indexing tasks of the index Algolia didn't publish yet"),
+            &["index"],
+        )
+        .unwrap();
+        let search_latency = IntGaugeVec::new(
+            Opts::new("algolia_search_latency_ms", "Percentiles of the latest search processing times"),
+            &["index", "quantile"],
+        )
+        .unwrap();
+        let poll_errors = IntCounterVec::new(
+            Opts::new("algolia_poll_errors_total", "Number of failed polls"),
+            &["index"],
+        )
+        .unwrap();
+        let threshold_breaches = IntCounterVec::new(
+            Opts::new("algolia_threshold_breaches_total", "Number of polls with the records count past the delta"),
+            &["index"],
+        )
+        .unwrap();
+        let api_response_time = HistogramVec::new(
+            HistogramOpts::new("algolia_api_response_seconds", "Response time of Algolia API requests"),
+            &["endpoint"],
+        )
+        .unwrap();
+        let api_calls = IntCounterVec::new(
+            Opts::new(
+                "algolia_monitor_api_calls_total",
+                "Algolia API calls made by the monitor, retries included",
+            ),
+            &["type"],
+        )
+        .unwrap();
+        let request_wait = HistogramVec::new(
+            HistogramOpts::new(
+                "algolia_request_wait_seconds",
+                "Time Algolia API requests waited for the pacing and the concurrency limit",
+            ),
+            &["endpoint"],
+        )
+        .unwrap();
+        let response_size = HistogramVec::new(
+            HistogramOpts::new("algolia_api_response_bytes", "Size of the decompressed Algolia API responses")
+                .buckets(exponential_buckets(256.0, 4.0, 10).unwrap()),
+            &["endpoint"],
+        )
+        .unwrap();
+        let poll_interval = IntGaugeVec::new(
+            Opts::new("algolia_poll_interval_seconds", "Delay between two polls of the index"),
+            &["index"],
+        )
+        .unwrap();
+        let next_poll = IntGaugeVec::new(
+            Opts::new("algolia_next_poll_timestamp_seconds", "Unix time of the next poll of the index"),
+            &["index"],
+        )
+        .unwrap();
+
+        let registry = Registry::new();
+        registry.register(Box::new(records.clone())).unwrap();
+        registry.register(Box::new(expected_records.clone())).unwrap();
+        registry.register(Box::new(records_delta.clone())).unwrap();
+        registry.register(Box::new(data_size.clone())).unwrap();
+        registry.register(Box::new(pending_tasks.clone())).unwrap();
+        registry.register(Box::new(search_latency.clone())).unwrap();
+        registry.register(Box::new(poll_errors.clone())).unwrap();
+        registry.register(Box::new(threshold_breaches.clone())).unwrap();
+        registry.register(Box::new(api_response_time.clone())).unwrap();
+        registry.register(Box::new(api_calls.clone())).unwrap();
+        registry.register(Box::new(request_wait.clone())).unwrap();
+        registry.register(Box::new(response_size.clone())).unwrap();
+        registry.register(Box::new(poll_interval.clone())).unwrap();
+        registry.register(Box::new(next_poll.clone())).unwrap();
+
+        PrometheusMetrics {
+            registry,
+            records,
+            expected_records,
+            records_delta,
+            data_size,
+            pending_tasks,
+            search_latency,
+            poll_errors,
+            threshold_breaches,
+            api_response_time,
+            api_calls,
+            request_wait,
+            response_size,
+            poll_interval,
+            next_poll,
+        }
+    }
+}
+
+impl MetricsBackend for PrometheusMetrics {
+    fn records(&self, index_name: &str, records: u64, expected_records: u64) {
+        self.records.with_label_values(&[index_name]).set(records as i64);
+        self.expected_records
+            .with_label_values(&[index_name])
+            .set(expected_records as i64);
+        self.records_delta
+            .with_label_values(&[index_name])
+            .set(records as i64 - expected_records as i64);
+    }
+
+    fn data_size(&self, index_name: &str, data_size: u64) {
+        self.data_size.with_label_values(&[index_name]).set(data_size as i64);
+    }
+
+    fn pending_tasks(&self, index_name: &str, pending_tasks: u64) {
+        self.pending_tasks.with_label_values(&[index_name]).set(pending_tasks as i64);
+    }
+
+    fn latency(&self, index_name: &str, latency: &Percentiles) {
+        for (quantile, value) in [("0.5", latency.p50), ("0.95", latency.p95), ("0.99", latency.p99)] {
+            self.search_latency
+                .with_label_values(&[index_name, quantile])
+                .set(value as i64);
+        }
+    }
+
+    fn poll_error(&self, index_name: &str) {
+        self.poll_errors.with_label_values(&[index_name]).inc();
+    }
+
+    fn threshold_breach(&self, index_name: &str) {
+        self.threshold_breaches.with_label_values(&[index_name]).inc();
+    }
+
+    fn api_response(&self, endpoint: &str, duration: Duration) {
+        self.api_response_time
+            .with_label_values(&[endpoint])
+            .observe(duration.as_secs_f64());
+        self.api_calls.with_label_values(&[api_call_type(endpoint)]).inc();
+    }
+
+    fn request_wait(&self, endpoint: &str, wait: Duration) {
+        self.request_wait.with_label_values(&[endpoint]).observe(wait.as_secs_f64());
+    }
+
+    fn response_size(&self, endpoint: &str, bytes: u64) {
+        self.response_size.with_label_values(&[endpoint]).observe(bytes as f64);
+    }
+
+    fn next_poll(&self, index_name: &str, delay: Duration, next_poll: DateTime<Utc>) {
+        self.poll_interval.with_label_values(&[index_name]).set(delay.as_secs() as i64);
+        self.next_poll.with_label_values(&[index_name]).set(next_poll.timestamp());
+    }
+}
+
+impl PrometheusMetrics {
+    /// Metrics in the Prometheus text format
+    pub(super) fn render(&self) -> String {
+        let mut buffer = vec![];
+        let _ = TextEncoder::new().encode(&self.registry.gather(), &mut buffer);
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+
+    /// Binds the `/metrics` endpoint and serves it from a background task
+    pub async fn serve(self, address: SocketAddr) -> std::io::Result<()> {
+        let listener = TcpListener::bind(address).await?;
+        let app = Router::new()
+            .route("/metrics", get(metrics_handler))
+            .with_state(Arc::new(self));
+
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+
+        Ok(())
+    }
+}
+
+async fn metrics_handler(State(metrics): State<Arc<PrometheusMetrics>>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics.render(),
+    )
+}
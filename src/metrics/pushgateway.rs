@@ -0,0 +1,75 @@
+use super::PrometheusMetrics;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+
+/// Job the metrics are grouped under in the Pushgateway
+const JOB: &str = "algolia_monitor";
+
+/// Prometheus Pushgateway receiving the metrics of a [`PrometheusMetrics`] backend, for monitors running
+/// where a scrape endpoint can't be reached
+///
+/// The metrics are grouped by the `algolia_monitor` job and the application ID, each push replacing the
+/// metrics of the group. Credentials in the URL are sent with basic authentication.
+#[derive(Clone)]
+pub struct Pushgateway {
+    client: reqwest::Client,
+    /// URL of the group of the metrics
+    url: String,
+    basic_auth: Option<(String, Option<String>)>,
+    metrics: PrometheusMetrics,
+}
+
+impl Pushgateway {
+    pub fn new(url: &str, app_id: Option<&str>, metrics: PrometheusMetrics) -> Self {
+        let mut group_url = format!("{}/metrics/job/{}", url.trim_end_matches('/'), JOB);
+        if let Some(app_id) = app_id {
+            group_url.push_str(&format!("/app/{}", utf8_percent_encode(app_id, NON_ALPHANUMERIC)));
+        }
+
+        Pushgateway {
+            client: reqwest::Client::new(),
+            url: group_url,
+            basic_auth: None,
+            metrics,
+        }
+    }
+
+    /// Pushes with this client, such as one trusting the CA of a proxy
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    pub fn with_basic_auth(mut self, username: String, password: Option<String>) -> Self {
+        self.basic_auth = Some((username, password));
+        self
+    }
+
+    /// Replaces the metrics of the group with the current values
+    pub async fn push(&self) -> Result<(), reqwest::Error> {
+        self.request(self.client.put(&self.url))
+            .header(reqwest::header::CONTENT_TYPE, "text/plain; version=0.0.4")
+            .body(self.metrics.render())
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    /// Removes the metrics of the group, so the ones of a stopped monitor don't linger
+    pub async fn delete(&self) -> Result<(), reqwest::Error> {
+        self.request(self.client.delete(&self.url))
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    fn request(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.basic_auth {
+            Some((username, password)) => request.basic_auth(username, password.as_ref()),
+            None => request,
+        }
+    }
+}
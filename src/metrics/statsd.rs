@@ -0,0 +1,101 @@
+use super::MetricsBackend;
+use crate::latency::Percentiles;
+use std::net::UdpSocket;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// StatsD metrics pushed over UDP with Datadog style tags
+///
+/// Sending is fire-and-forget, failed packets are only counted and reported
+/// as `statsd_send_errors` along with the following metrics.
+pub struct StatsdMetrics {
+    socket: UdpSocket,
+    prefix: String,
+    app_id: Option<String>,
+    send_errors: AtomicU64,
+}
+
+impl StatsdMetrics {
+    pub fn connect(address: &str, prefix: &str, app_id: Option<&str>) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.connect(address)?;
+        socket.set_nonblocking(true)?;
+
+        Ok(StatsdMetrics {
+            socket,
+            prefix: match prefix {
+                "" => String::new(),
+                prefix => format!("{}.", prefix.trim_end_matches('.')),
+            },
+            app_id: app_id.map(str::to_string),
+            send_errors: AtomicU64::new(0),
+        })
+    }
+
+    fn send(&self, name: &str, value: impl std::fmt::Display, kind: &str, tags: &[(&str, &str)]) {
+        let mut packet = format!("{}{}:{}|{}", self.prefix, name, value, kind);
+        let app_tag = self.app_id.as_deref().map(|app_id| ("app", app_id));
+        for (position, (key, value)) in app_tag.iter().chain(tags).enumerate() {
+            packet.push_str(if position == 0 { "|#" } else { "," });
+            packet.push_str(&format!("{key}:{value}"));
+        }
+
+        if self.socket.send(packet.as_bytes()).is_err() {
+            self.send_errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn send_errors(&self) {
+        let send_errors = self.send_errors.load(Ordering::Relaxed);
+        if send_errors > 0 {
+            self.send("statsd_send_errors", send_errors, "g", &[]);
+        }
+    }
+}
+
+impl MetricsBackend for StatsdMetrics {
+    fn records(&self, index_name: &str, records: u64, expected_records: u64) {
+        let tags = [("index", index_name)];
+        self.send("index_records", records, "g", &tags);
+        self.send("index_expected_records", expected_records, "g", &tags);
+
+        // A signed gauge value is a relative change in StatsD, so negative deltas are reset first
+        let delta = records as i64 - expected_records as i64;
+        if delta < 0 {
+            self.send("records_delta", 0, "g", &tags);
+        }
+        self.send("records_delta", delta, "g", &tags);
+        self.send_errors();
+    }
+
+    fn data_size(&self, index_name: &str, data_size: u64) {
+        self.send("index_data_size", data_size, "g", &[("index", index_name)]);
+    }
+
+    fn pending_tasks(&self, index_name: &str, pending_tasks: u64) {
+        self.send("index_pending_tasks", pending_tasks, "g", &[("index", index_name)]);
+    }
+
+    fn latency(&self, index_name: &str, latency: &Percentiles) {
+        for (quantile, value) in [("p50", latency.p50), ("p95", latency.p95), ("p99", latency.p99)] {
+            self.send("search_latency_ms", value, "g", &[("index", index_name), ("quantile", quantile)]);
+        }
+    }
+
+    fn poll_error(&self, index_name: &str) {
+        self.send("poll_errors", 1, "c", &[("index", index_name)]);
+    }
+
+    fn threshold_breach(&self, index_name: &str) {
+        self.send("threshold_breaches", 1, "c", &[("index", index_name)]);
+    }
+
+    fn api_response(&self, endpoint: &str, duration: Duration) {
+        self.send(
+            "api_response_time",
+            duration.as_millis(),
+            "ms",
+            &[("endpoint", endpoint)],
+        );
+    }
+}
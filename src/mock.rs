@@ -0,0 +1,279 @@
+use crate::algolia::{AlgoliaLogEntry, BrowseCount, FacetValues, IndexItems, IndexStats, LogType, SampleStrategy};
+use crate::error::MonitorError;
+use crate::replay::{Replay, ReplaySource};
+use crate::source::{LogSource, RecordSource};
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Application the scripted responses are queued for
+const MOCK_APP_ID: &str = "mock";
+
+/// Source answering with scripted responses, to test the handling of the polls of a [`Monitor`](crate::Monitor)
+/// without calling Algolia
+///
+/// Each call gets the next response scripted for the same call, index and argument, the responses of every
+/// index being kept apart so monitors of several indices can share the source. A call with no response left
+/// fails with [`MonitorError::ReplayEnded`]. The time the ages are measured against starts at the creation of
+/// the source and only moves with [`MockSource::advance`].
+pub struct MockSource {
+    replay: Replay,
+    source: ReplaySource,
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl Default for MockSource {
+    fn default() -> Self {
+        let replay = Replay::default();
+        MockSource {
+            source: replay.source(MOCK_APP_ID),
+            replay,
+            now: Mutex::new(Utc::now()),
+        }
+    }
+}
+
+impl MockSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn with<T: Serialize>(
+        self,
+        index_name: &str,
+        call: &str,
+        arg: Option<String>,
+        results: impl IntoIterator<Item = Result<T, MonitorError>>,
+    ) -> Self {
+        for result in results {
+            self.replay.push((MOCK_APP_ID, index_name, call, arg.clone()), result);
+        }
+        self
+    }
+
+    /// Polls of the index finding these records counts, in order
+    pub fn with_records(self, index_name: &str, counts: impl IntoIterator<Item = u64>) -> Self {
+        let stats = counts.into_iter().map(|records| {
+            Ok(IndexStats {
+                records,
+                data_size: None,
+                created_at: None,
+                updated_at: None,
+                processing_time_ms: None,
+            })
+        });
+        self.with_stats(index_name, stats)
+    }
+
+    /// Polls of the index, an error failing the poll it is the response of
+    pub fn with_stats(
+        self,
+        index_name: &str,
+        stats: impl IntoIterator<Item = Result<IndexStats, MonitorError>>,
+    ) -> Self {
+        self.with(index_name, "index_stats", None, stats)
+    }
+
+    /// Polls of the subset of the index matching the filters
+    pub fn with_filtered_stats(
+        self,
+        index_name: &str,
+        filters: &str,
+        stats: impl IntoIterator<Item = Result<IndexStats, MonitorError>>,
+    ) -> Self {
+        self.with(index_name, "filtered_stats", Some(filters.to_string()), stats)
+    }
+
+    /// Records counts of a replica or a compared index
+    pub fn with_total_records(
+        self,
+        index_name: &str,
+        counts: impl IntoIterator<Item = Result<u64, MonitorError>>,
+    ) -> Self {
+        self.with(index_name, "total_records", None, counts)
+    }
+
+    pub fn with_facet_values(
+        self,
+        index_name: &str,
+        attribute: &str,
+        values: impl IntoIterator<Item = Result<FacetValues, MonitorError>>,
+    ) -> Self {
+        self.with(index_name, "facet_values", Some(attribute.to_string()), values)
+    }
+
+    pub fn with_pending_tasks(
+        self,
+        index_name: &str,
+        counts: impl IntoIterator<Item = Result<u64, MonitorError>>,
+    ) -> Self {
+        self.with(index_name, "pending_tasks", None, counts)
+    }
+
+    /// Whether the task is published each time it is asked for
+    pub fn with_task_published(
+        self,
+        index_name: &str,
+        task_id: u64,
+        published: impl IntoIterator<Item = Result<bool, MonitorError>>,
+    ) -> Self {
+        self.with(index_name, "is_task_published", Some(task_id.to_string()), published)
+    }
+
+    pub fn with_settings(
+        self,
+        index_name: &str,
+        settings: impl IntoIterator<Item = Result<Map<String, Value>, MonitorError>>,
+    ) -> Self {
+        self.with(index_name, "settings", None, settings)
+    }
+
+    /// Samples of the objectIDs of the index, or of the subset matching the filters
+    pub fn with_object_ids(
+        self,
+        index_name: &str,
+        filters: Option<&str>,
+        samples: impl IntoIterator<Item = Result<Vec<String>, MonitorError>>,
+    ) -> Self {
+        self.with(index_name, "object_ids", filters.map(str::to_string), samples)
+    }
+
+    pub fn with_browse_counts(
+        self,
+        index_name: &str,
+        filters: Option<&str>,
+        counts: impl IntoIterator<Item = Result<BrowseCount, MonitorError>>,
+    ) -> Self {
+        self.with(index_name, "browse_count", filters.map(str::to_string), counts)
+    }
+
+    pub fn with_items(
+        self,
+        index_name: &str,
+        items: IndexItems,
+        counts: impl IntoIterator<Item = Result<u64, MonitorError>>,
+    ) -> Self {
+        self.with(index_name, "items_count", Some(items.to_string()), counts)
+    }
+
+    /// Fetches of the logs of the type the monitor is set up for, each returning a batch of entries newest first
+    pub fn with_logs(
+        self,
+        index_name: &str,
+        batches: impl IntoIterator<Item = Result<Vec<AlgoliaLogEntry>, MonitorError>>,
+    ) -> Self {
+        self.with(index_name, "logs", None, batches)
+    }
+
+    /// Fetches of the logs of the type, such as the error logs of the error rate check
+    pub fn with_logs_of_type(
+        self,
+        index_name: &str,
+        log_type: LogType,
+        batches: impl IntoIterator<Item = Result<Vec<AlgoliaLogEntry>, MonitorError>>,
+    ) -> Self {
+        self.with(index_name, "logs", Some(log_type.to_string()), batches)
+    }
+
+    /// Moves the time of the source forward, such as past the age a pending task is alerted at
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap();
+        *now += chrono::Duration::from_std(duration).unwrap_or(chrono::Duration::MAX);
+    }
+}
+
+impl RecordSource for MockSource {
+    fn index_stats<'a>(&'a self, index_name: &'a str) -> BoxFuture<'a, Result<IndexStats, MonitorError>> {
+        self.source.index_stats(index_name)
+    }
+
+    fn filtered_stats<'a>(
+        &'a self,
+        index_name: &'a str,
+        filters: &'a str,
+    ) -> BoxFuture<'a, Result<IndexStats, MonitorError>> {
+        self.source.filtered_stats(index_name, filters)
+    }
+
+    fn total_records<'a>(&'a self, index_name: &'a str) -> BoxFuture<'a, Result<u64, MonitorError>> {
+        self.source.total_records(index_name)
+    }
+
+    fn facet_values<'a>(
+        &'a self,
+        index_name: &'a str,
+        attribute: &'a str,
+    ) -> BoxFuture<'a, Result<FacetValues, MonitorError>> {
+        self.source.facet_values(index_name, attribute)
+    }
+
+    fn pending_tasks<'a>(&'a self, index_name: &'a str) -> BoxFuture<'a, Result<u64, MonitorError>> {
+        self.source.pending_tasks(index_name)
+    }
+
+    fn is_task_published<'a>(
+        &'a self,
+        index_name: &'a str,
+        task_id: u64,
+    ) -> BoxFuture<'a, Result<bool, MonitorError>> {
+        self.source.is_task_published(index_name, task_id)
+    }
+
+    fn settings<'a>(&'a self, index_name: &'a str) -> BoxFuture<'a, Result<Map<String, Value>, MonitorError>> {
+        self.source.settings(index_name)
+    }
+
+    fn object_ids<'a>(
+        &'a self,
+        index_name: &'a str,
+        filters: Option<&'a str>,
+        strategy: SampleStrategy,
+        size: u64,
+    ) -> BoxFuture<'a, Result<Vec<String>, MonitorError>> {
+        self.source.object_ids(index_name, filters, strategy, size)
+    }
+
+    fn browse_count<'a>(
+        &'a self,
+        index_name: &'a str,
+        filters: Option<&'a str>,
+        max_records: u64,
+        progress: &'a mut (dyn FnMut(&BrowseCount) + Send),
+    ) -> BoxFuture<'a, Result<BrowseCount, MonitorError>> {
+        self.source.browse_count(index_name, filters, max_records, progress)
+    }
+
+    fn items_count<'a>(
+        &'a self,
+        index_name: &'a str,
+        items: IndexItems,
+    ) -> BoxFuture<'a, Result<u64, MonitorError>> {
+        self.source.items_count(index_name, items)
+    }
+
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap()
+    }
+}
+
+impl LogSource for MockSource {
+    fn logs<'a>(
+        &'a self,
+        index_name: &'a str,
+        since: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<Vec<AlgoliaLogEntry>, MonitorError>> {
+        self.source.logs(index_name, since)
+    }
+
+    fn logs_of_type<'a>(
+        &'a self,
+        index_name: &'a str,
+        log_type: LogType,
+        since: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<Vec<AlgoliaLogEntry>, MonitorError>> {
+        self.source.logs_of_type(index_name, log_type, since)
+    }
+}
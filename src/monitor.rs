@@ -0,0 +1,969 @@
+use crate::algolia::{AlgoliaLogEntry, BrowseCount, DestructiveOperation, IndexItems, LogType};
+use crate::baseline::Baseline;
+use crate::builder::MonitorBuilder;
+use crate::churn::{Churn, ObjectSample};
+use crate::error::MonitorError;
+use crate::facet::{FacetBaseline, FacetCounts};
+use crate::items::{ItemsBaseline, ItemsCount};
+use crate::latency::{LatencyWindow, Percentiles};
+use crate::rate::{RateWindow, Rates};
+use crate::log_filter::LogFilter;
+use crate::log_stream::{LogCursor, LogStream};
+use crate::replica::ReplicaCount;
+use crate::runner::{Check, PollEnd, RunOptions};
+use crate::settings::{SettingsBaseline, SettingsDiff};
+use crate::source::{DataSource, RecordSource};
+use crate::threshold::{CountDelta, ThresholdBreach, Thresholds};
+use chrono::{DateTime, Utc};
+use futures::future::{join4, join_all};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::cmp::Reverse;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Records count of an index next to the count it is expected to have
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RecordsCount {
+    pub expected_records: u64,
+    pub current_records: u64,
+}
+
+impl RecordsCount {
+    /// Difference of the current count from the expected one
+    pub fn delta(&self) -> i64 {
+        self.current_records as i64 - self.expected_records as i64
+    }
+}
+
+/// Outcome of polling the records count of an index
+#[derive(Debug, Clone)]
+pub struct Poll {
+    pub records: RecordsCount,
+    /// Threshold the count breached, `None` when it is within the thresholds
+    pub breach: Option<ThresholdBreach>,
+    /// Log entries fetched because of the breach
+    pub logs: Vec<AlgoliaLogEntry>,
+    /// Whether the count just returned within the thresholds after a breach
+    pub recovered: bool,
+    /// Previous expected count when a fixed baseline got refreshed by this poll
+    pub refreshed_from: Option<u64>,
+    /// Size of the index data in bytes, known when counting from the list of indices
+    pub data_size: Option<u64>,
+    /// Processing time of the search that counted the records, in milliseconds
+    pub processing_time_ms: Option<u64>,
+    /// Percentiles of the latest search processing times
+    pub latency: Option<Percentiles>,
+    /// Rates of change of the records count over the latest polls
+    pub rates: Option<Rates>,
+    /// Records counts of the replicas fetched along with the index, in the order they were given
+    pub replicas: Vec<ReplicaCount>,
+    /// Records count of the compared index, `Err` with the failure when it couldn't be fetched, which doesn't
+    /// fail the poll
+    pub comparison: Option<Result<Comparison, String>>,
+    /// Records counts of the values of each watched facet attribute
+    pub facets: Vec<FacetCounts>,
+    /// Whether the index was recreated since the previous poll, after a poll found it deleted or with a new
+    /// creation time in the list of indices
+    pub recreated: bool,
+}
+
+/// What a [`Monitor`] went through, handed to the callback of [`MonitorBuilder::on_event`] as it happens
+#[derive(Debug, Clone)]
+pub enum MonitorEvent {
+    /// Records count found by a poll
+    CountSampled(RecordsCount),
+    /// Poll breaching a threshold after polls within them, the following polls of the breach only sampling
+    /// the count
+    BreachStarted { records: RecordsCount, breach: ThresholdBreach },
+    /// Poll finding the count within the thresholds again after a breach
+    BreachResolved(RecordsCount),
+    /// Log entry handed out by a poll, in the order of the entries of the poll
+    LogEntry(Box<AlgoliaLogEntry>),
+    /// Poll that failed, with the error it failed with
+    Error(String),
+    /// Everything a poll of the records count found, after the other events of the poll
+    Polled(Box<Poll>),
+    /// Server errors counted by a check of [`Monitor::run`] after the poll
+    ErrorsCounted(ErrorRate),
+    /// Tasks found waiting by a check of [`Monitor::run`]
+    TasksChecked(TaskBacklog),
+    /// Settings of the index read by a check of [`Monitor::run`] on the first check and when they changed, with
+    /// the change unless it is the first check
+    SettingsChecked {
+        diff: Option<SettingsDiff>,
+        settings: BTreeMap<String, Value>,
+    },
+    /// Synonyms or rules count read by a check of [`Monitor::run`]
+    ItemsChecked(ItemsCount),
+    /// ObjectIDs sampled by [`Monitor::run`] compared to the previous sample
+    ChurnSampled(Churn),
+    /// Records counted so far by the browse verifying the start of a breach, after each page
+    Browsing(BrowseCount),
+    /// Exact count of the browse verifying the start of a breach, next to the count of the poll
+    Verified { count: BrowseCount, records: u64 },
+    /// Check of [`Monitor::run`] that failed without failing the poll, turned off for the rest of the run when
+    /// `disabled`
+    CheckFailed { check: Check, error: String, disabled: bool },
+    /// End of a poll of [`Monitor::run`] along with its checks, after their other events
+    PollEnded(Box<PollEnd>),
+    /// Time from now until the next poll of [`Monitor::run`], again when a reload changes it
+    NextPoll(Duration),
+}
+
+/// Records count of the index the monitored one is compared to, such as the same index in another application
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Comparison {
+    pub records: u64,
+    /// Whether the count is further from the monitored index's than the compare delta allows
+    pub out_of_sync: bool,
+}
+
+/// Server errors in the error logs since the previous check
+#[derive(Debug, Clone)]
+pub struct ErrorRate {
+    pub errors: u64,
+    pub per_minute: f64,
+    /// Number of errors by the status code Algolia answered with
+    pub by_code: BTreeMap<u16, u64>,
+    /// Newest of the failed requests
+    pub examples: Vec<AlgoliaLogEntry>,
+}
+
+/// Indexing tasks of the index waiting for Algolia to publish them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TaskBacklog {
+    /// Number of tasks not published yet, as reported in the list of indices
+    pub pending: u64,
+    /// Time since the oldest task of the build logs that isn't published yet was logged, `None` when none
+    /// is known to wait
+    pub oldest_age: Option<Duration>,
+}
+
+/// Number of search processing times the latency percentiles are taken from by default
+const DEFAULT_LATENCY_WINDOW: usize = 100;
+
+/// Number of rates of change the loss rate threshold is checked against by default
+const DEFAULT_RATE_WINDOW: usize = 5;
+
+/// Fewest samples the latency threshold is checked against, unless the window is smaller
+const MIN_LATENCY_SAMPLES: usize = 10;
+
+/// Number of failed requests kept as examples in an [`ErrorRate`]
+const ERROR_EXAMPLES: usize = 2;
+
+/// Most tasks of the build logs followed until they are published, the tasks logged afterwards being left out
+const MAX_FOLLOWED_TASKS: usize = 1000;
+
+/// Most tasks asked for on each check of the backlog; when that many got published since the last check, the
+/// backlog is moving and the age of the oldest one isn't looked for further
+const MAX_TASK_CHECKS: usize = 20;
+
+pub(crate) type BreachCallback = Box<dyn FnMut(&RecordsCount, &ThresholdBreach) + Send>;
+pub(crate) type RecoveryCallback = Box<dyn FnMut(&RecordsCount) + Send>;
+pub(crate) type LogsCallback = Box<dyn FnMut(&[AlgoliaLogEntry]) + Send>;
+pub(crate) type DestructiveCallback = Box<dyn FnMut(&AlgoliaLogEntry, DestructiveOperation) + Send>;
+pub(crate) type EventCallback = Box<dyn FnMut(&MonitorEvent) + Send>;
+
+/// Interval between the polls of [`Monitor::run`] by default
+pub(crate) const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Most log entries kept from polls without a breach, to be handed out with the logs of the next one
+const MAX_PENDING_LOGS: usize = 1000;
+
+/// Watches the records count of a single index against a baseline
+///
+/// Each poll compares the count to the baseline and fetches the new log entries when it breached the
+/// thresholds, calling the callbacks of its [`MonitorBuilder`] along the way. The index is read through the
+/// source, any [`DataSource`] unless given as a concrete one such as a `MockSource`.
+pub struct Monitor<S: ?Sized = dyn DataSource> {
+    pub(crate) source: Arc<S>,
+    pub(crate) index_name: String,
+    /// Filters of the subset of records to count, the whole index when `None`
+    pub(crate) filters: Option<String>,
+    baseline: Baseline,
+    pub(crate) thresholds: Thresholds,
+    pub(crate) max_data_size: Option<u64>,
+    pub(crate) latency: LatencyWindow,
+    pub(crate) max_p95_ms: Option<u64>,
+    pub(crate) rates: RateWindow,
+    pub(crate) max_loss_rate: Option<f64>,
+    pub(crate) replicas: Vec<String>,
+    pub(crate) replica_delta: CountDelta,
+    pub(crate) compared: Option<ComparedIndex>,
+    pub(crate) facets: Vec<FacetBaseline>,
+    pub(crate) log_filter: LogFilter,
+    pub(crate) watch_destructive: bool,
+    /// Entries fetched while watching for destructive operations that weren't handed out yet, newest first
+    pending_logs: Vec<AlgoliaLogEntry>,
+    /// Tail of the logs of each fetched type, a single one of the client's type unless following
+    pub(crate) log_streams: Vec<LogStream<S>>,
+    /// Every how many entries of the query logs one is handed out while following
+    pub(crate) query_sample: u32,
+    query_count: u32,
+    /// Position in the error logs, kept apart from the other cursors so checking errors doesn't skip entries
+    error_cursor: LogCursor,
+    last_error_check: Option<Instant>,
+    /// Position in the build logs, which the tasks waiting to be published are read from
+    task_cursor: LogCursor,
+    /// Tasks of the build logs not known to be published, by ID along with the time they were logged
+    followed_tasks: BTreeMap<u64, DateTime<Utc>>,
+    /// Settings the index was last seen with, `None` unless watching them
+    pub(crate) settings: Option<SettingsBaseline>,
+    /// Counts of the watched synonyms and rules
+    pub(crate) items: Vec<ItemsBaseline>,
+    /// ObjectIDs of the previous poll, `None` unless sampling them
+    pub(crate) object_sample: Option<ObjectSample>,
+    breached: bool,
+    /// Whether a poll found the index, so it not being found means it was deleted
+    seen: bool,
+    deleted: bool,
+    created_at: Option<DateTime<Utc>>,
+    updated_at: Option<DateTime<Utc>>,
+    pub(crate) rebaseline_on_recreate: bool,
+    pub(crate) zero_guard: bool,
+    pub(crate) on_breach: Option<BreachCallback>,
+    pub(crate) on_recovery: Option<RecoveryCallback>,
+    pub(crate) on_logs: Option<LogsCallback>,
+    pub(crate) on_destructive: Option<DestructiveCallback>,
+    pub(crate) on_event: Option<EventCallback>,
+    pub(crate) run: RunOptions,
+}
+
+impl<S: DataSource + ?Sized> Monitor<S> {
+    /// Monitor of the index reading it through the source, such as an [`AlgoliaClient`](crate::AlgoliaClient)
+    pub(crate) fn new(source: Arc<S>, index_name: impl Into<String>, baseline: Baseline) -> Self {
+        let index_name = index_name.into();
+        Monitor {
+            log_streams: vec![Self::log_stream(&source, &index_name, None, None)],
+            source,
+            index_name,
+            filters: None,
+            baseline,
+            thresholds: Thresholds::default(),
+            max_data_size: None,
+            latency: LatencyWindow::new(DEFAULT_LATENCY_WINDOW),
+            max_p95_ms: None,
+            rates: RateWindow::new(DEFAULT_RATE_WINDOW),
+            max_loss_rate: None,
+            replicas: vec![],
+            replica_delta: CountDelta::Absolute(0),
+            compared: None,
+            facets: vec![],
+            log_filter: LogFilter::default(),
+            watch_destructive: false,
+            pending_logs: vec![],
+            query_sample: 1,
+            query_count: 0,
+            error_cursor: LogCursor::new(Some(LogType::Error), None),
+            last_error_check: None,
+            task_cursor: LogCursor::new(Some(LogType::Build), None),
+            followed_tasks: BTreeMap::new(),
+            settings: None,
+            items: vec![],
+            object_sample: None,
+            breached: false,
+            seen: false,
+            deleted: false,
+            created_at: None,
+            updated_at: None,
+            rebaseline_on_recreate: false,
+            zero_guard: true,
+            on_breach: None,
+            on_recovery: None,
+            on_logs: None,
+            on_destructive: None,
+            on_event: None,
+            run: RunOptions::new(DEFAULT_INTERVAL),
+        }
+    }
+
+    /// Builder of a monitor of the index reading it through the source, see [`MonitorBuilder`]
+    pub fn builder(source: Arc<S>, index_name: impl Into<String>, baseline: Baseline) -> MonitorBuilder<S> {
+        MonitorBuilder::new(source, index_name, baseline)
+    }
+
+    /// Tail of the logs fetching as soon as it is polled, the polls being spaced out by the caller
+    pub(crate) fn log_stream(
+        source: &Arc<S>,
+        index_name: &str,
+        log_type: Option<LogType>,
+        since: Option<DateTime<Utc>>,
+    ) -> LogStream<S> {
+        LogStream::of(source.clone(), index_name, log_type)
+            .with_interval(Duration::ZERO)
+            .with_since(since)
+    }
+
+    pub fn index_name(&self) -> &str {
+        &self.index_name
+    }
+
+    pub fn expected_records(&self) -> u64 {
+        self.baseline.expected_records()
+    }
+
+    /// Timestamp of the newest log entry handed out, the latest one among the fetched log types
+    pub fn logs_since(&self) -> Option<DateTime<Utc>> {
+        self.log_streams.iter().filter_map(LogStream::since).max()
+    }
+
+    /// Replaces the expected records count, such as with one read from the database the index is fed from
+    pub fn set_expected_records(&mut self, expected_records: u64) {
+        self.baseline.reset(expected_records);
+    }
+
+    /// Replaces the thresholds the next polls are checked against, such as on a reload of the configuration
+    pub fn set_thresholds(&mut self, thresholds: Thresholds) {
+        self.thresholds = thresholds;
+    }
+
+    /// Replaces the filter of the log entries handed out by the next polls
+    pub fn set_log_filter(&mut self, log_filter: LogFilter) {
+        self.log_filter = log_filter;
+    }
+
+    /// Compares the current records count to the baseline, fetching the new logs on a breach
+    pub async fn poll_records(&mut self) -> Result<Poll, MonitorError> {
+        let poll = self.check_records().await;
+        match &poll {
+            Ok(poll) if self.on_event.is_some() => self.emit(MonitorEvent::Polled(Box::new(poll.clone()))),
+            Ok(_) => {}
+            Err(error) => self.emit(MonitorEvent::Error(error.to_string())),
+        }
+
+        poll
+    }
+
+    pub(crate) fn emit(&mut self, event: MonitorEvent) {
+        if let Some(on_event) = &mut self.on_event {
+            on_event(&event);
+        }
+    }
+
+    async fn check_records(&mut self) -> Result<Poll, MonitorError> {
+        let source = &self.source;
+        let compared = async {
+            let compared = self.compared.as_ref()?;
+            Some(compared.source.total_records(&compared.index_name).await)
+        };
+        let stats = async {
+            match &self.filters {
+                Some(filters) => source.filtered_stats(&self.index_name, filters).await,
+                None => source.index_stats(&self.index_name).await,
+            }
+        };
+        let (stats, replicas, compared_records, facet_values) = join4(
+            stats,
+            join_all(self.replicas.iter().map(|replica| source.total_records(replica))),
+            compared,
+            join_all(self.facets.iter().map(|facet| source.facet_values(&self.index_name, facet.attribute()))),
+        )
+        .await;
+        let stats = match stats {
+            Err(error) if self.seen && error.is_not_found() => {
+                self.deleted = true;
+                return Err(MonitorError::IndexDeleted(self.index_name.clone()));
+            }
+            stats => stats?,
+        };
+        self.seen = true;
+
+        // A new index has another creation time, and its update time can be older than the last one seen
+        let recreated = std::mem::replace(&mut self.deleted, false)
+            || self.created_at.zip(stats.created_at).is_some_and(|(seen, created_at)| created_at != seen)
+            || self.updated_at.zip(stats.updated_at).is_some_and(|(seen, updated_at)| updated_at < seen);
+        self.created_at = stats.created_at.or(self.created_at);
+        self.updated_at = stats.updated_at.or(self.updated_at);
+        if recreated && self.rebaseline_on_recreate {
+            self.baseline.reset(stats.records);
+        }
+        if recreated {
+            self.rates.clear();
+        }
+        self.rates.record(Instant::now(), stats.records);
+        let rates = self.rates.rates();
+
+        let replicas = self.replica_counts(stats.records, replicas)?;
+        let facet_values = facet_values.into_iter().collect::<Result<Vec<_>, _>>()?;
+        let facets = self
+            .facets
+            .iter_mut()
+            .zip(facet_values)
+            .map(|(facet, values)| facet.check(values))
+            .collect();
+        let comparison = compared_records.zip(self.compared.as_ref()).map(|(result, compared)| {
+            let records = result.map_err(|error| error.to_string())?;
+            Ok(Comparison {
+                records,
+                out_of_sync: compared.delta.exceeded(stats.records, records),
+            })
+        });
+        let records = RecordsCount {
+            expected_records: self.baseline.expected_records(),
+            current_records: stats.records,
+        };
+        // The count only ever is 0 when Algolia answered with it, failed requests are errors
+        let zero = self.zero_guard && records.current_records == 0 && records.expected_records > 0;
+        let breach = match (zero, self.baseline.is_warming_up()) {
+            (true, _) => Some(ThresholdBreach::ZeroRecords),
+            (false, true) => None,
+            (false, false) => self.thresholds.check(records.expected_records, records.current_records),
+        };
+        let breach = breach.or(match (self.max_data_size, stats.data_size) {
+            (Some(limit), Some(data_size)) if data_size > limit => {
+                Some(ThresholdBreach::DataSize { limit, data_size })
+            }
+            _ => None,
+        });
+
+        if let Some(processing_time_ms) = stats.processing_time_ms {
+            self.latency.record(processing_time_ms);
+        }
+        let latency = self.latency.percentiles();
+        let min_samples = MIN_LATENCY_SAMPLES.min(self.latency.size());
+        let breach = breach.or(match (self.max_p95_ms, latency) {
+            (Some(limit), Some(latency)) if latency.samples >= min_samples && latency.p95 > limit => {
+                Some(ThresholdBreach::Latency { limit, p95: latency.p95 })
+            }
+            _ => None,
+        });
+        let breach = breach.or(match (self.max_loss_rate, rates.and_then(|rates| rates.sustained_loss)) {
+            (Some(limit), Some(loss)) if loss > limit => Some(ThresholdBreach::LossRate { limit, loss }),
+            _ => None,
+        });
+
+        self.emit(MonitorEvent::CountSampled(records));
+        if let Some(breach) = breach {
+            if !std::mem::replace(&mut self.breached, true) {
+                self.emit(MonitorEvent::BreachStarted { records, breach });
+            }
+            if let Some(on_breach) = &mut self.on_breach {
+                on_breach(&records, &breach);
+            }
+
+            return Ok(Poll {
+                records,
+                breach: Some(breach),
+                logs: self.take_logs().await?,
+                recovered: false,
+                refreshed_from: None,
+                data_size: stats.data_size,
+                processing_time_ms: stats.processing_time_ms,
+                latency,
+                rates,
+                replicas,
+                comparison,
+                facets,
+                recreated,
+            });
+        }
+
+        if self.watch_destructive {
+            let mut logs = self.fetch_logs().await?;
+            logs.append(&mut self.pending_logs);
+            logs.truncate(MAX_PENDING_LOGS);
+            self.pending_logs = logs;
+        }
+
+        let recovered = std::mem::replace(&mut self.breached, false);
+        if recovered {
+            self.emit(MonitorEvent::BreachResolved(records));
+        }
+        if let (true, Some(on_recovery)) = (recovered, &mut self.on_recovery) {
+            on_recovery(&records);
+        }
+
+        Ok(Poll {
+            records,
+            breach: None,
+            logs: vec![],
+            recovered,
+            refreshed_from: self.baseline.record(records.current_records),
+            data_size: stats.data_size,
+            processing_time_ms: stats.processing_time_ms,
+            latency,
+            rates,
+            replicas,
+            comparison,
+            facets,
+            recreated,
+        })
+    }
+
+    /// Compares the fetched replica counts to the count of the index, a replica that isn't found is
+    /// reported as missing instead of failing the poll
+    fn replica_counts(
+        &self,
+        primary_records: u64,
+        results: Vec<Result<u64, MonitorError>>,
+    ) -> Result<Vec<ReplicaCount>, MonitorError> {
+        self.replicas
+            .iter()
+            .zip(results)
+            .map(|(replica, result)| {
+                let records = match result {
+                    Ok(records) => Some(records),
+                    Err(error) if error.is_not_found() => None,
+                    Err(error) => return Err(error),
+                };
+
+                let out_of_sync =
+                    records.is_none_or(|records| self.replica_delta.exceeded(primary_records, records));
+                Ok(ReplicaCount {
+                    index_name: replica.clone(),
+                    records,
+                    out_of_sync,
+                })
+            })
+            .collect()
+    }
+
+    /// Counts the server errors logged since the previous check, `None` on the first check,
+    /// which only finds where the error logs are at
+    pub async fn check_errors(&mut self) -> Result<Option<ErrorRate>, MonitorError> {
+        let logs = self
+            .source
+            .logs_of_type(&self.index_name, LogType::Error, self.error_cursor.timestamp)
+            .await?;
+        let logs = self.error_cursor.advance(logs);
+
+        let checked_at = Instant::now();
+        let Some(previous_check) = self.last_error_check.replace(checked_at) else {
+            return Ok(None);
+        };
+
+        let errors: Vec<_> = logs
+            .into_iter()
+            .filter(|log| log.status_code().is_some_and(|code| code >= 500))
+            .collect();
+        let mut by_code = BTreeMap::new();
+        for code in errors.iter().filter_map(AlgoliaLogEntry::status_code) {
+            *by_code.entry(code).or_default() += 1;
+        }
+        let minutes = (checked_at - previous_check).as_secs_f64() / 60.0;
+
+        Ok(Some(ErrorRate {
+            errors: errors.len() as u64,
+            per_minute: errors.len() as f64 / minutes.max(1.0 / 60.0),
+            by_code,
+            examples: errors.into_iter().take(ERROR_EXAMPLES).collect(),
+        }))
+    }
+
+    /// Reads the number of tasks waiting to be published, and follows the tasks of the build logs to tell how
+    /// long the oldest one has waited
+    ///
+    /// Algolia publishes the tasks of an index in order, so the followed tasks are asked for oldest first
+    /// until one isn't published yet.
+    pub async fn check_tasks(&mut self) -> Result<TaskBacklog, MonitorError> {
+        let pending = self.source.pending_tasks(&self.index_name).await?;
+        let logs = self
+            .source
+            .logs_of_type(&self.index_name, LogType::Build, self.task_cursor.timestamp)
+            .await?;
+        for log in self.task_cursor.advance(logs) {
+            if let (Some(task_id), true) = (log.task_id(), self.followed_tasks.len() < MAX_FOLLOWED_TASKS) {
+                self.followed_tasks.entry(task_id).or_insert(log.timestamp);
+            }
+        }
+        if pending == 0 {
+            self.followed_tasks.clear();
+        }
+
+        let mut oldest_age = None;
+        for _ in 0..MAX_TASK_CHECKS {
+            let Some((&task_id, &logged_at)) = self.followed_tasks.first_key_value() else {
+                break;
+            };
+            match self.source.is_task_published(&self.index_name, task_id).await {
+                Ok(false) => {
+                    oldest_age = Some((self.source.now() - logged_at).to_std().unwrap_or_default());
+                    break;
+                }
+                // Algolia forgets about the tasks some time after publishing them
+                Ok(true) => {}
+                Err(error) if error.is_not_found() => {}
+                Err(error) => return Err(error),
+            }
+            self.followed_tasks.remove(&task_id);
+        }
+
+        Ok(TaskBacklog { pending, oldest_age })
+    }
+
+    /// Fetches the settings of the index and compares them to the ones it was last seen with, `None` when
+    /// unchanged or on the first check, which only takes them as the baseline
+    pub async fn check_settings(&mut self) -> Result<Option<SettingsDiff>, MonitorError> {
+        let Some(baseline) = &mut self.settings else {
+            return Ok(None);
+        };
+        let settings = self.source.settings(&self.index_name).await?;
+
+        Ok(baseline.check(settings))
+    }
+
+    /// Fetches a sample of the objectIDs and compares it to the previous one, `None` on the first sample or
+    /// unless sampling them
+    pub async fn check_churn(&mut self) -> Result<Option<Churn>, MonitorError> {
+        let Some(sample) = &mut self.object_sample else {
+            return Ok(None);
+        };
+        let object_ids = self
+            .source
+            .object_ids(&self.index_name, self.filters.as_deref(), sample.strategy(), sample.size())
+            .await?;
+
+        Ok(sample.check(object_ids))
+    }
+
+    /// Counts the records of the index, or of the subset, exactly by browsing them, such as to check the
+    /// `nbHits` of a breach, stopping at `max_records`
+    pub async fn browse_count(
+        &mut self,
+        max_records: u64,
+        mut progress: impl FnMut(&BrowseCount) + Send,
+    ) -> Result<BrowseCount, MonitorError> {
+        self.source
+            .browse_count(&self.index_name, self.filters.as_deref(), max_records, &mut progress)
+            .await
+    }
+
+    /// Fetches the count of the synonyms or the rules and compares it to the baseline, `None` unless watched
+    pub async fn check_items(&mut self, items: IndexItems) -> Result<Option<ItemsCount>, MonitorError> {
+        let Some(baseline) = self.items.iter_mut().find(|baseline| baseline.items() == items) else {
+            return Ok(None);
+        };
+        let count = self.source.items_count(&self.index_name, items).await?;
+
+        Ok(Some(baseline.check(count)))
+    }
+
+    /// Settings the index was last seen with, `None` until they were checked or unless watching them
+    pub fn settings(&self) -> Option<&BTreeMap<String, Value>> {
+        self.settings.as_ref()?.snapshot()
+    }
+
+    /// Fetches the log entries that weren't handed out before
+    pub async fn poll_logs(&mut self) -> Result<Vec<AlgoliaLogEntry>, MonitorError> {
+        let logs = self.take_logs().await;
+        if let Err(error) = &logs {
+            self.emit(MonitorEvent::Error(error.to_string()));
+        }
+
+        logs
+    }
+
+    async fn take_logs(&mut self) -> Result<Vec<AlgoliaLogEntry>, MonitorError> {
+        let mut logs = self.fetch_logs().await?;
+        logs.append(&mut self.pending_logs);
+
+        let logs: Vec<_> = logs
+            .into_iter()
+            .filter(|log| self.log_filter.matches(log))
+            .collect();
+        if let (false, Some(on_logs)) = (logs.is_empty(), &mut self.on_logs) {
+            on_logs(&logs);
+        }
+        if self.on_event.is_some() {
+            for log in &logs {
+                self.emit(MonitorEvent::LogEntry(Box::new(log.clone())));
+            }
+        }
+
+        Ok(logs)
+    }
+
+    /// Fetches the log entries that weren't seen before, reporting the destructive operations among them
+    async fn fetch_logs(&mut self) -> Result<Vec<AlgoliaLogEntry>, MonitorError> {
+        let mut logs = vec![];
+
+        for log_stream in &mut self.log_streams {
+            let log_type = log_stream.log_type();
+            for mut log in log_stream.next_batch().await? {
+                if log_type == Some(LogType::Query) {
+                    if let Some(processing_time_ms) = log.processing_time() {
+                        self.latency.record(processing_time_ms);
+                    }
+
+                    self.query_count = (self.query_count + 1) % self.query_sample;
+                    if self.query_count != 0 {
+                        continue;
+                    }
+                }
+
+                log.log_type = log_type;
+                logs.push(log);
+            }
+        }
+
+        if self.log_streams.len() > 1 {
+            logs.sort_by_key(|log| Reverse(log.timestamp));
+        }
+
+        if let Some(on_destructive) = &mut self.on_destructive {
+            for log in &logs {
+                if let Some(operation) = log.destructive_operation(&self.index_name) {
+                    on_destructive(log, operation);
+                }
+            }
+        }
+
+        Ok(logs)
+    }
+}
+
+/// Index counted through another source to compare the monitored one to
+pub(crate) struct ComparedIndex {
+    pub(crate) source: Arc<dyn RecordSource>,
+    pub(crate) index_name: String,
+    pub(crate) delta: CountDelta,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algolia::IndexStats;
+    use crate::mock::MockSource;
+    use serde_json::json;
+    use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+    type Builder = MonitorBuilder<MockSource>;
+
+    /// Monitor expecting 1000 records of `products`, along with the events of its polls
+    struct Polls {
+        monitor: Monitor<MockSource>,
+        events: UnboundedReceiver<MonitorEvent>,
+    }
+
+    impl Polls {
+        fn new(source: MockSource, configure: impl FnOnce(Builder) -> Builder) -> Self {
+            let (sender, events) = mpsc::unbounded_channel();
+            let builder = Monitor::builder(Arc::new(source), "products", Baseline::fixed(1000, None));
+            Polls {
+                monitor: configure(builder).with_event_channel(sender).build(),
+                events,
+            }
+        }
+
+        /// Events of the next poll, each described in a line
+        async fn poll(&mut self) -> Vec<String> {
+            let _ = self.monitor.poll_records().await;
+
+            let mut events = vec![];
+            while let Ok(event) = self.events.try_recv() {
+                events.push(describe(&event));
+            }
+            events
+        }
+    }
+
+    fn describe(event: &MonitorEvent) -> String {
+        match event {
+            MonitorEvent::CountSampled(records) => format!("sampled {}", records.current_records),
+            MonitorEvent::BreachStarted { records, breach } => {
+                format!("breach at {}: {}", records.current_records, breach)
+            }
+            MonitorEvent::BreachResolved(records) => format!("resolved at {}", records.current_records),
+            MonitorEvent::LogEntry(log) => format!("log {}", log.extra["sha1"].as_str().unwrap_or_default()),
+            MonitorEvent::Error(error) => format!("error: {error}"),
+            MonitorEvent::Polled(poll) => match (poll.breach, poll.recovered) {
+                (Some(_), _) => format!("polled {} breached", poll.records.current_records),
+                (None, true) => format!("polled {} recovered", poll.records.current_records),
+                (None, false) => format!("polled {}", poll.records.current_records),
+            },
+            event => panic!("{event:?} only comes out of a run"),
+        }
+    }
+
+    fn stats(records: u64) -> Result<IndexStats, MonitorError> {
+        Ok(IndexStats {
+            records,
+            data_size: None,
+            created_at: None,
+            updated_at: None,
+            processing_time_ms: None,
+        })
+    }
+
+    fn log(sha1: &str) -> AlgoliaLogEntry {
+        serde_json::from_value(json!({
+            "timestamp": "2024-05-02T10:14:03Z",
+            "method": "POST",
+            "url": "/1/indexes/products/batch",
+            "answer_code": "200",
+            "sha1": sha1,
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn count_at_the_minimum_delta_stays_within_it() {
+        let source = MockSource::new().with_records("products", [1000, 900]);
+        let mut polls = Polls::new(source, |monitor| monitor.with_thresholds(Thresholds::from_delta(-100)));
+
+        assert_eq!(polls.poll().await, ["sampled 1000", "polled 1000"]);
+        assert_eq!(polls.poll().await, ["sampled 900", "polled 900"]);
+    }
+
+    #[tokio::test]
+    async fn breach_past_the_minimum_delta_starts_once_and_resolves_back_at_it() {
+        let source = MockSource::new()
+            .with_records("products", [899, 850, 900, 899])
+            .with_logs("products", [Ok(vec![log("a2"), log("a1")]), Ok(vec![]), Ok(vec![log("a3")])]);
+        let mut polls = Polls::new(source, |monitor| monitor.with_thresholds(Thresholds::from_delta(-100)));
+
+        assert_eq!(
+            polls.poll().await,
+            [
+                "sampled 899",
+                "breach at 899: difference -101 is below the minimum delta of -100",
+                "log a2",
+                "log a1",
+                "polled 899 breached",
+            ]
+        );
+        // The breach goes on without starting again, the logs having nothing new
+        assert_eq!(polls.poll().await, ["sampled 850", "polled 850 breached"]);
+        assert_eq!(polls.poll().await, ["sampled 900", "resolved at 900", "polled 900 recovered"]);
+        // Right past the limit again after the recovery, a new breach
+        assert_eq!(
+            polls.poll().await,
+            [
+                "sampled 899",
+                "breach at 899: difference -101 is below the minimum delta of -100",
+                "log a3",
+                "polled 899 breached",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn breach_past_the_maximum_delta() {
+        let source = MockSource::new()
+            .with_records("products", [1100, 1101])
+            .with_logs("products", [Ok(vec![])]);
+        let thresholds = Thresholds::from_limits(None, None, Some(100), None).unwrap().unwrap();
+        let mut polls = Polls::new(source, |monitor| monitor.with_thresholds(thresholds));
+
+        assert_eq!(polls.poll().await, ["sampled 1100", "polled 1100"]);
+        assert_eq!(
+            polls.poll().await,
+            [
+                "sampled 1101",
+                "breach at 1101: difference +101 is above the maximum delta of +100",
+                "polled 1101 breached",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn breach_past_the_delta_percentage_either_way() {
+        let source = MockSource::new()
+            .with_records("products", [900, 1100, 1101, 1000, 899])
+            .with_logs("products", [Ok(vec![]), Ok(vec![])]);
+        let thresholds = Thresholds::from_limits(None, None, None, Some(10.0)).unwrap().unwrap();
+        let mut polls = Polls::new(source, |monitor| monitor.with_thresholds(thresholds));
+
+        assert_eq!(polls.poll().await, ["sampled 900", "polled 900"]);
+        assert_eq!(polls.poll().await, ["sampled 1100", "polled 1100"]);
+        assert_eq!(
+            polls.poll().await,
+            [
+                "sampled 1101",
+                "breach at 1101: difference +10.10% is past the delta of ±10%",
+                "polled 1101 breached",
+            ]
+        );
+        assert_eq!(polls.poll().await, ["sampled 1000", "resolved at 1000", "polled 1000 recovered"]);
+        assert_eq!(
+            polls.poll().await,
+            [
+                "sampled 899",
+                "breach at 899: difference -10.10% is past the delta of ±10%",
+                "polled 899 breached",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn empty_index_breaches_within_the_delta_unless_the_zero_guard_is_off() {
+        let source = MockSource::new()
+            .with_records("products", [0])
+            .with_logs("products", [Ok(vec![])]);
+        let mut polls = Polls::new(source, |monitor| monitor.with_thresholds(Thresholds::from_delta(-2000)));
+
+        assert_eq!(
+            polls.poll().await,
+            ["sampled 0", "breach at 0: dropped to 0 records", "polled 0 breached"]
+        );
+
+        let source = MockSource::new().with_records("products", [0]);
+        let mut polls = Polls::new(source, |monitor| {
+            monitor.with_thresholds(Thresholds::from_delta(-2000)).with_zero_guard(false)
+        });
+
+        assert_eq!(polls.poll().await, ["sampled 0", "polled 0"]);
+    }
+
+    #[tokio::test]
+    async fn failed_poll_is_an_error_leaving_the_breach_going() {
+        let source = MockSource::new()
+            .with_stats("products", [stats(800), Err(MonitorError::Timeout(Duration::from_secs(10)))])
+            .with_stats("products", [stats(800)])
+            .with_logs("products", [Ok(vec![]), Ok(vec![])]);
+        let mut polls = Polls::new(source, |monitor| monitor.with_thresholds(Thresholds::from_delta(-100)));
+
+        assert_eq!(
+            polls.poll().await,
+            [
+                "sampled 800",
+                "breach at 800: difference -200 is below the minimum delta of -100",
+                "polled 800 breached",
+            ]
+        );
+        assert_eq!(polls.poll().await, ["error: Algolia request timed out after 10s"]);
+        assert_eq!(polls.poll().await, ["sampled 800", "polled 800 breached"]);
+    }
+
+    #[tokio::test]
+    async fn filtered_log_entries_are_left_out_of_the_events() {
+        let source = MockSource::new()
+            .with_records("products", [800])
+            .with_logs("products", [Ok(vec![log("a2"), log("a1")])]);
+        let mut polls = Polls::new(source, |monitor| {
+            monitor
+                .with_thresholds(Thresholds::from_delta(-100))
+                .with_log_filter(LogFilter::default().with_answer_codes(vec![500]))
+        });
+
+        assert_eq!(
+            polls.poll().await,
+            [
+                "sampled 800",
+                "breach at 800: difference -200 is below the minimum delta of -100",
+                "polled 800 breached",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn thresholds_set_while_running_apply_to_the_next_poll() {
+        let source = MockSource::new()
+            .with_records("products", [950, 950])
+            .with_logs("products", [Ok(vec![])]);
+        let mut polls = Polls::new(source, |monitor| monitor.with_thresholds(Thresholds::from_delta(-100)));
+
+        assert_eq!(polls.poll().await, ["sampled 950", "polled 950"]);
+
+        polls.monitor.set_thresholds(Thresholds::from_delta(-10));
+        assert_eq!(
+            polls.poll().await,
+            [
+                "sampled 950",
+                "breach at 950: difference -50 is below the minimum delta of -10",
+                "polled 950 breached",
+            ]
+        );
+    }
+}
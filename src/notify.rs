@@ -0,0 +1,889 @@
+mod command;
+mod heartbeat;
+mod pagerduty;
+mod slack;
+mod telegram;
+
+use crate::{
+    now, AlgoliaLogEntry, DestructiveOperation, FacetDrop, IndexItems, ReplicaCount, SettingsDiff,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tokio::time::sleep;
+use tracing::{debug, error, info};
+
+pub use command::{shell, Commands};
+pub use heartbeat::Heartbeat;
+pub use pagerduty::PagerDuty;
+pub use slack::Slack;
+pub use telegram::Telegram;
+
+/// Number of times a failed notification is retried before it is dropped
+const DELIVERY_RETRIES: u32 = 2;
+
+/// Records count that moved past the configured delta
+#[derive(Debug, Clone, Serialize)]
+pub struct Breach {
+    pub app_id: String,
+    pub index_name: String,
+    /// Subset of the records that moved, `None` for the whole index
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subset: Option<String>,
+    pub expected_records: u64,
+    pub current_records: u64,
+    pub delta: i64,
+    /// Whether the breach was already notified and is still ongoing after the renotify interval
+    pub reminder: bool,
+    /// Whether the breach started during the quiet hours and is only notified after their end
+    pub quiet_hours: bool,
+    pub timestamp: DateTime<Utc>,
+    pub logs: Vec<AlgoliaLogEntry>,
+}
+
+/// Records count that returned within the configured delta after a breach
+#[derive(Debug, Clone, Serialize)]
+pub struct Recovery {
+    pub app_id: String,
+    pub index_name: String,
+    /// Subset of the records that moved, `None` for the whole index
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subset: Option<String>,
+    pub expected_records: u64,
+    pub current_records: u64,
+    pub delta: i64,
+    /// How long the breach lasted, in seconds
+    pub breach_duration_secs: u64,
+    /// Lowest records count seen during the breach
+    pub min_records: u64,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Index that can't be read, such as after the API key lost its search rights
+#[derive(Debug, Clone, Serialize)]
+pub struct Unreachable {
+    pub app_id: String,
+    pub index_name: String,
+    pub error: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Log entry of a request that cleared or deleted the index
+#[derive(Debug, Clone, Serialize)]
+pub struct Destructive {
+    pub app_id: String,
+    pub index_name: String,
+    pub operation: DestructiveOperation,
+    pub timestamp: DateTime<Utc>,
+    pub log: AlgoliaLogEntry,
+}
+
+/// Searches on the index failing with server errors more often than the threshold allows
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchErrors {
+    pub app_id: String,
+    pub index_name: String,
+    pub errors: u64,
+    pub per_minute: f64,
+    pub by_code: BTreeMap<u16, u64>,
+    pub timestamp: DateTime<Utc>,
+    pub examples: Vec<AlgoliaLogEntry>,
+}
+
+/// Indexing tasks piling up, more of them waiting to be published than allowed or the oldest for too long
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingTasks {
+    pub app_id: String,
+    pub index_name: String,
+    pub pending_tasks: u64,
+    /// How long the oldest task of the build logs has waited, in seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub oldest_task_age_secs: Option<u64>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Replicas with a records count further from the one of their primary index than the replica delta allows,
+/// or that are missing
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplicasOutOfSync {
+    pub app_id: String,
+    pub index_name: String,
+    pub records: u64,
+    pub replicas: Vec<ReplicaCount>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Index with a records count further from the one of the index it is compared to than the delta allows
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparisonOutOfSync {
+    pub app_id: String,
+    pub index_name: String,
+    pub records: u64,
+    pub compared_app_id: String,
+    pub compared_index_name: String,
+    pub compared_records: u64,
+    /// Difference of the compared count from the count of the index
+    pub delta: i64,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Values of a facet attribute that lost more records than the facet delta allows, or disappeared
+#[derive(Debug, Clone, Serialize)]
+pub struct FacetDrops {
+    pub app_id: String,
+    pub index_name: String,
+    pub attribute: String,
+    pub drops: Vec<FacetDrop>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Records of an index replaced by different ones, more of a sample of their objectIDs than allowed having
+/// disappeared since the previous poll
+#[derive(Debug, Clone, Serialize)]
+pub struct ContentChurn {
+    pub app_id: String,
+    pub index_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subset: Option<String>,
+    /// Size of the previous sample
+    pub sampled: usize,
+    pub disappeared: usize,
+    pub churn_percent: f64,
+    /// Some of the objectIDs that disappeared
+    pub examples: Vec<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Synonyms or rules count of an index further below the expected one than its delta allows
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemsDropped {
+    pub app_id: String,
+    pub index_name: String,
+    /// `synonyms` or `rules`
+    pub items: IndexItems,
+    pub expected: u64,
+    pub current: u64,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Settings of an index that changed since they were last seen
+#[derive(Debug, Clone, Serialize)]
+pub struct SettingsChanged {
+    pub app_id: String,
+    pub index_name: String,
+    pub diff: SettingsDiff,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Index that a poll found deleted after earlier polls found it
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexDeleted {
+    pub app_id: String,
+    pub index_name: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Index found again after it was deleted, or with a new creation time in the list of indices
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexRecreated {
+    pub app_id: String,
+    pub index_name: String,
+    pub expected_records: u64,
+    pub current_records: u64,
+    /// Whether the expected records count was reset to the current one
+    pub rebaselined: bool,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Summary of the polls of an index over a `--digest` period, sent in place of the other alerts
+#[derive(Debug, Clone, Serialize)]
+pub struct Digest {
+    pub app_id: String,
+    pub index_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subset: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub polls: u64,
+    pub failed_polls: u64,
+    /// Records counts of the first and the last poll of the period, `None` when no poll counted them
+    pub first_records: Option<u64>,
+    pub last_records: Option<u64>,
+    pub min_records: Option<u64>,
+    pub max_records: Option<u64>,
+    /// Write operations among the log entries fetched during the period
+    pub write_operations: u64,
+    /// Breaches going on during the period, the ones started before it included
+    pub breaches: Vec<DigestBreach>,
+    /// Algolia API calls the monitor made since midnight UTC for all its indices, and the ones a day would take
+    pub api_calls_today: u64,
+    pub projected_daily_api_calls: u64,
+    /// Critical event that sent the digest before the end of the period, such as `index has no records left`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub critical: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Breach of a [`Digest`], which lasted until its recovery or the end of the period
+#[derive(Debug, Clone, Serialize)]
+pub struct DigestBreach {
+    /// Threshold the count breached, such as `difference -1200 is below the minimum delta of -1000`
+    pub breach: String,
+    pub started_at: DateTime<Utc>,
+    pub duration_secs: u64,
+    /// Whether the breach still goes on at the end of the period
+    pub ongoing: bool,
+}
+
+impl Digest {
+    /// Several lines of text, the first one telling the index, the period and the critical event
+    pub fn text(&self) -> String {
+        let index = match &self.subset {
+            Some(subset) => format!("{} subset {}", self.index_name, subset),
+            None => self.index_name.clone(),
+        };
+        let mut lines = vec![format!(
+            "Digest of {} from {} to {}{}",
+            index,
+            self.started_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+            self.ended_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+            self.critical
+                .as_ref()
+                .map_or_else(String::new, |critical| format!(", sent early as {critical}"))
+        )];
+        lines.push(match (self.first_records, self.last_records, self.min_records, self.max_records) {
+            (Some(first), Some(last), Some(min), Some(max)) => format!(
+                "{} polls, {} failed: {} records at the start, {} at the end, {} to {}",
+                self.polls, self.failed_polls, first, last, min, max
+            ),
+            _ => format!("{} polls, {} failed: no records count", self.polls, self.failed_polls),
+        });
+        lines.push(format!("{} write operations in the fetched logs", self.write_operations));
+        lines.push(format!(
+            "{} API calls of the monitor today, about {} a day at the usual delays",
+            self.api_calls_today, self.projected_daily_api_calls
+        ));
+        match self.breaches.is_empty() {
+            true => lines.push("No breach".to_string()),
+            false => lines.extend(self.breaches.iter().map(|breach| {
+                format!(
+                    "Breach from {} for {}{}: {}",
+                    breach.started_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+                    humantime::format_duration(Duration::from_secs(breach.duration_secs)),
+                    if breach.ongoing { ", ongoing" } else { "" },
+                    breach.breach
+                )
+            })),
+        }
+
+        lines.join("\n")
+    }
+}
+
+/// Event sent to the notification channels
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Alert {
+    Breach(Breach),
+    Recovery(Recovery),
+    Unreachable(Unreachable),
+    Destructive(Box<Destructive>),
+    SearchErrors(SearchErrors),
+    PendingTasks(PendingTasks),
+    ReplicasOutOfSync(ReplicasOutOfSync),
+    ComparisonOutOfSync(ComparisonOutOfSync),
+    FacetDrops(FacetDrops),
+    ContentChurn(ContentChurn),
+    ItemsDropped(ItemsDropped),
+    SettingsChanged(SettingsChanged),
+    IndexDeleted(IndexDeleted),
+    IndexRecreated(IndexRecreated),
+    Digest(Box<Digest>),
+}
+
+/// How a poll that breached is reported, decided by [`AlertState`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Notice {
+    /// First poll of the breach, reported everywhere
+    New,
+    /// Ongoing breach last notified a renotify interval ago, reported again
+    Reminder,
+    /// Ongoing breach notified recently, left out of the notifications and the output
+    Quiet,
+}
+
+/// Notification state of a single index, used to throttle notifications
+///
+/// A breach is notified when it starts, then again every renotify interval while it lasts, until the
+/// recovery that lets the next breach be notified right away.
+#[derive(Debug, Default)]
+pub struct AlertState {
+    last_notified: Option<Instant>,
+    breached_at: Option<Instant>,
+    min_records: Option<u64>,
+    unreachable: bool,
+    empty: bool,
+    /// Whether the ongoing breach started during the quiet hours, before it was notified
+    quiet_start: bool,
+}
+
+impl AlertState {
+    /// Marks the index as breached, returning whether the renotify interval allows notifying about it
+    pub fn breach(&mut self, interval: Duration) -> bool {
+        self.notice(interval) != Notice::Quiet
+    }
+
+    /// Marks the index as breached, returning how to report the breach
+    pub fn notice(&mut self, interval: Duration) -> Notice {
+        let notice = match self.last_notified {
+            None => Notice::New,
+            Some(last_notified) if last_notified.elapsed() >= interval => Notice::Reminder,
+            Some(_) => Notice::Quiet,
+        };
+        if notice == Notice::New {
+            self.breached_at.get_or_insert_with(Instant::now);
+        }
+        if notice != Notice::Quiet {
+            self.last_notified = Some(Instant::now());
+        }
+        notice
+    }
+
+    /// Marks the index as breached with 0 records, reporting it as a new breach when it just became empty
+    /// and following the renotify interval afterwards
+    pub fn empty(&mut self, interval: Duration) -> Notice {
+        if std::mem::replace(&mut self.empty, true) {
+            return self.notice(interval);
+        }
+
+        self.breached_at = self.breached_at.or(Some(Instant::now()));
+        self.last_notified = Some(Instant::now());
+        Notice::New
+    }
+
+    /// Marks the index as breached during the quiet hours without notifying it, so the breach is notified as
+    /// new once they end unless it already was before they started
+    pub fn quiet(&mut self) {
+        if !self.is_breached() {
+            self.breached_at.get_or_insert_with(Instant::now);
+            self.quiet_start = true;
+        }
+    }
+
+    /// Whether the ongoing breach started during the quiet hours
+    pub fn started_quietly(&self) -> bool {
+        self.quiet_start
+    }
+
+    /// Time since the ongoing breach started, `None` without one
+    pub fn breached_for(&self) -> Option<Duration> {
+        self.breached_at.map(|breached_at| breached_at.elapsed())
+    }
+
+    /// Keeps the lowest records count seen during the ongoing breach
+    pub fn breached_records(&mut self, records: u64) {
+        self.min_records = Some(self.min_records.map_or(records, |min_records| min_records.min(records)));
+    }
+
+    /// Lowest records count seen during the ongoing breach, `None` without one
+    pub fn min_records(&self) -> Option<u64> {
+        self.min_records
+    }
+
+    /// Whether a breach was notified since the last recovery
+    pub fn is_breached(&self) -> bool {
+        self.last_notified.is_some()
+    }
+
+    /// Marks the index as recovered from a breach, so the next breach is notified right away
+    pub fn recover(&mut self) {
+        self.last_notified = None;
+        self.breached_at = None;
+        self.min_records = None;
+        self.empty = false;
+        self.quiet_start = false;
+    }
+
+    /// Marks the index as not reachable, returning whether it just became unreachable
+    pub fn unreachable(&mut self) -> bool {
+        !std::mem::replace(&mut self.unreachable, true)
+    }
+
+    /// Marks the index as polled successfully
+    pub fn reachable(&mut self) {
+        self.unreachable = false;
+    }
+}
+
+/// Extra header sent with webhook requests, given as `Name: value`
+#[derive(Debug, Clone)]
+pub struct WebhookHeader {
+    name: HeaderName,
+    value: HeaderValue,
+}
+
+impl FromStr for WebhookHeader {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (name, value) = value
+            .split_once(':')
+            .ok_or_else(|| "expected a header in `Name: value` format".to_string())?;
+
+        Ok(WebhookHeader {
+            name: name.trim().parse().map_err(|_| format!("invalid header name `{}`", name.trim()))?,
+            value: value.trim().parse().map_err(|_| "invalid header value".to_string())?,
+        })
+    }
+}
+
+/// Generic outgoing webhook receiving alerts as JSON
+#[derive(Clone)]
+pub struct Webhook {
+    client: reqwest::Client,
+    url: String,
+    headers: HeaderMap,
+}
+
+impl Webhook {
+    pub fn new(client: reqwest::Client, url: String, headers: &[WebhookHeader]) -> Self {
+        Webhook {
+            client,
+            url,
+            headers: headers
+                .iter()
+                .map(|header| (header.name.clone(), header.value.clone()))
+                .collect(),
+        }
+    }
+
+    async fn send(&self, alert: &Alert) -> Result<(), reqwest::Error> {
+        self.client
+            .post(&self.url)
+            .headers(self.headers.clone())
+            .json(alert)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+/// Channels notified about breaches and recoveries
+///
+/// Deliveries run in their own tasks so a slow receiver never delays polling.
+#[derive(Clone, Default)]
+pub struct Notifier {
+    webhook: Option<Webhook>,
+    slack: Option<Slack>,
+    telegram: Option<Telegram>,
+    pagerduty: Option<PagerDuty>,
+    commands: Option<Commands>,
+    heartbeat: Option<Heartbeat>,
+    /// Whether the alerts are printed instead of delivered
+    dry_run: bool,
+    /// Whether only the digests are sent, the other alerts going into them
+    digest: bool,
+}
+
+impl Notifier {
+    pub fn with_webhook(mut self, webhook: Webhook) -> Self {
+        self.webhook = Some(webhook);
+        self
+    }
+
+    pub fn with_slack(mut self, slack: Slack) -> Self {
+        self.slack = Some(slack);
+        self
+    }
+
+    pub fn with_telegram(mut self, telegram: Telegram) -> Self {
+        self.telegram = Some(telegram);
+        self
+    }
+
+    pub fn with_pagerduty(mut self, pagerduty: PagerDuty) -> Self {
+        self.pagerduty = Some(pagerduty);
+        self
+    }
+
+    pub fn with_commands(mut self, commands: Commands) -> Self {
+        self.commands = Some(commands);
+        self
+    }
+
+    pub fn with_heartbeat(mut self, heartbeat: Heartbeat) -> Self {
+        self.heartbeat = Some(heartbeat);
+        self
+    }
+
+    /// Prints the alerts as JSON along with the channels they would go to instead of delivering them, the
+    /// heartbeat not being pinged either
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Sends only the [`Digest`]s of `--digest`, leaving the other alerts out
+    pub fn with_digest(mut self, digest: bool) -> Self {
+        self.digest = digest;
+        self
+    }
+
+    /// Pings the heartbeat URL after a successful poll, with the records count when the poll counted them
+    pub fn heartbeat(&self, index_name: &str, records: Option<u64>) {
+        self.ping(
+            true,
+            match records {
+                Some(records) => format!("{index_name}: {records} records"),
+                None => index_name.to_string(),
+            },
+        );
+    }
+
+    /// Pings the heartbeat fail URL after a failed poll
+    pub fn heartbeat_failed(&self, index_name: &str, error: impl Display) {
+        self.ping(false, format!("{index_name}: {error}"));
+    }
+
+    fn ping(&self, succeeded: bool, body: String) {
+        if self.dry_run {
+            debug!("[{}] Dry run, not pinging the heartbeat: {}", now(), body);
+            return;
+        }
+        if let Some(heartbeat) = self.heartbeat.clone() {
+            tokio::spawn(async move { heartbeat.ping(succeeded, body).await });
+        }
+    }
+
+    pub fn notify(&self, alert: Alert) {
+        self.spawn(alert);
+    }
+
+    /// Notifies the channels and waits for the deliveries, for use right before the monitor stops
+    pub async fn notify_and_wait(&self, alert: Alert) {
+        for delivery in self.spawn(alert) {
+            let _ = delivery.await;
+        }
+    }
+
+    fn spawn(&self, alert: Alert) -> Vec<JoinHandle<()>> {
+        if self.digest && !matches!(alert, Alert::Digest(_)) {
+            let alert = serde_json::to_string(&alert).unwrap_or_default();
+            debug!("[{}] Leaving the alert to the digest: {}", now(), alert);
+            return vec![];
+        }
+        if self.dry_run {
+            self.print(&alert);
+            return vec![];
+        }
+
+        let mut deliveries = vec![];
+
+        if let Some(webhook) = self.webhook.clone() {
+            let alert = alert.clone();
+            deliveries.push(tokio::spawn(async move {
+                deliver("webhook", || webhook.send(&alert)).await;
+            }));
+        }
+
+        if let Some(telegram) = self.telegram.clone() {
+            let alert = alert.clone();
+            deliveries.push(tokio::spawn(async move {
+                deliver("Telegram", || telegram.send(&alert)).await;
+            }));
+        }
+
+        if let Some(pagerduty) = self.pagerduty.clone() {
+            let alert = alert.clone();
+            deliveries.push(tokio::spawn(async move {
+                deliver("PagerDuty", || pagerduty.send(&alert)).await;
+            }));
+        }
+
+        if let Some(commands) = self.commands.clone() {
+            let alert = alert.clone();
+            deliveries.push(tokio::spawn(async move {
+                commands.run(&alert).await;
+            }));
+        }
+
+        if let Some(slack) = self.slack.clone() {
+            deliveries.push(tokio::spawn(async move {
+                deliver("Slack", || slack.send(&alert)).await;
+            }));
+        }
+
+        deliveries
+    }
+
+    /// Prints the alert of a dry run with the channels it would be delivered to
+    fn print(&self, alert: &Alert) {
+        let channels: Vec<_> = [
+            ("webhook", self.webhook.is_some()),
+            ("Telegram", self.telegram.is_some()),
+            ("PagerDuty", self.pagerduty.is_some()),
+            ("commands", self.commands.is_some()),
+            ("Slack", self.slack.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(channel, configured)| configured.then_some(channel))
+        .collect();
+        let alert = serde_json::to_string(alert).unwrap_or_default();
+
+        match channels.is_empty() {
+            true => info!("[{}] Dry run, no channel to notify of {}", now(), alert),
+            false => info!("[{}] Dry run, not notifying {} of {}", now(), channels.join(", "), alert),
+        }
+    }
+}
+
+/// Sends a notification, retrying a couple of times before logging the failure
+async fn deliver<F, Fut>(channel: &str, send: F)
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = Result<(), reqwest::Error>>,
+{
+    let mut attempt = 0;
+    loop {
+        let error = match send().await {
+            Ok(()) => return,
+            Err(error) => error,
+        };
+
+        if attempt >= DELIVERY_RETRIES {
+            error!(
+                "[{}] Failed to deliver {} notification: {}",
+                now(),
+                channel,
+                error.without_url()
+            );
+            return;
+        }
+        attempt += 1;
+        sleep(Duration::from_secs(attempt as u64)).await;
+    }
+}
+
+/// Cuts the text to at most `length` bytes without splitting a character
+pub(super) fn truncate(text: &str, length: usize) -> &str {
+    if text.len() <= length {
+        return text;
+    }
+
+    let mut end = length;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_secs(3600);
+
+    /// Moves the last notification of the breach back by `ago`, as if it was sent that long ago
+    fn notified_ago(alert_state: &mut AlertState, ago: Duration) {
+        alert_state.last_notified = Some(Instant::now() - ago);
+    }
+
+    #[test]
+    fn breach_is_new_then_quiet_during_the_renotify_interval() {
+        let mut alert_state = AlertState::default();
+
+        assert_eq!(alert_state.notice(INTERVAL), Notice::New);
+        assert!(alert_state.is_breached());
+        assert!(alert_state.breached_for().is_some());
+        assert_eq!(alert_state.notice(INTERVAL), Notice::Quiet);
+        assert!(!alert_state.breach(INTERVAL));
+    }
+
+    #[test]
+    fn breach_is_reminded_once_the_renotify_interval_elapsed() {
+        let mut alert_state = AlertState::default();
+        alert_state.notice(INTERVAL);
+
+        notified_ago(&mut alert_state, INTERVAL - Duration::from_secs(1));
+        assert_eq!(alert_state.notice(INTERVAL), Notice::Quiet);
+
+        notified_ago(&mut alert_state, INTERVAL);
+        assert_eq!(alert_state.notice(INTERVAL), Notice::Reminder);
+        // The reminder starts the next interval
+        assert_eq!(alert_state.notice(INTERVAL), Notice::Quiet);
+    }
+
+    #[test]
+    fn reminder_keeps_the_start_of_the_breach() {
+        let mut alert_state = AlertState::default();
+        alert_state.notice(INTERVAL);
+        alert_state.breached_at = Some(Instant::now() - 2 * INTERVAL);
+
+        notified_ago(&mut alert_state, INTERVAL);
+        assert_eq!(alert_state.notice(INTERVAL), Notice::Reminder);
+        assert!(alert_state.breached_for().unwrap() >= 2 * INTERVAL);
+    }
+
+    #[test]
+    fn zero_renotify_interval_reminds_on_every_poll() {
+        let mut alert_state = AlertState::default();
+
+        assert_eq!(alert_state.notice(Duration::ZERO), Notice::New);
+        assert_eq!(alert_state.notice(Duration::ZERO), Notice::Reminder);
+        assert!(alert_state.breach(Duration::ZERO));
+    }
+
+    #[test]
+    fn breach_right_after_a_recovery_is_new() {
+        let mut alert_state = AlertState::default();
+        alert_state.notice(INTERVAL);
+        alert_state.breached_records(9_000);
+
+        alert_state.recover();
+        assert!(!alert_state.is_breached());
+        assert_eq!(alert_state.breached_for(), None);
+        assert_eq!(alert_state.min_records(), None);
+
+        // Not a reminder, even though the previous breach was notified within the interval
+        assert_eq!(alert_state.notice(INTERVAL), Notice::New);
+    }
+
+    #[test]
+    fn breach_after_a_recovery_at_the_renotify_interval_is_new() {
+        let mut alert_state = AlertState::default();
+        alert_state.notice(INTERVAL);
+        notified_ago(&mut alert_state, INTERVAL);
+
+        alert_state.recover();
+
+        assert_eq!(alert_state.notice(INTERVAL), Notice::New);
+        assert_eq!(alert_state.notice(INTERVAL), Notice::Quiet);
+    }
+
+    #[test]
+    fn lowest_records_of_the_breach_are_kept_until_the_recovery() {
+        let mut alert_state = AlertState::default();
+        alert_state.notice(INTERVAL);
+
+        alert_state.breached_records(9_000);
+        alert_state.breached_records(8_500);
+        alert_state.breached_records(8_800);
+        assert_eq!(alert_state.min_records(), Some(8_500));
+
+        alert_state.recover();
+        alert_state.notice(INTERVAL);
+        alert_state.breached_records(9_500);
+        assert_eq!(alert_state.min_records(), Some(9_500));
+    }
+
+    #[test]
+    fn index_becoming_empty_is_new_then_follows_the_renotify_interval() {
+        let mut alert_state = AlertState::default();
+
+        assert_eq!(alert_state.empty(INTERVAL), Notice::New);
+        assert_eq!(alert_state.empty(INTERVAL), Notice::Quiet);
+
+        notified_ago(&mut alert_state, INTERVAL);
+        assert_eq!(alert_state.empty(INTERVAL), Notice::Reminder);
+    }
+
+    #[test]
+    fn index_becoming_empty_during_a_breach_is_new_in_the_same_breach() {
+        let mut alert_state = AlertState::default();
+        alert_state.notice(INTERVAL);
+        alert_state.breached_at = Some(Instant::now() - INTERVAL);
+
+        // Within the renotify interval of the breach, yet the drop to 0 is reported right away
+        assert_eq!(alert_state.empty(INTERVAL), Notice::New);
+        assert!(alert_state.breached_for().unwrap() >= INTERVAL);
+        assert_eq!(alert_state.empty(INTERVAL), Notice::Quiet);
+    }
+
+    #[test]
+    fn index_refilling_partly_stays_in_the_notified_breach() {
+        let mut alert_state = AlertState::default();
+        alert_state.empty(INTERVAL);
+
+        assert_eq!(alert_state.notice(INTERVAL), Notice::Quiet);
+    }
+
+    #[test]
+    fn index_emptied_again_after_a_recovery_is_new() {
+        let mut alert_state = AlertState::default();
+        alert_state.empty(INTERVAL);
+
+        alert_state.recover();
+
+        assert_eq!(alert_state.empty(INTERVAL), Notice::New);
+    }
+
+    #[test]
+    fn breach_during_the_quiet_hours_is_new_once_they_end() {
+        let mut alert_state = AlertState::default();
+
+        alert_state.quiet();
+        alert_state.breached_at = Some(Instant::now() - INTERVAL);
+        alert_state.quiet();
+        assert!(!alert_state.is_breached());
+        assert!(alert_state.started_quietly());
+
+        assert_eq!(alert_state.notice(INTERVAL), Notice::New);
+        assert!(alert_state.started_quietly());
+        // The breach is timed from its start during the quiet hours
+        assert!(alert_state.breached_for().unwrap() >= INTERVAL);
+        assert_eq!(alert_state.notice(INTERVAL), Notice::Quiet);
+    }
+
+    #[test]
+    fn index_emptied_during_the_quiet_hours_is_new_once_they_end() {
+        let mut alert_state = AlertState::default();
+        alert_state.quiet();
+
+        assert_eq!(alert_state.empty(INTERVAL), Notice::New);
+        assert!(alert_state.started_quietly());
+    }
+
+    #[test]
+    fn quiet_hours_leave_a_notified_breach_alone() {
+        let mut alert_state = AlertState::default();
+        alert_state.notice(INTERVAL);
+
+        alert_state.quiet();
+
+        assert!(!alert_state.started_quietly());
+        assert_eq!(alert_state.notice(INTERVAL), Notice::Quiet);
+    }
+
+    #[test]
+    fn recovery_during_the_quiet_hours_forgets_the_quiet_breach() {
+        let mut alert_state = AlertState::default();
+        alert_state.quiet();
+        alert_state.breached_records(0);
+
+        alert_state.recover();
+
+        assert!(!alert_state.started_quietly());
+        assert_eq!(alert_state.breached_for(), None);
+        assert_eq!(alert_state.min_records(), None);
+        assert_eq!(alert_state.notice(INTERVAL), Notice::New);
+        assert!(!alert_state.started_quietly());
+    }
+
+    #[test]
+    fn unreachable_is_reported_once_until_reachable_again() {
+        let mut alert_state = AlertState::default();
+
+        assert!(alert_state.unreachable());
+        assert!(!alert_state.unreachable());
+
+        alert_state.reachable();
+        assert!(alert_state.unreachable());
+    }
+}
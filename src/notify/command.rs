@@ -0,0 +1,256 @@
+use super::Alert;
+use crate::{now, AlgoliaLogEntry};
+use rand::Rng;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tracing::{error, info, warn};
+
+/// Number of log files written so far, keeping apart the files of commands running at the same time
+static LOG_FILES: AtomicU64 = AtomicU64::new(0);
+
+/// Names tried for a log file before giving up on the ones taken by files of other users
+const LOG_FILE_ATTEMPTS: usize = 10;
+
+/// Shell commands run on breaches and recoveries, with the details in environment variables
+///
+/// `ALGOLIA_INDEX`, `EXPECTED_RECORDS`, `CURRENT_RECORDS`, `DELTA` and `EVENT` describe the alert,
+/// `ALGOLIA_SUBSET` names the subset when one moved, and `ALGOLIA_LOGS` is the path of a file with the
+/// fetched log entries as JSON lines, removed once the command finishes.
+#[derive(Clone)]
+pub struct Commands {
+    on_breach: Option<String>,
+    on_recovery: Option<String>,
+    timeout: Duration,
+}
+
+/// File with the log entries for a command, removed when dropped so it is also removed when the monitor
+/// exits while the command runs
+struct LogsFile(PathBuf);
+
+impl LogsFile {
+    /// Writes the entries to a new file of the directory that only the user of the monitor can read
+    ///
+    /// The file is never one that exists already, so another user of a shared temporary directory can't have a
+    /// file of theirs, or one their symlink points to, written over.
+    async fn create(directory: &Path, logs: &[u8]) -> io::Result<Self> {
+        let mut attempts = 0;
+        loop {
+            let path = directory.join(format!(
+                "algolia-monitor-{}-{}-{:08x}.ndjson",
+                std::process::id(),
+                LOG_FILES.fetch_add(1, Ordering::Relaxed),
+                rand::thread_rng().gen::<u32>()
+            ));
+            let mut options = tokio::fs::OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            options.mode(0o600);
+            match options.open(&path).await {
+                Ok(mut file) => {
+                    let logs_file = LogsFile(path);
+                    file.write_all(logs).await?;
+                    file.flush().await?;
+                    return Ok(logs_file);
+                }
+                Err(error) if error.kind() == io::ErrorKind::AlreadyExists && attempts < LOG_FILE_ATTEMPTS => {
+                    attempts += 1;
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+}
+
+impl Drop for LogsFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Alert a command runs for
+struct Event<'a> {
+    name: &'static str,
+    index_name: &'a str,
+    subset: Option<&'a str>,
+    expected_records: u64,
+    current_records: u64,
+    delta: i64,
+    logs: &'a [AlgoliaLogEntry],
+}
+
+impl Commands {
+    pub fn new(on_breach: Option<String>, on_recovery: Option<String>, timeout: Duration) -> Self {
+        Commands {
+            on_breach,
+            on_recovery,
+            timeout,
+        }
+    }
+
+    /// Runs the command of a breach or recovery, logging how it ended instead of failing
+    pub(super) async fn run(&self, alert: &Alert) {
+        let (command, event) = match alert {
+            Alert::Breach(breach) => (
+                &self.on_breach,
+                Event {
+                    name: "breach",
+                    index_name: &breach.index_name,
+                    subset: breach.subset.as_deref(),
+                    expected_records: breach.expected_records,
+                    current_records: breach.current_records,
+                    delta: breach.delta,
+                    logs: &breach.logs,
+                },
+            ),
+            Alert::Recovery(recovery) => (
+                &self.on_recovery,
+                Event {
+                    name: "recovery",
+                    index_name: &recovery.index_name,
+                    subset: recovery.subset.as_deref(),
+                    expected_records: recovery.expected_records,
+                    current_records: recovery.current_records,
+                    delta: recovery.delta,
+                    logs: &[],
+                },
+            ),
+            _ => return,
+        };
+        let Some(command) = command else {
+            return;
+        };
+
+        let logs: String = event.logs.iter().map(|log| log.to_json() + "\n").collect();
+        let directory = std::env::temp_dir();
+        let logs_file = match LogsFile::create(&directory, logs.as_bytes()).await {
+            Ok(logs_file) => logs_file,
+            Err(error) => {
+                error!(
+                    "[{}] Failed to write the logs for the {} command to {}: {}",
+                    now(),
+                    event.name,
+                    directory.display(),
+                    error
+                );
+                return;
+            }
+        };
+
+        self.execute(command, &event, &logs_file.0).await;
+    }
+
+    async fn execute(&self, command: &str, event: &Event<'_>, logs_path: &Path) {
+        let mut process = shell(command);
+        process
+            .env("ALGOLIA_INDEX", event.index_name)
+            .env("EXPECTED_RECORDS", event.expected_records.to_string())
+            .env("CURRENT_RECORDS", event.current_records.to_string())
+            .env("DELTA", event.delta.to_string())
+            .env("EVENT", event.name)
+            .env("ALGOLIA_LOGS", logs_path)
+            .stdin(Stdio::null())
+            // Standard output is kept apart from the log entries the monitor prints there
+            .stdout(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(subset) = event.subset {
+            process.env("ALGOLIA_SUBSET", subset);
+        }
+
+        let child = match process.spawn() {
+            Ok(child) => child,
+            Err(error) => {
+                error!("[{}] Failed to run the {} command `{}`: {}", now(), event.name, command, error);
+                return;
+            }
+        };
+
+        // Dropping the child when the timeout is reached kills it
+        match tokio::time::timeout(self.timeout, child.wait_with_output()).await {
+            Ok(Ok(output)) => {
+                for line in String::from_utf8_lossy(&output.stdout).lines() {
+                    info!("{line}");
+                }
+                info!(
+                    "[{}] The {} command of {} {}",
+                    now(),
+                    event.name,
+                    event.index_name,
+                    match output.status.code() {
+                        Some(code) => format!("exited with status {code}"),
+                        None => "was stopped by a signal".to_string(),
+                    }
+                );
+            }
+            Ok(Err(error)) => error!("[{}] The {} command failed: {}", now(), event.name, error),
+            Err(_) => warn!(
+                "[{}] The {} command of {} was killed after {}",
+                now(),
+                event.name,
+                event.index_name,
+                humantime::format_duration(self.timeout)
+            ),
+        }
+    }
+}
+
+/// Command running the line in the shell of the platform
+#[cfg(unix)]
+pub fn shell(command: &str) -> tokio::process::Command {
+    let mut process = tokio::process::Command::new("sh");
+    process.arg("-c").arg(command);
+    process
+}
+
+/// Command running the line in the shell of the platform
+#[cfg(not(unix))]
+pub fn shell(command: &str) -> tokio::process::Command {
+    let mut process = tokio::process::Command::new("cmd");
+    process.arg("/C").arg(command);
+    process
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("algolia-monitor-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    #[tokio::test]
+    async fn logs_file_has_the_entries_until_dropped() {
+        let directory = directory("logs-file");
+
+        let logs_file = LogsFile::create(&directory, b"{}\n").await.unwrap();
+        let path = logs_file.0.clone();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{}\n");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+
+        drop(logs_file);
+        assert!(!path.exists());
+        std::fs::remove_dir(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn logs_files_of_commands_at_the_same_time_are_apart() {
+        let directory = directory("logs-files");
+
+        let first = LogsFile::create(&directory, b"first\n").await.unwrap();
+        let second = LogsFile::create(&directory, b"second\n").await.unwrap();
+
+        assert_ne!(first.0, second.0);
+        assert_eq!(std::fs::read_to_string(&first.0).unwrap(), "first\n");
+        drop((first, second));
+        std::fs::remove_dir(&directory).unwrap();
+    }
+}
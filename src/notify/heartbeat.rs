@@ -0,0 +1,55 @@
+use crate::now;
+use std::time::Duration;
+use tracing::{error, warn};
+
+/// Time a ping has to reach the heartbeat service, short enough that pings never pile up
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Dead man's switch service, such as healthchecks.io, pinged after every poll so it alerts once the pings
+/// stop coming
+///
+/// Each ping is a POST request with the index and its records count as the body, which the service shows
+/// in the log of the check. Failed polls ping the fail URL instead, `<url>/fail` unless another one is given.
+#[derive(Clone)]
+pub struct Heartbeat {
+    client: reqwest::Client,
+    url: String,
+    fail_url: String,
+}
+
+impl Heartbeat {
+    pub fn new(client: reqwest::Client, url: String, fail_url: Option<String>) -> Self {
+        Heartbeat {
+            fail_url: fail_url.unwrap_or_else(|| format!("{}/fail", url.trim_end_matches('/'))),
+            client,
+            url,
+        }
+    }
+
+    pub(super) async fn ping(&self, succeeded: bool, body: String) {
+        let url = match succeeded {
+            true => &self.url,
+            false => &self.fail_url,
+        };
+
+        let result = self
+            .client
+            .post(url)
+            .timeout(PING_TIMEOUT)
+            .body(body)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+
+        // The URL is left out of the error, as the ones of heartbeat services work as a secret
+        match result {
+            Ok(_) => {}
+            Err(error) if error.is_timeout() => warn!(
+                "[{}] Heartbeat ping got no answer within {}",
+                now(),
+                humantime::format_duration(PING_TIMEOUT)
+            ),
+            Err(error) => error!("[{}] Failed to ping the heartbeat URL: {}", now(), error.without_url()),
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use super::{truncate, Alert};
+use crate::DestructiveOperation;
+use serde_json::{json, Value};
+
+const EVENTS_URL: &str = "https://events.pagerduty.com/v2/enqueue";
+
+/// Length limit of the summary of a PagerDuty event
+const MAX_SUMMARY_LENGTH: usize = 1024;
+/// Kind of the incidents of the records count breaches, the destructive ones being named after the operation
+const RECORDS: &str = "records";
+
+/// PagerDuty service receiving breaches and destructive operations as incidents through the Events API v2
+///
+/// The breaches of an index share the same deduplication key, so an ongoing breach updates its incident and
+/// the recovery resolves it, while each kind of destructive operation opens an incident of its own.
+#[derive(Clone)]
+pub struct PagerDuty {
+    client: reqwest::Client,
+    routing_key: String,
+    /// Host the monitor runs on, reported as the source of the events
+    source: String,
+}
+
+impl PagerDuty {
+    pub fn new(client: reqwest::Client, routing_key: String) -> Self {
+        PagerDuty {
+            client,
+            routing_key,
+            source: gethostname::gethostname().to_string_lossy().into_owned(),
+        }
+    }
+
+    pub(super) async fn send(&self, alert: &Alert) -> Result<(), reqwest::Error> {
+        let Some(event) = self.event(alert) else {
+            return Ok(());
+        };
+
+        self.client
+            .post(EVENTS_URL)
+            .json(&event)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    /// Event of the alert, `None` for the alerts that don't open or resolve an incident
+    fn event(&self, alert: &Alert) -> Option<Value> {
+        let (dedup_key, index_name, summary, severity, custom_details) = match alert {
+            Alert::Breach(breach) => (
+                dedup_key(&breach.app_id, &breach.index_name, breach.subset.as_deref(), RECORDS),
+                &breach.index_name,
+                match breach.current_records {
+                    0 => format!("{} has no records left", index(&breach.index_name, breach.subset.as_deref())),
+                    _ => format!(
+                        "Records count of {} breached the threshold: expected {}, now {} (delta {:+})",
+                        index(&breach.index_name, breach.subset.as_deref()),
+                        breach.expected_records,
+                        breach.current_records,
+                        breach.delta
+                    ),
+                },
+                match breach.current_records {
+                    0 => "critical",
+                    _ => "warning",
+                },
+                json!({
+                    "expected_records": breach.expected_records,
+                    "current_records": breach.current_records,
+                    "delta": breach.delta,
+                    "subset": breach.subset,
+                    "quiet_hours": breach.quiet_hours,
+                }),
+            ),
+            Alert::Destructive(destructive) => (
+                dedup_key(
+                    &destructive.app_id,
+                    &destructive.index_name,
+                    None,
+                    &destructive.operation.to_string(),
+                ),
+                &destructive.index_name,
+                format!("{} ran on {}", destructive.operation, destructive.index_name),
+                match destructive.operation {
+                    DestructiveOperation::DeleteIndex => "critical",
+                    DestructiveOperation::ClearObjects => "warning",
+                },
+                json!({
+                    "operation": destructive.operation.to_string(),
+                    "method": destructive.log.method,
+                    "url": destructive.log.url,
+                    "logged_at": destructive.log.timestamp,
+                }),
+            ),
+            Alert::Recovery(recovery) => {
+                let subset = recovery.subset.as_deref();
+                return Some(json!({
+                    "routing_key": self.routing_key,
+                    "event_action": "resolve",
+                    "dedup_key": dedup_key(&recovery.app_id, &recovery.index_name, subset, RECORDS),
+                }));
+            }
+            _ => return None,
+        };
+
+        Some(json!({
+            "routing_key": self.routing_key,
+            "event_action": "trigger",
+            "dedup_key": dedup_key,
+            "payload": {
+                "summary": truncate(&summary, MAX_SUMMARY_LENGTH),
+                "source": self.source,
+                "severity": severity,
+                "component": index_name,
+                "custom_details": custom_details,
+            },
+        }))
+    }
+}
+
+/// Key of the incident of a kind of alert of an index, the same across runs of the monitor so a restart
+/// updates it
+fn dedup_key(app_id: &str, index_name: &str, subset: Option<&str>, kind: &str) -> String {
+    match subset {
+        Some(subset) => format!("algolia-monitor/{app_id}/{index_name}/{subset}/{kind}"),
+        None => format!("algolia-monitor/{app_id}/{index_name}/{kind}"),
+    }
+}
+
+fn index(index_name: &str, subset: Option<&str>) -> String {
+    match subset {
+        Some(subset) => format!("{index_name} subset {subset}"),
+        None => index_name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notify::{Breach, Destructive, Recovery};
+    use chrono::Utc;
+
+    fn pagerduty() -> PagerDuty {
+        PagerDuty::new(reqwest::Client::new(), "routing-key".to_string())
+    }
+
+    fn breach(index_name: &str, subset: Option<&str>, current_records: u64, reminder: bool) -> Alert {
+        Alert::Breach(Breach {
+            app_id: "APPID".to_string(),
+            index_name: index_name.to_string(),
+            subset: subset.map(str::to_string),
+            expected_records: 10_000,
+            current_records,
+            delta: current_records as i64 - 10_000,
+            reminder,
+            quiet_hours: false,
+            timestamp: Utc::now(),
+            logs: vec![],
+        })
+    }
+
+    fn recovery(index_name: &str, subset: Option<&str>) -> Alert {
+        Alert::Recovery(Recovery {
+            app_id: "APPID".to_string(),
+            index_name: index_name.to_string(),
+            subset: subset.map(str::to_string),
+            expected_records: 10_000,
+            current_records: 9_990,
+            delta: -10,
+            breach_duration_secs: 120,
+            min_records: 0,
+            timestamp: Utc::now(),
+        })
+    }
+
+    fn destructive(index_name: &str, operation: DestructiveOperation) -> Alert {
+        let log = json!({
+            "timestamp": "2024-05-02T10:14:03Z",
+            "method": "DELETE",
+            "url": "/1/indexes/products",
+        });
+        Alert::Destructive(Box::new(Destructive {
+            app_id: "APPID".to_string(),
+            index_name: index_name.to_string(),
+            operation,
+            timestamp: Utc::now(),
+            log: serde_json::from_value(log).unwrap(),
+        }))
+    }
+
+    fn key(alert: &Alert) -> String {
+        let event = pagerduty().event(alert).unwrap();
+        event["dedup_key"].as_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn polls_of_one_breach_update_the_same_incident() {
+        let first = key(&breach("products", None, 8_000, false));
+
+        assert_eq!(key(&breach("products", None, 5_000, false)), first);
+        assert_eq!(key(&breach("products", None, 0, true)), first);
+        assert_eq!(key(&recovery("products", None)), first);
+        assert_eq!(first, "algolia-monitor/APPID/products/records");
+    }
+
+    #[test]
+    fn breach_triggers_and_recovery_resolves() {
+        let trigger = pagerduty().event(&breach("products", None, 0, false)).unwrap();
+        let resolve = pagerduty().event(&recovery("products", None)).unwrap();
+
+        assert_eq!(trigger["event_action"], "trigger");
+        assert_eq!(trigger["payload"]["severity"], "critical");
+        assert_eq!(resolve["event_action"], "resolve");
+        assert_eq!(resolve["dedup_key"], trigger["dedup_key"]);
+    }
+
+    #[test]
+    fn indices_and_subsets_have_incidents_of_their_own() {
+        let keys = [
+            key(&breach("products", None, 8_000, false)),
+            key(&breach("products_fr", None, 8_000, false)),
+            key(&breach("products", Some("store_1"), 8_000, false)),
+            key(&breach("products", Some("store_2"), 8_000, false)),
+        ];
+
+        for (position, key) in keys.iter().enumerate() {
+            assert!(!keys[position + 1..].contains(key), "{key} is shared");
+        }
+        assert_eq!(key(&recovery("products", Some("store_2"))), keys[3]);
+    }
+
+    #[test]
+    fn applications_have_incidents_of_their_own() {
+        let Alert::Breach(mut other) = breach("products", None, 8_000, false) else {
+            unreachable!()
+        };
+        other.app_id = "OTHERAPP".to_string();
+
+        assert_ne!(key(&Alert::Breach(other)), key(&breach("products", None, 8_000, false)));
+    }
+
+    #[test]
+    fn kinds_of_alerts_have_incidents_of_their_own() {
+        let breach = key(&breach("products", None, 0, false));
+        let delete_index = key(&destructive("products", DestructiveOperation::DeleteIndex));
+        let clear_objects = key(&destructive("products", DestructiveOperation::ClearObjects));
+
+        assert_ne!(breach, delete_index);
+        assert_ne!(breach, clear_objects);
+        assert_ne!(delete_index, clear_objects);
+        assert_eq!(key(&destructive("products", DestructiveOperation::DeleteIndex)), delete_index);
+    }
+}
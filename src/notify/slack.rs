@@ -0,0 +1,245 @@
+use super::{truncate, Alert};
+use serde_json::json;
+use std::time::Duration;
+
+/// Length of the log block kept well below the limits of a Slack message
+const MAX_LOG_BLOCK_LENGTH: usize = 3000;
+
+/// Slack incoming webhook receiving formatted alert messages
+#[derive(Clone)]
+pub struct Slack {
+    client: reqwest::Client,
+    url: String,
+    log_lines: usize,
+}
+
+impl Slack {
+    pub fn new(client: reqwest::Client, url: String, log_lines: usize) -> Self {
+        Slack {
+            client,
+            url,
+            log_lines,
+        }
+    }
+
+    pub(super) async fn send(&self, alert: &Alert) -> Result<(), reqwest::Error> {
+        self.client
+            .post(&self.url)
+            .json(&json!({ "text": self.message(alert) }))
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    fn message(&self, alert: &Alert) -> String {
+        match alert {
+            Alert::Breach(breach) => {
+                let mut message = format!(
+                    "{}\nExpected {} records, now {} (delta {:+})",
+                    match (breach.current_records, breach.reminder) {
+                        (0, _) => format!(
+                            ":sos: *CRITICAL: {} has no records left*",
+                            index(&breach.index_name, breach.subset.as_deref())
+                        ),
+                        (_, true) => format!(
+                            ":repeat: *Records count of {} is still past the threshold*",
+                            index(&breach.index_name, breach.subset.as_deref())
+                        ),
+                        _ => format!(
+                            ":rotating_light: *Records count of {} breached the threshold*",
+                            index(&breach.index_name, breach.subset.as_deref())
+                        ),
+                    },
+                    breach.expected_records,
+                    breach.current_records,
+                    breach.delta
+                );
+                if breach.quiet_hours {
+                    message.push_str("\nStarted during the quiet hours");
+                }
+
+                let log_lines: Vec<_> = breach
+                    .logs
+                    .iter()
+                    .filter(|log| log.is_write())
+                    .take(self.log_lines)
+                    .map(|log| {
+                        format!(
+                            "{} {} {} {}",
+                            log.timestamp.to_rfc3339(),
+                            log.method.as_deref().unwrap_or("-"),
+                            log.url.as_deref().unwrap_or("-"),
+                            log.answer_code.as_deref().unwrap_or("-")
+                        )
+                    })
+                    .collect();
+
+                if !log_lines.is_empty() {
+                    message.push_str("\n```\n");
+                    message.push_str(truncate(&log_lines.join("\n"), MAX_LOG_BLOCK_LENGTH));
+                    message.push_str("\n```");
+                }
+
+                message
+            }
+            Alert::Recovery(recovery) => format!(
+                ":white_check_mark: *Records count of {} recovered after {}*\n\
+                 Expected {} records, now {} (delta {:+}), lowest {} during the breach",
+                index(&recovery.index_name, recovery.subset.as_deref()),
+                humantime::format_duration(Duration::from_secs(recovery.breach_duration_secs)),
+                recovery.expected_records,
+                recovery.current_records,
+                recovery.delta,
+                recovery.min_records
+            ),
+            Alert::Unreachable(unreachable) => format!(
+                ":warning: *Index `{}` is not reachable*\n{}",
+                unreachable.index_name, unreachable.error
+            ),
+            Alert::SearchErrors(errors) => {
+                let codes: Vec<_> = errors.by_code.iter().map(|(code, count)| format!("{code}: {count}")).collect();
+                let mut message = format!(
+                    ":fire: *Searches on `{}` are failing*\n{} server errors ({:.1} per minute), {}",
+                    errors.index_name,
+                    errors.errors,
+                    errors.per_minute,
+                    codes.join(", ")
+                );
+
+                let examples: Vec<_> = errors.examples.iter().map(|log| log.to_json()).collect();
+                if !examples.is_empty() {
+                    message.push_str("\n```\n");
+                    message.push_str(truncate(&examples.join("\n"), MAX_LOG_BLOCK_LENGTH));
+                    message.push_str("\n```");
+                }
+
+                message
+            }
+            Alert::PendingTasks(pending) => format!(
+                ":hourglass_flowing_sand: *Indexing of `{}` is falling behind*\n\
+                 {} tasks waiting to be published{}",
+                pending.index_name,
+                pending.pending_tasks,
+                match pending.oldest_task_age_secs {
+                    Some(age) => {
+                        format!(", the oldest for {}", humantime::format_duration(Duration::from_secs(age)))
+                    }
+                    None => String::new(),
+                }
+            ),
+            Alert::ReplicasOutOfSync(out_of_sync) => {
+                let mut message = format!(
+                    ":twisted_rightwards_arrows: *Replicas of `{}` are out of sync*\n`{}`: {} records",
+                    out_of_sync.index_name, out_of_sync.index_name, out_of_sync.records
+                );
+                for replica in out_of_sync.replicas.iter().filter(|replica| replica.out_of_sync) {
+                    match replica.records {
+                        Some(records) => message.push_str(&format!(
+                            "\n`{}`: {} records ({:+})",
+                            replica.index_name,
+                            records,
+                            records as i64 - out_of_sync.records as i64
+                        )),
+                        None => message.push_str(&format!("\n`{}`: missing", replica.index_name)),
+                    }
+                }
+
+                message
+            }
+            Alert::ComparisonOutOfSync(out_of_sync) => format!(
+                ":left_right_arrow: *`{}` in `{}` is out of sync with `{}` in `{}`*\n\
+                 {} records, compared {} (delta {:+})",
+                out_of_sync.index_name,
+                out_of_sync.app_id,
+                out_of_sync.compared_index_name,
+                out_of_sync.compared_app_id,
+                out_of_sync.records,
+                out_of_sync.compared_records,
+                out_of_sync.delta
+            ),
+            Alert::FacetDrops(facet_drops) => {
+                let mut message = format!(
+                    ":chart_with_downwards_trend: *Values of `{}` in `{}` lost records*",
+                    facet_drops.attribute, facet_drops.index_name
+                );
+                for drop in &facet_drops.drops {
+                    match drop.current_records {
+                        0 => message.push_str(&format!(
+                            "\n`{}`: gone, had {} records",
+                            drop.value, drop.expected_records
+                        )),
+                        current_records => message.push_str(&format!(
+                            "\n`{}`: {} records, had {}",
+                            drop.value, current_records, drop.expected_records
+                        )),
+                    }
+                }
+
+                message
+            }
+            Alert::ContentChurn(churn) => format!(
+                ":arrows_counterclockwise: *Records of `{}` churned*\n\
+                 {} of {} sampled objectIDs ({:.1}%) disappeared, such as `{}`",
+                churn.index_name,
+                churn.disappeared,
+                churn.sampled,
+                churn.churn_percent,
+                churn.examples.join("`, `")
+            ),
+            Alert::ItemsDropped(dropped) => format!(
+                ":broom: *The {} of `{}` dropped*\nNow {}, expected {}",
+                dropped.items, dropped.index_name, dropped.current, dropped.expected
+            ),
+            Alert::SettingsChanged(changed) => {
+                let mut message = format!(
+                    ":gear: *Settings of `{}` changed*\n{}",
+                    changed.index_name,
+                    changed.diff.summary()
+                );
+                if let Ok(diff) = serde_json::to_string_pretty(&changed.diff) {
+                    message.push_str("\n```\n");
+                    message.push_str(truncate(&diff, MAX_LOG_BLOCK_LENGTH));
+                    message.push_str("\n```");
+                }
+
+                message
+            }
+            Alert::IndexDeleted(deleted) => {
+                format!(":wastebasket: *Index `{}` was deleted*", deleted.index_name)
+            }
+            Alert::IndexRecreated(recreated) => format!(
+                ":recycle: *Index `{}` was recreated*\nNow {} records, expected {}{}",
+                recreated.index_name,
+                recreated.current_records,
+                recreated.expected_records,
+                match recreated.rebaselined {
+                    true => ", the expected count is reset to the new one",
+                    false => "",
+                }
+            ),
+            Alert::Digest(digest) => {
+                let text = digest.text();
+                let (title, details) = text.split_once('\n').unwrap_or((&text, ""));
+                format!(":bar_chart: *{title}*\n```\n{details}\n```")
+            }
+            Alert::Destructive(destructive) => format!(
+                ":boom: *`{}` ran on `{}`*\n{} {} {}",
+                destructive.operation,
+                destructive.index_name,
+                destructive.log.timestamp.to_rfc3339(),
+                destructive.log.method.as_deref().unwrap_or("-"),
+                destructive.log.url.as_deref().unwrap_or("-")
+            ),
+        }
+    }
+}
+
+/// Index name in code formatting, along with the subset of its records when there is one
+fn index(index_name: &str, subset: Option<&str>) -> String {
+    match subset {
+        Some(subset) => format!("`{index_name}` subset `{subset}`"),
+        None => format!("`{index_name}`"),
+    }
+}
@@ -0,0 +1,172 @@
+use super::{truncate, Alert};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio::time::sleep;
+
+const API_URL: &str = "https://api.telegram.org";
+
+/// Length of the log block, kept well below the 4096 characters of a Telegram message
+const MAX_LOG_BLOCK_LENGTH: usize = 3000;
+
+/// Number of times a message is sent again after the Bot API answered that it is rate limited
+const RATE_LIMIT_RETRIES: u32 = 3;
+
+/// Longest wait before sending a rate limited message again, whatever the Bot API advises
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Telegram bot sending breaches, recoveries and destructive operations to a chat
+#[derive(Clone)]
+pub struct Telegram {
+    client: reqwest::Client,
+    /// `sendMessage` URL, which holds the bot token
+    url: String,
+    chat_id: String,
+    log_lines: usize,
+}
+
+impl Telegram {
+    pub fn new(client: reqwest::Client, token: &str, chat_id: String, log_lines: usize) -> Self {
+        Telegram {
+            client,
+            url: format!("{API_URL}/bot{token}/sendMessage"),
+            chat_id,
+            log_lines,
+        }
+    }
+
+    /// Sends the message of the alert, waiting as long as the Bot API advises when rate limited
+    ///
+    /// Errors leave out the URL, so the bot token doesn't end up in the output.
+    pub(super) async fn send(&self, alert: &Alert) -> Result<(), reqwest::Error> {
+        let Some(text) = self.message(alert) else {
+            return Ok(());
+        };
+        let body = json!({ "chat_id": self.chat_id, "text": text, "parse_mode": "MarkdownV2" });
+
+        let mut attempt = 0;
+        loop {
+            let response = self
+                .client
+                .post(&self.url)
+                .json(&body)
+                .send()
+                .await
+                .map_err(reqwest::Error::without_url)?;
+
+            if response.status() == StatusCode::TOO_MANY_REQUESTS && attempt < RATE_LIMIT_RETRIES {
+                let retry_after = response
+                    .json::<Value>()
+                    .await
+                    .ok()
+                    .and_then(|body| body["parameters"]["retry_after"].as_u64())
+                    .unwrap_or(1);
+                sleep(Duration::from_secs(retry_after).min(MAX_RETRY_AFTER)).await;
+                attempt += 1;
+                continue;
+            }
+
+            response.error_for_status().map_err(reqwest::Error::without_url)?;
+            return Ok(());
+        }
+    }
+
+    /// MarkdownV2 message of the alert, `None` for the alerts the bot doesn't send
+    fn message(&self, alert: &Alert) -> Option<String> {
+        Some(match alert {
+            Alert::Breach(breach) => {
+                let index = index(&breach.index_name, breach.subset.as_deref());
+                let mut message = format!(
+                    "{}\nExpected {} records, now {} \\(delta {}\\)",
+                    match (breach.current_records, breach.reminder) {
+                        (0, _) => format!("🆘 *CRITICAL: {index} has no records left*"),
+                        (_, true) => format!("🔁 *Records count of {index} is still past the threshold*"),
+                        _ => format!("🚨 *Records count of {index} breached the threshold*"),
+                    },
+                    breach.expected_records,
+                    breach.current_records,
+                    escape(&format!("{:+}", breach.delta))
+                );
+                if breach.quiet_hours {
+                    message.push_str("\nStarted during the quiet hours");
+                }
+
+                let log_lines: Vec<_> = breach
+                    .logs
+                    .iter()
+                    .filter(|log| log.is_write())
+                    .take(self.log_lines)
+                    .map(|log| {
+                        format!(
+                            "{} {} {} {}",
+                            log.timestamp.to_rfc3339(),
+                            log.method.as_deref().unwrap_or("-"),
+                            log.url.as_deref().unwrap_or("-"),
+                            log.answer_code.as_deref().unwrap_or("-")
+                        )
+                    })
+                    .collect();
+
+                if !log_lines.is_empty() {
+                    message.push_str("\n```\n");
+                    message.push_str(&escape_code(truncate(&log_lines.join("\n"), MAX_LOG_BLOCK_LENGTH)));
+                    message.push_str("\n```");
+                }
+
+                message
+            }
+            Alert::Recovery(recovery) => format!(
+                "✅ *Records count of {} recovered after {}*\n\
+                 Expected {} records, now {} \\(delta {}\\), lowest {} during the breach",
+                index(&recovery.index_name, recovery.subset.as_deref()),
+                escape(&humantime::format_duration(Duration::from_secs(recovery.breach_duration_secs)).to_string()),
+                recovery.expected_records,
+                recovery.current_records,
+                escape(&format!("{:+}", recovery.delta)),
+                recovery.min_records
+            ),
+            Alert::Destructive(destructive) => format!(
+                "💥 *`{}` ran on `{}`*\n{}",
+                escape_code(&destructive.operation.to_string()),
+                escape_code(&destructive.index_name),
+                escape(&format!(
+                    "{} {} {}",
+                    destructive.log.timestamp.to_rfc3339(),
+                    destructive.log.method.as_deref().unwrap_or("-"),
+                    destructive.log.url.as_deref().unwrap_or("-")
+                ))
+            ),
+            Alert::Digest(digest) => {
+                let text = digest.text();
+                let (title, details) = text.split_once('\n').unwrap_or((&text, ""));
+                format!("📊 *{}*\n```\n{}\n```", escape(title), escape_code(details))
+            }
+            _ => return None,
+        })
+    }
+}
+
+/// Index name in code formatting, along with the subset of its records when there is one
+fn index(index_name: &str, subset: Option<&str>) -> String {
+    match subset {
+        Some(subset) => format!("`{}` subset `{}`", escape_code(index_name), escape_code(subset)),
+        None => format!("`{}`", escape_code(index_name)),
+    }
+}
+
+/// Text with the characters MarkdownV2 reserves escaped
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        if "_*[]()~`>#+-=|{}.!\\".contains(character) {
+            escaped.push('\\');
+        }
+        escaped.push(character);
+    }
+    escaped
+}
+
+/// Text in code formatting, where only backticks and backslashes are escaped
+fn escape_code(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('`', "\\`")
+}
@@ -0,0 +1,707 @@
+mod dashboard;
+mod debug_state;
+mod elasticsearch;
+mod history;
+#[cfg(feature = "kafka")]
+mod kafka;
+mod log_file;
+mod loki;
+mod mqtt;
+mod sinks;
+mod trend;
+mod usage;
+
+use crate::notify::{Digest, Recovery};
+use crate::{now, AlgoliaLogEntry, FacetCounts, LogType, MonitorError, RecordsCount};
+use chrono::SecondsFormat;
+use serde_json::{json, Value};
+use std::fmt::Display;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::info;
+
+pub use self::dashboard::{Dashboard, DashboardUi, Tui};
+pub use self::debug_state::DebugState;
+pub use self::elasticsearch::{Elasticsearch, ElasticsearchAuth, ElasticsearchIndexer, ElasticsearchTarget};
+pub use self::history::{Day, History, HistoryReader, HistoryWriter, Sample, TimelineEntry};
+#[cfg(feature = "kafka")]
+pub use self::kafka::{Kafka, KafkaTarget};
+pub use self::log_file::LogFile;
+pub use self::loki::{Loki, LokiPusher, LokiTarget};
+pub use self::mqtt::{Mqtt, MqttConnection, MqttTarget};
+pub use self::sinks::{OutputTarget, SinkDelivery, Sinks};
+pub use self::trend::Trend;
+pub use self::usage::ApiUsage;
+
+/// Longest URL shown in pretty output, longer ones lose their middle part
+const MAX_URL_LENGTH: usize = 48;
+
+/// Rendering of log entries on stdout
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// One JSON object per line, as received from Algolia
+    Json,
+    /// One aligned line per entry with the method, URL, status and processing time
+    Pretty,
+}
+
+/// When to color the output, given with `--color`
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum ColorMode {
+    /// Color terminals, unless `NO_COLOR` is set
+    Auto,
+    Always,
+    Never,
+}
+
+/// Colors of a single output stream, plain text when coloring is off
+#[derive(Debug, Clone, Copy)]
+pub struct Palette {
+    enabled: bool,
+}
+
+impl Palette {
+    fn new(color: ColorMode, is_terminal: bool) -> Self {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        Palette {
+            enabled: match color {
+                ColorMode::Always => true,
+                ColorMode::Never => false,
+                ColorMode::Auto => is_terminal && !no_color,
+            },
+        }
+    }
+
+    fn paint(&self, code: &str, text: impl Display) -> String {
+        match self.enabled {
+            true => format!("\x1b[{code}m{text}\x1b[0m"),
+            false => text.to_string(),
+        }
+    }
+
+    pub fn breach(&self, text: impl Display) -> String {
+        self.paint("31", text)
+    }
+
+    pub fn recovery(&self, text: impl Display) -> String {
+        self.paint("32", text)
+    }
+
+    pub fn write(&self, text: impl Display) -> String {
+        self.paint("33", text)
+    }
+
+    pub fn error(&self, text: impl Display) -> String {
+        self.paint("1;31", text)
+    }
+}
+
+/// Destinations of the output besides the terminal, along with how it is rendered
+pub struct OutputOptions {
+    /// Files and webhooks of the events, stdout among them giving its format
+    pub outputs: Vec<OutputTarget>,
+    pub color: ColorMode,
+    /// Counts the sparklines after the counts are drawn from
+    pub sparkline_window: usize,
+    pub ascii: bool,
+    /// Algolia operations a day the delays between the polls are stretched to stay under
+    pub max_daily_operations: Option<u64>,
+    /// File the log entries are appended to, rotated past `log_file_max_size` keeping `log_file_keep` of them
+    pub log_file: Option<PathBuf>,
+    pub log_file_max_size: u64,
+    pub log_file_keep: usize,
+    /// Failed deliveries in a row after which a file or webhook of `outputs` is given up on
+    pub output_max_errors: Option<u32>,
+    /// Whether the `--tui` dashboard takes the place of stdout
+    pub dashboard: bool,
+    pub history_db: Option<PathBuf>,
+    pub loki: Option<LokiTarget>,
+    pub elasticsearch: Option<ElasticsearchTarget>,
+    pub mqtt: Option<MqttTarget>,
+    #[cfg(feature = "kafka")]
+    pub kafka: Option<KafkaTarget>,
+}
+
+/// Tasks and threads delivering the output to its destinations, which get everything queued once finished
+pub struct OutputTasks {
+    /// Dashboard to start on the terminal, with `--tui`
+    pub dashboard: Option<DashboardUi>,
+    history: Option<HistoryWriter>,
+    sinks: SinkDelivery,
+    loki: Option<LokiPusher>,
+    elasticsearch: Option<ElasticsearchIndexer>,
+    #[cfg(feature = "kafka")]
+    kafka: Option<Kafka>,
+    mqtt: Option<MqttConnection>,
+}
+
+impl OutputTasks {
+    /// Delivers the rest of the output, once the [`Output`] and its clones are dropped so nothing comes after
+    pub async fn finish(self) {
+        if let Some(writer) = self.history {
+            writer.finish();
+        }
+        self.sinks.finish().await;
+        if let Some(pusher) = self.loki {
+            pusher.finish().await;
+        }
+        if let Some(indexer) = self.elasticsearch {
+            indexer.finish().await;
+        }
+        #[cfg(feature = "kafka")]
+        if let Some(kafka) = self.kafka {
+            kafka.flush().await;
+        }
+        if let Some(connection) = self.mqtt {
+            connection.finish().await;
+        }
+    }
+}
+
+/// Formatting of everything the monitor prints, log entries on stdout and messages on stderr
+#[derive(Clone)]
+pub struct Output {
+    /// Format of stdout, `None` when the log entries only go to the other outputs
+    format: Option<OutputFormat>,
+    pub stdout: Palette,
+    pub stderr: Palette,
+    /// Index name in front of the lines when several indices are monitored
+    pub prefix: String,
+    log_file: Option<Arc<LogFile>>,
+    /// Database recording the polls and events, which does nothing without --history-db
+    pub history: History,
+    /// Grafana Loki receiving the fetched log entries, which does nothing without --loki-url
+    pub loki: Loki,
+    /// Elasticsearch or OpenSearch indexing the fetched log entries, which does nothing without
+    /// --elasticsearch-url
+    pub elasticsearch: Elasticsearch,
+    /// Kafka topic receiving the samples, events and log entries, which does nothing without --kafka-brokers
+    #[cfg(feature = "kafka")]
+    pub kafka: Kafka,
+    /// `--tui` dashboard, which takes the place of stdout while it is running
+    pub dashboard: Dashboard,
+    /// MQTT broker receiving the records counts, breaches and recoveries, which does nothing without --mqtt-url
+    pub mqtt: Mqtt,
+    /// Files and webhooks of --output receiving the events of the monitors
+    pub sinks: Sinks,
+    /// Latest counts of the indices, shown as a sparkline after the counts in text output
+    pub trend: Trend,
+    /// State of the indices printed on SIGUSR1
+    pub state: DebugState,
+    /// Algolia API calls of the monitors, which the delays between the polls are stretched by
+    pub usage: ApiUsage,
+}
+
+impl Output {
+    pub fn new(format: Option<OutputFormat>, color: ColorMode) -> Self {
+        Output {
+            format,
+            stdout: Palette::new(color, std::io::stdout().is_terminal()),
+            stderr: Palette::new(color, std::io::stderr().is_terminal()),
+            prefix: String::new(),
+            log_file: None,
+            history: History::default(),
+            loki: Loki::default(),
+            elasticsearch: Elasticsearch::default(),
+            #[cfg(feature = "kafka")]
+            kafka: Kafka::default(),
+            mqtt: Mqtt::default(),
+            dashboard: Dashboard::default(),
+            sinks: Sinks::default(),
+            trend: Trend::default(),
+            state: DebugState::default(),
+            usage: ApiUsage::default(),
+        }
+    }
+
+    /// Output to the terminal and to every destination of the options, along with the tasks delivering to them
+    pub fn open(options: OutputOptions, client: reqwest::Client) -> Result<(Output, OutputTasks), MonitorError> {
+        let stdout = options.outputs.iter().find_map(|output| match output {
+            OutputTarget::Stdout(format) => Some(*format),
+            _ => None,
+        });
+        let output = Output::new(stdout, options.color)
+            .with_trend(Trend::new(options.sparkline_window, options.ascii))
+            .with_usage(ApiUsage::new(options.max_daily_operations));
+        let state = DebugState::new(output.trend.clone(), output.usage.clone());
+        let mut output = output.with_state(state);
+
+        if let Some(path) = &options.log_file {
+            let log_file = LogFile::open(path.clone(), options.log_file_max_size, options.log_file_keep)
+                .map_err(|source| MonitorError::Io { path: path.clone(), source })?;
+            output = output.with_log_file(log_file);
+        }
+        let dashboard = options.dashboard.then(|| {
+            let (dashboard, ui) = Dashboard::new();
+            output.dashboard = dashboard;
+            ui
+        });
+        let history = match &options.history_db {
+            Some(path) => {
+                let (history, writer) = History::open(path).map_err(|error| {
+                    let path = path.display();
+                    MonitorError::InvalidArgument(format!("Failed to open history database {path}: {error}"))
+                })?;
+                output.history = history;
+                Some(writer)
+            }
+            None => None,
+        };
+        let (sinks, delivery) = Sinks::open(
+            &options.outputs,
+            client.clone(),
+            options.log_file_max_size,
+            options.log_file_keep,
+            options.output_max_errors,
+        )?;
+        output.sinks = sinks;
+        let loki = options.loki.map(|target| {
+            let (loki, pusher) = Loki::start(client.clone(), target);
+            output.loki = loki;
+            pusher
+        });
+        let elasticsearch = options.elasticsearch.map(|target| {
+            let (elasticsearch, indexer) = Elasticsearch::start(client, target);
+            output.elasticsearch = elasticsearch;
+            indexer
+        });
+        let mqtt = match options.mqtt {
+            Some(target) => {
+                let (mqtt, connection) = Mqtt::connect(target)
+                    .map_err(|error| MonitorError::InvalidArgument(format!("Invalid --mqtt-url: {error}")))?;
+                output.mqtt = mqtt;
+                Some(connection)
+            }
+            None => None,
+        };
+        #[cfg(feature = "kafka")]
+        let kafka = match &options.kafka {
+            Some(target) => {
+                let kafka = Kafka::connect(&target.brokers, &target.topic, &target.config).map_err(|error| {
+                    MonitorError::InvalidArgument(format!("Failed to create the Kafka producer: {error}"))
+                })?;
+                output.kafka = kafka.clone();
+                Some(kafka)
+            }
+            None => None,
+        };
+
+        let tasks = OutputTasks {
+            dashboard,
+            history,
+            sinks: delivery,
+            loki,
+            elasticsearch,
+            #[cfg(feature = "kafka")]
+            kafka,
+            mqtt,
+        };
+        Ok((output, tasks))
+    }
+
+    /// Also appends the printed log entries to the file, without colors
+    pub fn with_log_file(mut self, log_file: LogFile) -> Self {
+        self.log_file = Some(Arc::new(log_file));
+        self
+    }
+
+    pub fn with_history(mut self, history: History) -> Self {
+        self.history = history;
+        self
+    }
+
+    pub fn with_loki(mut self, loki: Loki) -> Self {
+        self.loki = loki;
+        self
+    }
+
+    pub fn with_elasticsearch(mut self, elasticsearch: Elasticsearch) -> Self {
+        self.elasticsearch = elasticsearch;
+        self
+    }
+
+    #[cfg(feature = "kafka")]
+    pub fn with_kafka(mut self, kafka: Kafka) -> Self {
+        self.kafka = kafka;
+        self
+    }
+
+    pub fn with_dashboard(mut self, dashboard: Dashboard) -> Self {
+        self.dashboard = dashboard;
+        self
+    }
+
+    pub fn with_mqtt(mut self, mqtt: Mqtt) -> Self {
+        self.mqtt = mqtt;
+        self
+    }
+
+    pub fn with_sinks(mut self, sinks: Sinks) -> Self {
+        self.sinks = sinks;
+        self
+    }
+
+    pub fn with_trend(mut self, trend: Trend) -> Self {
+        self.trend = trend;
+        self
+    }
+
+    pub fn with_usage(mut self, usage: ApiUsage) -> Self {
+        self.usage = usage;
+        self
+    }
+
+    pub fn with_state(mut self, state: DebugState) -> Self {
+        self.state = state;
+        self
+    }
+
+    pub fn with_prefix(&self, prefix: String) -> Self {
+        Output {
+            prefix,
+            ..self.clone()
+        }
+    }
+
+    /// Records the count of a poll in the history database and the trend, and publishes it to Kafka and MQTT
+    pub fn sample(&self, index_name: &str, records: RecordsCount, processing_time_ms: Option<u64>) {
+        self.trend.record(index_name, records.current_records);
+        self.mqtt.records(index_name, records.current_records);
+        #[cfg(feature = "kafka")]
+        self.kafka.sample(index_name, records, processing_time_ms);
+        self.history.sample(index_name, records, processing_time_ms);
+    }
+
+    /// Records an event of the index in the history database and publishes it to Kafka and MQTT, such as a
+    /// `breach`
+    pub fn event(&self, index_name: &str, kind: &'static str, message: impl Display) {
+        let message = message.to_string();
+        self.mqtt.event(index_name, kind, &message);
+        #[cfg(feature = "kafka")]
+        self.kafka.event(index_name, kind, &message);
+        self.history.event(index_name, kind, message);
+    }
+
+    /// Ships a batch of log entries of the index to Loki, Elasticsearch, the dashboard and Kafka, `name` being
+    /// the one of the index along with its subset
+    pub fn ship_logs(&self, name: &str, index_name: &str, log_type: LogType, logs: &[AlgoliaLogEntry]) {
+        self.loki.push(index_name, log_type, logs);
+        self.elasticsearch.push(index_name, logs);
+        self.dashboard.logs(name, logs);
+        #[cfg(feature = "kafka")]
+        self.kafka.logs(index_name, logs);
+    }
+
+    /// `, trend ▁▂▃▅▇ 1180..1500` to append to the count of the index in text output, empty otherwise
+    pub fn trend(&self, index_name: &str) -> String {
+        match (self.format, self.trend.sparkline(index_name)) {
+            (Some(OutputFormat::Pretty), Some(sparkline)) => format!(", trend {sparkline}"),
+            _ => String::new(),
+        }
+    }
+
+    /// Prints a log entry on stdout, JSON is never colored so it stays parseable
+    pub fn print_log(&self, log: &AlgoliaLogEntry) {
+        let format = self.format.unwrap_or(OutputFormat::Json);
+        let line = match format {
+            OutputFormat::Json => log.to_json(),
+            OutputFormat::Pretty => pretty(log),
+        };
+
+        let colored = match format {
+            OutputFormat::Json => line.clone(),
+            OutputFormat::Pretty if failed(log) => self.stdout.error(&line),
+            OutputFormat::Pretty if log.is_write() => self.stdout.write(&line),
+            OutputFormat::Pretty => line.clone(),
+        };
+        if self.format.is_some() {
+            self.println(&colored);
+        }
+
+        if let Some(log_file) = &self.log_file {
+            log_file.write_line(&format!("{}{}", self.prefix, line));
+        }
+    }
+
+    /// Prints a recovery as a JSON line on stdout with `type` set to `recovery`, only in JSON output
+    pub fn print_recovery(&self, recovery: &Recovery) {
+        if self.format != Some(OutputFormat::Json) {
+            return;
+        }
+
+        let line = json!({
+            "type": "recovery",
+            "index": recovery.index_name,
+            "subset": recovery.subset,
+            "expected_records": recovery.expected_records,
+            "current_records": recovery.current_records,
+            "delta": recovery.delta,
+            "breach_duration_secs": recovery.breach_duration_secs,
+            "min_records": recovery.min_records,
+            "timestamp": recovery.timestamp,
+        })
+        .to_string();
+        self.println(&line);
+
+        if let Some(log_file) = &self.log_file {
+            log_file.write_line(&format!("{}{}", self.prefix, line));
+        }
+    }
+
+    /// Prints a digest as a JSON line on stdout with `type` set to `digest` in JSON output, as text on stderr
+    /// otherwise
+    pub fn print_digest(&self, digest: &Digest) {
+        if self.format != Some(OutputFormat::Json) {
+            info!("{}[{}] {}", self.prefix, now(), digest.text());
+            return;
+        }
+
+        let mut line = json!({ "type": "digest" });
+        if let (Some(line), Value::Object(digest)) = (line.as_object_mut(), json!(digest)) {
+            line.extend(digest);
+        }
+        let line = line.to_string();
+        self.println(&line);
+
+        if let Some(log_file) = &self.log_file {
+            log_file.write_line(&format!("{}{}", self.prefix, line));
+        }
+    }
+
+    /// Prints the counts of the values of a facet attribute as a JSON line on stdout, only in JSON output
+    /// where they can be told apart from the log entries by their `facet` field
+    pub fn print_facets(&self, index_name: &str, facets: &FacetCounts) {
+        if self.format != Some(OutputFormat::Json) {
+            return;
+        }
+
+        let line = json!({
+            "index": index_name,
+            "facet": facets.attribute,
+            "counts": facets.counts,
+            "drops": facets.drops,
+        })
+        .to_string();
+        self.println(&line);
+
+        if let Some(log_file) = &self.log_file {
+            log_file.write_line(&format!("{}{}", self.prefix, line));
+        }
+    }
+
+    /// Prints a line on stdout with the prefix, unless the dashboard has the terminal
+    fn println(&self, line: &str) {
+        if !self.dashboard.is_active() {
+            println!("{}{}", self.prefix, line);
+        }
+    }
+}
+
+fn failed(log: &AlgoliaLogEntry) -> bool {
+    log.status_code().is_some_and(|code| code >= 400)
+}
+
+/// `2024-05-02T10:14:03Z  POST    /1/indexes/products/batch  200  143ms  (+512 objects)`,
+/// marked with `!` in front when Algolia answered with an error and with the log type after the
+/// timestamp when it is known
+pub fn pretty(log: &AlgoliaLogEntry) -> String {
+    let mut line = format!(
+        "{} {}{}  {:<6}  {:<width$}  {:>3}  {:>6}",
+        if failed(log) { '!' } else { ' ' },
+        log.timestamp.to_rfc3339_opts(SecondsFormat::Secs, true),
+        log.log_type.map_or_else(String::new, |log_type| format!("  {log_type:<5}")),
+        log.method.as_deref().unwrap_or("-"),
+        shorten_url(log.url.as_deref().unwrap_or("-")),
+        log.answer_code.as_deref().unwrap_or("-"),
+        log.processing_time_ms
+            .as_deref()
+            .map_or_else(|| "-".to_string(), |time| format!("{time}ms")),
+        width = MAX_URL_LENGTH
+    );
+
+    if let Some(objects) = object_changes(log) {
+        line.push_str("  ");
+        line.push_str(&objects);
+    }
+
+    line.trim_end().to_string()
+}
+
+/// Keeps the start and the end of a long URL, which hold the index name and the operation
+fn shorten_url(url: &str) -> String {
+    let length = url.chars().count();
+    if length <= MAX_URL_LENGTH {
+        return url.to_string();
+    }
+
+    let head = MAX_URL_LENGTH / 2;
+    let tail = MAX_URL_LENGTH - head - 1;
+    let start: String = url.chars().take(head).collect();
+    let end: String = url.chars().skip(length - tail).collect();
+    format!("{start}…{end}")
+}
+
+/// Estimate of the objects a write added and deleted, such as `(+512 objects)` or `(+3/-2 objects)`
+fn object_changes(log: &AlgoliaLogEntry) -> Option<String> {
+    if !log.is_write() {
+        return None;
+    }
+
+    let body = log.query_body.as_deref()?;
+    let (added, deleted) = match serde_json::from_str::<Value>(body) {
+        Ok(body) => {
+            let requests = body.get("requests")?.as_array()?;
+            let deleted = requests
+                .iter()
+                .filter(|request| request.get("action").and_then(Value::as_str) == Some("deleteObject"))
+                .count();
+            (requests.len() - deleted, deleted)
+        }
+        // Algolia cuts long bodies in its logs, so the actions are counted in the text instead
+        Err(_) => {
+            let actions = body.matches("\"action\"").count();
+            let deleted = body.matches("\"deleteObject\"").count().min(actions);
+            (actions - deleted, deleted)
+        }
+    };
+
+    match (added, deleted) {
+        (0, 0) => None,
+        (added, 0) => Some(format!("(+{added} objects)")),
+        (0, deleted) => Some(format!("(-{deleted} objects)")),
+        (added, deleted) => Some(format!("(+{added}/-{deleted} objects)")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LogType;
+
+    fn log(entry: Value) -> AlgoliaLogEntry {
+        serde_json::from_value(entry).unwrap()
+    }
+
+    #[test]
+    fn query_shows_its_method_url_status_and_time() {
+        let query = log(json!({
+            "timestamp": "2024-05-02T10:14:03.512Z",
+            "method": "GET",
+            "url": "/1/indexes/products/query",
+            "answer_code": "200",
+            "processing_time_ms": "3",
+            "query_body": "{\"params\":\"query=shoes\"}",
+        }));
+
+        assert_eq!(
+            pretty(&query),
+            "  2024-05-02T10:14:03Z  GET     /1/indexes/products/query                         200     3ms"
+        );
+    }
+
+    #[test]
+    fn batch_shows_the_objects_it_adds_and_deletes() {
+        let body = json!({ "requests": [
+            { "action": "addObject", "body": { "name": "shoe" } },
+            { "action": "updateObject", "body": { "objectID": "2" } },
+            { "action": "deleteObject", "body": { "objectID": "3" } },
+        ]});
+        let mut batch = log(json!({
+            "timestamp": "2024-05-02 10:14:03",
+            "method": "POST",
+            "url": "/1/indexes/products/batch",
+            "answer_code": 200,
+            "processing_time_ms": 143,
+            "query_body": body.to_string(),
+        }));
+        batch.log_type = Some(LogType::Build);
+
+        assert_eq!(
+            pretty(&batch),
+            "  2024-05-02T10:14:03Z  build  POST    /1/indexes/products/batch                         \
+             200   143ms  (+2/-1 objects)"
+        );
+    }
+
+    #[test]
+    fn batch_cut_short_in_the_logs_counts_the_actions_of_its_text() {
+        let batch = log(json!({
+            "timestamp": "2024-05-02T10:14:03Z",
+            "method": "POST",
+            "url": "/1/indexes/products/batch",
+            "answer_code": "200",
+            "processing_time_ms": "90",
+            "query_body": r#"{"requests":[{"action":"addObject","body":{}},{"action":"addObject","body":{"na"#,
+        }));
+
+        assert_eq!(
+            pretty(&batch),
+            "  2024-05-02T10:14:03Z  POST    /1/indexes/products/batch                         200    90ms  \
+             (+2 objects)"
+        );
+    }
+
+    #[test]
+    fn failed_request_is_marked() {
+        let mut delete = log(json!({
+            "timestamp": "2024-05-02T10:14:03Z",
+            "method": "DELETE",
+            "url": "/1/indexes/products",
+            "answer_code": "404",
+            "processing_time_ms": "1",
+        }));
+        delete.log_type = Some(LogType::Error);
+
+        assert_eq!(
+            pretty(&delete),
+            "! 2024-05-02T10:14:03Z  error  DELETE  /1/indexes/products                               \
+             404     1ms"
+        );
+    }
+
+    #[test]
+    fn short_log_type_is_aligned_with_the_others() {
+        let mut query = log(json!({
+            "timestamp": "2024-05-02T10:14:03Z",
+            "method": "GET",
+            "url": "/1/indexes/products/query",
+            "answer_code": "200",
+            "processing_time_ms": "3",
+        }));
+        query.log_type = Some(LogType::All);
+
+        assert_eq!(
+            pretty(&query),
+            "  2024-05-02T10:14:03Z  all    GET     /1/indexes/products/query                         \
+             200     3ms"
+        );
+    }
+
+    #[test]
+    fn long_url_keeps_its_start_and_end() {
+        let settings = log(json!({
+            "timestamp": "2024-05-02T10:14:03Z",
+            "method": "PUT",
+            "url": "/1/indexes/products_with_a_very_long_name/settings?forwardToReplicas=true",
+            "answer_code": "200",
+            "processing_time_ms": "12",
+        }));
+
+        assert_eq!(
+            pretty(&settings),
+            "  2024-05-02T10:14:03Z  PUT     /1/indexes/products_with…?forwardToReplicas=true  200    12ms"
+        );
+        assert_eq!(shorten_url("/1/indexes/products/query"), "/1/indexes/products/query");
+    }
+
+    #[test]
+    fn entry_with_only_a_timestamp_shows_dashes() {
+        let entry = log(json!({ "timestamp": "2024-05-02T10:14:03Z" }));
+
+        assert_eq!(
+            pretty(&entry),
+            "  2024-05-02T10:14:03Z  -       -                                                   -       -"
+        );
+    }
+}
@@ -0,0 +1,644 @@
+use crate::output;
+use crate::metrics::MetricsBackend;
+use crate::{AlgoliaLogEntry, Percentiles};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::symbols::Marker;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Axis, Block, Chart, Dataset, GraphType, List, ListItem, Paragraph, Tabs};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// Records counts kept for the chart of each index
+const MAX_SAMPLES: usize = 300;
+
+/// Log entries kept for the log pane
+const MAX_LOG_LINES: usize = 1_000;
+
+/// Lines printed on stderr kept while the dashboard runs, printed again once it closes
+const MAX_MESSAGES: usize = 100;
+
+/// Time between redraws when nothing is pressed
+const REDRAW_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Set by the panic hook, so the dashboard stops drawing over the restored terminal
+static PANICKED: AtomicBool = AtomicBool::new(false);
+
+/// Handle feeding the polls and log entries of the monitored indices to the `--tui` dashboard, doing nothing
+/// without one
+///
+/// The polls keep running in their own tasks, the dashboard only renders what they send it from its own
+/// thread. It is also a metrics backend, which is where the records counts, latencies and errors come from.
+#[derive(Clone, Default)]
+pub struct Dashboard {
+    shared: Option<Arc<Shared>>,
+}
+
+/// Dashboard waiting for [`DashboardUi::start`] to take over the terminal
+pub struct DashboardUi {
+    shared: Arc<Shared>,
+    updates: Receiver<Update>,
+}
+
+/// Thread drawing the dashboard, which gives the terminal back when it finishes
+pub struct Tui {
+    thread: JoinHandle<VecDeque<String>>,
+    stop: Arc<AtomicBool>,
+    #[cfg(unix)]
+    stderr: Option<stderr::Redirect>,
+}
+
+struct Shared {
+    updates: Sender<Update>,
+    /// Wakes the monitors up for an immediate poll, pressing `r`
+    poll_now: Notify,
+}
+
+enum Update {
+    Records { index_name: String, records: u64, expected_records: u64 },
+    Breach { index_name: String },
+    Poll { index_name: String, duration: Duration, error: Option<String> },
+    ApiResponse { duration: Duration },
+    Log { index_name: String, line: String, failed: bool, write: bool },
+    /// Line the monitor printed on stderr while the dashboard had the terminal
+    Message(String),
+}
+
+impl Dashboard {
+    pub fn new() -> (Dashboard, DashboardUi) {
+        let (updates, receiver) = mpsc::channel();
+        let shared = Arc::new(Shared {
+            updates,
+            poll_now: Notify::new(),
+        });
+
+        (
+            Dashboard {
+                shared: Some(shared.clone()),
+            },
+            DashboardUi {
+                shared,
+                updates: receiver,
+            },
+        )
+    }
+
+    /// Whether the dashboard has the terminal, so nothing else may print on stdout
+    pub fn is_active(&self) -> bool {
+        self.shared.is_some()
+    }
+
+    /// Adds the log entries of a poll to the log pane
+    pub fn logs(&self, index_name: &str, logs: &[AlgoliaLogEntry]) {
+        for log in logs {
+            self.send(Update::Log {
+                index_name: index_name.to_string(),
+                line: output::pretty(log),
+                failed: log.status_code().is_some_and(|code| code >= 400),
+                write: log.is_write(),
+            });
+        }
+    }
+
+    /// Resolves when an immediate poll is asked for, never without a dashboard
+    pub async fn poll_requested(&self) {
+        match &self.shared {
+            Some(shared) => shared.poll_now.notified().await,
+            None => std::future::pending().await,
+        }
+    }
+
+    fn send(&self, update: Update) {
+        if let Some(shared) = &self.shared {
+            // The dashboard stops reading once it is closed, the polls go on until they shut down
+            let _ = shared.updates.send(update);
+        }
+    }
+}
+
+impl MetricsBackend for Dashboard {
+    fn records(&self, index_name: &str, records: u64, expected_records: u64) {
+        self.send(Update::Records {
+            index_name: index_name.to_string(),
+            records,
+            expected_records,
+        });
+    }
+
+    fn data_size(&self, _index_name: &str, _data_size: u64) {}
+
+    fn pending_tasks(&self, _index_name: &str, _pending_tasks: u64) {}
+
+    fn latency(&self, _index_name: &str, _latency: &Percentiles) {}
+
+    fn poll_error(&self, _index_name: &str) {}
+
+    fn threshold_breach(&self, index_name: &str) {
+        self.send(Update::Breach {
+            index_name: index_name.to_string(),
+        });
+    }
+
+    fn api_response(&self, _endpoint: &str, duration: Duration) {
+        self.send(Update::ApiResponse { duration });
+    }
+
+    fn poll(&self, index_name: &str, duration: Duration, error: Option<&str>) {
+        self.send(Update::Poll {
+            index_name: index_name.to_string(),
+            duration,
+            error: error.map(str::to_string),
+        });
+    }
+}
+
+impl DashboardUi {
+    /// Takes over the terminal and starts drawing, calling `on_quit` when `q` is pressed
+    ///
+    /// Messages printed on stderr go to the status line meanwhile, and a panic gives the terminal back
+    /// before it is reported.
+    pub fn start(self, on_quit: impl FnOnce() + Send + 'static) -> std::io::Result<Tui> {
+        let terminal = ratatui::try_init()?;
+        let restore_terminal = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            PANICKED.store(true, Ordering::Relaxed);
+            #[cfg(unix)]
+            stderr::restore();
+            restore_terminal(info);
+        }));
+
+        #[cfg(unix)]
+        let stderr = stderr::Redirect::start(self.shared.updates.clone());
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                let mut app = App::new(self.shared);
+                let quit = app.run(terminal, &self.updates, &stop);
+                ratatui::restore();
+                if quit {
+                    on_quit();
+                }
+                app.messages
+            })
+        };
+
+        Ok(Tui {
+            thread,
+            stop,
+            #[cfg(unix)]
+            stderr,
+        })
+    }
+}
+
+impl Tui {
+    /// Closes the dashboard and gives the terminal and stderr back, printing the latest messages it showed
+    pub fn finish(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        let messages = self.thread.join().unwrap_or_default();
+        #[cfg(unix)]
+        drop(self.stderr);
+        for message in messages {
+            eprintln!("{message}");
+        }
+    }
+}
+
+struct IndexState {
+    name: String,
+    samples: VecDeque<u64>,
+    records: Option<u64>,
+    expected_records: u64,
+    breached: bool,
+    polls: u64,
+    errors: u64,
+    last_poll: Option<Duration>,
+    last_error: Option<String>,
+}
+
+struct LogLine {
+    index_name: String,
+    line: String,
+    failed: bool,
+    write: bool,
+}
+
+struct App {
+    shared: Arc<Shared>,
+    indices: Vec<IndexState>,
+    selected: usize,
+    logs: VecDeque<LogLine>,
+    /// Log entries received while the log pane is paused, shown once it resumes
+    held: Vec<LogLine>,
+    paused: bool,
+    filter: String,
+    editing_filter: bool,
+    api_response: Option<Duration>,
+    messages: VecDeque<String>,
+}
+
+impl App {
+    fn new(shared: Arc<Shared>) -> Self {
+        App {
+            shared,
+            indices: vec![],
+            selected: 0,
+            logs: VecDeque::new(),
+            held: vec![],
+            paused: false,
+            filter: String::new(),
+            editing_filter: false,
+            api_response: None,
+            messages: VecDeque::new(),
+        }
+    }
+
+    /// Draws until `q` is pressed, returning true, or until `stop` is set
+    fn run(&mut self, mut terminal: DefaultTerminal, updates: &Receiver<Update>, stop: &AtomicBool) -> bool {
+        while !stop.load(Ordering::Relaxed) && !PANICKED.load(Ordering::Relaxed) {
+            while let Ok(update) = updates.try_recv() {
+                self.update(update);
+            }
+            if terminal.draw(|frame| self.draw(frame)).is_err() {
+                return false;
+            }
+
+            match event::poll(REDRAW_INTERVAL) {
+                Ok(true) => match event::read() {
+                    Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => {
+                        if self.key(key) {
+                            return true;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(_) => return false,
+                },
+                Ok(false) => {}
+                Err(_) => return false,
+            }
+        }
+
+        false
+    }
+
+    fn index(&mut self, index_name: String) -> &mut IndexState {
+        let position = match self.indices.iter().position(|index| index.name == index_name) {
+            Some(position) => position,
+            None => {
+                self.indices.push(IndexState {
+                    name: index_name,
+                    samples: VecDeque::new(),
+                    records: None,
+                    expected_records: 0,
+                    breached: false,
+                    polls: 0,
+                    errors: 0,
+                    last_poll: None,
+                    last_error: None,
+                });
+                self.indices.len() - 1
+            }
+        };
+        &mut self.indices[position]
+    }
+
+    fn update(&mut self, update: Update) {
+        match update {
+            Update::Records {
+                index_name,
+                records,
+                expected_records,
+            } => {
+                let index = self.index(index_name);
+                if index.samples.len() == MAX_SAMPLES {
+                    index.samples.pop_front();
+                }
+                index.samples.push_back(records);
+                index.records = Some(records);
+                index.expected_records = expected_records;
+                // A breach is reported right after the records count of the same poll
+                index.breached = false;
+            }
+            Update::Breach { index_name } => self.index(index_name).breached = true,
+            Update::Poll {
+                index_name,
+                duration,
+                error,
+            } => {
+                let index = self.index(index_name);
+                index.polls += 1;
+                index.last_poll = Some(duration);
+                if error.is_some() {
+                    index.errors += 1;
+                }
+                index.last_error = error;
+            }
+            Update::ApiResponse { duration } => self.api_response = Some(duration),
+            Update::Log {
+                index_name,
+                line,
+                failed,
+                write,
+            } => {
+                let line = LogLine {
+                    index_name,
+                    line,
+                    failed,
+                    write,
+                };
+                match self.paused {
+                    true => self.held.push(line),
+                    false => self.push_log(line),
+                }
+            }
+            Update::Message(message) => {
+                if self.messages.len() == MAX_MESSAGES {
+                    self.messages.pop_front();
+                }
+                self.messages.push_back(message);
+            }
+        }
+    }
+
+    fn push_log(&mut self, line: LogLine) {
+        if self.logs.len() == MAX_LOG_LINES {
+            self.logs.pop_front();
+        }
+        self.logs.push_back(line);
+    }
+
+    /// Handles a key, returning true to quit
+    fn key(&mut self, key: KeyEvent) -> bool {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return true;
+        }
+
+        if self.editing_filter {
+            match key.code {
+                KeyCode::Enter => self.editing_filter = false,
+                KeyCode::Esc => {
+                    self.editing_filter = false;
+                    self.filter.clear();
+                }
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Char(character) => self.filter.push(character),
+                _ => {}
+            }
+            return false;
+        }
+
+        match key.code {
+            KeyCode::Char('q') => return true,
+            KeyCode::Esc => self.filter.clear(),
+            KeyCode::Char('/') => self.editing_filter = true,
+            KeyCode::Char('r') => self.shared.poll_now.notify_waiters(),
+            KeyCode::Char('p') | KeyCode::Char(' ') => {
+                self.paused = !self.paused;
+                if !self.paused {
+                    for line in std::mem::take(&mut self.held) {
+                        self.push_log(line);
+                    }
+                }
+            }
+            KeyCode::Tab | KeyCode::Right | KeyCode::Char('l') if !self.indices.is_empty() => {
+                self.selected = (self.selected + 1) % self.indices.len();
+            }
+            KeyCode::BackTab | KeyCode::Left | KeyCode::Char('h') if !self.indices.is_empty() => {
+                self.selected = (self.selected + self.indices.len() - 1) % self.indices.len();
+            }
+            _ => {}
+        }
+
+        false
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [tabs, chart, logs, status, help] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Percentage(45),
+            Constraint::Min(3),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let titles: Vec<_> = self
+            .indices
+            .iter()
+            .map(|index| Line::from(index.name.as_str()).style(Style::new().fg(state_color(index))))
+            .collect();
+        frame.render_widget(
+            Tabs::new(titles)
+                .select(self.selected)
+                .highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+            tabs,
+        );
+
+        match self.indices.get(self.selected) {
+            Some(index) => draw_chart(frame, chart, index),
+            None => {
+                let waiting = Paragraph::new("Waiting for the first poll...").block(Block::bordered());
+                frame.render_widget(waiting, chart);
+            }
+        }
+        self.draw_logs(frame, logs);
+        self.draw_status(frame, status);
+
+        let keys = match self.editing_filter {
+            true => format!(" Filter: {}_   Enter keep  Esc clear", self.filter),
+            false => " q quit  ←/→ index  / filter  p pause  r poll now".to_string(),
+        };
+        frame.render_widget(Paragraph::new(keys).dim(), help);
+    }
+
+    fn draw_logs(&self, frame: &mut Frame, area: Rect) {
+        let filter = self.filter.to_lowercase();
+        let several = self.indices.len() > 1;
+        let lines: Vec<_> = self
+            .logs
+            .iter()
+            .filter(|log| {
+                filter.is_empty()
+                    || log.line.to_lowercase().contains(&filter)
+                    || log.index_name.to_lowercase().contains(&filter)
+            })
+            .collect();
+
+        // The latest entries are at the bottom, so only the ones fitting the pane are kept
+        let height = area.height.saturating_sub(2) as usize;
+        let items: Vec<_> = lines[lines.len().saturating_sub(height)..]
+            .iter()
+            .map(|log| {
+                let style = match (log.failed, log.write) {
+                    (true, _) => Style::new().fg(Color::Red).bold(),
+                    (false, true) => Style::new().fg(Color::Yellow),
+                    (false, false) => Style::new(),
+                };
+                let line = match several {
+                    true => format!("[{}] {}", log.index_name, log.line),
+                    false => log.line.clone(),
+                };
+                ListItem::new(line).style(style)
+            })
+            .collect();
+
+        let mut title = format!(" Log entries ({}) ", lines.len());
+        if !self.filter.is_empty() {
+            title = format!(" Log entries matching `{}` ({}) ", self.filter, lines.len());
+        }
+        if self.paused {
+            title.push_str(&format!("PAUSED, {} new ", self.held.len()));
+        }
+        frame.render_widget(List::new(items).block(Block::bordered().title(title)), area);
+    }
+
+    fn draw_status(&self, frame: &mut Frame, area: Rect) {
+        let polls: u64 = self.indices.iter().map(|index| index.polls).sum();
+        let errors: u64 = self.indices.iter().map(|index| index.errors).sum();
+        let mut spans = vec![Span::raw(format!(" {polls} polls  "))];
+        spans.push(match errors {
+            0 => Span::raw("0 errors"),
+            errors => Span::raw(format!("{errors} errors")).red().bold(),
+        });
+        if let Some(duration) = self.indices.get(self.selected).and_then(|index| index.last_poll) {
+            spans.push(Span::raw(format!("  last poll {}ms", duration.as_millis())));
+        }
+        if let Some(duration) = self.api_response {
+            spans.push(Span::raw(format!("  API {}ms", duration.as_millis())));
+        }
+        if let Some(message) = self.messages.back() {
+            spans.push(Span::raw(format!("  {}", message.trim())).dim());
+        }
+        frame.render_widget(Paragraph::new(Line::from(spans)).reversed(), area);
+    }
+}
+
+/// Records counts of the index over the kept polls, with its current count and delta in the title
+fn draw_chart(frame: &mut Frame, area: Rect, index: &IndexState) {
+    let points: Vec<_> = index
+        .samples
+        .iter()
+        .enumerate()
+        .map(|(position, records)| (position as f64, *records as f64))
+        .collect();
+    let lowest = index.samples.iter().min().copied().unwrap_or_default();
+    let highest = index.samples.iter().max().copied().unwrap_or_default().max(lowest + 1);
+
+    let title = match index.records {
+        Some(records) => Line::from(vec![
+            Span::raw(format!(" {}: {} records, ", index.name, records)),
+            Span::raw(format!(
+                "{:+} vs {} expected ",
+                records as i64 - index.expected_records as i64,
+                index.expected_records
+            ))
+            .fg(state_color(index))
+            .bold(),
+        ]),
+        None => Line::from(format!(" {}: no records count yet ", index.name)),
+    };
+    let mut block = Block::bordered().title(title);
+    if let Some(error) = &index.last_error {
+        block = block.title_bottom(Line::from(format!(" {error} ")).red());
+    }
+
+    let dataset = Dataset::default()
+        .marker(Marker::Braille)
+        .graph_type(GraphType::Line)
+        .style(Style::new().fg(state_color(index)))
+        .data(&points);
+    let chart = Chart::new(vec![dataset])
+        .block(block)
+        .x_axis(Axis::default().bounds([0.0, points.len().max(2) as f64 - 1.0]))
+        .y_axis(
+            Axis::default()
+                .bounds([lowest as f64, highest as f64])
+                .labels([lowest.to_string(), highest.to_string()]),
+        );
+    frame.render_widget(chart, area);
+}
+
+/// Red for a breached index, yellow when the count is off the expected one, green otherwise
+fn state_color(index: &IndexState) -> Color {
+    match index.records {
+        _ if index.breached => Color::Red,
+        Some(records) if records != index.expected_records => Color::Yellow,
+        Some(_) => Color::Green,
+        None => Color::Reset,
+    }
+}
+
+/// Redirection of stderr to the status line, as the messages would garble the dashboard
+#[cfg(unix)]
+mod stderr {
+    use super::Update;
+    use std::fs::File;
+    use std::io::{BufRead, BufReader};
+    use std::os::fd::FromRawFd;
+    use std::sync::atomic::{AtomicI32, Ordering};
+    use std::sync::mpsc::Sender;
+
+    /// Duplicate of the original stderr while it is redirected
+    static ORIGINAL: AtomicI32 = AtomicI32::new(-1);
+
+    /// Gives stderr back when dropped
+    pub struct Redirect;
+
+    impl Redirect {
+        /// Sends the lines written to stderr as messages, leaving it as it is when it can't be redirected
+        pub fn start(updates: Sender<Update>) -> Option<Redirect> {
+            let mut pipe = [0; 2];
+            // SAFETY: the pipe descriptors are fresh ones, and the read end is owned by the File from here on
+            unsafe {
+                if libc::pipe(pipe.as_mut_ptr()) != 0 {
+                    return None;
+                }
+                let original = libc::dup(libc::STDERR_FILENO);
+                if original < 0 || libc::dup2(pipe[1], libc::STDERR_FILENO) < 0 {
+                    libc::close(pipe[0]);
+                    libc::close(pipe[1]);
+                    return None;
+                }
+                libc::close(pipe[1]);
+                ORIGINAL.store(original, Ordering::Relaxed);
+
+                let reader = BufReader::new(File::from_raw_fd(pipe[0]));
+                std::thread::spawn(move || {
+                    for line in reader.lines().map_while(Result::ok) {
+                        let _ = updates.send(Update::Message(line));
+                    }
+                });
+            }
+
+            Some(Redirect)
+        }
+    }
+
+    impl Drop for Redirect {
+        fn drop(&mut self) {
+            restore();
+        }
+    }
+
+    /// Puts the original stderr back, which also ends the thread reading the redirected one
+    pub fn restore() {
+        let original = ORIGINAL.swap(-1, Ordering::Relaxed);
+        if original >= 0 {
+            // SAFETY: the descriptor was duplicated by `Redirect::start` and is only closed here
+            unsafe {
+                libc::dup2(original, libc::STDERR_FILENO);
+                libc::close(original);
+            }
+        }
+    }
+}
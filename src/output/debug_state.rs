@@ -0,0 +1,201 @@
+use super::{ApiUsage, Trend};
+use crate::metrics::MetricsBackend;
+use crate::{now, Percentiles};
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tracing::info;
+
+/// State of the monitored indices shared by their monitors, printed on SIGUSR1 to look into a long running
+/// monitor, which polls every index right away on SIGUSR2
+///
+/// It is kept up to date as a metrics backend, along with the log position the monitors report after each
+/// poll. Where there are no such signals, such as on Windows, the `/state` and `/poll` endpoints of
+/// `--status-addr` take their place.
+#[derive(Clone)]
+pub struct DebugState {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    started: Instant,
+    started_at: DateTime<Utc>,
+    indices: Mutex<BTreeMap<String, IndexState>>,
+    poll_now: Notify,
+    trend: Trend,
+    usage: ApiUsage,
+}
+
+#[derive(Default)]
+struct IndexState {
+    records: Option<u64>,
+    expected_records: Option<u64>,
+    /// Whether the latest poll breached, reported right after its records count
+    breached: bool,
+    breached_since: Option<DateTime<Utc>>,
+    last_success_at: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    /// Timestamp of the newest log entry the monitor handed out
+    logs_since: Option<DateTime<Utc>>,
+}
+
+impl Default for DebugState {
+    fn default() -> Self {
+        Self::new(Trend::default(), ApiUsage::default())
+    }
+}
+
+impl DebugState {
+    /// State telling the trend of the indices and the API calls along with what the metrics report
+    pub fn new(trend: Trend, usage: ApiUsage) -> Self {
+        DebugState {
+            shared: Arc::new(Shared {
+                started: Instant::now(),
+                started_at: Utc::now(),
+                indices: Mutex::new(BTreeMap::new()),
+                poll_now: Notify::new(),
+                trend,
+                usage,
+            }),
+        }
+    }
+
+    fn update(&self, index_name: &str, update: impl FnOnce(&mut IndexState)) {
+        let mut indices = self.shared.indices.lock().unwrap();
+        update(indices.entry(index_name.to_string()).or_default());
+    }
+
+    /// Position of the monitor of the index in the logs after a poll
+    pub fn logs_since(&self, index_name: &str, since: Option<DateTime<Utc>>) {
+        self.update(index_name, |index| index.logs_since = since);
+    }
+
+    /// Resolves when SIGUSR2 or the `/poll` endpoint asks for an immediate poll
+    pub async fn poll_requested(&self) {
+        self.shared.poll_now.notified().await
+    }
+
+    /// Prints the state on SIGUSR1 and asks the monitors for a poll on SIGUSR2 from a background task, doing
+    /// nothing where there are no such signals
+    pub fn listen(&self) {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+
+            let mut dump = signal(SignalKind::user_defined1()).expect("failed to listen for SIGUSR1");
+            let mut poll = signal(SignalKind::user_defined2()).expect("failed to listen for SIGUSR2");
+            let state = self.clone();
+            tokio::spawn(async move {
+                loop {
+                    tokio::select! {
+                        Some(()) = dump.recv() => {
+                            for line in state.lines() {
+                                info!("[{}] {}", now(), line);
+                            }
+                        }
+                        Some(()) = poll.recv() => state.poll_now("asked by SIGUSR2"),
+                        else => break,
+                    }
+                }
+            });
+        }
+    }
+
+    /// Polls every index right away, for the reason such as `asked by SIGUSR2`
+    pub fn poll_now(&self, reason: &str) {
+        info!("[{}] Polling every index right away, {}", now(), reason);
+        self.shared.poll_now.notify_waiters();
+    }
+
+    /// A line about the process and one about each index
+    pub fn lines(&self) -> Vec<String> {
+        let shared = &self.shared;
+        let mut lines = vec![format!(
+            "State after {} since {}: {}, {} resident",
+            humantime::format_duration(Duration::from_secs(shared.started.elapsed().as_secs())),
+            shared.started_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+            shared.usage.describe(),
+            resident_memory()
+                .map_or_else(|| "unknown memory".to_string(), |bytes| format!("{} MiB", bytes >> 20))
+        )];
+
+        let time = |time: Option<DateTime<Utc>>| match time {
+            Some(time) => time.to_rfc3339_opts(SecondsFormat::Secs, true),
+            None => "never".to_string(),
+        };
+        for (name, index) in shared.indices.lock().unwrap().iter() {
+            let records = match (index.records, index.expected_records) {
+                (Some(records), Some(expected_records)) => {
+                    format!("{records} records, expected {expected_records}")
+                }
+                _ => "no records count".to_string(),
+            };
+            let breach = match (index.breached, index.breached_since) {
+                (true, Some(since)) => format!("breached since {}", time(Some(since))),
+                _ => "not breached".to_string(),
+            };
+            lines.push(format!(
+                "{}: {}, {}, last success {}, last error {}, logs since {}{}",
+                name,
+                records,
+                breach,
+                time(index.last_success_at),
+                index.last_error.as_deref().unwrap_or("none"),
+                time(index.logs_since),
+                shared.trend.sparkline(name).map_or_else(String::new, |sparkline| format!(", trend {sparkline}"))
+            ));
+        }
+
+        lines
+    }
+}
+
+/// Resident memory of the process in bytes, only known on Linux
+fn resident_memory() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find_map(|line| line.strip_prefix("VmRSS:"))?;
+    let kilobytes: u64 = line.trim().trim_end_matches("kB").trim().parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+impl MetricsBackend for DebugState {
+    fn records(&self, index_name: &str, records: u64, expected_records: u64) {
+        self.update(index_name, |index| {
+            index.records = Some(records);
+            index.expected_records = Some(expected_records);
+            index.breached = false;
+        });
+    }
+
+    fn data_size(&self, _index_name: &str, _data_size: u64) {}
+
+    fn pending_tasks(&self, _index_name: &str, _pending_tasks: u64) {}
+
+    fn latency(&self, _index_name: &str, _latency: &Percentiles) {}
+
+    fn poll_error(&self, _index_name: &str) {}
+
+    fn threshold_breach(&self, index_name: &str) {
+        self.update(index_name, |index| {
+            index.breached = true;
+            index.breached_since.get_or_insert_with(Utc::now);
+        });
+    }
+
+    fn api_response(&self, _endpoint: &str, _duration: Duration) {}
+
+    fn poll(&self, index_name: &str, _duration: Duration, error: Option<&str>) {
+        self.update(index_name, |index| match error {
+            Some(error) => index.last_error = Some(error.to_string()),
+            None => {
+                index.last_error = None;
+                index.last_success_at = Some(Utc::now());
+                if !index.breached {
+                    index.breached_since = None;
+                }
+            }
+        });
+    }
+}
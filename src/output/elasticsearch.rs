@@ -0,0 +1,363 @@
+use crate::{now, AlgoliaLogEntry};
+use reqwest::StatusCode;
+use serde_json::{Map, Value};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+/// Log entries kept while Elasticsearch can't be reached, the oldest ones are dropped past it
+const MAX_QUEUED_ENTRIES: usize = 10_000;
+
+/// Number of times an entry Elasticsearch was too busy to index is sent again before it is dropped
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Longest wait before indexing again after Elasticsearch failed
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Time a bulk request has to complete
+const BULK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Number of rejected entries logged for each bulk request, the others are only counted
+const MAX_LOGGED_REJECTIONS: usize = 5;
+
+/// Handle queueing the fetched log entries for Elasticsearch or OpenSearch, doing nothing without a URL
+///
+/// Entries are indexed with the `_bulk` API by a background task every flush interval, or as soon as a
+/// batch is full, so an outage of the cluster never holds up the polls.
+#[derive(Clone, Default)]
+pub struct Elasticsearch {
+    shared: Option<Arc<Shared>>,
+}
+
+/// Task indexing the entries of the [`Elasticsearch`] handles, retrying while the cluster fails
+pub struct ElasticsearchIndexer {
+    shared: Arc<Shared>,
+    task: JoinHandle<()>,
+}
+
+/// Cluster the entries are indexed in
+pub struct ElasticsearchTarget {
+    pub url: String,
+    /// Prefix of the indices, followed by the month of each entry such as `algolia-logs-2024.05`
+    pub index_prefix: String,
+    pub auth: Option<ElasticsearchAuth>,
+    pub batch_size: usize,
+    pub flush_interval: Duration,
+}
+
+pub enum ElasticsearchAuth {
+    Basic(String, Option<String>),
+    /// Encoded API key sent as `Authorization: ApiKey <key>`
+    ApiKey(String),
+}
+
+struct Shared {
+    queue: Mutex<Queue>,
+    index_prefix: String,
+    batch_size: usize,
+    wake: Notify,
+}
+
+#[derive(Default)]
+struct Queue {
+    entries: VecDeque<Entry>,
+    /// Entries dropped from a full queue since it was last reported
+    dropped: u64,
+    finished: bool,
+}
+
+struct Entry {
+    index: String,
+    document: String,
+    attempts: u32,
+}
+
+/// Reason a bulk request failed, transient failures are retried with the same entries
+enum BulkError {
+    Transient(String),
+    Rejected(String),
+}
+
+impl Elasticsearch {
+    pub fn start(client: reqwest::Client, target: ElasticsearchTarget) -> (Elasticsearch, ElasticsearchIndexer) {
+        let shared = Arc::new(Shared {
+            queue: Mutex::default(),
+            index_prefix: target.index_prefix.clone(),
+            batch_size: target.batch_size.max(1),
+            wake: Notify::new(),
+        });
+        let task = tokio::spawn(index_entries(client, target, shared.clone()));
+
+        (
+            Elasticsearch {
+                shared: Some(shared.clone()),
+            },
+            ElasticsearchIndexer { shared, task },
+        )
+    }
+
+    /// Queues the log entries of a poll, with the monitored index in `algolia_index`
+    pub fn push(&self, index_name: &str, logs: &[AlgoliaLogEntry]) {
+        let Some(shared) = &self.shared else {
+            return;
+        };
+        if logs.is_empty() {
+            return;
+        }
+
+        let mut queue = shared.queue.lock().unwrap();
+        for log in logs {
+            queue.entries.push_back(Entry {
+                index: format!("{}-{}", shared.index_prefix, log.timestamp.format("%Y.%m")),
+                document: document(index_name, log).to_string(),
+                attempts: 0,
+            });
+        }
+        queue.trim();
+        let full = queue.entries.len() >= shared.batch_size;
+        drop(queue);
+
+        if full {
+            shared.wake.notify_one();
+        }
+    }
+}
+
+impl ElasticsearchIndexer {
+    /// Indexes what is still queued once more, giving up on it when the cluster fails again
+    pub async fn finish(self) {
+        self.shared.queue.lock().unwrap().finished = true;
+        self.shared.wake.notify_one();
+        let _ = tokio::time::timeout(BULK_TIMEOUT * 2, self.task).await;
+    }
+}
+
+impl Queue {
+    fn trim(&mut self) {
+        while self.entries.len() > MAX_QUEUED_ENTRIES {
+            self.entries.pop_front();
+            self.dropped += 1;
+        }
+    }
+}
+
+/// Log entry with the Algolia timestamp as `@timestamp` and numbers for the numeric fields Algolia sends as
+/// strings, so they can be aggregated
+fn document(index_name: &str, log: &AlgoliaLogEntry) -> Value {
+    let mut document = match serde_json::to_value(log) {
+        Ok(Value::Object(document)) => document,
+        _ => Map::new(),
+    };
+    document.remove("timestamp");
+    document.insert("@timestamp".to_string(), Value::String(log.timestamp.to_rfc3339()));
+    document.insert("algolia_index".to_string(), Value::String(index_name.to_string()));
+    for field in ["answer_code", "processing_time_ms", "nb_api_calls"] {
+        let number = document.get(field).and_then(Value::as_str).and_then(|value| value.parse::<u64>().ok());
+        if let Some(number) = number {
+            document.insert(field.to_string(), Value::from(number));
+        }
+    }
+
+    Value::Object(document)
+}
+
+async fn index_entries(client: reqwest::Client, target: ElasticsearchTarget, shared: Arc<Shared>) {
+    let url = format!("{}/_bulk", target.url.trim_end_matches('/'));
+    let mut failures = 0;
+
+    loop {
+        let finished = wait(&shared, target.flush_interval).await;
+
+        // A queue holding more than a batch is indexed right away, and everything goes out when finishing
+        loop {
+            let (batch, dropped) = {
+                let mut queue = shared.queue.lock().unwrap();
+                let size = queue.entries.len().min(shared.batch_size);
+                let batch: Vec<_> = queue.entries.drain(..size).collect();
+                (batch, std::mem::take(&mut queue.dropped))
+            };
+            if dropped > 0 {
+                error!(
+                    "[{}] Dropped {} log entries queued for Elasticsearch while it failed",
+                    now(),
+                    dropped
+                );
+            }
+            if batch.is_empty() {
+                break;
+            }
+
+            match bulk(&client, &url, &target, &batch).await {
+                Ok(busy) => {
+                    if failures > 0 {
+                        info!("[{}] Indexing log entries in Elasticsearch again", now());
+                    }
+                    failures = 0;
+
+                    let retried = batch
+                        .into_iter()
+                        .enumerate()
+                        .filter(|(position, entry)| busy.contains(position) && entry.attempts + 1 < MAX_ATTEMPTS)
+                        .map(|(_, entry)| Entry {
+                            attempts: entry.attempts + 1,
+                            ..entry
+                        });
+                    requeue(&shared, retried.collect());
+                }
+                Err(BulkError::Rejected(error)) => {
+                    warn!("[{}] Elasticsearch rejected {} log entries: {}", now(), batch.len(), error);
+                }
+                Err(BulkError::Transient(error)) => {
+                    if failures == 0 {
+                        error!(
+                            "[{}] Failed to index log entries in Elasticsearch, retrying: {}",
+                            now(),
+                            error
+                        );
+                    }
+                    failures += 1;
+                    requeue(&shared, batch);
+                    if finished {
+                        return;
+                    }
+
+                    // The flush interval is waited for next, only finishing cuts the rest of the backoff short
+                    let retry = Duration::from_secs(1 << failures.min(6)).min(MAX_RETRY_DELAY);
+                    let retry = tokio::time::sleep(retry.saturating_sub(target.flush_interval));
+                    tokio::pin!(retry);
+                    loop {
+                        tokio::select! {
+                            _ = &mut retry => break,
+                            _ = shared.wake.notified() => {
+                                if shared.queue.lock().unwrap().finished {
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                    break;
+                }
+            }
+
+            if !finished && shared.queue.lock().unwrap().entries.len() < shared.batch_size {
+                break;
+            }
+        }
+
+        if finished {
+            return;
+        }
+    }
+}
+
+/// Puts entries back in front of the queue, to be indexed again first
+fn requeue(shared: &Shared, entries: Vec<Entry>) {
+    let mut queue = shared.queue.lock().unwrap();
+    for entry in entries.into_iter().rev() {
+        queue.entries.push_front(entry);
+    }
+    queue.trim();
+}
+
+/// Waits for the flush interval or a full batch, returning whether the indexer is finishing
+async fn wait(shared: &Shared, flush_interval: Duration) -> bool {
+    let flush = tokio::time::sleep(flush_interval);
+    tokio::pin!(flush);
+    loop {
+        tokio::select! {
+            _ = &mut flush => return shared.queue.lock().unwrap().finished,
+            _ = shared.wake.notified() => {
+                let queue = shared.queue.lock().unwrap();
+                if queue.finished || queue.entries.len() >= shared.batch_size {
+                    return queue.finished;
+                }
+            }
+        }
+    }
+}
+
+/// Indexes the entries, returning the positions of the ones Elasticsearch was too busy to index
+async fn bulk(
+    client: &reqwest::Client,
+    url: &str,
+    target: &ElasticsearchTarget,
+    batch: &[Entry],
+) -> Result<Vec<usize>, BulkError> {
+    let mut body = String::new();
+    for entry in batch {
+        body.push_str(&serde_json::json!({ "index": { "_index": entry.index } }).to_string());
+        body.push('\n');
+        body.push_str(&entry.document);
+        body.push('\n');
+    }
+
+    let mut request = client
+        .post(url)
+        .timeout(BULK_TIMEOUT)
+        .header(reqwest::header::CONTENT_TYPE, "application/x-ndjson")
+        .body(body);
+    request = match &target.auth {
+        Some(ElasticsearchAuth::Basic(username, password)) => request.basic_auth(username, password.as_ref()),
+        Some(ElasticsearchAuth::ApiKey(key)) => {
+            request.header(reqwest::header::AUTHORIZATION, format!("ApiKey {key}"))
+        }
+        None => request,
+    };
+
+    let response = request
+        .send()
+        .await
+        .map_err(|error| BulkError::Transient(error.without_url().to_string()))?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        let error = format!("{} {}", status, body.trim());
+        return match status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+            true => Err(BulkError::Transient(error)),
+            false => Err(BulkError::Rejected(error)),
+        };
+    }
+
+    let result: Value = response
+        .json()
+        .await
+        .map_err(|error| BulkError::Transient(error.without_url().to_string()))?;
+    if result["errors"] != Value::Bool(true) {
+        return Ok(vec![]);
+    }
+
+    // Busy entries are retried, the other failed ones are logged and dropped as they would fail again
+    let mut busy = vec![];
+    let mut rejected = 0;
+    for (position, item) in result["items"].as_array().into_iter().flatten().enumerate() {
+        let item = &item["index"];
+        match item["status"].as_u64() {
+            Some(429) => busy.push(position),
+            Some(status) if status >= 300 => {
+                rejected += 1;
+                if rejected <= MAX_LOGGED_REJECTIONS {
+                    warn!(
+                        "[{}] Elasticsearch rejected a log entry for {}: {}: {}",
+                        now(),
+                        item["_index"].as_str().unwrap_or("-"),
+                        item["error"]["type"].as_str().unwrap_or("error"),
+                        item["error"]["reason"].as_str().unwrap_or("-")
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+    if rejected > MAX_LOGGED_REJECTIONS {
+        warn!(
+            "[{}] Elasticsearch rejected {} more log entries of the same request",
+            now(),
+            rejected - MAX_LOGGED_REJECTIONS
+        );
+    }
+
+    Ok(busy)
+}
@@ -0,0 +1,320 @@
+use crate::{now, RecordsCount};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{params, Connection, OpenFlags};
+use serde::Serialize;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::JoinHandle;
+use tracing::{error, info};
+
+/// Tables of the history database, created when missing
+///
+/// Timestamps are RFC 3339 in UTC with seconds, so they sort and compare as text.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS samples (
+        timestamp TEXT NOT NULL,
+        index_name TEXT NOT NULL,
+        records INTEGER NOT NULL,
+        expected_records INTEGER NOT NULL,
+        processing_time_ms INTEGER
+    );
+    CREATE INDEX IF NOT EXISTS samples_by_index ON samples (index_name, timestamp);
+    CREATE TABLE IF NOT EXISTS events (
+        timestamp TEXT NOT NULL,
+        index_name TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        message TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS events_by_index ON events (index_name, timestamp);
+";
+
+/// Handle recording the polls and events of the monitored indices in the history database, doing nothing
+/// without one
+///
+/// Entries are handed to a dedicated thread, so a slow disk never holds up the polls.
+#[derive(Clone, Default)]
+pub struct History {
+    sender: Option<Sender<Entry>>,
+}
+
+/// Thread writing the entries of the [`History`] handles, each batch holding what queued up while the
+/// previous one was written
+pub struct HistoryWriter {
+    thread: JoinHandle<()>,
+}
+
+enum Entry {
+    Sample {
+        timestamp: DateTime<Utc>,
+        index_name: String,
+        records: RecordsCount,
+        processing_time_ms: Option<u64>,
+    },
+    Event {
+        timestamp: DateTime<Utc>,
+        index_name: String,
+        kind: &'static str,
+        message: String,
+    },
+}
+
+/// Records count of a poll read back from the history database
+pub struct Sample {
+    pub timestamp: String,
+    pub records: u64,
+    pub expected_records: u64,
+    pub processing_time_ms: Option<u64>,
+}
+
+/// Poll or event read back from the history database, a poll having a count and an event a type
+#[derive(Serialize)]
+pub struct TimelineEntry {
+    pub timestamp: String,
+    #[serde(rename = "index")]
+    pub index_name: String,
+    #[serde(rename = "count")]
+    pub records: Option<u64>,
+    /// Difference of the count from the expected records count
+    #[serde(rename = "delta_vs_baseline")]
+    pub delta: Option<i64>,
+    #[serde(rename = "event_type")]
+    pub kind: Option<String>,
+    #[serde(rename = "detail")]
+    pub message: Option<String>,
+}
+
+/// Records counts of the polls of a single day read back from the history database
+pub struct Day {
+    /// Date in UTC, such as `2024-05-02`
+    pub date: String,
+    pub min_records: u64,
+    pub max_records: u64,
+    pub avg_records: f64,
+    pub samples: u64,
+}
+
+impl History {
+    /// Opens the database, creating it and its tables when missing, and starts the thread writing to it
+    pub fn open(path: &Path) -> rusqlite::Result<(History, HistoryWriter)> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(SCHEMA)?;
+
+        let (sender, receiver) = mpsc::channel();
+        let path = path.to_path_buf();
+        let thread = std::thread::spawn(move || write_entries(connection, path, receiver));
+
+        Ok((History { sender: Some(sender) }, HistoryWriter { thread }))
+    }
+
+    /// Records the count of a poll, with the processing time of the search that counted the records
+    pub fn sample(&self, index_name: &str, records: RecordsCount, processing_time_ms: Option<u64>) {
+        self.send(Entry::Sample {
+            timestamp: Utc::now(),
+            index_name: index_name.to_string(),
+            records,
+            processing_time_ms,
+        });
+    }
+
+    /// Records an event of the index, such as a `breach` or an `error`
+    pub fn event(&self, index_name: &str, kind: &'static str, message: impl Display) {
+        self.send(Entry::Event {
+            timestamp: Utc::now(),
+            index_name: index_name.to_string(),
+            kind,
+            message: message.to_string(),
+        });
+    }
+
+    fn send(&self, entry: Entry) {
+        if let Some(sender) = &self.sender {
+            // The writer only stops once every handle is dropped
+            let _ = sender.send(entry);
+        }
+    }
+}
+
+impl HistoryWriter {
+    /// Waits for the queued entries to be written, which ends once every [`History`] handle is dropped
+    pub fn finish(self) {
+        let _ = self.thread.join();
+    }
+}
+
+fn write_entries(mut connection: Connection, path: PathBuf, receiver: Receiver<Entry>) {
+    // Reported once until a write succeeds again, so a full disk doesn't flood stderr
+    let mut failed = false;
+
+    while let Ok(entry) = receiver.recv() {
+        let entries: Vec<_> = std::iter::once(entry).chain(receiver.try_iter()).collect();
+        match write_batch(&mut connection, &entries) {
+            Ok(()) if failed => {
+                failed = false;
+                info!("[{}] Writing to history database {} again", now(), path.display());
+            }
+            Ok(()) => {}
+            Err(error) if !failed => {
+                failed = true;
+                error!(
+                    "[{}] Failed to write to history database {}: {}",
+                    now(),
+                    path.display(),
+                    error
+                );
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+fn write_batch(connection: &mut Connection, entries: &[Entry]) -> rusqlite::Result<()> {
+    let transaction = connection.transaction()?;
+    {
+        let mut insert_sample = transaction.prepare_cached(
+            "INSERT INTO samples (timestamp, index_name, records, expected_records, processing_time_ms)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        let mut insert_event = transaction
+            .prepare_cached("INSERT INTO events (timestamp, index_name, kind, message) VALUES (?1, ?2, ?3, ?4)")?;
+
+        for entry in entries {
+            match entry {
+                Entry::Sample {
+                    timestamp,
+                    index_name,
+                    records,
+                    processing_time_ms,
+                } => insert_sample.execute(params![
+                    timestamp_text(timestamp),
+                    index_name,
+                    records.current_records,
+                    records.expected_records,
+                    processing_time_ms
+                ])?,
+                Entry::Event {
+                    timestamp,
+                    index_name,
+                    kind,
+                    message,
+                } => insert_event.execute(params![timestamp_text(timestamp), index_name, kind, message])?,
+            };
+        }
+    }
+    transaction.commit()
+}
+
+/// History database opened read-only, for printing what the monitor recorded
+pub struct HistoryReader {
+    connection: Connection,
+}
+
+impl HistoryReader {
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        Ok(HistoryReader {
+            connection: Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?,
+        })
+    }
+
+    /// Latest `limit` samples of the index in the time range, oldest first
+    pub fn samples(
+        &self,
+        index_name: &str,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        limit: u32,
+    ) -> rusqlite::Result<Vec<Sample>> {
+        let mut statement = self.connection.prepare(
+            "SELECT timestamp, records, expected_records, processing_time_ms FROM samples
+             WHERE index_name = ?1 AND timestamp >= ?2 AND timestamp <= ?3
+             ORDER BY timestamp DESC LIMIT ?4",
+        )?;
+        let (since, until) = range(since, until);
+        let mut samples = statement
+            .query_map(params![index_name, since, until, limit], |row| {
+                Ok(Sample {
+                    timestamp: row.get(0)?,
+                    records: row.get(1)?,
+                    expected_records: row.get(2)?,
+                    processing_time_ms: row.get(3)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        samples.reverse();
+
+        Ok(samples)
+    }
+
+    /// Polls and events in the time range ordered by time, of every index when `index_name` is `None`
+    pub fn timeline(
+        &self,
+        index_name: Option<&str>,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> rusqlite::Result<Vec<TimelineEntry>> {
+        let mut statement = self.connection.prepare(
+            "SELECT timestamp, index_name, records, records - expected_records, NULL, NULL FROM samples
+             WHERE (?1 IS NULL OR index_name = ?1) AND timestamp >= ?2 AND timestamp <= ?3
+             UNION ALL
+             SELECT timestamp, index_name, NULL, NULL, kind, message FROM events
+             WHERE (?1 IS NULL OR index_name = ?1) AND timestamp >= ?2 AND timestamp <= ?3
+             ORDER BY timestamp",
+        )?;
+        let (since, until) = range(since, until);
+        let entries = statement
+            .query_map(params![index_name, since, until], |row| {
+                Ok(TimelineEntry {
+                    timestamp: row.get(0)?,
+                    index_name: row.get(1)?,
+                    records: row.get(2)?,
+                    delta: row.get(3)?,
+                    kind: row.get(4)?,
+                    message: row.get(5)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(entries)
+    }
+
+    /// Lowest, highest and average count of each day of the index in the time range
+    pub fn days(
+        &self,
+        index_name: &str,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> rusqlite::Result<Vec<Day>> {
+        let mut statement = self.connection.prepare(
+            "SELECT substr(timestamp, 1, 10) AS date, MIN(records), MAX(records), AVG(records), COUNT(*)
+             FROM samples
+             WHERE index_name = ?1 AND timestamp >= ?2 AND timestamp <= ?3
+             GROUP BY date ORDER BY date",
+        )?;
+        let (since, until) = range(since, until);
+        let days = statement
+            .query_map(params![index_name, since, until], |row| {
+                Ok(Day {
+                    date: row.get(0)?,
+                    min_records: row.get(1)?,
+                    max_records: row.get(2)?,
+                    avg_records: row.get(3)?,
+                    samples: row.get(4)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(days)
+    }
+}
+
+/// Bounds of the time range as text, covering every timestamp when a bound is missing
+fn range(since: Option<DateTime<Utc>>, until: Option<DateTime<Utc>>) -> (String, String) {
+    (
+        since.as_ref().map(timestamp_text).unwrap_or_default(),
+        until.as_ref().map_or_else(|| "9999".to_string(), timestamp_text),
+    )
+}
+
+fn timestamp_text(timestamp: &DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
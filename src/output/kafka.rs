@@ -0,0 +1,175 @@
+use crate::{now, AlgoliaLogEntry, RecordsCount};
+use chrono::{SecondsFormat, Utc};
+use rdkafka::config::ClientConfig;
+use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+use rdkafka::producer::{BaseRecord, DeliveryResult, Producer, ProducerContext, ThreadedProducer};
+use rdkafka::util::Timeout;
+use rdkafka::ClientContext;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// Messages librdkafka holds while the brokers can't keep up, new ones are dropped past it
+const MAX_QUEUED_MESSAGES: &str = "10000";
+
+/// Time the queued messages have to be delivered when the monitor stops
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Brokers the messages are published to, such as `kafka-1:9092,kafka-2:9092`
+pub struct KafkaTarget {
+    pub brokers: String,
+    pub topic: String,
+    /// librdkafka settings of the producer, applied last
+    pub config: Vec<(String, String)>,
+}
+
+/// Handle publishing the samples, events and log entries of the monitored indices to a Kafka topic, doing
+/// nothing without brokers
+///
+/// Messages are JSON objects keyed by the index name, with their kind in `type`. They are queued by
+/// librdkafka and delivered by its own thread, so a slow broker never holds up the polls.
+#[derive(Clone, Default)]
+pub struct Kafka {
+    shared: Option<Arc<Shared>>,
+}
+
+struct Shared {
+    producer: ThreadedProducer<Delivery>,
+    topic: String,
+    /// Messages dropped from a full queue since it was last reported
+    dropped: AtomicU64,
+}
+
+/// Reports the failed deliveries once until one succeeds again
+#[derive(Default)]
+struct Delivery {
+    failed: AtomicBool,
+}
+
+impl ClientContext for Delivery {}
+
+impl ProducerContext for Delivery {
+    type DeliveryOpaque = ();
+
+    fn delivery(&self, result: &DeliveryResult<'_>, _: ()) {
+        match result {
+            Ok(_) => {
+                if self.failed.swap(false, Ordering::Relaxed) {
+                    info!("[{}] Publishing to Kafka again", now());
+                }
+            }
+            Err((error, _)) => {
+                if !self.failed.swap(true, Ordering::Relaxed) {
+                    error!("[{}] Failed to publish to Kafka: {}", now(), error);
+                }
+            }
+        }
+    }
+}
+
+impl Kafka {
+    /// Creates the producer, `config` holding librdkafka settings such as `security.protocol=SASL_SSL` that
+    /// are applied last
+    pub fn connect(brokers: &str, topic: &str, config: &[(String, String)]) -> Result<Kafka, KafkaError> {
+        let mut client_config = ClientConfig::new();
+        client_config
+            .set("bootstrap.servers", brokers)
+            .set("queue.buffering.max.messages", MAX_QUEUED_MESSAGES);
+        for (key, value) in config {
+            client_config.set(key, value);
+        }
+
+        Ok(Kafka {
+            shared: Some(Arc::new(Shared {
+                producer: client_config.create_with_context(Delivery::default())?,
+                topic: topic.to_string(),
+                dropped: AtomicU64::new(0),
+            })),
+        })
+    }
+
+    /// Publishes the records count of a poll
+    pub fn sample(&self, index_name: &str, records: RecordsCount, processing_time_ms: Option<u64>) {
+        self.publish(
+            index_name,
+            json!({
+                "type": "records",
+                "index": index_name,
+                "records": records.current_records,
+                "expected_records": records.expected_records,
+                "processing_time_ms": processing_time_ms,
+                "timestamp": Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            }),
+        );
+    }
+
+    /// Publishes an event of the index, such as a `breach` or an `error`
+    pub fn event(&self, index_name: &str, kind: &str, message: &str) {
+        self.publish(
+            index_name,
+            json!({
+                "type": "event",
+                "index": index_name,
+                "event": kind,
+                "message": message,
+                "timestamp": Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            }),
+        );
+    }
+
+    /// Publishes the log entries of a poll, each in the `log` field of its own message
+    pub fn logs(&self, index_name: &str, logs: &[AlgoliaLogEntry]) {
+        for log in logs {
+            self.publish(
+                index_name,
+                json!({
+                    "type": "log",
+                    "index": index_name,
+                    "log": log,
+                    "timestamp": log.timestamp.to_rfc3339_opts(SecondsFormat::Secs, true),
+                }),
+            );
+        }
+    }
+
+    fn publish(&self, index_name: &str, message: Value) {
+        let Some(shared) = &self.shared else {
+            return;
+        };
+
+        let payload = message.to_string();
+        let record = BaseRecord::to(&shared.topic).key(index_name).payload(&payload);
+        match shared.producer.send(record) {
+            Ok(()) => {
+                let dropped = shared.dropped.swap(0, Ordering::Relaxed);
+                if dropped > 0 {
+                    warn!("[{}] Dropped {} messages for Kafka while its queue was full", now(), dropped);
+                }
+            }
+            Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), _)) => {
+                if shared.dropped.fetch_add(1, Ordering::Relaxed) == 0 {
+                    warn!("[{}] Kafka queue is full, dropping messages until it drains", now());
+                }
+            }
+            Err((error, _)) => error!("[{}] Failed to publish to Kafka: {}", now(), error),
+        }
+    }
+
+    /// Waits for the queued messages to be delivered, giving up on them after a while
+    ///
+    /// Flushing blocks, so it happens away from the runtime threads.
+    pub async fn flush(self) {
+        let Some(shared) = self.shared else {
+            return;
+        };
+
+        let _ = tokio::task::spawn_blocking(move || {
+            if let Err(error) = shared.producer.flush(Timeout::After(FLUSH_TIMEOUT)) {
+                error!("[{}] Failed to deliver the last messages to Kafka: {}", now(), error);
+            }
+        })
+        .await;
+    }
+}
@@ -0,0 +1,154 @@
+use crate::now;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::{error, info};
+
+/// File receiving a copy of the printed lines, rotated to `.1`, `.2`, ... once it reaches its size limit
+pub struct LogFile {
+    path: PathBuf,
+    max_size: u64,
+    keep: usize,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    file: Option<File>,
+    size: u64,
+    /// Whether the last write failed, so the failure is reported only once
+    failed: bool,
+}
+
+impl LogFile {
+    pub fn open(path: PathBuf, max_size: u64, keep: usize) -> std::io::Result<Self> {
+        let log_file = LogFile {
+            path,
+            max_size,
+            keep,
+            state: Mutex::default(),
+        };
+        log_file.reopen(&mut log_file.state.lock().unwrap())?;
+        Ok(log_file)
+    }
+
+    /// Appends a line, reporting a failure on stderr the first time it happens
+    pub fn write_line(&self, line: &str) {
+        let mut state = self.state.lock().unwrap();
+
+        match self.try_write_line(&mut state, line) {
+            Ok(()) if state.failed => {
+                state.failed = false;
+                info!("[{}] Writing to log file {} again", now(), self.path.display());
+            }
+            Ok(()) => {}
+            Err(error) if !state.failed => {
+                state.failed = true;
+                state.file = None;
+                error!(
+                    "[{}] Failed to write to log file {}: {}",
+                    now(),
+                    self.path.display(),
+                    error
+                );
+            }
+            Err(_) => state.file = None,
+        }
+    }
+
+    fn try_write_line(&self, state: &mut State, line: &str) -> std::io::Result<()> {
+        let length = line.len() as u64 + 1;
+
+        if state.file.as_ref().is_none_or(|file| !is_same_file(file, &self.path)) {
+            self.reopen(state)?;
+        }
+        // A copytruncate rotation empties the file in place, which keeps it the same file but shorter
+        let size = state.file.as_ref().expect("log file is open").metadata()?.len();
+        if size < state.size {
+            state.size = size;
+        }
+        if state.size > 0 && state.size + length > self.max_size {
+            self.rotate(state)?;
+        }
+
+        let file = state.file.as_mut().expect("log file is open");
+        file.write_all(format!("{line}\n").as_bytes())?;
+        file.flush()?;
+        state.size += length;
+        Ok(())
+    }
+
+    /// Opens the file at its path again, such as after it got deleted or moved away
+    fn reopen(&self, state: &mut State) -> std::io::Result<()> {
+        let file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        state.size = file.metadata()?.len();
+        state.file = Some(file);
+        Ok(())
+    }
+
+    fn rotate(&self, state: &mut State) -> std::io::Result<()> {
+        state.file = None;
+
+        match self.keep {
+            0 => std::fs::remove_file(&self.path)?,
+            keep => {
+                for number in (1..keep).rev() {
+                    let from = self.rotated_path(number);
+                    if from.exists() {
+                        std::fs::rename(from, self.rotated_path(number + 1))?;
+                    }
+                }
+                std::fs::rename(&self.path, self.rotated_path(1))?;
+            }
+        }
+
+        self.reopen(state)
+    }
+
+    fn rotated_path(&self, number: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{number}"));
+        path.into()
+    }
+}
+
+/// Whether the open file is still the one at `path`, which external log rotation changes
+#[cfg(unix)]
+fn is_same_file(file: &File, path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (file.metadata(), std::fs::metadata(path)) {
+        (Ok(open), Ok(current)) => open.dev() == current.dev() && open.ino() == current.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_same_file(_file: &File, path: &Path) -> bool {
+    path.exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size_starts_again_from_a_file_truncated_in_place() {
+        let directory = std::env::temp_dir().join(format!("algolia-monitor-log-file-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("monitor.log");
+        let log_file = LogFile::open(path.clone(), 16, 1).unwrap();
+
+        log_file.write_line("first line");
+        // As logrotate's copytruncate does after copying the file away
+        OpenOptions::new().write(true).open(&path).unwrap().set_len(0).unwrap();
+        log_file.write_line("second line");
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "second line\n");
+        assert!(!log_file.rotated_path(1).exists());
+        // Windows keeps the directory of a file still open
+        drop(log_file);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
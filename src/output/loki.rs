@@ -0,0 +1,260 @@
+use crate::{now, AlgoliaLogEntry, LogType};
+use chrono::{TimeDelta, Utc};
+use reqwest::StatusCode;
+use serde_json::json;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+/// Log entries kept while Loki can't be reached, the oldest ones are dropped past it
+const MAX_QUEUED_ENTRIES: usize = 10_000;
+
+/// Log entries sent with a single push request
+const MAX_BATCH_SIZE: usize = 1_000;
+
+/// Age past which entries get the timestamp of that age, as Loki rejects entries too far out of order
+const MAX_ENTRY_AGE: TimeDelta = TimeDelta::hours(1);
+
+/// Longest wait before pushing again after Loki failed
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Time a push request has to complete
+const PUSH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Handle queueing the fetched log entries for Grafana Loki, doing nothing without a Loki URL
+///
+/// Entries are pushed by a background task, so an outage of Loki never holds up the polls.
+#[derive(Clone, Default)]
+pub struct Loki {
+    shared: Option<Arc<Shared>>,
+}
+
+/// Task pushing the entries of the [`Loki`] handles, retrying while Loki fails
+pub struct LokiPusher {
+    shared: Arc<Shared>,
+    task: JoinHandle<()>,
+}
+
+/// Loki instance the entries are pushed to
+pub struct LokiTarget {
+    /// Base URL, `/loki/api/v1/push` is added to it
+    pub url: String,
+    pub basic_auth: Option<(String, Option<String>)>,
+    /// Tenant sent as `X-Scope-OrgID` to a multi-tenant Loki
+    pub tenant: Option<String>,
+}
+
+struct Shared {
+    queue: Mutex<Queue>,
+    wake: Notify,
+}
+
+#[derive(Default)]
+struct Queue {
+    entries: VecDeque<Entry>,
+    /// Entries dropped from a full queue since it was last reported
+    dropped: u64,
+    finished: bool,
+}
+
+struct Entry {
+    index_name: String,
+    log_type: LogType,
+    /// Nanoseconds since the Unix epoch
+    timestamp: i64,
+    line: String,
+}
+
+/// Reason a push failed, transient failures are retried with the same entries
+enum PushError {
+    Transient(String),
+    Rejected(String),
+}
+
+impl Loki {
+    pub fn start(client: reqwest::Client, target: LokiTarget) -> (Loki, LokiPusher) {
+        let shared = Arc::new(Shared {
+            queue: Mutex::default(),
+            wake: Notify::new(),
+        });
+        let task = tokio::spawn(push_entries(client, target, shared.clone()));
+
+        (
+            Loki {
+                shared: Some(shared.clone()),
+            },
+            LokiPusher { shared, task },
+        )
+    }
+
+    /// Queues the log entries of a poll, labeled with the index and their type
+    pub fn push(&self, index_name: &str, log_type: LogType, logs: &[AlgoliaLogEntry]) {
+        let Some(shared) = &self.shared else {
+            return;
+        };
+        if logs.is_empty() {
+            return;
+        }
+
+        let oldest = Utc::now() - MAX_ENTRY_AGE;
+        let mut queue = shared.queue.lock().unwrap();
+        for log in logs {
+            queue.entries.push_back(Entry {
+                index_name: index_name.to_string(),
+                log_type: log.log_type.unwrap_or(log_type),
+                timestamp: log.timestamp.max(oldest).timestamp_nanos_opt().unwrap_or_default(),
+                line: log.to_json(),
+            });
+        }
+        queue.trim();
+        drop(queue);
+
+        shared.wake.notify_one();
+    }
+}
+
+impl LokiPusher {
+    /// Pushes what is still queued once more, giving up on it when Loki fails again
+    pub async fn finish(self) {
+        self.shared.queue.lock().unwrap().finished = true;
+        self.shared.wake.notify_one();
+        let _ = tokio::time::timeout(PUSH_TIMEOUT * 2, self.task).await;
+    }
+}
+
+impl Queue {
+    fn trim(&mut self) {
+        while self.entries.len() > MAX_QUEUED_ENTRIES {
+            self.entries.pop_front();
+            self.dropped += 1;
+        }
+    }
+}
+
+async fn push_entries(client: reqwest::Client, target: LokiTarget, shared: Arc<Shared>) {
+    let url = format!("{}/loki/api/v1/push", target.url.trim_end_matches('/'));
+    let mut failures = 0;
+
+    loop {
+        let (batch, dropped, finished) = {
+            let mut queue = shared.queue.lock().unwrap();
+            let size = queue.entries.len().min(MAX_BATCH_SIZE);
+            let batch: Vec<_> = queue.entries.drain(..size).collect();
+            (batch, std::mem::take(&mut queue.dropped), queue.finished)
+        };
+        if dropped > 0 {
+            error!("[{}] Dropped {} log entries queued for Loki while it failed", now(), dropped);
+        }
+        if batch.is_empty() {
+            match finished {
+                true => return,
+                false => {
+                    shared.wake.notified().await;
+                    continue;
+                }
+            }
+        }
+
+        match push(&client, &url, &target, &batch).await {
+            Ok(()) => {
+                if failures > 0 {
+                    info!("[{}] Pushing log entries to Loki again", now());
+                }
+                failures = 0;
+            }
+            Err(PushError::Rejected(error)) => {
+                warn!("[{}] Loki rejected {} log entries: {}", now(), batch.len(), error);
+            }
+            Err(PushError::Transient(error)) => {
+                if failures == 0 {
+                    error!("[{}] Failed to push log entries to Loki, retrying: {}", now(), error);
+                }
+                failures += 1;
+
+                let finished = {
+                    let mut queue = shared.queue.lock().unwrap();
+                    for entry in batch.into_iter().rev() {
+                        queue.entries.push_front(entry);
+                    }
+                    queue.trim();
+                    queue.finished
+                };
+                if finished {
+                    return;
+                }
+
+                // New entries don't cut the wait short, only finishing does for the last push
+                let retry = tokio::time::sleep(Duration::from_secs(1 << failures.min(6)).min(MAX_RETRY_DELAY));
+                tokio::pin!(retry);
+                loop {
+                    tokio::select! {
+                        _ = &mut retry => break,
+                        _ = shared.wake.notified() => {
+                            if shared.queue.lock().unwrap().finished {
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+async fn push(
+    client: &reqwest::Client,
+    url: &str,
+    target: &LokiTarget,
+    batch: &[Entry],
+) -> Result<(), PushError> {
+    let mut streams: BTreeMap<(&str, String), Vec<_>> = BTreeMap::new();
+    for entry in batch {
+        streams
+            .entry((&entry.index_name, entry.log_type.to_string()))
+            .or_default()
+            .push((entry.timestamp, &entry.line));
+    }
+    let streams: Vec<_> = streams
+        .into_iter()
+        .map(|((index_name, log_type), mut values)| {
+            values.sort_by_key(|(timestamp, _)| *timestamp);
+            json!({
+                "stream": { "job": "algolia-monitor", "index": index_name, "type": log_type },
+                "values": values
+                    .into_iter()
+                    .map(|(timestamp, line)| [timestamp.to_string(), line.clone()])
+                    .collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+
+    let mut request = client
+        .post(url)
+        .timeout(PUSH_TIMEOUT)
+        .json(&json!({ "streams": streams }));
+    if let Some((username, password)) = &target.basic_auth {
+        request = request.basic_auth(username, password.as_ref());
+    }
+    if let Some(tenant) = &target.tenant {
+        request = request.header("X-Scope-OrgID", tenant);
+    }
+
+    let response = request
+        .send()
+        .await
+        .map_err(|error| PushError::Transient(error.without_url().to_string()))?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+
+    let body = response.text().await.unwrap_or_default();
+    let error = format!("{} {}", status, body.trim());
+    match status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+        true => Err(PushError::Transient(error)),
+        false => Err(PushError::Rejected(error)),
+    }
+}
@@ -0,0 +1,168 @@
+use crate::now;
+use chrono::{SecondsFormat, Utc};
+use rumqttc::{AsyncClient, ConnectionError, Event, EventLoop, MqttOptions, Outgoing, Packet, QoS, Transport};
+use serde_json::json;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+/// Publish requests queued for the event loop, new ones are dropped past it while the broker is away
+const MAX_QUEUED_MESSAGES: usize = 1_000;
+
+/// Longest wait before connecting again after the broker went away
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// Time the queued messages have to go out when the monitor stops
+const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Events published to `<prefix>/<index>/events`, the other ones only go to the history and Kafka
+const EVENT_KINDS: [&str; 2] = ["breach", "recovery"];
+
+/// Handle publishing the records counts and the breaches of the monitored indices to an MQTT broker, doing
+/// nothing without one
+///
+/// The count is retained on `<prefix>/<index>/records`, so dashboards get it as soon as they subscribe, and
+/// breaches and recoveries go to `<prefix>/<index>/events` as JSON.
+#[derive(Clone, Default)]
+pub struct Mqtt {
+    shared: Option<Arc<Shared>>,
+}
+
+/// Task running the MQTT event loop, which connects again whenever the broker goes away
+pub struct MqttConnection {
+    client: AsyncClient,
+    task: JoinHandle<()>,
+}
+
+/// Broker the messages are published to
+pub struct MqttTarget {
+    /// `mqtt://host:1883`, or `mqtts://host:8883` for TLS, optionally with the credentials
+    pub url: String,
+    pub topic_prefix: String,
+    pub credentials: Option<(String, String)>,
+}
+
+struct Shared {
+    client: AsyncClient,
+    topic_prefix: String,
+    /// Whether publishing failed since it last succeeded, so it is only reported once
+    failed: AtomicBool,
+}
+
+impl Mqtt {
+    pub fn connect(target: MqttTarget) -> Result<(Mqtt, MqttConnection), String> {
+        let url =
+            reqwest::Url::parse(&target.url).map_err(|error| format!("invalid URL `{}`: {error}", target.url))?;
+        let (tls, default_port) = match url.scheme() {
+            "mqtt" | "tcp" => (false, 1883),
+            "mqtts" | "ssl" => (true, 8883),
+            scheme => return Err(format!("unsupported scheme `{scheme}`, expected mqtt or mqtts")),
+        };
+        let host = url.host_str().ok_or_else(|| format!("missing host in `{}`", target.url))?;
+
+        // Two monitors with the same client id would keep kicking each other off the broker
+        let client_id = format!(
+            "algolia-monitor-{}-{}",
+            gethostname::gethostname().to_string_lossy(),
+            std::process::id()
+        );
+        let mut options = MqttOptions::new(client_id, host, url.port().unwrap_or(default_port));
+        options.set_keep_alive(Duration::from_secs(30));
+        let credentials = match (target.credentials, url.username()) {
+            (Some(credentials), _) => Some(credentials),
+            (None, "") => None,
+            (None, username) => Some((username.to_string(), url.password().unwrap_or_default().to_string())),
+        };
+        if let Some((username, password)) = credentials {
+            options.set_credentials(username, password);
+        }
+        if tls {
+            options.set_transport(Transport::tls_with_default_config());
+        }
+
+        let (client, event_loop) = AsyncClient::new(options, MAX_QUEUED_MESSAGES);
+        let task = tokio::spawn(run_event_loop(event_loop));
+
+        Ok((
+            Mqtt {
+                shared: Some(Arc::new(Shared {
+                    client: client.clone(),
+                    topic_prefix: target.topic_prefix.trim_end_matches('/').to_string(),
+                    failed: AtomicBool::new(false),
+                })),
+            },
+            MqttConnection { client, task },
+        ))
+    }
+
+    /// Publishes the records count of a poll, retained so it stays the current value
+    pub fn records(&self, index_name: &str, records: u64) {
+        self.publish(index_name, "records", true, records.to_string());
+    }
+
+    /// Publishes a breach or a recovery of the index, ignoring the other events
+    pub fn event(&self, index_name: &str, kind: &str, message: &str) {
+        if !EVENT_KINDS.contains(&kind) {
+            return;
+        }
+
+        let payload = json!({
+            "index": index_name,
+            "event": kind,
+            "message": message,
+            "timestamp": Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        });
+        self.publish(index_name, "events", false, payload.to_string());
+    }
+
+    fn publish(&self, index_name: &str, topic: &str, retain: bool, payload: String) {
+        let Some(shared) = &self.shared else {
+            return;
+        };
+
+        let topic = format!("{}/{}/{}", shared.topic_prefix, index_name, topic);
+        match shared.client.try_publish(topic, QoS::AtLeastOnce, retain, payload) {
+            Ok(()) => shared.failed.store(false, Ordering::Relaxed),
+            Err(error) => {
+                if !shared.failed.swap(true, Ordering::Relaxed) {
+                    warn!("[{}] Dropping MQTT messages until the broker is back: {}", now(), error);
+                }
+            }
+        }
+    }
+}
+
+impl MqttConnection {
+    /// Disconnects once the queued messages are sent, giving up on them when the broker is away
+    pub async fn finish(self) {
+        let _ = self.client.try_disconnect();
+        let _ = tokio::time::timeout(DISCONNECT_TIMEOUT, self.task).await;
+    }
+}
+
+/// Polls the event loop until it disconnects, which also sends the queued messages and reconnects
+async fn run_event_loop(mut event_loop: EventLoop) {
+    let mut failures = 0;
+    loop {
+        match event_loop.poll().await {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                if failures > 0 {
+                    info!("[{}] Connected to the MQTT broker again", now());
+                }
+                failures = 0;
+            }
+            Ok(Event::Outgoing(Outgoing::Disconnect)) => return,
+            Ok(_) => {}
+            Err(ConnectionError::RequestsDone) => return,
+            Err(error) => {
+                if failures == 0 {
+                    error!("[{}] Failed to reach the MQTT broker, retrying: {}", now(), error);
+                }
+                failures += 1;
+                tokio::time::sleep(Duration::from_secs(1 << failures.min(5)).min(MAX_RECONNECT_DELAY)).await;
+            }
+        }
+    }
+}
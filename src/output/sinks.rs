@@ -0,0 +1,367 @@
+use super::{pretty, LogFile, Output, OutputFormat};
+use crate::{now, EventSink, EventSinks, MonitorError, MonitorEvent};
+use chrono::{SecondsFormat, Utc};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// Time the delivery of an event to an output webhook has to complete
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Events queued for a webhook that can't keep up, new ones are dropped past it
+const MAX_QUEUED_EVENTS: usize = 10_000;
+
+/// Destination of the events of the monitors, given with `--output`
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputTarget {
+    /// Log entries on stdout, `json` or `pretty`, also given as `text`
+    Stdout(OutputFormat),
+    /// Every event as a line of the file, `json-file=<PATH>` or `text-file=<PATH>`
+    File(OutputFormat, PathBuf),
+    /// Every event POSTed as JSON to the URL, `webhook=<URL>`
+    Webhook(String),
+}
+
+impl FromStr for OutputTarget {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.split_once('=') {
+            None if value == "json" => Ok(OutputTarget::Stdout(OutputFormat::Json)),
+            None if value == "pretty" || value == "text" => Ok(OutputTarget::Stdout(OutputFormat::Pretty)),
+            Some(("json-file", path)) if !path.is_empty() => {
+                Ok(OutputTarget::File(OutputFormat::Json, path.into()))
+            }
+            Some(("text-file", path)) if !path.is_empty() => {
+                Ok(OutputTarget::File(OutputFormat::Pretty, path.into()))
+            }
+            Some(("webhook", url)) if url.starts_with("http://") || url.starts_with("https://") => {
+                Ok(OutputTarget::Webhook(url.to_string()))
+            }
+            _ => Err("expected `json`, `pretty`, `text`, `json-file=<PATH>`, `text-file=<PATH>` or \
+                `webhook=<URL>`"
+                .to_string()),
+        }
+    }
+}
+
+/// Outputs of the events besides stdout, opened once and shared by the monitors of all the indices, which do
+/// nothing without `--output`
+#[derive(Clone, Default)]
+pub struct Sinks {
+    files: Vec<(OutputFormat, PathBuf, Arc<LogFile>)>,
+    webhooks: Vec<WebhookOutput>,
+    max_errors: Option<u32>,
+}
+
+/// Handle of the task delivering the events to a webhook of `--output`, in the order they were queued
+#[derive(Clone)]
+struct WebhookOutput {
+    url: String,
+    sender: Sender<Option<Value>>,
+    /// Events dropped from a full queue since it was last reported
+    dropped: Arc<AtomicU64>,
+    /// Latest failed delivery, reported by the next event handed to the webhook
+    failure: Arc<Mutex<Option<String>>>,
+}
+
+/// Tasks delivering the events queued for the webhooks of `--output`
+pub struct SinkDelivery {
+    webhooks: Vec<(Sender<Option<Value>>, JoinHandle<()>)>,
+}
+
+impl Sinks {
+    /// Opens the files and starts delivering to the webhooks, stdout being left to the [`Output`]
+    pub fn open(
+        targets: &[OutputTarget],
+        client: reqwest::Client,
+        max_size: u64,
+        keep: usize,
+        max_errors: Option<u32>,
+    ) -> Result<(Sinks, SinkDelivery), MonitorError> {
+        let mut sinks = Sinks {
+            max_errors,
+            ..Sinks::default()
+        };
+        let mut delivery = SinkDelivery { webhooks: vec![] };
+
+        for target in targets {
+            match target {
+                OutputTarget::Stdout(_) => {}
+                OutputTarget::File(format, path) => {
+                    let file = LogFile::open(path.clone(), max_size, keep).map_err(|source| MonitorError::Io {
+                        path: path.clone(),
+                        source,
+                    })?;
+                    sinks.files.push((*format, path.clone(), Arc::new(file)));
+                }
+                OutputTarget::Webhook(url) => {
+                    let (sender, receiver) = mpsc::channel(MAX_QUEUED_EVENTS);
+                    let failure = Arc::new(Mutex::new(None));
+                    let task = tokio::spawn(deliver(client.clone(), url.clone(), receiver, failure.clone()));
+                    delivery.webhooks.push((sender.clone(), task));
+                    sinks.webhooks.push(WebhookOutput {
+                        url: url.clone(),
+                        sender,
+                        dropped: Arc::default(),
+                        failure,
+                    });
+                }
+            }
+        }
+
+        Ok((sinks, delivery))
+    }
+
+    /// Sinks of the events of the index, its log entries going to stdout through the output first
+    pub fn for_index(&self, index_name: &str, output: &Output) -> EventSinks {
+        let mut sinks = EventSinks::new()
+            .with_max_errors(self.max_errors)
+            .with_sink(StdoutSink { output: output.clone() });
+        for (format, path, file) in &self.files {
+            sinks = sinks.with_sink(FileSink {
+                index_name: index_name.to_string(),
+                format: *format,
+                path: path.clone(),
+                file: file.clone(),
+            });
+        }
+        for webhook in &self.webhooks {
+            sinks = sinks.with_sink(WebhookSink {
+                index_name: index_name.to_string(),
+                webhook: webhook.clone(),
+            });
+        }
+
+        sinks
+    }
+}
+
+impl SinkDelivery {
+    /// Delivers the events still queued, giving up on a webhook that doesn't answer in time
+    pub async fn finish(self) {
+        for (sender, task) in self.webhooks {
+            let _ = tokio::time::timeout(WEBHOOK_TIMEOUT * 2, async {
+                let _ = sender.send(None).await;
+                let _ = task.await;
+            })
+            .await;
+        }
+    }
+}
+
+/// Log entries printed on stdout and copied to `--log-file`, the other events being printed by the monitor
+struct StdoutSink {
+    output: Output,
+}
+
+impl EventSink for StdoutSink {
+    fn name(&self) -> String {
+        "stdout".to_string()
+    }
+
+    fn handle(&mut self, event: &MonitorEvent) -> Result<(), MonitorError> {
+        if let MonitorEvent::LogEntry(log) = event {
+            self.output.print_log(log);
+        }
+
+        Ok(())
+    }
+}
+
+/// Every event of an index as a line of a `--output` file, which reports failing writes itself
+struct FileSink {
+    index_name: String,
+    format: OutputFormat,
+    path: PathBuf,
+    file: Arc<LogFile>,
+}
+
+impl EventSink for FileSink {
+    fn name(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn handle(&mut self, event: &MonitorEvent) -> Result<(), MonitorError> {
+        let line = match self.format {
+            OutputFormat::Json => event_json(&self.index_name, event).map(|event| event.to_string()),
+            OutputFormat::Pretty => event_text(&self.index_name, event),
+        };
+        if let Some(line) = line {
+            self.file.write_line(&line);
+        }
+
+        Ok(())
+    }
+}
+
+/// Every event of an index queued for a webhook of `--output`
+struct WebhookSink {
+    index_name: String,
+    webhook: WebhookOutput,
+}
+
+impl EventSink for WebhookSink {
+    fn name(&self) -> String {
+        self.webhook.url.clone()
+    }
+
+    fn handle(&mut self, event: &MonitorEvent) -> Result<(), MonitorError> {
+        if let Some(event) = event_json(&self.index_name, event) {
+            self.webhook.queue(event);
+        }
+
+        match self.webhook.failure.lock().unwrap().take() {
+            Some(failure) => Err(MonitorError::Sink(failure)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl WebhookOutput {
+    /// Queues the event for the delivery task, dropping it with a warning when the webhook is too far behind
+    fn queue(&self, event: Value) {
+        match self.sender.try_send(Some(event)) {
+            Ok(()) => {
+                let dropped = self.dropped.swap(0, Ordering::Relaxed);
+                if dropped > 0 {
+                    warn!("[{}] Dropped {} events for {} while its queue was full", now(), dropped, self.url);
+                }
+            }
+            Err(TrySendError::Full(_)) => {
+                if self.dropped.fetch_add(1, Ordering::Relaxed) == 0 {
+                    warn!("[{}] Queue of {} is full, dropping events until it drains", now(), self.url);
+                }
+            }
+            Err(TrySendError::Closed(_)) => {}
+        }
+    }
+}
+
+/// POSTs the queued events one at a time until told to finish, keeping the latest failure for the sinks
+async fn deliver(
+    client: reqwest::Client,
+    url: String,
+    mut receiver: Receiver<Option<Value>>,
+    failure: Arc<Mutex<Option<String>>>,
+) {
+    while let Some(Some(event)) = receiver.recv().await {
+        let result = client
+            .post(&url)
+            .timeout(WEBHOOK_TIMEOUT)
+            .json(&event)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        if let Err(error) = result {
+            *failure.lock().unwrap() = Some(format!("Failed to deliver an event: {error}"));
+        }
+    }
+
+    info!("[{}] Stopped delivering the events to {}", now(), url);
+}
+
+/// Event as a JSON object with its `type`, the index and the time it was handled at, `None` for the outcome of
+/// a poll, which the events before it cover, and for the checks and the pacing of a run
+fn event_json(index_name: &str, event: &MonitorEvent) -> Option<Value> {
+    let timestamp = Utc::now();
+    let event = match event {
+        MonitorEvent::CountSampled(records) => json!({
+            "type": "sample",
+            "index": index_name,
+            "expected_records": records.expected_records,
+            "current_records": records.current_records,
+            "timestamp": timestamp,
+        }),
+        MonitorEvent::BreachStarted { records, breach } => json!({
+            "type": "breach",
+            "index": index_name,
+            "expected_records": records.expected_records,
+            "current_records": records.current_records,
+            "delta": records.delta(),
+            "breach": breach.to_string(),
+            "timestamp": timestamp,
+        }),
+        MonitorEvent::BreachResolved(records) => json!({
+            "type": "recovery",
+            "index": index_name,
+            "expected_records": records.expected_records,
+            "current_records": records.current_records,
+            "delta": records.delta(),
+            "timestamp": timestamp,
+        }),
+        MonitorEvent::LogEntry(log) => json!({
+            "type": "log",
+            "index": index_name,
+            "log": log,
+        }),
+        MonitorEvent::Error(error) => json!({
+            "type": "error",
+            "index": index_name,
+            "error": error,
+            "timestamp": timestamp,
+        }),
+        _ => return None,
+    };
+
+    Some(event)
+}
+
+/// `[2024-05-02T10:14:03Z] products: 1200 records, expected 1500, difference -300 is below ...`, the log
+/// entries in the pretty format after the index name, `None` like [`event_json`]
+fn event_text(index_name: &str, event: &MonitorEvent) -> Option<String> {
+    let timestamp = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+    let line = match event {
+        MonitorEvent::CountSampled(records) => format!(
+            "[{}] {}: {} records, expected {}",
+            timestamp, index_name, records.current_records, records.expected_records
+        ),
+        MonitorEvent::BreachStarted { records, breach } => format!(
+            "[{}] {}: {} records, expected {}, {}",
+            timestamp, index_name, records.current_records, records.expected_records, breach
+        ),
+        MonitorEvent::BreachResolved(records) => format!(
+            "[{}] {}: recovered with {} records, expected {}",
+            timestamp, index_name, records.current_records, records.expected_records
+        ),
+        MonitorEvent::LogEntry(log) => format!("{}: {}", index_name, pretty(log)),
+        MonitorEvent::Error(error) => format!("[{timestamp}] {index_name}: poll failed: {error}"),
+        _ => return None,
+    };
+
+    Some(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_past_the_queue_limit_are_dropped_and_counted() {
+        let (sender, mut receiver) = mpsc::channel(MAX_QUEUED_EVENTS);
+        let webhook = WebhookOutput {
+            url: "https://example.com/hook".to_string(),
+            sender,
+            dropped: Arc::default(),
+            failure: Arc::default(),
+        };
+
+        for _ in 0..MAX_QUEUED_EVENTS + 2 {
+            webhook.queue(json!({ "type": "sample" }));
+        }
+        assert_eq!(webhook.dropped.load(Ordering::Relaxed), 2);
+
+        // Once the webhook catches up the next event is queued and the dropped ones reported
+        receiver.try_recv().unwrap();
+        webhook.queue(json!({ "type": "sample" }));
+        assert_eq!(webhook.dropped.load(Ordering::Relaxed), 0);
+        assert_eq!(receiver.len(), MAX_QUEUED_EVENTS);
+    }
+}
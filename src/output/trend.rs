@@ -0,0 +1,64 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+/// Levels of a sparkline from the lowest count to the highest
+const LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+/// Levels of `--ascii`, for terminals without unicode
+const ASCII_LEVELS: [char; 8] = ['_', '.', '-', '~', '=', '+', '*', '#'];
+
+/// Latest records counts of the monitored indices kept in memory, whether or not they also go to the history
+/// database, to show their trend next to the counts
+#[derive(Clone, Default)]
+pub struct Trend {
+    samples: Arc<Mutex<HashMap<String, VecDeque<u64>>>>,
+    /// Counts kept for each index, none when it is 0
+    window: usize,
+    ascii: bool,
+}
+
+impl Trend {
+    pub fn new(window: usize, ascii: bool) -> Self {
+        Trend {
+            window,
+            ascii,
+            ..Trend::default()
+        }
+    }
+
+    /// Keeps the count of a poll, dropping the oldest one past the window
+    pub fn record(&self, index_name: &str, records: u64) {
+        if self.window == 0 {
+            return;
+        }
+
+        let mut samples = self.samples.lock().unwrap();
+        let samples = samples.entry(index_name.to_string()).or_default();
+        if samples.len() == self.window {
+            samples.pop_front();
+        }
+        samples.push_back(records);
+    }
+
+    /// `▁▂▃▅▇ 1180..1500` for the kept counts of the index, `None` before there are two of them
+    pub fn sparkline(&self, index_name: &str) -> Option<String> {
+        let samples = self.samples.lock().unwrap();
+        let samples = samples.get(index_name).filter(|samples| samples.len() > 1)?;
+
+        let min = *samples.iter().min()?;
+        let max = *samples.iter().max()?;
+        let levels = match self.ascii {
+            true => ASCII_LEVELS,
+            false => LEVELS,
+        };
+        let line: String = samples
+            .iter()
+            .map(|&records| match max - min {
+                // Equal counts are a flat line in the middle rather than at the bottom, which reads as a drop
+                0 => levels[levels.len() / 2],
+                range => levels[((records - min) as u128 * (levels.len() - 1) as u128 / range as u128) as usize],
+            })
+            .collect();
+
+        Some(format!("{line} {min}..{max}"))
+    }
+}
@@ -0,0 +1,173 @@
+use crate::metrics::{api_call_type, MetricsBackend};
+use crate::{now, Percentiles};
+use chrono::{NaiveDate, Utc};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Time the daily API calls are projected from, the latest calls telling the rate of the current polling
+const PROJECTION_WINDOW: Duration = Duration::from_secs(3600);
+/// Shortest time the projection spreads the calls over, so the requests of the first polls don't project a
+/// burst into a whole day
+const MIN_PROJECTION_TIME: Duration = Duration::from_secs(60);
+const DAY: Duration = Duration::from_secs(86_400);
+
+/// Algolia API calls made by all the monitors, counted by their [`api_call_type`] as a metrics backend
+///
+/// With `--max-daily-operations`, a projection of the calls of a day past the budget stretches the delay
+/// between the polls by as much as it goes over, until the polls at their usual delay fit in the budget again.
+/// The projection goes by the calls of the latest hour as if they were made at the usual delay, so stretching
+/// the delay doesn't bring it back under the budget.
+#[derive(Clone)]
+pub struct ApiUsage {
+    shared: Arc<Mutex<Usage>>,
+    max_daily_operations: Option<u64>,
+}
+
+struct Usage {
+    started: Instant,
+    by_type: BTreeMap<&'static str, u64>,
+    /// Day of the calls of `today`, in UTC
+    day: NaiveDate,
+    today: u64,
+    /// Time of the calls of the projection window, with the stretch of the delay they were made at
+    recent: VecDeque<(Instant, f64)>,
+    /// Factor the delays between the polls are multiplied by to stay within the budget
+    stretch: f64,
+}
+
+impl Default for ApiUsage {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl ApiUsage {
+    pub fn new(max_daily_operations: Option<u64>) -> Self {
+        ApiUsage {
+            shared: Arc::new(Mutex::new(Usage {
+                started: Instant::now(),
+                by_type: BTreeMap::new(),
+                day: Utc::now().date_naive(),
+                today: 0,
+                recent: VecDeque::new(),
+                stretch: 1.0,
+            })),
+            max_daily_operations,
+        }
+    }
+
+    /// Calls made since the start, by type
+    pub fn calls(&self) -> BTreeMap<&'static str, u64> {
+        self.shared.lock().unwrap().by_type.clone()
+    }
+
+    /// Calls made since midnight UTC
+    pub fn today(&self) -> u64 {
+        let mut usage = self.shared.lock().unwrap();
+        usage.roll_day();
+        usage.today
+    }
+
+    /// Calls a day would take at the usual delays, going by the latest hour
+    pub fn projected_daily(&self) -> u64 {
+        self.shared.lock().unwrap().projected_daily() as u64
+    }
+
+    /// `1234 API calls: 800 query, 400 logs, 34 other, 56 today, about 2345 a day at the usual delays`
+    pub fn describe(&self) -> String {
+        let calls = self.calls();
+        let types: Vec<_> = calls.iter().map(|(call_type, count)| format!("{count} {call_type}")).collect();
+        format!(
+            "{} API calls{}, {} today, about {} a day at the usual delays",
+            calls.values().sum::<u64>(),
+            match types.is_empty() {
+                true => String::new(),
+                false => format!(": {}", types.join(", ")),
+            },
+            self.today(),
+            self.projected_daily()
+        )
+    }
+
+    /// Delay until the next poll of an index, stretched when the projection goes over the daily budget
+    pub fn stretch(&self, delay: Duration) -> Duration {
+        let Some(max_daily_operations) = self.max_daily_operations else {
+            return delay;
+        };
+
+        let mut usage = self.shared.lock().unwrap();
+        let projected = usage.projected_daily();
+        let stretch = (projected / max_daily_operations as f64).max(1.0);
+        // Only a change of a tenth is told, the projection moving a little with every call
+        let previous = std::mem::replace(&mut usage.stretch, stretch);
+        if stretch > 1.0 && (stretch - previous).abs() >= previous / 10.0 {
+            warn!(
+                "[{}] About {} API calls a day at the usual delays, over --max-daily-operations {}, polling \
+                 {:.1} times less often",
+                now(),
+                projected as u64,
+                max_daily_operations,
+                stretch
+            );
+        } else if stretch == 1.0 && previous > 1.0 {
+            info!(
+                "[{}] About {} API calls a day, within --max-daily-operations {}, back to the usual delays",
+                now(),
+                projected as u64,
+                max_daily_operations
+            );
+        }
+
+        delay.mul_f64(stretch)
+    }
+}
+
+impl Usage {
+    /// Starts counting the calls of the day over at midnight UTC
+    fn roll_day(&mut self) {
+        let day = Utc::now().date_naive();
+        if day != self.day {
+            self.day = day;
+            self.today = 0;
+        }
+    }
+
+    fn projected_daily(&mut self) -> f64 {
+        let window_start = Instant::now().checked_sub(PROJECTION_WINDOW);
+        while let Some(&(called, _)) = self.recent.front() {
+            match window_start {
+                Some(start) if called < start => self.recent.pop_front(),
+                _ => break,
+            };
+        }
+
+        let time = self.started.elapsed().clamp(MIN_PROJECTION_TIME, PROJECTION_WINDOW);
+        let calls: f64 = self.recent.iter().map(|&(_, stretch)| stretch).sum();
+        calls * DAY.as_secs_f64() / time.as_secs_f64()
+    }
+}
+
+impl MetricsBackend for ApiUsage {
+    fn records(&self, _index_name: &str, _records: u64, _expected_records: u64) {}
+
+    fn data_size(&self, _index_name: &str, _data_size: u64) {}
+
+    fn pending_tasks(&self, _index_name: &str, _pending_tasks: u64) {}
+
+    fn latency(&self, _index_name: &str, _latency: &Percentiles) {}
+
+    fn poll_error(&self, _index_name: &str) {}
+
+    fn threshold_breach(&self, _index_name: &str) {}
+
+    fn api_response(&self, endpoint: &str, _duration: Duration) {
+        let mut usage = self.shared.lock().unwrap();
+        *usage.by_type.entry(api_call_type(endpoint)).or_default() += 1;
+        usage.roll_day();
+        usage.today += 1;
+        let stretch = usage.stretch;
+        usage.recent.push_back((Instant::now(), stretch));
+    }
+}
@@ -0,0 +1,65 @@
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Token bucket spacing out the requests of an application, shared by the clients of its keys
+///
+/// A full bucket lets a second worth of requests go at once, the following ones wait for their turn. The turn
+/// is taken as soon as a request asks for it, so the waiting requests go in the order they came.
+#[derive(Debug)]
+pub struct RequestPacer {
+    per_second: f64,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// Requests that may go right away, below zero by the requests waiting for their turn
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RequestPacer {
+    /// Pacer letting `per_second` requests go every second, which has to be above zero
+    pub fn new(per_second: f64) -> Self {
+        RequestPacer {
+            per_second,
+            bucket: Mutex::new(Bucket {
+                tokens: per_second.max(1.0),
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn per_second(&self) -> f64 {
+        self.per_second
+    }
+
+    /// Takes the turn of a request, returning how long it waits for it
+    pub fn reserve(&self) -> Duration {
+        let mut bucket = self.refill();
+        bucket.tokens -= 1.0;
+        self.wait(bucket.tokens)
+    }
+
+    /// How long a request asking for its turn now would wait, which tells how far behind the requests are
+    pub fn backlog(&self) -> Duration {
+        let bucket = self.refill();
+        self.wait(bucket.tokens - 1.0)
+    }
+
+    fn refill(&self) -> MutexGuard<'_, Bucket> {
+        let mut bucket = self.bucket.lock().unwrap();
+        let now = Instant::now();
+        let refilled = now.duration_since(bucket.updated).as_secs_f64() * self.per_second;
+        bucket.tokens = (bucket.tokens + refilled).min(self.per_second.max(1.0));
+        bucket.updated = now;
+        bucket
+    }
+
+    fn wait(&self, tokens: f64) -> Duration {
+        match tokens < 0.0 {
+            true => Duration::from_secs_f64(-tokens / self.per_second),
+            false => Duration::ZERO,
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use rand::Rng;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Factor the interval grows by after each stable poll, backing off gradually
+const BACKOFF_FACTOR: f64 = 1.5;
+
+/// Shortest interval the jitter may bring the polls to, like the shortest `--delay`
+const MIN_DELAY: Duration = Duration::from_secs(1);
+
+/// Randomization of the delay between polls, so monitors started together don't poll Algolia in bursts
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Jitter {
+    /// Up to this percentage of the delay, such as `10%`
+    Percent(f64),
+    /// Up to this duration, such as `3s`
+    Fixed(Duration),
+}
+
+impl Jitter {
+    /// Largest shift of the delay either way
+    fn spread(&self, delay: Duration) -> Duration {
+        match *self {
+            Jitter::Percent(percent) => delay.mul_f64(percent / 100.0),
+            Jitter::Fixed(spread) => spread,
+        }
+    }
+}
+
+impl FromStr for Jitter {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if let Some(percent) = value.strip_suffix('%') {
+            return match percent.trim().parse::<f64>() {
+                Ok(percent) if (0.0..100.0).contains(&percent) => Ok(Jitter::Percent(percent)),
+                _ => Err(format!("invalid jitter `{value}`, expected a percentage below 100%")),
+            };
+        }
+
+        match humantime::parse_duration(value) {
+            Ok(spread) => Ok(Jitter::Fixed(spread)),
+            Err(error) => Err(format!("invalid jitter `{value}`, expected a percentage or a duration: {error}")),
+        }
+    }
+}
+
+/// Interval between the polls of an index, backing off up to a longest one while the index is stable and
+/// randomized by the jitter
+#[derive(Debug, Clone)]
+pub struct PollDelay {
+    base: Duration,
+    /// Longest interval of the adaptive mode, the interval being fixed without it
+    max: Option<Duration>,
+    jitter: Option<Jitter>,
+    current: Duration,
+    /// How many times faster than the interval the polls go, above 1 when replaying
+    speed: f64,
+}
+
+impl PollDelay {
+    pub fn new(base: Duration, max: Option<Duration>, jitter: Option<Jitter>) -> Self {
+        PollDelay {
+            base,
+            max: max.map(|max| max.max(base)),
+            jitter,
+            current: base,
+            speed: 1.0,
+        }
+    }
+
+    /// Shortens the time between the polls by the factor, such as to go through a replay faster
+    pub fn with_speed(mut self, speed: f64) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Interval the delay backs off from, which a poll the pacing holds back is skipped by
+    pub fn base(&self) -> Duration {
+        self.base
+    }
+
+    /// Longest time between two polls, for telling when an index went too long without one
+    pub fn longest(base: Duration, max: Option<Duration>, jitter: Option<Jitter>) -> Duration {
+        let longest = max.map_or(base, |max| max.max(base));
+        longest + jitter.map_or(Duration::ZERO, |jitter| jitter.spread(longest))
+    }
+
+    /// Backs the interval off after a poll that found the index as it was, when adaptive
+    pub fn stable(&mut self) {
+        if let Some(max) = self.max {
+            self.current = self.current.mul_f64(BACKOFF_FACTOR).min(max);
+        }
+    }
+
+    /// Goes back to the base interval after a poll that found a change or failed
+    pub fn reset(&mut self) {
+        self.current = self.base;
+    }
+
+    /// Time from the start of a poll to the next one, picked anew each time with the jitter
+    pub fn next(&self) -> Duration {
+        self.jittered().div_f64(self.speed)
+    }
+
+    fn jittered(&self) -> Duration {
+        let Some(jitter) = self.jitter else {
+            return self.current;
+        };
+
+        let spread = jitter.spread(self.current).as_secs_f64();
+        if spread == 0.0 {
+            return self.current;
+        }
+        let shift = rand::thread_rng().gen_range(-spread..=spread);
+        Duration::from_secs_f64((self.current.as_secs_f64() + shift).max(0.0)).max(MIN_DELAY.min(self.current))
+    }
+}
@@ -0,0 +1,45 @@
+use chrono::{DateTime, Local, NaiveTime, Utc};
+use chrono_tz::Tz;
+use std::str::FromStr;
+
+/// Daily window during which breaches are only printed, such as `02:30-04:30`, ending the next day when it
+/// ends before it starts
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QuietHours {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl QuietHours {
+    fn contains(&self, time: NaiveTime) -> bool {
+        match self.start <= self.end {
+            true => self.start <= time && time < self.end,
+            false => self.start <= time || time < self.end,
+        }
+    }
+}
+
+impl FromStr for QuietHours {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid quiet hours `{value}`, expected `HH:MM-HH:MM` such as `02:30-04:30`");
+        let (start, end) = value.split_once('-').ok_or_else(invalid)?;
+        let time = |time: &str| NaiveTime::parse_from_str(time.trim(), "%H:%M").map_err(|_| invalid());
+        let (start, end) = (time(start)?, time(end)?);
+        if start == end {
+            return Err(format!("invalid quiet hours `{value}`, the window starts and ends at the same time"));
+        }
+
+        Ok(QuietHours { start, end })
+    }
+}
+
+/// Whether the time falls in one of the windows, read in the timezone or in the local time without one
+pub fn is_quiet(windows: &[QuietHours], timezone: Option<Tz>, time: DateTime<Utc>) -> bool {
+    let time = match timezone {
+        Some(timezone) => time.with_timezone(&timezone).time(),
+        None => time.with_timezone(&Local).time(),
+    };
+    windows.iter().any(|window| window.contains(time))
+}
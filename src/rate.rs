@@ -0,0 +1,71 @@
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// Latest records counts with the time they were polled at, keeping `size` rates of change from them
+#[derive(Debug)]
+pub struct RateWindow {
+    samples: VecDeque<(Instant, u64)>,
+    size: usize,
+}
+
+/// Rates of change of the records count in a [`RateWindow`], in records per minute
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rates {
+    /// Rate between the two latest polls
+    pub latest: f64,
+    /// Rate from the oldest to the latest poll in the window
+    pub window: f64,
+    /// Number of rates the window rate is taken from
+    pub polls: usize,
+    /// Slowest loss between consecutive polls of a full window, `None` unless every one of them lost records
+    pub sustained_loss: Option<f64>,
+}
+
+impl RateWindow {
+    pub fn new(size: usize) -> Self {
+        RateWindow {
+            samples: VecDeque::with_capacity(size + 2),
+            size: size.max(1),
+        }
+    }
+
+    pub fn record(&mut self, polled_at: Instant, records: u64) {
+        self.samples.push_back((polled_at, records));
+        if self.samples.len() > self.size + 1 {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Forgets the samples, such as when the count starts over after the index got recreated
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Rates from the elapsed time between the polls, `None` before the second one
+    pub fn rates(&self) -> Option<Rates> {
+        let rates: Vec<_> = self
+            .samples
+            .iter()
+            .zip(self.samples.iter().skip(1))
+            .map(|(from, to)| per_minute(*from, *to))
+            .collect();
+        let latest = *rates.last()?;
+        let sustained_loss = (rates.len() == self.size && rates.iter().all(|rate| *rate < 0.0))
+            .then(|| rates.iter().map(|rate| -rate).fold(f64::INFINITY, f64::min));
+
+        Some(Rates {
+            latest,
+            window: per_minute(*self.samples.front()?, *self.samples.back()?),
+            polls: rates.len(),
+            sustained_loss,
+        })
+    }
+}
+
+fn per_minute((from_time, from_records): (Instant, u64), (to_time, to_records): (Instant, u64)) -> f64 {
+    let minutes = to_time.duration_since(from_time).as_secs_f64() / 60.0;
+    match minutes > 0.0 {
+        true => (to_records as f64 - from_records as f64) / minutes,
+        false => 0.0,
+    }
+}
@@ -0,0 +1,505 @@
+use crate::algolia::{AlgoliaLogEntry, BrowseCount, FacetValues, IndexItems, IndexStats, LogType, SampleStrategy};
+use crate::error::MonitorError;
+use crate::now;
+use crate::source::{DataSource, LogSource, RecordSource};
+use chrono::{DateTime, Utc};
+use futures::future::{ready, BoxFuture};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{LineWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::warn;
+
+/// Response of a source written by a [`Recorder`] as a line of JSON
+#[derive(Debug, Serialize, Deserialize)]
+struct Recorded {
+    /// When the response came
+    at: DateTime<Utc>,
+    app_id: String,
+    index: String,
+    /// Method of the source that got the response, such as `index_stats`
+    call: String,
+    /// Argument telling the responses of a call for the same index apart, such as the facet attribute
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    arg: Option<String>,
+    #[serde(flatten)]
+    outcome: Outcome,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Outcome {
+    Ok(Value),
+    Error(RecordedError),
+}
+
+impl Outcome {
+    fn of<T: Serialize>(result: &Result<T, MonitorError>) -> Self {
+        match result {
+            Ok(value) => Outcome::Ok(serde_json::to_value(value).unwrap_or_default()),
+            Err(error) => Outcome::Error(error.into()),
+        }
+    }
+}
+
+/// Failure of a recorded call, keeping what the monitor tells apart, such as an index that isn't found
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum RecordedError {
+    Api { status: u16, message: String },
+    IndexNotFound { index_name: String },
+    Timeout { seconds: f64 },
+    Other { message: String },
+}
+
+impl From<&MonitorError> for RecordedError {
+    fn from(error: &MonitorError) -> Self {
+        match error {
+            MonitorError::Api { status, message } => RecordedError::Api {
+                status: status.as_u16(),
+                message: message.clone(),
+            },
+            MonitorError::IndexNotFound(index_name) => RecordedError::IndexNotFound {
+                index_name: index_name.clone(),
+            },
+            MonitorError::Timeout(timeout) => RecordedError::Timeout {
+                seconds: timeout.as_secs_f64(),
+            },
+            error => RecordedError::Other {
+                message: error.to_string(),
+            },
+        }
+    }
+}
+
+impl From<RecordedError> for MonitorError {
+    fn from(error: RecordedError) -> Self {
+        match error {
+            RecordedError::Api { status, message } => match StatusCode::from_u16(status) {
+                Ok(status) => MonitorError::Api { status, message },
+                Err(_) => MonitorError::Replayed(format!("Algolia API error {status}: {message}")),
+            },
+            RecordedError::IndexNotFound { index_name } => MonitorError::IndexNotFound(index_name),
+            RecordedError::Timeout { seconds } => {
+                MonitorError::Timeout(Duration::from_secs_f64(seconds.max(0.0)))
+            }
+            RecordedError::Other { message } => MonitorError::Replayed(message),
+        }
+    }
+}
+
+/// File the responses of [`RecordingSource`]s are written to as JSON lines, for a [`Replay`] to hand them to
+/// a monitor again
+///
+/// Every response is written as it comes, so the file has all of them even when the monitor gets killed.
+#[derive(Clone)]
+pub struct Recorder {
+    path: PathBuf,
+    file: Arc<Mutex<LineWriter<File>>>,
+}
+
+impl Recorder {
+    /// Recorder writing to the file, replacing what it held
+    pub fn create(path: &Path) -> Result<Self, MonitorError> {
+        let file = File::create(path).map_err(|source| MonitorError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        Ok(Recorder {
+            path: path.to_path_buf(),
+            file: Arc::new(Mutex::new(LineWriter::new(file))),
+        })
+    }
+
+    /// Source recording the responses another one gets for the indices of the application
+    pub fn source(&self, app_id: &str, source: Arc<dyn DataSource>) -> RecordingSource {
+        RecordingSource {
+            source,
+            app_id: app_id.to_string(),
+            recorder: self.clone(),
+        }
+    }
+
+    /// Writes the response, a failure only being logged as the monitor goes on without the recording
+    fn write(&self, recorded: &Recorded) {
+        let mut line = serde_json::to_string(recorded).unwrap_or_default();
+        line.push('\n');
+        if let Err(error) = self.file.lock().unwrap().write_all(line.as_bytes()) {
+            warn!("[{}] Failed to record a response to {}: {}", now(), self.path.display(), error);
+        }
+    }
+}
+
+/// Source handing out the responses of another one, each written to the file of its [`Recorder`] on the way
+pub struct RecordingSource {
+    source: Arc<dyn DataSource>,
+    app_id: String,
+    recorder: Recorder,
+}
+
+impl RecordingSource {
+    fn record<T: Serialize>(
+        &self,
+        index_name: &str,
+        call: &str,
+        arg: Option<String>,
+        result: Result<T, MonitorError>,
+    ) -> Result<T, MonitorError> {
+        self.recorder.write(&Recorded {
+            at: Utc::now(),
+            app_id: self.app_id.clone(),
+            index: index_name.to_string(),
+            call: call.to_string(),
+            arg,
+            outcome: Outcome::of(&result),
+        });
+
+        result
+    }
+}
+
+impl RecordSource for RecordingSource {
+    fn index_stats<'a>(&'a self, index_name: &'a str) -> BoxFuture<'a, Result<IndexStats, MonitorError>> {
+        Box::pin(async move {
+            let result = self.source.index_stats(index_name).await;
+            self.record(index_name, "index_stats", None, result)
+        })
+    }
+
+    fn filtered_stats<'a>(
+        &'a self,
+        index_name: &'a str,
+        filters: &'a str,
+    ) -> BoxFuture<'a, Result<IndexStats, MonitorError>> {
+        Box::pin(async move {
+            let result = self.source.filtered_stats(index_name, filters).await;
+            self.record(index_name, "filtered_stats", Some(filters.to_string()), result)
+        })
+    }
+
+    fn total_records<'a>(&'a self, index_name: &'a str) -> BoxFuture<'a, Result<u64, MonitorError>> {
+        Box::pin(async move {
+            let result = self.source.total_records(index_name).await;
+            self.record(index_name, "total_records", None, result)
+        })
+    }
+
+    fn facet_values<'a>(
+        &'a self,
+        index_name: &'a str,
+        attribute: &'a str,
+    ) -> BoxFuture<'a, Result<FacetValues, MonitorError>> {
+        Box::pin(async move {
+            let result = self.source.facet_values(index_name, attribute).await;
+            self.record(index_name, "facet_values", Some(attribute.to_string()), result)
+        })
+    }
+
+    fn pending_tasks<'a>(&'a self, index_name: &'a str) -> BoxFuture<'a, Result<u64, MonitorError>> {
+        Box::pin(async move {
+            let result = self.source.pending_tasks(index_name).await;
+            self.record(index_name, "pending_tasks", None, result)
+        })
+    }
+
+    fn is_task_published<'a>(
+        &'a self,
+        index_name: &'a str,
+        task_id: u64,
+    ) -> BoxFuture<'a, Result<bool, MonitorError>> {
+        Box::pin(async move {
+            let result = self.source.is_task_published(index_name, task_id).await;
+            self.record(index_name, "is_task_published", Some(task_id.to_string()), result)
+        })
+    }
+
+    fn settings<'a>(&'a self, index_name: &'a str) -> BoxFuture<'a, Result<Map<String, Value>, MonitorError>> {
+        Box::pin(async move {
+            let result = self.source.settings(index_name).await;
+            self.record(index_name, "settings", None, result)
+        })
+    }
+
+    fn object_ids<'a>(
+        &'a self,
+        index_name: &'a str,
+        filters: Option<&'a str>,
+        strategy: SampleStrategy,
+        size: u64,
+    ) -> BoxFuture<'a, Result<Vec<String>, MonitorError>> {
+        Box::pin(async move {
+            let result = self.source.object_ids(index_name, filters, strategy, size).await;
+            self.record(index_name, "object_ids", filters.map(str::to_string), result)
+        })
+    }
+
+    fn browse_count<'a>(
+        &'a self,
+        index_name: &'a str,
+        filters: Option<&'a str>,
+        max_records: u64,
+        progress: &'a mut (dyn FnMut(&BrowseCount) + Send),
+    ) -> BoxFuture<'a, Result<BrowseCount, MonitorError>> {
+        Box::pin(async move {
+            let result = self.source.browse_count(index_name, filters, max_records, progress).await;
+            self.record(index_name, "browse_count", filters.map(str::to_string), result)
+        })
+    }
+
+    fn items_count<'a>(
+        &'a self,
+        index_name: &'a str,
+        items: IndexItems,
+    ) -> BoxFuture<'a, Result<u64, MonitorError>> {
+        Box::pin(async move {
+            let result = self.source.items_count(index_name, items).await;
+            self.record(index_name, "items_count", Some(items.to_string()), result)
+        })
+    }
+
+    fn now(&self) -> DateTime<Utc> {
+        self.source.now()
+    }
+}
+
+impl LogSource for RecordingSource {
+    fn logs<'a>(
+        &'a self,
+        index_name: &'a str,
+        since: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<Vec<AlgoliaLogEntry>, MonitorError>> {
+        Box::pin(async move {
+            let result = self.source.logs(index_name, since).await;
+            self.record(index_name, "logs", None, result)
+        })
+    }
+
+    fn logs_of_type<'a>(
+        &'a self,
+        index_name: &'a str,
+        log_type: LogType,
+        since: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<Vec<AlgoliaLogEntry>, MonitorError>> {
+        Box::pin(async move {
+            let result = self.source.logs_of_type(index_name, log_type, since).await;
+            self.record(index_name, "logs", Some(log_type.to_string()), result)
+        })
+    }
+
+    // Recorded as the logs of the type, the page size only changing how many requests fetched them
+    fn logs_paged<'a>(
+        &'a self,
+        index_name: &'a str,
+        log_type: LogType,
+        since: Option<DateTime<Utc>>,
+        page_size: u32,
+    ) -> BoxFuture<'a, Result<Vec<AlgoliaLogEntry>, MonitorError>> {
+        Box::pin(async move {
+            let result = self.source.logs_paged(index_name, log_type, since, page_size).await;
+            self.record(index_name, "logs", Some(log_type.to_string()), result)
+        })
+    }
+}
+
+/// Application, index, call and argument the responses of a [`Replay`] are queued by
+type RecordedCall = (String, String, String, Option<String>);
+
+/// Responses of a [`RecordedCall`] along with the time they were recorded at, in order
+type Responses = HashMap<RecordedCall, VecDeque<(DateTime<Utc>, Outcome)>>;
+
+/// Responses read from a file written by a [`Recorder`], handed out again in the order they were recorded
+///
+/// Each call of a source gets the next response recorded for the same call, index and argument, whatever the
+/// time it was recorded at, so the monitor goes through the recording at the pace it polls. A call with no
+/// response left fails with [`MonitorError::ReplayEnded`].
+#[derive(Clone, Default)]
+pub struct Replay {
+    responses: Arc<Mutex<Responses>>,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Self, MonitorError> {
+        let content = std::fs::read_to_string(path).map_err(|source| MonitorError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        let mut responses = Responses::new();
+        for (number, line) in content.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let recorded: Recorded = serde_json::from_str(line).map_err(|error| {
+                MonitorError::InvalidArgument(format!(
+                    "Invalid recorded response on line {} of {}: {}",
+                    number + 1,
+                    path.display(),
+                    error
+                ))
+            })?;
+            let call = (recorded.app_id, recorded.index, recorded.call, recorded.arg);
+            responses.entry(call).or_default().push_back((recorded.at, recorded.outcome));
+        }
+
+        Ok(Replay {
+            responses: Arc::new(Mutex::new(responses)),
+        })
+    }
+
+    /// Queues a response after the ones recorded for the same call
+    #[cfg(any(test, feature = "testing"))]
+    pub(crate) fn push<T: Serialize>(
+        &self,
+        (app_id, index_name, call, arg): (&str, &str, &str, Option<String>),
+        result: Result<T, MonitorError>,
+    ) {
+        let outcome = Outcome::of(&result);
+        let call = (app_id.to_string(), index_name.to_string(), call.to_string(), arg);
+        self.responses.lock().unwrap().entry(call).or_default().push_back((Utc::now(), outcome));
+    }
+
+    /// Source handing out the responses recorded for the indices of the application
+    pub fn source(&self, app_id: &str) -> ReplaySource {
+        ReplaySource {
+            replay: self.clone(),
+            app_id: app_id.to_string(),
+            recorded_at: Mutex::new(None),
+        }
+    }
+}
+
+/// Source of the indices of an application answering with the responses of a [`Replay`]
+pub struct ReplaySource {
+    replay: Replay,
+    app_id: String,
+    /// Time the last response handed out was recorded at
+    recorded_at: Mutex<Option<DateTime<Utc>>>,
+}
+
+impl ReplaySource {
+    /// Next response recorded for the call, failing with the end of the replay when none is left
+    fn next<T: DeserializeOwned>(
+        &self,
+        index_name: &str,
+        call: &str,
+        arg: Option<String>,
+    ) -> Result<T, MonitorError> {
+        let recorded = (self.app_id.clone(), index_name.to_string(), call.to_string(), arg);
+        let response = self.replay.responses.lock().unwrap().get_mut(&recorded).and_then(VecDeque::pop_front);
+        let outcome = response.map(|(recorded_at, outcome)| {
+            *self.recorded_at.lock().unwrap() = Some(recorded_at);
+            outcome
+        });
+
+        match outcome {
+            Some(Outcome::Ok(value)) => Ok(serde_json::from_value(value)?),
+            Some(Outcome::Error(error)) => Err(error.into()),
+            None => Err(MonitorError::ReplayEnded {
+                index_name: index_name.to_string(),
+                call: call.to_string(),
+            }),
+        }
+    }
+}
+
+impl RecordSource for ReplaySource {
+    fn index_stats<'a>(&'a self, index_name: &'a str) -> BoxFuture<'a, Result<IndexStats, MonitorError>> {
+        Box::pin(ready(self.next(index_name, "index_stats", None)))
+    }
+
+    fn filtered_stats<'a>(
+        &'a self,
+        index_name: &'a str,
+        filters: &'a str,
+    ) -> BoxFuture<'a, Result<IndexStats, MonitorError>> {
+        Box::pin(ready(self.next(index_name, "filtered_stats", Some(filters.to_string()))))
+    }
+
+    fn total_records<'a>(&'a self, index_name: &'a str) -> BoxFuture<'a, Result<u64, MonitorError>> {
+        Box::pin(ready(self.next(index_name, "total_records", None)))
+    }
+
+    fn facet_values<'a>(
+        &'a self,
+        index_name: &'a str,
+        attribute: &'a str,
+    ) -> BoxFuture<'a, Result<FacetValues, MonitorError>> {
+        Box::pin(ready(self.next(index_name, "facet_values", Some(attribute.to_string()))))
+    }
+
+    fn pending_tasks<'a>(&'a self, index_name: &'a str) -> BoxFuture<'a, Result<u64, MonitorError>> {
+        Box::pin(ready(self.next(index_name, "pending_tasks", None)))
+    }
+
+    fn is_task_published<'a>(
+        &'a self,
+        index_name: &'a str,
+        task_id: u64,
+    ) -> BoxFuture<'a, Result<bool, MonitorError>> {
+        Box::pin(ready(self.next(index_name, "is_task_published", Some(task_id.to_string()))))
+    }
+
+    fn settings<'a>(&'a self, index_name: &'a str) -> BoxFuture<'a, Result<Map<String, Value>, MonitorError>> {
+        Box::pin(ready(self.next(index_name, "settings", None)))
+    }
+
+    fn object_ids<'a>(
+        &'a self,
+        index_name: &'a str,
+        filters: Option<&'a str>,
+        _strategy: SampleStrategy,
+        _size: u64,
+    ) -> BoxFuture<'a, Result<Vec<String>, MonitorError>> {
+        Box::pin(ready(self.next(index_name, "object_ids", filters.map(str::to_string))))
+    }
+
+    fn browse_count<'a>(
+        &'a self,
+        index_name: &'a str,
+        filters: Option<&'a str>,
+        _max_records: u64,
+        progress: &'a mut (dyn FnMut(&BrowseCount) + Send),
+    ) -> BoxFuture<'a, Result<BrowseCount, MonitorError>> {
+        let count = self.next(index_name, "browse_count", filters.map(str::to_string));
+        if let Ok(count) = &count {
+            progress(count);
+        }
+        Box::pin(ready(count))
+    }
+
+    fn items_count<'a>(
+        &'a self,
+        index_name: &'a str,
+        items: IndexItems,
+    ) -> BoxFuture<'a, Result<u64, MonitorError>> {
+        Box::pin(ready(self.next(index_name, "items_count", Some(items.to_string()))))
+    }
+
+    /// Time the last response handed out was recorded at, the current time before the first one
+    fn now(&self) -> DateTime<Utc> {
+        self.recorded_at.lock().unwrap().unwrap_or_else(Utc::now)
+    }
+}
+
+impl LogSource for ReplaySource {
+    fn logs<'a>(
+        &'a self,
+        index_name: &'a str,
+        _since: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<Vec<AlgoliaLogEntry>, MonitorError>> {
+        Box::pin(ready(self.next(index_name, "logs", None)))
+    }
+
+    fn logs_of_type<'a>(
+        &'a self,
+        index_name: &'a str,
+        log_type: LogType,
+        _since: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<Vec<AlgoliaLogEntry>, MonitorError>> {
+        Box::pin(ready(self.next(index_name, "logs", Some(log_type.to_string()))))
+    }
+}
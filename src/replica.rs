@@ -0,0 +1,11 @@
+use serde::Serialize;
+
+/// Records count of a replica, compared to the one of its primary index
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReplicaCount {
+    pub index_name: String,
+    /// `None` when the replica doesn't exist
+    pub records: Option<u64>,
+    /// Whether the count is further from the primary's than the allowed delta, or the replica is missing
+    pub out_of_sync: bool,
+}
@@ -0,0 +1,61 @@
+use crate::{redact_keys, MonitorError};
+use sentry::protocol::Event;
+use sentry::types::Dsn;
+use sentry::{ClientInitGuard, ClientOptions, Level};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Arc;
+
+/// Sends the failures that stop the monitor of an index, and the panics, to Sentry
+///
+/// Nothing is reported once the returned guard is dropped, which also waits for the last events to go out.
+pub fn init(dsn: &str) -> Result<ClientInitGuard, String> {
+    let dsn: Dsn = dsn.parse().map_err(|error| format!("Invalid --sentry-dsn: {error}"))?;
+
+    // The panic integration comes with the default ones, reporting a panic before the task or the process ends
+    Ok(sentry::init(ClientOptions {
+        dsn: Some(dsn),
+        release: sentry::release_name!(),
+        attach_stacktrace: true,
+        before_send: Some(Arc::new(|event| Some(redact_event(event)))),
+        ..ClientOptions::default()
+    }))
+}
+
+/// Masks the API keys in the messages of an event, such as the one of a panic
+fn redact_event(mut event: Event<'static>) -> Event<'static> {
+    if let Some(message) = &mut event.message {
+        *message = redact_keys(message);
+    }
+    for exception in &mut event.exception.values {
+        if let Some(value) = &mut exception.value {
+            *value = redact_keys(value);
+        }
+    }
+    event
+}
+
+/// Reports the error the monitor of an index gave up on
+pub fn capture_error(app_id: &str, index_name: &str, polls: u64, error: &MonitorError) {
+    sentry::with_scope(|scope| set_tags(scope, app_id, index_name, polls), || sentry::capture_error(error));
+}
+
+/// Reports a breach as a warning message
+pub fn capture_breach(app_id: &str, index_name: &str, polls: u64, message: &str) {
+    sentry::with_scope(
+        |scope| set_tags(scope, app_id, index_name, polls),
+        || sentry::capture_message(message, Level::Warning),
+    );
+}
+
+fn set_tags(scope: &mut sentry::Scope, app_id: &str, index_name: &str, polls: u64) {
+    scope.set_tag("app_id", hash(app_id));
+    scope.set_tag("index_name", index_name);
+    scope.set_tag("poll", polls);
+}
+
+/// Application id as 16 hex digits, so events of an application can be grouped without it being sent
+fn hash(app_id: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    app_id.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
@@ -0,0 +1,400 @@
+use crate::algolia::IndexItems;
+use crate::error::MonitorError;
+use crate::log_filter::LogFilter;
+use crate::monitor::{Monitor, MonitorEvent, Poll};
+use crate::now;
+use crate::poll_delay::PollDelay;
+use crate::source::DataSource;
+use crate::threshold::Thresholds;
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::time::{sleep_until, Instant};
+use tracing::debug;
+
+/// Interval between the checks of the settings, synonyms and rules of [`Monitor::run`] by default
+pub(crate) const DEFAULT_SETTINGS_INTERVAL: Duration = Duration::from_secs(300);
+
+pub(crate) type StretchCallback = Box<dyn Fn(Duration) -> Duration + Send>;
+pub(crate) type PacingCallback = Box<dyn Fn() -> Duration + Send>;
+
+/// Message to a running [`Monitor::run`], sent through the channel given to
+/// [`MonitorBuilder::with_controls`](crate::MonitorBuilder::with_controls)
+///
+/// The run takes them while it waits for the next poll, so the ones sent during a poll apply after it.
+pub enum Control {
+    /// Polls right away instead of at the end of the delay
+    PollNow,
+    /// Ends the run, as does dropping the sender
+    Stop,
+    /// Replaces the expected records count, see [`Monitor::set_expected_records`]
+    ExpectedRecords(u64),
+    /// Goes on with the settings of a reload of the configuration, the delay of the coming poll counting from
+    /// the start of the previous one again
+    Reload(Box<Reload>),
+}
+
+/// Settings [`Monitor::run`] goes on with after a reload of the configuration
+pub struct Reload {
+    pub thresholds: Thresholds,
+    pub log_filter: LogFilter,
+    pub poll_delay: PollDelay,
+}
+
+/// Why [`Monitor::run`] ended
+#[derive(Debug)]
+pub enum RunEnd {
+    /// A [`Control::Stop`] came, or the sender of the controls was dropped
+    Stopped,
+    /// The most polls were made or the end time passed
+    Finished,
+    /// A replayed source ran out of recorded responses, the poll that found it being left out of the run
+    ReplayEnded(MonitorError),
+    /// A poll failed with a permanent error, or too many in a row failed
+    GaveUp,
+}
+
+/// Check [`Monitor::run`] makes besides polling the records count
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Check {
+    Tasks,
+    Settings,
+    Items(IndexItems),
+    Churn,
+    /// Exact count browsing the index at the start of a breach
+    Verification,
+}
+
+/// End of a poll of [`Monitor::run`], after the events of the poll and its checks
+#[derive(Debug, Clone)]
+pub struct PollEnd {
+    /// Number of polls of the run so far, this one included
+    pub polls: u64,
+    /// Time the poll and its checks took
+    pub elapsed: Duration,
+    /// Records count the poll found, `None` when it failed
+    pub records: Option<u64>,
+    /// Error the poll or one of its checks failed with
+    pub error: Option<Arc<MonitorError>>,
+    /// Number of polls in a row that failed, this one included
+    pub consecutive_errors: u32,
+    /// Whether the run gives up on the index after the poll
+    pub gives_up: bool,
+    /// Timestamp of the newest log entry handed out so far
+    pub logs_since: Option<DateTime<Utc>>,
+}
+
+/// Count of the polls of an index failing in a row, which a run gives up after
+///
+/// A deleted index is usually about to be recreated by a reindex, so it isn't counted as a failure.
+#[derive(Debug, Clone, Copy)]
+pub struct Failures {
+    consecutive: u32,
+    max: Option<u32>,
+}
+
+impl Failures {
+    /// Failures giving up after `max` consecutive ones, or only on permanent errors when `None`
+    pub fn new(max: Option<u32>) -> Self {
+        Failures { consecutive: 0, max }
+    }
+
+    /// Number of polls in a row that failed
+    pub fn consecutive(&self) -> u32 {
+        self.consecutive
+    }
+
+    pub fn succeeded(&mut self) {
+        self.consecutive = 0;
+    }
+
+    /// Counts the failed poll, returning whether to give up on the index
+    pub fn failed(&mut self, error: &MonitorError) -> bool {
+        if let MonitorError::IndexDeleted(_) = error {
+            return false;
+        }
+        self.consecutive += 1;
+
+        error.is_permanent() || self.max.is_some_and(|max| self.consecutive >= max)
+    }
+}
+
+/// Options of [`Monitor::run`], set through its builder
+pub(crate) struct RunOptions {
+    pub(crate) poll_delay: PollDelay,
+    pub(crate) check_errors: bool,
+    pub(crate) check_tasks: bool,
+    pub(crate) settings_interval: Duration,
+    /// Most records counted by browsing at the start of a breach, `None` unless verifying them
+    pub(crate) browse_max_records: Option<u64>,
+    pub(crate) max_consecutive_errors: Option<u32>,
+    pub(crate) max_polls: Option<u64>,
+    pub(crate) until: Option<DateTime<Utc>>,
+    pub(crate) stretch: Option<StretchCallback>,
+    pub(crate) pacing: Option<PacingCallback>,
+    pub(crate) controls: Option<UnboundedReceiver<Control>>,
+}
+
+impl RunOptions {
+    pub(crate) fn new(interval: Duration) -> Self {
+        RunOptions {
+            poll_delay: PollDelay::new(interval, None, None),
+            check_errors: false,
+            check_tasks: false,
+            settings_interval: DEFAULT_SETTINGS_INTERVAL,
+            browse_max_records: None,
+            max_consecutive_errors: None,
+            max_polls: None,
+            until: None,
+            stretch: None,
+            pacing: None,
+            controls: None,
+        }
+    }
+
+    /// Time from the start of a poll to the next one, stretched by the callback
+    fn next_delay(&self) -> Duration {
+        let delay = self.poll_delay.next();
+        self.stretch.as_ref().map_or(delay, |stretch| stretch(delay))
+    }
+
+    async fn next_control(&mut self) -> Option<Control> {
+        match &mut self.controls {
+            Some(controls) => controls.recv().await,
+            None => std::future::pending().await,
+        }
+    }
+}
+
+/// When the settings and the synonyms or rules were last checked, which they are every settings interval
+#[derive(Default)]
+struct CheckedAt {
+    settings: Option<Instant>,
+    items: Option<Instant>,
+}
+
+impl<S: DataSource + ?Sized> Monitor<S> {
+    /// Polls the records count and makes the checks after each poll until the run ends, returning why
+    ///
+    /// The outcome of the polls only comes out through the callbacks, such as
+    /// [`MonitorBuilder::on_event`](crate::MonitorBuilder::on_event), each poll ending with a
+    /// [`MonitorEvent::PollEnded`]. The delay between the polls counts from the start of a poll, backing off
+    /// while the polls find the count of the previous one and nothing breached, and a poll the pacing of the
+    /// requests would hold past the next one is skipped. A check the key has no permission for is turned off
+    /// for the rest of the run, while the other errors of the checks fail the poll.
+    pub async fn run(&mut self) -> RunEnd {
+        let mut failures = Failures::new(self.run.max_consecutive_errors);
+        let mut polls = 0;
+        let mut last_records = None;
+        let mut breached = false;
+        let mut checked_at = CheckedAt::default();
+
+        loop {
+            polls += 1;
+            let started = Instant::now();
+            let mut records = None;
+            let mut verify = false;
+            let mut stable = false;
+            let result = self.poll_records().await.map(|poll| {
+                records = Some(poll.records.current_records);
+                // Only the start of a breach is verified, a browse being too costly for every poll of it
+                verify = poll.breach.is_some() && !breached;
+                breached = poll.breach.is_some();
+                stable = !out_of_sync(&poll) && records == last_records;
+            });
+            let result = match result {
+                Ok(()) => self.check_all(started, &mut checked_at).await,
+                Err(error) => Err(error),
+            };
+            if let (true, Some(records)) = (verify, records) {
+                self.verify(records).await;
+            }
+
+            let result = match result {
+                Err(error) if error.is_replay_end() => return RunEnd::ReplayEnded(error),
+                result => result,
+            };
+            match result.is_ok() && stable {
+                true => self.run.poll_delay.stable(),
+                false => self.run.poll_delay.reset(),
+            }
+            last_records = records;
+            let gives_up = match &result {
+                Ok(()) => {
+                    failures.succeeded();
+                    false
+                }
+                Err(error) => failures.failed(error),
+            };
+            self.emit(MonitorEvent::PollEnded(Box::new(PollEnd {
+                polls,
+                elapsed: started.elapsed(),
+                records,
+                error: result.err().map(Arc::new),
+                consecutive_errors: failures.consecutive(),
+                gives_up,
+                logs_since: self.logs_since(),
+            })));
+
+            if gives_up {
+                return RunEnd::GaveUp;
+            }
+            if self.run.until.is_some_and(|until| until < Utc::now())
+                || self.run.max_polls.is_some_and(|max_polls| polls >= max_polls)
+            {
+                return RunEnd::Finished;
+            }
+            if let Some(end) = self.wait(started).await {
+                return end;
+            }
+        }
+    }
+
+    /// Waits for the next poll, taking the controls that come meanwhile, `Some` when the run ends instead
+    async fn wait(&mut self, started: Instant) -> Option<RunEnd> {
+        let mut next_poll = started + self.run.next_delay();
+        self.emit_next_poll(next_poll);
+        loop {
+            tokio::select! {
+                _ = sleep_until(next_poll) => {
+                    let backlog = self.run.pacing.as_ref().map_or(Duration::ZERO, |pacing| pacing());
+                    let delay = self.run.poll_delay.base();
+                    if backlog <= delay {
+                        return None;
+                    }
+                    debug!(
+                        "[{}] Skipping a poll of {}, the requests are paced {} behind",
+                        now(),
+                        self.index_name,
+                        humantime::format_duration(Duration::from_millis(backlog.as_millis() as u64))
+                    );
+                    next_poll += delay;
+                }
+                control = self.run.next_control() => match control {
+                    Some(Control::PollNow) => return None,
+                    Some(Control::Stop) | None => return Some(RunEnd::Stopped),
+                    Some(Control::ExpectedRecords(records)) => self.set_expected_records(records),
+                    Some(Control::Reload(reload)) => {
+                        self.set_thresholds(reload.thresholds);
+                        self.set_log_filter(reload.log_filter);
+                        self.run.poll_delay = reload.poll_delay;
+                        next_poll = started + self.run.next_delay();
+                        self.emit_next_poll(next_poll);
+                    }
+                },
+            }
+        }
+    }
+
+    fn emit_next_poll(&mut self, next_poll: Instant) {
+        self.emit(MonitorEvent::NextPoll(next_poll.saturating_duration_since(Instant::now())));
+    }
+
+    /// Makes the checks after a poll that found the records count, in turn until one fails
+    async fn check_all(&mut self, started: Instant, checked_at: &mut CheckedAt) -> Result<(), MonitorError> {
+        if self.run.check_errors {
+            if let Some(errors) = self.check_errors().await? {
+                self.emit(MonitorEvent::ErrorsCounted(errors));
+            }
+        }
+        if self.run.check_tasks {
+            match self.check_tasks().await {
+                Ok(backlog) => self.emit(MonitorEvent::TasksChecked(backlog)),
+                Err(error) if turns_off(&error) => self.disable(Check::Tasks, &error),
+                Err(error) => return Err(error),
+            }
+        }
+
+        let interval = self.run.settings_interval;
+        if self.settings.is_some() && checked_at.settings.is_none_or(|at| at.elapsed() >= interval) {
+            match self.check_settings().await {
+                Ok(diff) => {
+                    // The settings come out on the first check, for the caller to have them from the start
+                    let first = checked_at.settings.replace(started).is_none();
+                    if let (true, Some(settings)) = (diff.is_some() || first, self.settings()) {
+                        let settings = settings.clone();
+                        self.emit(MonitorEvent::SettingsChecked { diff, settings });
+                    }
+                }
+                Err(error) if turns_off(&error) => self.disable(Check::Settings, &error),
+                Err(error) => return Err(error),
+            }
+        }
+        if !self.items.is_empty() && checked_at.items.is_none_or(|at| at.elapsed() >= interval) {
+            let watched: Vec<_> = self.items.iter().map(|baseline| baseline.items()).collect();
+            for items in watched {
+                match self.check_items(items).await {
+                    Ok(Some(count)) => self.emit(MonitorEvent::ItemsChecked(count)),
+                    Ok(None) => {}
+                    Err(error) if turns_off(&error) => self.disable(Check::Items(items), &error),
+                    Err(error) => return Err(error),
+                }
+            }
+            checked_at.items = Some(started);
+        }
+        if self.object_sample.is_some() {
+            match self.check_churn().await {
+                Ok(Some(churn)) => self.emit(MonitorEvent::ChurnSampled(churn)),
+                Ok(None) => {}
+                Err(error) if turns_off(&error) => self.disable(Check::Churn, &error),
+                Err(error) => return Err(error),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Turns the check off for the rest of the run after a permanent error, such as a key without the ACL it
+    /// needs
+    fn disable(&mut self, check: Check, error: &MonitorError) {
+        match check {
+            Check::Tasks => self.run.check_tasks = false,
+            Check::Settings => self.settings = None,
+            Check::Items(items) => self.items.retain(|baseline| baseline.items() != items),
+            Check::Churn => self.object_sample = None,
+            Check::Verification => self.run.browse_max_records = None,
+        }
+        let error = error.to_string();
+        self.emit(MonitorEvent::CheckFailed { check, error, disabled: true });
+    }
+
+    /// Counts the records exactly by browsing them at the start of a breach, a failure not failing the poll
+    async fn verify(&mut self, records: u64) {
+        let Some(max_records) = self.run.browse_max_records else {
+            return;
+        };
+        let on_event = &mut self.on_event;
+        let mut progress = |count: &_| {
+            if let Some(on_event) = on_event {
+                on_event(&MonitorEvent::Browsing(*count));
+            }
+        };
+        let count = self
+            .source
+            .browse_count(&self.index_name, self.filters.as_deref(), max_records, &mut progress)
+            .await;
+        match count {
+            Ok(count) => self.emit(MonitorEvent::Verified { count, records }),
+            Err(error) if error.is_permanent() => self.disable(Check::Verification, &error),
+            Err(error) => {
+                let error = error.to_string();
+                self.emit(MonitorEvent::CheckFailed { check: Check::Verification, error, disabled: false });
+            }
+        }
+    }
+}
+
+/// Whether the error of a check turns it off rather than failing the poll, an index or task not being found
+/// having nothing to do with the permissions of the key
+fn turns_off(error: &MonitorError) -> bool {
+    error.is_permanent() && !error.is_not_found()
+}
+
+/// Whether the replicas or the compared index are out of sync with the index, which like a breach keeps the
+/// delay from backing off
+fn out_of_sync(poll: &Poll) -> bool {
+    poll.breach.is_some()
+        || poll.replicas.iter().any(|replica| replica.out_of_sync)
+        || matches!(poll.comparison, Some(Ok(comparison)) if comparison.out_of_sync)
+}
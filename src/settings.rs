@@ -0,0 +1,107 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// Settings of an index as they were last seen, to catch a deploy or a dashboard edit changing them
+///
+/// The settings are kept with their keys sorted, so the same settings always compare and serialize the same,
+/// and without the ignored keys, which change on purpose.
+#[derive(Debug, Clone)]
+pub struct SettingsBaseline {
+    ignored: Vec<String>,
+    snapshot: Option<BTreeMap<String, Value>>,
+}
+
+/// Settings keys that differ from the baseline
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SettingsDiff {
+    pub added: BTreeMap<String, Value>,
+    pub removed: BTreeMap<String, Value>,
+    pub modified: BTreeMap<String, SettingsChange>,
+}
+
+/// Value of a settings key before and after it changed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SettingsChange {
+    pub before: Value,
+    pub after: Value,
+}
+
+impl SettingsBaseline {
+    /// Starts from the snapshot when one was kept, such as by a previous run, or from the first settings checked
+    pub fn new(ignored: Vec<String>, snapshot: Option<Map<String, Value>>) -> Self {
+        let mut baseline = SettingsBaseline {
+            ignored,
+            snapshot: None,
+        };
+        baseline.snapshot = snapshot.map(|settings| baseline.normalize(settings));
+        baseline
+    }
+
+    /// Settings the next ones are compared to, `None` until the first check
+    pub fn snapshot(&self) -> Option<&BTreeMap<String, Value>> {
+        self.snapshot.as_ref()
+    }
+
+    /// Compares the settings to the baseline and adopts them, `None` when they are the first ones or unchanged
+    pub fn check(&mut self, settings: Map<String, Value>) -> Option<SettingsDiff> {
+        let settings = self.normalize(settings);
+        let previous = self.snapshot.replace(settings.clone())?;
+        let diff = SettingsDiff::between(&previous, &settings);
+
+        (!diff.is_empty()).then_some(diff)
+    }
+
+    fn normalize(&self, settings: Map<String, Value>) -> BTreeMap<String, Value> {
+        settings
+            .into_iter()
+            .filter(|(key, _)| !self.ignored.contains(key))
+            .collect()
+    }
+}
+
+impl SettingsDiff {
+    pub fn between(before: &BTreeMap<String, Value>, after: &BTreeMap<String, Value>) -> Self {
+        let mut diff = SettingsDiff {
+            added: BTreeMap::new(),
+            removed: BTreeMap::new(),
+            modified: BTreeMap::new(),
+        };
+
+        for (key, value) in before {
+            match after.get(key) {
+                None => {
+                    diff.removed.insert(key.clone(), value.clone());
+                }
+                Some(after) if after != value => {
+                    let change = SettingsChange {
+                        before: value.clone(),
+                        after: after.clone(),
+                    };
+                    diff.modified.insert(key.clone(), change);
+                }
+                Some(_) => {}
+            }
+        }
+        for (key, value) in after {
+            if !before.contains_key(key) {
+                diff.added.insert(key.clone(), value.clone());
+            }
+        }
+
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+
+    /// Changed keys, added ones first then removed and modified ones, such as `+ranking, -distinct, ~synonyms`
+    pub fn summary(&self) -> String {
+        let added = self.added.keys().map(|key| format!("+{key}"));
+        let removed = self.removed.keys().map(|key| format!("-{key}"));
+        let modified = self.modified.keys().map(|key| format!("~{key}"));
+
+        added.chain(removed).chain(modified).collect::<Vec<_>>().join(", ")
+    }
+}
@@ -0,0 +1,55 @@
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// File of `--settings-dir` keeping the settings an index was last seen with, so a change made while the
+/// monitor was stopped is caught when it starts again
+pub struct SettingsFile {
+    path: PathBuf,
+}
+
+impl SettingsFile {
+    /// File of the index in the directory, named after the application and the index such as
+    /// `ABCDEF1234-products.json`
+    pub fn new(directory: &Path, app_id: &str, index_name: &str) -> Self {
+        let name: String = format!("{app_id}-{index_name}")
+            .chars()
+            .map(|char| match char.is_ascii_alphanumeric() || "-_.".contains(char) {
+                true => char,
+                false => '_',
+            })
+            .collect();
+        SettingsFile {
+            path: directory.join(format!("{name}.json")),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Settings written by a previous run, `None` when there is no file yet
+    pub fn load(&self) -> Result<Option<Map<String, Value>>, String> {
+        let contents = match std::fs::read(&self.path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error.to_string()),
+        };
+
+        serde_json::from_slice(&contents).map(Some).map_err(|error| error.to_string())
+    }
+
+    /// Replaces the settings in the file, writing them next to it first so a crash never leaves it half written
+    pub fn save(&self, settings: &BTreeMap<String, Value>) -> Result<(), String> {
+        let contents = serde_json::to_vec_pretty(settings).map_err(|error| error.to_string())?;
+        let partial = self.path.with_extension("json.partial");
+        if let Some(directory) = self.path.parent() {
+            std::fs::create_dir_all(directory).map_err(|error| error.to_string())?;
+        }
+
+        std::fs::write(&partial, contents)
+            .and_then(|()| std::fs::rename(&partial, &self.path))
+            .map_err(|error| error.to_string())
+    }
+}
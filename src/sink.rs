@@ -0,0 +1,212 @@
+use crate::error::MonitorError;
+use crate::monitor::MonitorEvent;
+use crate::now;
+use std::panic::{self, AssertUnwindSafe};
+use tracing::{error, warn};
+
+/// Consumer of the [`MonitorEvent`]s of a monitor, such as a printer of the log entries or a file of the events
+pub trait EventSink: Send {
+    /// Name the failures of the sink are reported with, such as the path of its file
+    fn name(&self) -> String;
+
+    fn handle(&mut self, event: &MonitorEvent) -> Result<(), MonitorError>;
+}
+
+/// Sink among [`EventSinks`], with the failures it had in a row
+struct Slot {
+    sink: Box<dyn EventSink>,
+    consecutive_errors: u32,
+    disabled: bool,
+}
+
+/// Sinks each event is handed to in turn, kept apart so that a failing sink doesn't keep the others from
+/// getting it
+///
+/// Each sink gets the events in the order [`EventSinks::handle`] is called with them, the order the monitor
+/// emits them in when it is given to [`MonitorBuilder::on_event`](crate::MonitorBuilder::on_event). A sink
+/// returning an error or panicking is reported and gets the next event anyway, unless it failed the maximum
+/// number of times in a row, which disables it for the rest of the run.
+#[derive(Default)]
+pub struct EventSinks {
+    slots: Vec<Slot>,
+    max_errors: Option<u32>,
+}
+
+impl EventSinks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_sink(mut self, sink: impl EventSink + 'static) -> Self {
+        self.slots.push(Slot {
+            sink: Box::new(sink),
+            consecutive_errors: 0,
+            disabled: false,
+        });
+        self
+    }
+
+    /// Disables a sink once it failed that many times in a row, never without it
+    pub fn with_max_errors(mut self, max_errors: Option<u32>) -> Self {
+        self.max_errors = max_errors;
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Hands the event to each sink that isn't disabled, in the order they were added
+    pub fn handle(&mut self, event: &MonitorEvent) {
+        for slot in self.slots.iter_mut().filter(|slot| !slot.disabled) {
+            let failure = match panic::catch_unwind(AssertUnwindSafe(|| slot.sink.handle(event))) {
+                Ok(Ok(())) => {
+                    slot.consecutive_errors = 0;
+                    continue;
+                }
+                Ok(Err(error)) => error.to_string(),
+                Err(_) => "panicked".to_string(),
+            };
+
+            slot.consecutive_errors += 1;
+            warn!("[{}] Output {} failed to handle an event: {}", now(), slot.sink.name(), failure);
+            if self.max_errors.is_some_and(|max_errors| slot.consecutive_errors >= max_errors) {
+                slot.disabled = true;
+                error!(
+                    "[{}] Output {} is disabled after failing {} times in a row",
+                    now(),
+                    slot.sink.name(),
+                    slot.consecutive_errors
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Copy)]
+    enum Outcome {
+        Handled,
+        Failed,
+        Panicked,
+    }
+
+    /// Sink writing `<name>: <error of the event>` to a record shared with the other sinks before it does
+    /// what it is told, handling the events once it ran out of outcomes
+    struct Scripted {
+        name: &'static str,
+        record: Arc<Mutex<Vec<String>>>,
+        outcomes: VecDeque<Outcome>,
+    }
+
+    impl EventSink for Scripted {
+        fn name(&self) -> String {
+            self.name.to_string()
+        }
+
+        fn handle(&mut self, event: &MonitorEvent) -> Result<(), MonitorError> {
+            let MonitorEvent::Error(event) = event else {
+                panic!("unexpected event {event:?}");
+            };
+            self.record.lock().unwrap().push(format!("{}: {}", self.name, event));
+
+            match self.outcomes.pop_front().unwrap_or(Outcome::Handled) {
+                Outcome::Handled => Ok(()),
+                Outcome::Failed => Err(MonitorError::Sink(format!("{} failed", self.name))),
+                Outcome::Panicked => panic!("{} panicked", self.name),
+            }
+        }
+    }
+
+    fn scripted(name: &'static str, record: &Arc<Mutex<Vec<String>>>, outcomes: &[Outcome]) -> Scripted {
+        Scripted {
+            name,
+            record: record.clone(),
+            outcomes: outcomes.iter().copied().collect(),
+        }
+    }
+
+    fn handle_events(sinks: &mut EventSinks, count: usize) {
+        for event in 1..=count {
+            sinks.handle(&MonitorEvent::Error(event.to_string()));
+        }
+    }
+
+    fn recorded(record: &Arc<Mutex<Vec<String>>>) -> Vec<String> {
+        record.lock().unwrap().clone()
+    }
+
+    #[test]
+    fn each_event_goes_through_the_sinks_in_the_order_they_were_added() {
+        let record = Arc::default();
+        let mut sinks = EventSinks::new()
+            .with_sink(scripted("first", &record, &[]))
+            .with_sink(scripted("second", &record, &[]));
+
+        handle_events(&mut sinks, 3);
+
+        assert_eq!(
+            recorded(&record),
+            ["first: 1", "second: 1", "first: 2", "second: 2", "first: 3", "second: 3"]
+        );
+    }
+
+    #[test]
+    fn panicking_sink_leaves_the_event_to_the_next_sinks() {
+        let record = Arc::default();
+        let mut sinks = EventSinks::new()
+            .with_sink(scripted("panicking", &record, &[Outcome::Panicked, Outcome::Panicked]))
+            .with_sink(scripted("stdout", &record, &[]));
+
+        handle_events(&mut sinks, 3);
+
+        assert_eq!(
+            recorded(&record),
+            ["panicking: 1", "stdout: 1", "panicking: 2", "stdout: 2", "panicking: 3", "stdout: 3"]
+        );
+    }
+
+    #[test]
+    fn failing_sink_keeps_getting_the_events_without_max_errors() {
+        let record = Arc::default();
+        let mut sinks = EventSinks::new().with_sink(scripted("webhook", &record, &[Outcome::Failed; 5]));
+
+        handle_events(&mut sinks, 6);
+
+        assert_eq!(recorded(&record).len(), 6);
+    }
+
+    #[test]
+    fn sink_is_disabled_once_it_failed_max_errors_in_a_row() {
+        let record = Arc::default();
+        let mut sinks = EventSinks::new()
+            .with_max_errors(Some(2))
+            .with_sink(scripted("webhook", &record, &[Outcome::Failed, Outcome::Panicked]))
+            .with_sink(scripted("stdout", &record, &[]));
+
+        handle_events(&mut sinks, 4);
+
+        assert_eq!(
+            recorded(&record),
+            ["webhook: 1", "stdout: 1", "webhook: 2", "stdout: 2", "stdout: 3", "stdout: 4"]
+        );
+    }
+
+    #[test]
+    fn handled_event_starts_the_errors_in_a_row_over() {
+        let record = Arc::default();
+        let outcomes = [Outcome::Failed, Outcome::Handled, Outcome::Failed, Outcome::Failed];
+        let mut sinks = EventSinks::new()
+            .with_max_errors(Some(2))
+            .with_sink(scripted("webhook", &record, &outcomes));
+
+        handle_events(&mut sinks, 5);
+
+        assert_eq!(recorded(&record), ["webhook: 1", "webhook: 2", "webhook: 3", "webhook: 4"]);
+    }
+}
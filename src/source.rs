@@ -0,0 +1,207 @@
+use crate::algolia::{
+    AlgoliaClient, AlgoliaLogEntry, BrowseCount, FacetValues, IndexItems, IndexStats, LogType, SampleStrategy,
+};
+use crate::error::MonitorError;
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use serde_json::{Map, Value};
+
+/// Where a [`Monitor`](crate::Monitor) reads the records of the indices from, Algolia itself through an
+/// [`AlgoliaClient`] or the responses recorded in a file through a [`Replay`](crate::Replay)
+///
+/// The methods are the reads of the client the monitor relies on, each answering for the index it is given so
+/// a single source serves all the indices of an application, polled on their own schedules.
+pub trait RecordSource: Send + Sync {
+    /// Records count of the index along with what the list of indices tells about it
+    fn index_stats<'a>(&'a self, index_name: &'a str) -> BoxFuture<'a, Result<IndexStats, MonitorError>>;
+
+    /// Records count of the objects matching the filters
+    fn filtered_stats<'a>(
+        &'a self,
+        index_name: &'a str,
+        filters: &'a str,
+    ) -> BoxFuture<'a, Result<IndexStats, MonitorError>>;
+
+    fn total_records<'a>(&'a self, index_name: &'a str) -> BoxFuture<'a, Result<u64, MonitorError>>;
+
+    fn facet_values<'a>(
+        &'a self,
+        index_name: &'a str,
+        attribute: &'a str,
+    ) -> BoxFuture<'a, Result<FacetValues, MonitorError>>;
+
+    /// Number of indexing tasks of the index that aren't published yet
+    fn pending_tasks<'a>(&'a self, index_name: &'a str) -> BoxFuture<'a, Result<u64, MonitorError>>;
+
+    fn is_task_published<'a>(
+        &'a self,
+        index_name: &'a str,
+        task_id: u64,
+    ) -> BoxFuture<'a, Result<bool, MonitorError>>;
+
+    fn settings<'a>(&'a self, index_name: &'a str) -> BoxFuture<'a, Result<Map<String, Value>, MonitorError>>;
+
+    /// IDs of a sample of the objects of the index, or of the ones matching the filters
+    fn object_ids<'a>(
+        &'a self,
+        index_name: &'a str,
+        filters: Option<&'a str>,
+        strategy: SampleStrategy,
+        size: u64,
+    ) -> BoxFuture<'a, Result<Vec<String>, MonitorError>>;
+
+    /// Counts the objects of the index, or of the ones matching the filters, stopping at `max_records`
+    fn browse_count<'a>(
+        &'a self,
+        index_name: &'a str,
+        filters: Option<&'a str>,
+        max_records: u64,
+        progress: &'a mut (dyn FnMut(&BrowseCount) + Send),
+    ) -> BoxFuture<'a, Result<BrowseCount, MonitorError>>;
+
+    fn items_count<'a>(
+        &'a self,
+        index_name: &'a str,
+        items: IndexItems,
+    ) -> BoxFuture<'a, Result<u64, MonitorError>>;
+
+    /// Time the ages of what the source returns are measured against, such as how long a task has waited to be
+    /// published, the time a replayed response was recorded at rather than the current one
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Where a [`Monitor`](crate::Monitor) reads the logs of the indices from, like a [`RecordSource`] does the
+/// records
+pub trait LogSource: Send + Sync {
+    /// Log entries of the type the source is set up for from `since` on, newest first
+    fn logs<'a>(
+        &'a self,
+        index_name: &'a str,
+        since: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<Vec<AlgoliaLogEntry>, MonitorError>>;
+
+    /// Log entries of the type from `since` on, newest first
+    fn logs_of_type<'a>(
+        &'a self,
+        index_name: &'a str,
+        log_type: LogType,
+        since: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<Vec<AlgoliaLogEntry>, MonitorError>>;
+
+    /// Log entries of the type from `since` on, fetched `page_size` at a time by a source reading them in pages
+    fn logs_paged<'a>(
+        &'a self,
+        index_name: &'a str,
+        log_type: LogType,
+        since: Option<DateTime<Utc>>,
+        _page_size: u32,
+    ) -> BoxFuture<'a, Result<Vec<AlgoliaLogEntry>, MonitorError>> {
+        self.logs_of_type(index_name, log_type, since)
+    }
+}
+
+/// Source of both the records and the logs, which a [`Monitor`](crate::Monitor) watches an index through
+pub trait DataSource: RecordSource + LogSource {}
+
+impl<S: RecordSource + LogSource + ?Sized> DataSource for S {}
+
+impl RecordSource for AlgoliaClient {
+    fn index_stats<'a>(&'a self, index_name: &'a str) -> BoxFuture<'a, Result<IndexStats, MonitorError>> {
+        Box::pin(AlgoliaClient::index_stats(self, index_name))
+    }
+
+    fn filtered_stats<'a>(
+        &'a self,
+        index_name: &'a str,
+        filters: &'a str,
+    ) -> BoxFuture<'a, Result<IndexStats, MonitorError>> {
+        Box::pin(AlgoliaClient::filtered_stats(self, index_name, filters))
+    }
+
+    fn total_records<'a>(&'a self, index_name: &'a str) -> BoxFuture<'a, Result<u64, MonitorError>> {
+        Box::pin(AlgoliaClient::total_records(self, index_name))
+    }
+
+    fn facet_values<'a>(
+        &'a self,
+        index_name: &'a str,
+        attribute: &'a str,
+    ) -> BoxFuture<'a, Result<FacetValues, MonitorError>> {
+        Box::pin(AlgoliaClient::facet_values(self, index_name, attribute))
+    }
+
+    fn pending_tasks<'a>(&'a self, index_name: &'a str) -> BoxFuture<'a, Result<u64, MonitorError>> {
+        Box::pin(AlgoliaClient::pending_tasks(self, index_name))
+    }
+
+    fn is_task_published<'a>(
+        &'a self,
+        index_name: &'a str,
+        task_id: u64,
+    ) -> BoxFuture<'a, Result<bool, MonitorError>> {
+        Box::pin(AlgoliaClient::is_task_published(self, index_name, task_id))
+    }
+
+    fn settings<'a>(&'a self, index_name: &'a str) -> BoxFuture<'a, Result<Map<String, Value>, MonitorError>> {
+        Box::pin(AlgoliaClient::settings(self, index_name))
+    }
+
+    fn object_ids<'a>(
+        &'a self,
+        index_name: &'a str,
+        filters: Option<&'a str>,
+        strategy: SampleStrategy,
+        size: u64,
+    ) -> BoxFuture<'a, Result<Vec<String>, MonitorError>> {
+        Box::pin(AlgoliaClient::object_ids(self, index_name, filters, strategy, size))
+    }
+
+    fn browse_count<'a>(
+        &'a self,
+        index_name: &'a str,
+        filters: Option<&'a str>,
+        max_records: u64,
+        progress: &'a mut (dyn FnMut(&BrowseCount) + Send),
+    ) -> BoxFuture<'a, Result<BrowseCount, MonitorError>> {
+        Box::pin(AlgoliaClient::browse_count(self, index_name, filters, max_records, progress))
+    }
+
+    fn items_count<'a>(
+        &'a self,
+        index_name: &'a str,
+        items: IndexItems,
+    ) -> BoxFuture<'a, Result<u64, MonitorError>> {
+        Box::pin(AlgoliaClient::items_count(self, index_name, items))
+    }
+}
+
+impl LogSource for AlgoliaClient {
+    fn logs<'a>(
+        &'a self,
+        index_name: &'a str,
+        since: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<Vec<AlgoliaLogEntry>, MonitorError>> {
+        Box::pin(self.get_logs(index_name, since))
+    }
+
+    fn logs_of_type<'a>(
+        &'a self,
+        index_name: &'a str,
+        log_type: LogType,
+        since: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<Vec<AlgoliaLogEntry>, MonitorError>> {
+        Box::pin(self.get_logs_of_type(index_name, log_type, since))
+    }
+
+    fn logs_paged<'a>(
+        &'a self,
+        index_name: &'a str,
+        log_type: LogType,
+        since: Option<DateTime<Utc>>,
+        page_size: u32,
+    ) -> BoxFuture<'a, Result<Vec<AlgoliaLogEntry>, MonitorError>> {
+        Box::pin(self.get_logs_paged(index_name, log_type, since, page_size))
+    }
+}
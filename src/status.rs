@@ -0,0 +1,409 @@
+use algolia_monitor::output::DebugState;
+use algolia_monitor::metrics::MetricsBackend;
+use algolia_monitor::{now, Percentiles};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
+use tracing::error;
+
+/// Number of poll delays without a successful poll after which an index is unhealthy
+const HEALTHY_DELAYS: u32 = 3;
+
+/// State of the monitored indices served at `/status` and `/healthz`, kept up to date as a metrics backend
+#[derive(Clone)]
+pub struct StatusApi {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    started: Instant,
+    started_at: DateTime<Utc>,
+    /// Longest time since the last successful poll of a healthy index, the one of the index polled least often
+    max_poll_age: RwLock<Duration>,
+    indices: RwLock<Vec<IndexStatus>>,
+    /// File the status is written to after each poll, for the `healthcheck` subcommand
+    state_file: Option<PathBuf>,
+    /// Whether writing the state file failed since it last succeeded, so it is only reported once
+    state_file_failed: AtomicBool,
+}
+
+struct IndexStatus {
+    name: String,
+    /// Longest time since the last successful poll of the index while it is healthy
+    max_poll_age: Duration,
+    /// Delay between the polls of the index, `None` until the first one is scheduled
+    interval: Option<Duration>,
+    next_poll: Option<DateTime<Utc>>,
+    last_poll: Option<DateTime<Utc>>,
+    /// Time of the last successful poll, or of the start before the first one
+    last_success: Instant,
+    last_success_at: Option<DateTime<Utc>>,
+    records: Option<u64>,
+    expected_records: Option<u64>,
+    breached_since: Option<DateTime<Utc>>,
+    /// Whether the latest poll breached, the breach going on until a poll doesn't
+    breached: bool,
+    last_error: Option<String>,
+}
+
+/// Status served at `/status` and written to the state file
+#[derive(Serialize, Deserialize)]
+pub struct StatusReport {
+    started_at: String,
+    /// Time the report was made, telling how fresh a state file is
+    updated_at: DateTime<Utc>,
+    uptime_secs: u64,
+    max_poll_age_secs: u64,
+    healthy: bool,
+    indices: Vec<IndexReport>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct IndexReport {
+    index: String,
+    last_poll: Option<String>,
+    /// Time of the last successful poll, `None` before the first one
+    last_success: Option<DateTime<Utc>>,
+    records: Option<u64>,
+    expected_records: Option<u64>,
+    delta: Option<i64>,
+    breached: bool,
+    /// Whether the breach left the index without records
+    critical: bool,
+    breach_duration_secs: Option<u64>,
+    last_error: Option<String>,
+    healthy: bool,
+    /// Delay between the polls of the index, `None` before the first one
+    interval_secs: Option<u64>,
+    next_poll: Option<DateTime<Utc>>,
+}
+
+impl StatusApi {
+    /// Status of the indices, given with their longest poll delay, healthy until they went three of their poll
+    /// delays without a successful poll
+    ///
+    /// With a `state_file`, the status is also written there after each poll, replacing the file at once so
+    /// it is never read half-written.
+    pub fn new(indices: impl IntoIterator<Item = (String, Duration)>, state_file: Option<PathBuf>) -> Self {
+        let started = Instant::now();
+        let indices: Vec<_> =
+            indices.into_iter().map(|(name, delay)| IndexStatus::new(name, delay, started)).collect();
+
+        StatusApi {
+            shared: Arc::new(Shared {
+                started,
+                started_at: Utc::now(),
+                max_poll_age: RwLock::new(max_poll_age(&indices)),
+                indices: RwLock::new(indices),
+                state_file,
+                state_file_failed: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    /// Binds the `/status` and `/healthz` endpoints and serves them from a background task, along with `/state`
+    /// telling the state printed on SIGUSR1 and `/poll` polling right away like SIGUSR2, which also work where
+    /// there are no such signals
+    pub async fn serve(self, address: SocketAddr, debug: DebugState) -> std::io::Result<()> {
+        let listener = TcpListener::bind(address).await?;
+        let state = {
+            let debug = debug.clone();
+            move || async move { debug.lines().join("\n") + "\n" }
+        };
+        let poll = move || async move {
+            debug.poll_now("asked at /poll");
+            "ok\n"
+        };
+        let app = Router::new()
+            .route("/status", get(status_handler))
+            .route("/healthz", get(health_handler))
+            .route("/state", get(state))
+            .route("/poll", post(poll))
+            .with_state(self.shared);
+
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+
+        Ok(())
+    }
+
+    /// Follows the indices and the poll delays of a reloaded config file, keeping the state of the remaining
+    /// indices, the added ones getting as long as at the start for their first poll
+    pub fn set_indices(&self, delays: impl IntoIterator<Item = (String, Duration)>) {
+        let mut indices = self.shared.indices.write().unwrap();
+        let mut previous = std::mem::take(&mut *indices);
+        for (name, delay) in delays {
+            match previous.iter().position(|index| index.name == name) {
+                Some(position) => {
+                    let mut index = previous.swap_remove(position);
+                    index.max_poll_age = delay * HEALTHY_DELAYS;
+                    indices.push(index);
+                }
+                None => indices.push(IndexStatus::new(name, delay, Instant::now())),
+            }
+        }
+        *self.shared.max_poll_age.write().unwrap() = max_poll_age(&indices);
+    }
+
+    fn update(&self, index_name: &str, update: impl FnOnce(&mut IndexStatus)) {
+        let mut indices = self.shared.indices.write().unwrap();
+        if let Some(index) = indices.iter_mut().find(|index| index.name == index_name) {
+            update(index);
+        }
+    }
+
+    fn write_state_file(&self) {
+        let Some(path) = &self.shared.state_file else {
+            return;
+        };
+
+        match write_atomically(path, &self.shared.report()) {
+            Ok(()) => self.shared.state_file_failed.store(false, Ordering::Relaxed),
+            Err(error) => {
+                if !self.shared.state_file_failed.swap(true, Ordering::Relaxed) {
+                    error!("[{}] Failed to write the state file {}: {}", now(), path.display(), error);
+                }
+            }
+        }
+    }
+}
+
+impl IndexStatus {
+    fn new(name: String, delay: Duration, started: Instant) -> Self {
+        IndexStatus {
+            name,
+            max_poll_age: delay * HEALTHY_DELAYS,
+            interval: None,
+            next_poll: None,
+            last_poll: None,
+            last_success: started,
+            last_success_at: None,
+            records: None,
+            expected_records: None,
+            breached_since: None,
+            breached: false,
+            last_error: None,
+        }
+    }
+}
+
+fn max_poll_age(indices: &[IndexStatus]) -> Duration {
+    indices.iter().map(|index| index.max_poll_age).max().unwrap_or_default()
+}
+
+impl Shared {
+    fn max_poll_age(&self) -> Duration {
+        *self.max_poll_age.read().unwrap()
+    }
+
+    fn healthy(&self, index: &IndexStatus) -> bool {
+        index.last_error.is_none() && index.last_success.elapsed() <= index.max_poll_age
+    }
+
+    fn report(&self) -> StatusReport {
+        let indices = self.indices.read().unwrap();
+        let indices: Vec<_> = indices
+            .iter()
+            .map(|index| IndexReport {
+                index: index.name.clone(),
+                last_poll: index.last_poll.map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true)),
+                last_success: index.last_success_at,
+                records: index.records,
+                expected_records: index.expected_records,
+                delta: index
+                    .records
+                    .zip(index.expected_records)
+                    .map(|(records, expected_records)| records as i64 - expected_records as i64),
+                breached: index.breached,
+                critical: index.breached && index.records == Some(0),
+                breach_duration_secs: index
+                    .breached_since
+                    .map(|since| (Utc::now() - since).num_seconds().max(0) as u64),
+                last_error: index.last_error.clone(),
+                healthy: self.healthy(index),
+                interval_secs: index.interval.map(|interval| interval.as_secs()),
+                next_poll: index.next_poll,
+            })
+            .collect();
+
+        StatusReport {
+            started_at: self.started_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+            updated_at: Utc::now(),
+            uptime_secs: self.started.elapsed().as_secs(),
+            max_poll_age_secs: self.max_poll_age().as_secs(),
+            healthy: indices.iter().all(|index| index.healthy),
+            indices,
+        }
+    }
+}
+
+impl StatusReport {
+    /// Reason the monitor is unhealthy: the report went stale, an index went too long without a successful
+    /// poll or its last poll failed, or a breach left an index without records
+    pub fn check(&self) -> Result<(), String> {
+        let max_age = chrono::Duration::seconds(self.max_poll_age_secs as i64);
+        if Utc::now() - self.updated_at > max_age {
+            return Err(format!("status not updated since {}", format_time(self.updated_at)));
+        }
+
+        for index in &self.indices {
+            if let Some(error) = &index.last_error {
+                return Err(format!("{}: last poll failed: {}", index.index, error));
+            }
+            if index.critical {
+                return Err(format!("{}: records count dropped to 0", index.index));
+            }
+            if !index.healthy {
+                return Err(match index.last_success {
+                    Some(last_success) => {
+                        format!("{}: no successful poll since {}", index.index, format_time(last_success))
+                    }
+                    None => format!("{}: no successful poll since the start", index.index),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Writes the report next to `path` and renames it over, which replaces the file in one go
+fn write_atomically(path: &Path, report: &StatusReport) -> std::io::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    std::fs::write(&temporary, serde_json::to_vec(report)?)?;
+    std::fs::rename(&temporary, path)
+}
+
+impl MetricsBackend for StatusApi {
+    fn records(&self, index_name: &str, records: u64, expected_records: u64) {
+        self.update(index_name, |index| {
+            index.records = Some(records);
+            index.expected_records = Some(expected_records);
+            // A breach is reported right after the records count of the same poll
+            index.breached = false;
+        });
+    }
+
+    fn data_size(&self, _index_name: &str, _data_size: u64) {}
+
+    fn pending_tasks(&self, _index_name: &str, _pending_tasks: u64) {}
+
+    fn latency(&self, _index_name: &str, _latency: &Percentiles) {}
+
+    fn poll_error(&self, _index_name: &str) {}
+
+    fn threshold_breach(&self, index_name: &str) {
+        self.update(index_name, |index| {
+            index.breached = true;
+            index.breached_since.get_or_insert_with(Utc::now);
+        });
+    }
+
+    fn api_response(&self, _endpoint: &str, _duration: Duration) {}
+
+    fn poll(&self, index_name: &str, _duration: Duration, error: Option<&str>) {
+        self.update(index_name, |index| {
+            index.last_poll = Some(Utc::now());
+            match error {
+                Some(error) => index.last_error = Some(error.to_string()),
+                None => {
+                    index.last_error = None;
+                    index.last_success = Instant::now();
+                    index.last_success_at = Some(Utc::now());
+                    if !index.breached {
+                        index.breached_since = None;
+                    }
+                }
+            }
+        });
+        self.write_state_file();
+    }
+
+    fn next_poll(&self, index_name: &str, delay: Duration, next_poll: DateTime<Utc>) {
+        self.update(index_name, |index| {
+            index.interval = Some(delay);
+            index.next_poll = Some(next_poll);
+        });
+    }
+}
+
+async fn status_handler(State(shared): State<Arc<Shared>>) -> impl IntoResponse {
+    Json(shared.report())
+}
+
+/// 200 while every index had a successful last poll within three poll delays, 503 naming the other ones
+async fn health_handler(State(shared): State<Arc<Shared>>) -> impl IntoResponse {
+    let indices = shared.indices.read().unwrap();
+    let unhealthy: Vec<_> = indices
+        .iter()
+        .filter(|index| !shared.healthy(index))
+        .map(|index| index.name.as_str())
+        .collect();
+
+    match unhealthy.is_empty() {
+        true => (StatusCode::OK, "ok\n".to_string()),
+        false => (StatusCode::SERVICE_UNAVAILABLE, format!("unhealthy: {}\n", unhealthy.join(", "))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Serves the API on a free port of the loopback interface, returning its base URL
+    async fn serve(debug: DebugState) -> String {
+        let address = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        StatusApi::new([], None).serve(address, debug).await.unwrap();
+        format!("http://{address}")
+    }
+
+    #[tokio::test]
+    async fn state_endpoint_tells_the_state_of_sigusr1() {
+        let debug = DebugState::default();
+        debug.logs_since("products", None);
+        let url = serve(debug).await;
+
+        let response = reqwest::get(format!("{url}/state")).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let state = response.text().await.unwrap();
+        assert!(state.starts_with("State after "), "{state}");
+        assert!(state.contains("products"), "{state}");
+    }
+
+    #[tokio::test]
+    async fn poll_endpoint_polls_right_away_like_sigusr2() {
+        let debug = DebugState::default();
+        let requested = tokio::spawn({
+            let debug = debug.clone();
+            async move { debug.poll_requested().await }
+        });
+        // The monitor waits for the request before it comes
+        tokio::task::yield_now().await;
+        let url = serve(debug).await;
+
+        let response = reqwest::Client::new().post(format!("{url}/poll")).send().await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.text().await.unwrap(), "ok\n");
+        tokio::time::timeout(Duration::from_secs(5), requested).await.unwrap().unwrap();
+        let response = reqwest::get(format!("{url}/poll")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+}
@@ -0,0 +1,59 @@
+use crate::output::ApiUsage;
+use crate::Percentiles;
+use std::time::Duration;
+use tracing::info;
+
+/// Statistics of a single monitored index, printed when the monitor stops
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub polls: u64,
+    pub min_records: Option<u64>,
+    pub max_records: Option<u64>,
+    pub last_records: Option<u64>,
+    pub breaches: u64,
+    pub log_lines: u64,
+    /// Whether a breach was still going on when the monitor stopped
+    pub breached: bool,
+    /// Search latency percentiles of the last poll
+    pub latency: Option<Percentiles>,
+}
+
+impl Summary {
+    pub fn records(&mut self, records: u64) {
+        self.min_records = Some(self.min_records.map_or(records, |min| min.min(records)));
+        self.max_records = Some(self.max_records.map_or(records, |max| max.max(records)));
+        self.last_records = Some(records);
+    }
+}
+
+/// Prints the end-of-run statistics of every index to stderr
+pub fn print_summaries(summaries: &[(String, Summary)], runtime: Duration, usage: &ApiUsage) {
+    let runtime = Duration::from_secs(runtime.as_secs());
+    info!("Summary after {}:", humantime::format_duration(runtime));
+
+    for (index_name, summary) in summaries {
+        let records = match (summary.min_records, summary.max_records, summary.last_records) {
+            (Some(min), Some(max), Some(last)) => format!("records min {min} / max {max} / last {last}"),
+            _ => "no records count".to_string(),
+        };
+
+        let latency = match summary.latency {
+            Some(latency) => format!(
+                ", latency p50 {}ms / p95 {}ms / p99 {}ms",
+                latency.p50, latency.p95, latency.p99
+            ),
+            None => String::new(),
+        };
+
+        let breached = match summary.breached {
+            true => ", breach ongoing",
+            false => "",
+        };
+
+        info!(
+            "  {}: {} polls, {}, {} threshold breaches, {} log lines{}{}",
+            index_name, summary.polls, records, summary.breaches, summary.log_lines, latency, breached
+        );
+    }
+    info!("  {}", usage.describe());
+}
@@ -0,0 +1,388 @@
+use crate::{index_sinks, report_next_poll, wait_for_poll, IndexClients, Settings, Wake};
+use algolia_monitor::metrics::Metrics;
+use algolia_monitor::notify::AlertState;
+use algolia_monitor::output::Output;
+#[cfg(feature = "sentry")]
+use algolia_monitor::reporting;
+use algolia_monitor::{
+    now, report_destructive, AlgoliaLogEntry, Baseline, DataSource, Failures, LogSource, LogStream, LogType,
+    Monitor, MonitorError, MonitorEvent, MonitoredIndex, Summary, Target,
+};
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use futures::stream::{self, BoxStream, SelectAll, StreamExt};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio::sync::{watch, Semaphore};
+use tracing::info;
+
+/// Prints the new log entries of a single index until shutdown, a permanent error, too many consecutive
+/// failures or a reload leaving it out, returning the statistics of the run
+///
+/// The entries come from a [`LogStream`] of each followed log type, which fetches once for every poll so the
+/// polls keep the delay, the pacing and the reloads of the monitors of the records count.
+pub(crate) async fn tail_index(
+    clients: IndexClients,
+    mut settings: watch::Receiver<Arc<Settings>>,
+    metrics: Metrics,
+    mut target: Target,
+    output: Output,
+    mut shutdown: watch::Receiver<bool>,
+) -> (String, Summary) {
+    let current = settings.borrow_and_update().clone();
+    let mut args = current.args.clone();
+    let mut defaults = current.defaults.clone();
+    let mut alert_options = current.alert_options.clone();
+    let mut notifier = current.notifier.clone();
+    let index_name = target.name();
+    let whole_index = target.subset.is_none();
+    let mut summary = Summary::default();
+    let mut alert_state = AlertState::default();
+    let mut error_alert_state = AlertState::default();
+
+    let source = clients.source.clone();
+    let mut logs = TailedLogs::new(source, &target.index_name, args.log_type, args.follow, args.since);
+    let mut logs_since = args.since;
+    let mut log_filter = target.log_filter(&defaults);
+    let mut query_count = 0;
+    // The error logs of the error rate are read apart from the followed ones, which can be sampled
+    let baseline = Baseline::fixed(0, None);
+    let mut errors = Monitor::builder(clients.source.clone(), target.index_name.clone(), baseline).build();
+
+    let (mut sinks, digest) = index_sinks(&target, &args, &settings, &output);
+    let mut failures = Failures::new(Some(args.max_consecutive_errors));
+    let mut poll_delay = target.poll_delay(&defaults);
+
+    'polls: loop {
+        let index = MonitoredIndex {
+            options: &alert_options,
+            notifier: &notifier,
+            metrics: &metrics,
+            output: &output,
+            index_name: &target.index_name,
+            name: &index_name,
+            subset: target.subset.as_ref().map(|subset| subset.name.as_str()),
+            app_id: &target.app_id,
+            compare: target.compare.as_ref(),
+        };
+        summary.polls += 1;
+        let poll_started = Instant::now();
+        let (mut batch, result) = logs.next_batch().await;
+        logs_since = logs_since.max(batch.last().map(|log| log.timestamp));
+        batch.retain(|log| match log.log_type {
+            Some(LogType::Query) => {
+                query_count = (query_count + 1) % args.sample;
+                query_count == 0
+            }
+            _ => true,
+        });
+        if args.alert_on_destructive && whole_index {
+            for log in &batch {
+                if let Some(operation) = log.destructive_operation(&target.index_name) {
+                    report_destructive(&output, &notifier, &target.app_id, &index_name, log, operation);
+                }
+            }
+        }
+        // Like the entries of a monitor, the ones the filter leaves out still count as seen
+        batch.retain(|log| log_filter.matches(log));
+        summary.log_lines += batch.len() as u64;
+        output.ship_logs(&index_name, &target.index_name, args.log_type, &batch);
+        for log in batch {
+            sinks.handle(&MonitorEvent::LogEntry(Box::new(log)));
+        }
+        if let Err(error) = &result {
+            sinks.handle(&MonitorEvent::Error(error.to_string()));
+        }
+        let result = match (result, args.error_rate_threshold.filter(|_| whole_index)) {
+            (Ok(()), Some(threshold)) => errors
+                .check_errors()
+                .await
+                .map(|errors| index.report_errors(errors, threshold, &mut error_alert_state)),
+            (result, _) => result,
+        };
+
+        // The poll that ran out of recorded responses didn't happen, so it is left out of the run
+        if let Some(error) = result.as_ref().err().filter(|error| error.is_replay_end()) {
+            info!("[{}] Replay of {} finished: {}", now(), index_name, error);
+            summary.polls -= 1;
+            break;
+        }
+        let error = result.as_ref().err().map(ToString::to_string);
+        metrics.poll(&index_name, poll_started.elapsed(), error.as_deref());
+        output.state.logs_since(&index_name, logs_since);
+        match &result {
+            Ok(()) => notifier.heartbeat(&index_name, None),
+            Err(error) => notifier.heartbeat_failed(&index_name, error),
+        }
+
+        match result {
+            Ok(()) => {
+                failures.succeeded();
+                alert_state.reachable();
+            }
+            Err(error) => {
+                let gives_up = failures.failed(&error);
+                #[cfg(feature = "sentry")]
+                if gives_up {
+                    reporting::capture_error(&target.app_id, &index_name, summary.polls, &error);
+                }
+                let (consecutive_errors, digest) = (failures.consecutive(), digest.as_ref());
+                index.report_failure(&error, &mut alert_state, consecutive_errors, gives_up, digest).await;
+                if gives_up {
+                    break;
+                }
+            }
+        }
+
+        if args.until.is_some_and(|until| until < Utc::now())
+            || args.max_iterations.is_some_and(|max_iterations| summary.polls >= max_iterations)
+        {
+            break;
+        }
+
+        // The delay counts from the start of the poll, so the time the poll took doesn't add to it
+        let started = tokio::time::Instant::from_std(poll_started);
+        let mut next_poll = started + output.usage.stretch(poll_delay.next());
+        report_next_poll(&metrics, &index_name, target.delay(&defaults), next_poll);
+        loop {
+            let (client, settings) = (&clients.index, &mut settings);
+            match wait_for_poll(&mut next_poll, client, &target, &defaults, &output, settings, &mut shutdown)
+                .await
+            {
+                Wake::Poll => break,
+                Wake::Stop => break 'polls,
+                Wake::Reload(current, reloaded) => {
+                    log_filter = reloaded.log_filter(&current.defaults);
+                    target = *reloaded;
+                    args = current.args.clone();
+                    defaults = current.defaults.clone();
+                    alert_options = current.alert_options.clone();
+                    notifier = current.notifier.clone();
+                    poll_delay = target.poll_delay(&defaults);
+                    next_poll = started + output.usage.stretch(poll_delay.next());
+                    report_next_poll(&metrics, &index_name, target.delay(&defaults), next_poll);
+                }
+            }
+        }
+    }
+
+    summary.breached = error_alert_state.breached_for().is_some();
+    (index_name, summary)
+}
+
+/// Streams of the log entries of an index, the query, build and error ones together while following, which
+/// fetch once for every poll of the tail
+struct TailedLogs {
+    logs: SelectAll<BoxStream<'static, Result<AlgoliaLogEntry, MonitorError>>>,
+    gates: Vec<Arc<Semaphore>>,
+    fetches: mpsc::UnboundedReceiver<()>,
+}
+
+impl TailedLogs {
+    fn new(
+        source: Arc<dyn DataSource>,
+        index_name: &str,
+        log_type: LogType,
+        follow: bool,
+        since: Option<DateTime<Utc>>,
+    ) -> Self {
+        let log_types = match follow {
+            true => vec![LogType::Query, LogType::Build, LogType::Error],
+            false => vec![log_type],
+        };
+        let (fetched, fetches) = mpsc::unbounded_channel();
+        let mut gates = vec![];
+        let mut streams = vec![];
+        for log_type in log_types {
+            let gate = Arc::new(Semaphore::new(0));
+            let source = GatedLogs {
+                source: source.clone(),
+                gate: gate.clone(),
+                fetched: fetched.clone(),
+            };
+            // The type is only shown on the entries while following, the others all being of the same one
+            let stream = LogStream::new(Arc::new(source), index_name, log_type)
+                .with_interval(Duration::ZERO)
+                .with_since(since)
+                .into_stream()
+                .map(move |log| {
+                    log.map(|mut log| {
+                        log.log_type = Some(log_type).filter(|_| follow);
+                        log
+                    })
+                });
+            gates.push(gate);
+            streams.push(stream.boxed());
+        }
+
+        TailedLogs {
+            logs: stream::select_all(streams),
+            gates,
+            fetches,
+        }
+    }
+
+    /// Lets every stream fetch once and takes the entries they hand out oldest first, along with the first
+    /// failed fetch
+    ///
+    /// A stream tells its fetch is done before handing out the entries of it, which are taken first, so the
+    /// entries of every fetch of the poll are in once each of them told.
+    async fn next_batch(&mut self) -> (Vec<AlgoliaLogEntry>, Result<(), MonitorError>) {
+        for gate in &self.gates {
+            gate.add_permits(1);
+        }
+
+        let mut batch = vec![];
+        let mut result = Ok(());
+        let mut pending = self.gates.len();
+        while pending > 0 {
+            tokio::select! {
+                biased;
+                Some(log) = self.logs.next() => match log {
+                    Ok(log) => batch.push(log),
+                    Err(error) if result.is_ok() => result = Err(error),
+                    Err(_) => {}
+                },
+                Some(()) = self.fetches.recv() => pending -= 1,
+                else => break,
+            }
+        }
+
+        // The types are merged by timestamp, the entries of each one coming oldest first
+        batch.sort_by_key(|log| log.timestamp);
+        (batch, result)
+    }
+}
+
+/// Logs of an index fetched through the source once the tail lets them, telling it when each fetch is done
+struct GatedLogs {
+    source: Arc<dyn DataSource>,
+    gate: Arc<Semaphore>,
+    fetched: UnboundedSender<()>,
+}
+
+impl GatedLogs {
+    fn fetch<'a>(
+        &'a self,
+        fetch: BoxFuture<'a, Result<Vec<AlgoliaLogEntry>, MonitorError>>,
+    ) -> BoxFuture<'a, Result<Vec<AlgoliaLogEntry>, MonitorError>> {
+        Box::pin(async move {
+            if let Ok(permit) = self.gate.acquire().await {
+                permit.forget();
+            }
+            let logs = fetch.await;
+            let _ = self.fetched.send(());
+            logs
+        })
+    }
+}
+
+impl LogSource for GatedLogs {
+    fn logs<'a>(
+        &'a self,
+        index_name: &'a str,
+        since: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<Vec<AlgoliaLogEntry>, MonitorError>> {
+        self.fetch(self.source.logs(index_name, since))
+    }
+
+    fn logs_of_type<'a>(
+        &'a self,
+        index_name: &'a str,
+        log_type: LogType,
+        since: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, Result<Vec<AlgoliaLogEntry>, MonitorError>> {
+        self.fetch(self.source.logs_of_type(index_name, log_type, since))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use algolia_monitor::MockSource;
+    use serde_json::json;
+
+    fn log(timestamp: &str, sha1: &str) -> AlgoliaLogEntry {
+        serde_json::from_value(json!({
+            "timestamp": format!("2024-05-02T10:00:{timestamp}Z"),
+            "method": "POST",
+            "url": "/1/indexes/products/batch",
+            "answer_code": "200",
+            "sha1": sha1,
+        }))
+        .unwrap()
+    }
+
+    fn sha1s(batch: &[AlgoliaLogEntry]) -> Vec<&str> {
+        batch.iter().map(|log| log.extra["sha1"].as_str().unwrap()).collect()
+    }
+
+    fn tailed(source: MockSource, follow: bool) -> TailedLogs {
+        TailedLogs::new(Arc::new(source), "products", LogType::Build, follow, None)
+    }
+
+    #[tokio::test]
+    async fn each_poll_fetches_once_and_hands_out_the_entries_oldest_first() {
+        let source = MockSource::new().with_logs_of_type(
+            "products",
+            LogType::Build,
+            [Ok(vec![log("02", "b2"), log("01", "b1")]), Ok(vec![]), Ok(vec![log("03", "b3"), log("02", "b2")])],
+        );
+        let mut logs = tailed(source, false);
+
+        let (batch, result) = logs.next_batch().await;
+        assert_eq!((sha1s(&batch), result.is_ok()), (vec!["b1", "b2"], true));
+        let (batch, result) = logs.next_batch().await;
+        assert_eq!((sha1s(&batch), result.is_ok()), (vec![], true));
+        // The entry seen by the first fetch is left out when it comes again
+        let (batch, result) = logs.next_batch().await;
+        assert_eq!((sha1s(&batch), result.is_ok()), (vec!["b3"], true));
+
+        let (batch, result) = logs.next_batch().await;
+        assert!(batch.is_empty());
+        assert!(result.unwrap_err().is_replay_end());
+    }
+
+    #[tokio::test]
+    async fn following_merges_the_types_by_timestamp() {
+        let source = MockSource::new()
+            .with_logs_of_type("products", LogType::Query, [Ok(vec![log("04", "q2"), log("01", "q1")])])
+            .with_logs_of_type("products", LogType::Build, [Ok(vec![log("03", "b1")])])
+            .with_logs_of_type("products", LogType::Error, [Ok(vec![log("02", "e1")])]);
+        let mut logs = tailed(source, true);
+
+        let (batch, result) = logs.next_batch().await;
+
+        assert!(result.is_ok());
+        assert_eq!(sha1s(&batch), ["q1", "e1", "b1", "q2"]);
+        let types: Vec<_> = batch.iter().map(|log| log.log_type).collect();
+        assert_eq!(types, [LogType::Query, LogType::Error, LogType::Build, LogType::Query].map(Some));
+    }
+
+    #[tokio::test]
+    async fn failed_fetch_leaves_the_other_types_handed_out() {
+        let timeout = MonitorError::Timeout(Duration::from_secs(10));
+        let source = MockSource::new()
+            .with_logs_of_type("products", LogType::Query, [Err(timeout)])
+            .with_logs_of_type("products", LogType::Build, [Ok(vec![log("01", "b1")])])
+            .with_logs_of_type("products", LogType::Error, [Ok(vec![])]);
+        let mut logs = tailed(source, true);
+
+        let (batch, result) = logs.next_batch().await;
+
+        assert_eq!(sha1s(&batch), ["b1"]);
+        assert!(matches!(result, Err(MonitorError::Timeout(_))), "{result:?}");
+    }
+
+    #[tokio::test]
+    async fn entries_of_the_type_of_the_client_are_left_without_a_type() {
+        let logs = [Ok(vec![log("01", "b1")])];
+        let source = MockSource::new().with_logs_of_type("products", LogType::Build, logs);
+        let mut logs = tailed(source, false);
+
+        let (batch, _) = logs.next_batch().await;
+
+        assert_eq!(batch[0].log_type, None);
+    }
+}
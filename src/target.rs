@@ -0,0 +1,296 @@
+use crate::config::parse_delay;
+use crate::{ApiKey, Config, IndexStats, Jitter, LogFilter, MonitorError, PollDelay, RecordSource, Thresholds};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Expected records count applied to every index or to the named one, given as `COUNT` or `INDEX_NAME=COUNT`
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExpectedRecords {
+    pub index_name: Option<String>,
+    pub count: u64,
+}
+
+impl FromStr for ExpectedRecords {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (index_name, count) = match value.rsplit_once('=') {
+            Some((index_name, count)) => (Some(index_name.to_string()), count),
+            None => (None, value),
+        };
+
+        let count = count
+            .parse()
+            .map_err(|_| format!("invalid records count `{count}`"))?;
+
+        Ok(ExpectedRecords { index_name, count })
+    }
+}
+
+/// Options of the monitors the targets fall back on where the config file doesn't set their own
+#[derive(Clone, Debug)]
+pub struct TargetDefaults {
+    pub thresholds: Thresholds,
+    pub delay: Duration,
+    /// Longest delay the polls back off to while the count stays the same, `None` without backing off
+    pub max_delay: Option<Duration>,
+    pub jitter: Option<Jitter>,
+    /// Speed the delays go by, faster than real time when replaying
+    pub speed: f64,
+    pub all_logs: bool,
+    /// Whether the logs of every target are tailed, whatever `all_logs` says
+    pub follow: bool,
+    pub only_writes: bool,
+    pub log_filter: LogFilter,
+    pub expected_records: Vec<ExpectedRecords>,
+}
+
+impl TargetDefaults {
+    /// Expected records for the index, preferring an `index=count` pair over a global count
+    pub fn expected_records_for(&self, index_name: &str) -> u64 {
+        let for_index = self
+            .expected_records
+            .iter()
+            .find(|expected| expected.index_name.as_deref() == Some(index_name));
+        let for_all = self
+            .expected_records
+            .iter()
+            .find(|expected| expected.index_name.is_none());
+
+        for_index.or(for_all).map(|expected| expected.count).unwrap_or(0)
+    }
+}
+
+/// Index to monitor with the credentials of its application
+#[derive(Clone)]
+pub struct Target {
+    pub app_id: String,
+    /// Name of the application in the output, such as `b2c-prod`, given in the config file or the application ID
+    /// when monitoring several, `None` when the index name is enough
+    pub app_name: Option<String>,
+    pub key: ApiKey,
+    pub index_name: String,
+    /// Overrides of the command line values for this index
+    pub expected_records: Option<u64>,
+    pub thresholds: Option<Thresholds>,
+    pub delay: Option<Duration>,
+    pub all_logs: Option<bool>,
+    pub only_writes: Option<bool>,
+    pub compare: Option<CompareTarget>,
+    /// Subset of the records this target counts, the whole index when `None`
+    pub subset: Option<Subset>,
+}
+
+impl Target {
+    /// Target of the whole index with the defaults of the command line
+    pub fn new(app_id: String, key: ApiKey, index_name: String) -> Self {
+        Target {
+            app_id,
+            app_name: None,
+            key,
+            index_name,
+            expected_records: None,
+            thresholds: None,
+            delay: None,
+            all_logs: None,
+            only_writes: None,
+            compare: None,
+            subset: None,
+        }
+    }
+
+    /// Indices of the config file, each followed by its subsets
+    pub fn from_config(config: &Config) -> Result<Vec<Target>, MonitorError> {
+        let mut targets = vec![];
+        for index in &config.indices {
+            let target = Target {
+                app_id: index.app_id.clone(),
+                app_name: index.app_name.clone(),
+                key: index.key()?,
+                index_name: index.name.clone(),
+                expected_records: index.expected_records,
+                thresholds: index.thresholds()?,
+                delay: match &index.delay {
+                    Some(delay) => Some(parse_delay(&delay.to_string()).map_err(|error| {
+                        MonitorError::InvalidArgument(format!("Invalid delay of index {}: {}", index.name, error))
+                    })?),
+                    None => None,
+                },
+                all_logs: index.all_logs,
+                only_writes: index.only_writes,
+                compare: match &index.compare {
+                    Some(compare) => Some(CompareTarget {
+                        app_id: compare.app_id.clone(),
+                        key: compare.key()?,
+                        index_name: compare.name.clone(),
+                    }),
+                    None => None,
+                },
+                subset: None,
+            };
+
+            let mut subsets = vec![];
+            // Subsets share the logs of their index, which would be printed once per subset
+            for subset in index.subsets.iter().filter(|_| index.all_logs != Some(true)) {
+                let thresholds = subset.thresholds(&index.name)?;
+                let filters = Subset {
+                    name: subset.name.clone(),
+                    filters: subset.filters.clone(),
+                };
+                subsets.push(target.with_subset(filters, subset.expected_records, thresholds));
+            }
+            targets.push(target);
+            targets.extend(subsets);
+        }
+
+        Ok(targets)
+    }
+
+    /// Names the applications of the targets by their ID when there are several, so the same index name in two
+    /// of them can be told apart, the names of the config file taking precedence
+    pub fn name_applications(targets: &mut [Target]) {
+        let Some(first) = targets.first().map(|target| target.app_id.clone()) else {
+            return;
+        };
+        if targets.iter().all(|target| target.app_id == first) {
+            return;
+        }
+
+        for target in targets {
+            target.app_name.get_or_insert_with(|| target.app_id.clone());
+        }
+    }
+
+    /// Target counting the subset of the records of this index, with the thresholds of the subset when it has
+    /// some and the ones of the index otherwise
+    pub fn with_subset(
+        &self,
+        subset: Subset,
+        expected_records: Option<u64>,
+        thresholds: Option<Thresholds>,
+    ) -> Self {
+        Target {
+            expected_records,
+            thresholds: thresholds.or(self.thresholds),
+            compare: None,
+            subset: Some(subset),
+            ..self.clone()
+        }
+    }
+
+    /// Name of the index in the output, along with the subset as in `products:store_1` and the application as in
+    /// `b2c-prod/products` when it has a name
+    pub fn name(&self) -> String {
+        let index_name = match &self.subset {
+            Some(subset) => format!("{}:{}", self.index_name, subset.name),
+            None => self.index_name.clone(),
+        };
+        match &self.app_name {
+            Some(app_name) => format!("{app_name}/{index_name}"),
+            None => index_name,
+        }
+    }
+
+    /// Thresholds of the target, the default ones unless the config file sets some
+    pub fn thresholds(&self, defaults: &TargetDefaults) -> Thresholds {
+        self.thresholds.unwrap_or(defaults.thresholds)
+    }
+
+    /// Delay between two polls of the target, the default one unless the config file sets one
+    pub fn delay(&self, defaults: &TargetDefaults) -> Duration {
+        self.delay.unwrap_or(defaults.delay)
+    }
+
+    pub fn poll_delay(&self, defaults: &TargetDefaults) -> PollDelay {
+        PollDelay::new(self.delay(defaults), defaults.max_delay, defaults.jitter).with_speed(defaults.speed)
+    }
+
+    /// Longest time between two polls of the target, backed off and jittered
+    pub fn longest_delay(&self, defaults: &TargetDefaults) -> Duration {
+        PollDelay::longest(self.delay(defaults), defaults.max_delay, defaults.jitter)
+    }
+
+    /// Whether the logs of the target are printed on every poll instead of watching its records count
+    pub fn tails_logs(&self, defaults: &TargetDefaults) -> bool {
+        self.all_logs.unwrap_or(defaults.all_logs) || defaults.follow
+    }
+
+    pub fn log_filter(&self, defaults: &TargetDefaults) -> LogFilter {
+        defaults.log_filter.clone().with_only_writes(self.only_writes.unwrap_or(defaults.only_writes))
+    }
+
+    /// Whether a monitor of the other target can go on as one of this target, as only its thresholds, delay or
+    /// log filter differ
+    pub fn same_monitor(&self, other: &Target) -> bool {
+        self.app_id == other.app_id
+            && self.key == other.key
+            && self.index_name == other.index_name
+            && self.expected_records == other.expected_records
+            && self.all_logs == other.all_logs
+            && self.compare == other.compare
+            && self.subset == other.subset
+    }
+
+    /// Expected records of the target, a subset starts from its current count unless the config file sets one
+    pub fn expected_records(&self, defaults: &TargetDefaults) -> u64 {
+        match (self.expected_records, &self.subset) {
+            (Some(expected_records), _) => expected_records,
+            (None, Some(_)) => 0,
+            (None, None) => defaults.expected_records_for(&self.index_name),
+        }
+    }
+
+    /// Statistics of the records the target counts, the ones matching the filters of its subset if it has one
+    pub async fn stats(&self, source: &dyn RecordSource) -> Result<IndexStats, MonitorError> {
+        match &self.subset {
+            Some(subset) => source.filtered_stats(&self.index_name, &subset.filters).await,
+            None => source.index_stats(&self.index_name).await,
+        }
+    }
+}
+
+/// Subset of the records of an index, given as `NAME=FILTERS`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Subset {
+    pub name: String,
+    pub filters: String,
+}
+
+impl FromStr for Subset {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.split_once('=') {
+            Some((name, filters)) if !name.trim().is_empty() && !filters.trim().is_empty() => Ok(Subset {
+                name: name.trim().to_string(),
+                filters: filters.trim().to_string(),
+            }),
+            _ => Err("expected a subset in `NAME=FILTERS` format".to_string()),
+        }
+    }
+}
+
+/// Index to compare a monitored one to, given as `APP_ID:KEY:INDEX_NAME`
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompareTarget {
+    pub app_id: String,
+    pub key: ApiKey,
+    pub index_name: String,
+}
+
+impl FromStr for CompareTarget {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.splitn(3, ':').collect::<Vec<_>>()[..] {
+            [app_id, key, index_name] if !app_id.is_empty() && !key.is_empty() && !index_name.is_empty() => {
+                Ok(CompareTarget {
+                    app_id: app_id.to_string(),
+                    key: ApiKey::new(key),
+                    index_name: index_name.to_string(),
+                })
+            }
+            _ => Err("expected an index in `APP_ID:KEY:INDEX_NAME` format".to_string()),
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use algolia_monitor::metrics::OtlpMetrics;
+use algolia_monitor::now;
+use opentelemetry::metrics::MeterProvider;
+use opentelemetry::trace::TracerProvider;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::{ExporterBuildError, MetricExporter, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use tracing::error;
+
+const SERVICE_NAME: &str = "algolia-monitor";
+
+/// Transport of the OTLP exports
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum OtlpProtocol {
+    /// gRPC, usually on port 4317
+    Grpc,
+    /// Protobuf over HTTP, usually on port 4318, with `/v1/metrics` and `/v1/traces` added to the endpoint
+    Http,
+}
+
+/// OpenTelemetry providers exporting the metrics, and the spans of the polls when enabled, to an OTLP endpoint
+pub struct Telemetry {
+    meter_provider: SdkMeterProvider,
+    tracer_provider: Option<SdkTracerProvider>,
+}
+
+impl Telemetry {
+    /// Starts the providers, returning the metrics backend recording to them
+    pub fn start(
+        endpoint: &str,
+        protocol: OtlpProtocol,
+        app_id: Option<&str>,
+        traces: bool,
+    ) -> Result<(Telemetry, OtlpMetrics), ExporterBuildError> {
+        let mut attributes = vec![KeyValue::new(
+            "host.name",
+            gethostname::gethostname().to_string_lossy().into_owned(),
+        )];
+        if let Some(app_id) = app_id {
+            attributes.push(KeyValue::new("algolia.app_id", app_id.to_string()));
+        }
+        let resource = Resource::builder()
+            .with_service_name(SERVICE_NAME)
+            .with_attributes(attributes)
+            .build();
+
+        let metric_exporter = match protocol {
+            OtlpProtocol::Grpc => MetricExporter::builder().with_tonic().with_endpoint(endpoint).build()?,
+            OtlpProtocol::Http => MetricExporter::builder()
+                .with_http()
+                .with_endpoint(signal_url(endpoint, "metrics"))
+                .build()?,
+        };
+        let meter_provider = SdkMeterProvider::builder()
+            .with_periodic_exporter(metric_exporter)
+            .with_resource(resource.clone())
+            .build();
+        let mut metrics = OtlpMetrics::new(&meter_provider.meter(SERVICE_NAME));
+
+        let tracer_provider = match traces {
+            true => {
+                let span_exporter = match protocol {
+                    OtlpProtocol::Grpc => SpanExporter::builder().with_tonic().with_endpoint(endpoint).build()?,
+                    OtlpProtocol::Http => SpanExporter::builder()
+                        .with_http()
+                        .with_endpoint(signal_url(endpoint, "traces"))
+                        .build()?,
+                };
+                let tracer_provider = SdkTracerProvider::builder()
+                    .with_batch_exporter(span_exporter)
+                    .with_resource(resource)
+                    .build();
+                metrics = metrics.with_tracer(tracer_provider.tracer(SERVICE_NAME));
+                Some(tracer_provider)
+            }
+            false => None,
+        };
+
+        Ok((
+            Telemetry {
+                meter_provider,
+                tracer_provider,
+            },
+            metrics,
+        ))
+    }
+
+    /// Exports what is still pending and stops the providers
+    ///
+    /// The providers block while they flush, so they are shut down away from the runtime threads.
+    pub async fn shutdown(self) {
+        let _ = tokio::task::spawn_blocking(move || {
+            if let Err(error) = self.meter_provider.shutdown() {
+                error!("[{}] Failed to export the last OpenTelemetry metrics: {}", now(), error);
+            }
+            if let Some(Err(error)) = self.tracer_provider.map(|provider| provider.shutdown()) {
+                error!("[{}] Failed to export the last OpenTelemetry spans: {}", now(), error);
+            }
+        })
+        .await;
+    }
+}
+
+fn signal_url(endpoint: &str, signal: &str) -> String {
+    format!("{}/v1/{}", endpoint.trim_end_matches('/'), signal)
+}
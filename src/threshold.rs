@@ -0,0 +1,172 @@
+use crate::error::MonitorError;
+use std::fmt;
+use std::str::FromStr;
+
+/// Limits the records count may move away from the expected value
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Thresholds {
+    /// Lowest allowed difference, zero or negative
+    pub min_delta: Option<i64>,
+    /// Highest allowed difference, zero or positive
+    pub max_delta: Option<i64>,
+    /// Difference in percent of the expected records count, in either direction
+    pub delta_percent: Option<f64>,
+}
+
+/// Threshold that fired, with the difference that made it fire
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ThresholdBreach {
+    MinDelta { limit: i64, difference: i64 },
+    MaxDelta { limit: i64, difference: i64 },
+    Percent { limit: f64, difference: f64 },
+    /// Records showed up in an index expected to be empty, where percentages are undefined
+    EmptyBaseline { records: u64 },
+    /// Every record is gone from an index expected to have some
+    ZeroRecords,
+    /// Index data grew past the maximum size in bytes
+    DataSize { limit: u64, data_size: u64 },
+    /// 95th percentile of the search processing times rose past the maximum in milliseconds
+    Latency { limit: u64, p95: u64 },
+    /// Records disappeared faster than the maximum loss in records per minute between each poll of the window
+    LossRate { limit: f64, loss: f64 },
+}
+
+impl fmt::Display for ThresholdBreach {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThresholdBreach::MinDelta { limit, difference } => {
+                write!(f, "difference {difference:+} is below the minimum delta of {limit:+}")
+            }
+            ThresholdBreach::MaxDelta { limit, difference } => {
+                write!(f, "difference {difference:+} is above the maximum delta of {limit:+}")
+            }
+            ThresholdBreach::Percent { limit, difference } => {
+                write!(f, "difference {difference:+.2}% is past the delta of ±{limit}%")
+            }
+            ThresholdBreach::EmptyBaseline { records } => {
+                write!(f, "{records} records found while none were expected")
+            }
+            ThresholdBreach::ZeroRecords => write!(f, "dropped to 0 records"),
+            ThresholdBreach::DataSize { limit, data_size } => {
+                write!(f, "data size of {data_size} bytes is above the maximum of {limit} bytes")
+            }
+            ThresholdBreach::Latency { limit, p95 } => {
+                write!(f, "p95 latency of {p95}ms is above the maximum of {limit}ms")
+            }
+            ThresholdBreach::LossRate { limit, loss } => {
+                write!(f, "is dropping by {loss:.1} records per minute, faster than the maximum of {limit}")
+            }
+        }
+    }
+}
+
+impl Thresholds {
+    /// Thresholds of the legacy signed delta, which watches drops when negative and growth when positive
+    pub fn from_delta(delta: i64) -> Self {
+        Thresholds {
+            min_delta: (delta < 0).then_some(delta),
+            max_delta: (delta > 0).then_some(delta),
+            delta_percent: None,
+        }
+    }
+
+    /// Thresholds from the individual limits, `None` when no limit is set
+    pub fn from_limits(
+        delta: Option<i64>,
+        min_delta: Option<i64>,
+        max_delta: Option<i64>,
+        delta_percent: Option<f64>,
+    ) -> Result<Option<Self>, MonitorError> {
+        let invalid = |reason: &str| Err(MonitorError::InvalidArgument(reason.to_string()));
+        if delta.is_some() && (min_delta.is_some() || max_delta.is_some()) {
+            return invalid("delta can't be combined with min_delta or max_delta");
+        }
+        if min_delta.is_some_and(|min_delta| min_delta > 0) {
+            return invalid("the minimum delta must be zero or negative");
+        }
+        if max_delta.is_some_and(|max_delta| max_delta < 0) {
+            return invalid("the maximum delta must be zero or positive");
+        }
+        if delta_percent.is_some_and(|percent| !percent.is_finite() || percent < 0.0) {
+            return invalid("the delta percentage must be zero or positive");
+        }
+
+        let mut thresholds = match (delta, min_delta, max_delta, delta_percent) {
+            (None, None, None, None) => return Ok(None),
+            (Some(delta), ..) => Thresholds::from_delta(delta),
+            (None, min_delta, max_delta, _) => Thresholds {
+                min_delta,
+                max_delta,
+                delta_percent: None,
+            },
+        };
+
+        thresholds.delta_percent = delta_percent;
+        Ok(Some(thresholds))
+    }
+
+    /// First threshold the current records count breaches, if any
+    pub fn check(&self, expected_records: u64, current_records: u64) -> Option<ThresholdBreach> {
+        let difference = current_records as i64 - expected_records as i64;
+
+        if let Some(limit) = self.min_delta {
+            if difference < limit {
+                return Some(ThresholdBreach::MinDelta { limit, difference });
+            }
+        }
+
+        if let Some(limit) = self.max_delta {
+            if difference > limit {
+                return Some(ThresholdBreach::MaxDelta { limit, difference });
+            }
+        }
+
+        if let Some(limit) = self.delta_percent {
+            if expected_records == 0 {
+                return (current_records > 0)
+                    .then_some(ThresholdBreach::EmptyBaseline { records: current_records });
+            }
+
+            let difference = difference as f64 * 100.0 / expected_records as f64;
+            if difference.abs() > limit {
+                return Some(ThresholdBreach::Percent { limit, difference });
+            }
+        }
+
+        None
+    }
+}
+
+/// Allowed difference of a records count from another one, given as `500` or `2%`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CountDelta {
+    Absolute(u64),
+    Percent(f64),
+}
+
+impl FromStr for CountDelta {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid delta `{value}`, expected a number of records or a percentage");
+        match value.trim().strip_suffix('%') {
+            Some(percent) => match percent.parse::<f64>() {
+                Ok(percent) if percent.is_finite() && percent >= 0.0 => Ok(CountDelta::Percent(percent)),
+                _ => Err(invalid()),
+            },
+            None => value.trim().parse().map(CountDelta::Absolute).map_err(|_| invalid()),
+        }
+    }
+}
+
+impl CountDelta {
+    /// Whether `records` is further from `reference_records` than allowed, percentages are of the reference
+    pub fn exceeded(&self, reference_records: u64, records: u64) -> bool {
+        let difference = reference_records.abs_diff(records);
+        match *self {
+            CountDelta::Absolute(limit) => difference > limit,
+            CountDelta::Percent(_) if reference_records == 0 => difference > 0,
+            CountDelta::Percent(limit) => difference as f64 * 100.0 / reference_records as f64 > limit,
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use crate::error::MonitorError;
+use reqwest::{Certificate, ClientBuilder};
+use std::path::PathBuf;
+
+/// Certificates the HTTP clients trust on top of the ones of the system, such as the CA of a proxy
+/// intercepting TLS, or no check of the certificates at all
+#[derive(Debug, Clone, Default)]
+pub struct TlsConfig {
+    root_certificates: Vec<Certificate>,
+    insecure: bool,
+}
+
+impl TlsConfig {
+    /// Reads the PEM certificates of the files, each of which may hold several, failing on a file that can't
+    /// be read or holds none
+    pub fn load(ca_certs: &[PathBuf], insecure: bool) -> Result<Self, MonitorError> {
+        let mut root_certificates = vec![];
+        for path in ca_certs {
+            let pem = std::fs::read(path).map_err(|source| MonitorError::Io {
+                path: path.clone(),
+                source,
+            })?;
+            let certificates = Certificate::from_pem_bundle(&pem).map_err(|error| {
+                MonitorError::InvalidArgument(format!("Invalid PEM certificate in {}: {}", path.display(), error))
+            })?;
+            if certificates.is_empty() {
+                return Err(MonitorError::InvalidArgument(format!(
+                    "No PEM certificate in {}",
+                    path.display()
+                )));
+            }
+            root_certificates.extend(certificates);
+        }
+
+        Ok(TlsConfig {
+            root_certificates,
+            insecure,
+        })
+    }
+
+    /// Whether any certificate is accepted, which leaves the requests open to interception
+    pub fn is_insecure(&self) -> bool {
+        self.insecure
+    }
+
+    /// Builder of an HTTP client trusting the certificates
+    pub fn client_builder(&self) -> ClientBuilder {
+        let mut builder = reqwest::Client::builder();
+        for certificate in &self.root_certificates {
+            builder = builder.add_root_certificate(certificate.clone());
+        }
+
+        builder.danger_accept_invalid_certs(self.insecure)
+    }
+
+    /// HTTP client trusting the certificates, for the requests that don't go to Algolia
+    pub fn client(&self) -> Result<reqwest::Client, MonitorError> {
+        Ok(self.client_builder().build()?)
+    }
+}
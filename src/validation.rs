@@ -0,0 +1,152 @@
+use algolia_monitor::{now, AlgoliaClient, ApiKey, MonitorError};
+use reqwest::StatusCode;
+use tracing::warn;
+
+/// ACLs that let a key change the data or the settings of the indices, which the monitor never does
+const WRITE_ACLS: [&str; 4] = ["addObject", "deleteObject", "deleteIndex", "editSettings"];
+
+/// Indices of an application read with the same API key, checked before they are monitored
+pub struct Credentials<'a> {
+    pub app_id: &'a str,
+    pub key: &'a ApiKey,
+    pub client: &'a AlgoliaClient,
+    /// ACLs the monitor needs the key to have, such as `search` and `logs`
+    pub acls: Vec<&'static str>,
+    pub index_names: Vec<&'a str>,
+}
+
+/// Fails with what keeps the indices from being monitored: an application ID that doesn't resolve, a key
+/// Algolia rejects or that lacks an ACL or access to one of the indices, or an index that doesn't exist
+///
+/// A failure to reach Algolia for another reason is only a warning, the polls retrying on their own, and so is
+/// a key that can write to the indices, such as the Admin API key.
+pub async fn validate(credentials: &Credentials<'_>) -> Result<(), MonitorError> {
+    let Credentials {
+        app_id,
+        key,
+        client,
+        ..
+    } = *credentials;
+    let invalid = |message: String| Err(MonitorError::InvalidArgument(message));
+
+    let permissions = match client.validate_key(key).await {
+        Ok(permissions) => permissions,
+        Err(error) if error.is_unresolved_host() => {
+            return invalid(format!("Application {app_id} doesn't exist, none of its hosts resolve: {error}"));
+        }
+        Err(MonitorError::Api { status, message })
+            if [StatusCode::UNAUTHORIZED, StatusCode::FORBIDDEN, StatusCode::NOT_FOUND].contains(&status) =>
+        {
+            return invalid(format!(
+                "Algolia rejected the API key {key} of application {app_id}: {message}"
+            ));
+        }
+        Err(error) => {
+            warn!("[{}] Could not validate the API key of application {}: {}", now(), app_id, error);
+            return Ok(());
+        }
+    };
+
+    // Keys with no ACL listed are left alone, as they may be allowed everything in a way not reported here
+    let missing: Vec<_> = credentials
+        .acls
+        .iter()
+        .filter(|acl| !permissions.acl.is_empty() && !permissions.allows(acl))
+        .map(|acl| format!("`{acl}`"))
+        .collect();
+    if !missing.is_empty() {
+        return invalid(format!(
+            "API key {key} of application {app_id} lacks the {} ACL the monitor needs",
+            missing.join(" and ")
+        ));
+    }
+
+    let writes: Vec<_> = WRITE_ACLS.iter().filter(|acl| permissions.allows(acl)).collect();
+    if !writes.is_empty() {
+        let writes: Vec<_> = writes.iter().map(|acl| format!("`{acl}`")).collect();
+        let needed: Vec<_> = credentials.acls.iter().map(|acl| format!("`{acl}`")).collect();
+        warn!(
+            "[{}] WARNING: API key {} of application {} can also write to the indices ({}), a key with only {} \
+             is enough to monitor {} and can't change them if it leaks",
+            now(),
+            key,
+            app_id,
+            writes.join(", "),
+            needed.join(", "),
+            credentials.index_names.join(", ")
+        );
+    }
+
+    if let Some(index_name) = credentials.index_names.iter().find(|name| !permissions.covers(name)) {
+        return invalid(format!(
+            "API key {key} of application {app_id} has no access to index {index_name}, only to {}",
+            permissions.indexes.join(", ")
+        ));
+    }
+
+    // The list of indices answers for all of them at once and tells what a mistyped name was meant to be
+    if permissions.allows("listIndexes") {
+        let names = match client.index_names().await {
+            Ok(names) => names,
+            Err(error) => {
+                warn!("[{}] Could not list the indices of application {}: {}", now(), app_id, error);
+                return Ok(());
+            }
+        };
+        let missing: Vec<_> = credentials
+            .index_names
+            .iter()
+            .filter(|index_name| !names.iter().any(|name| name == *index_name))
+            .map(|index_name| match closest(index_name, &names) {
+                Some(name) => format!("Index {index_name} doesn't exist in application {app_id}, did you mean {name}?"),
+                None => format!("Index {index_name} doesn't exist in application {app_id}"),
+            })
+            .collect();
+
+        return match missing.is_empty() {
+            true => Ok(()),
+            false => invalid(missing.join("\n")),
+        };
+    }
+
+    for index_name in &credentials.index_names {
+        match client.total_records(index_name).await {
+            Err(error) if error.is_not_found() => {
+                return invalid(format!("Index {index_name} doesn't exist in application {app_id}"));
+            }
+            // Any other failure shows again on the first poll
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Name of the index the mistyped one is the closest to, when close enough to be what was meant
+fn closest<'a>(index_name: &str, names: &'a [String]) -> Option<&'a str> {
+    let max_distance = (index_name.chars().count() / 3).max(2);
+
+    names
+        .iter()
+        .map(|name| (distance(&index_name.to_lowercase(), &name.to_lowercase()), name))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, name)| name.as_str())
+}
+
+/// Levenshtein distance between the two names, in characters
+fn distance(from: &str, to: &str) -> usize {
+    let to: Vec<_> = to.chars().collect();
+    let mut previous: Vec<_> = (0..=to.len()).collect();
+
+    for (row, from_char) in from.chars().enumerate() {
+        let mut current = vec![row + 1];
+        for (column, to_char) in to.iter().enumerate() {
+            let substitution = previous[column] + usize::from(from_char != *to_char);
+            current.push(substitution.min(previous[column + 1] + 1).min(current[column] + 1));
+        }
+        previous = current;
+    }
+
+    previous[to.len()]
+}
@@ -0,0 +1,276 @@
+//! Requests of the Algolia client against a mock server, and the errors each kind of response turns into
+
+use algolia_monitor::{AlgoliaClient, ApiKey, LogType, MonitorError};
+use reqwest::StatusCode;
+use serde_json::json;
+use std::time::Duration;
+use wiremock::matchers::{header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const INDEX: &str = "products";
+
+fn client(server: &MockServer) -> AlgoliaClient {
+    AlgoliaClient::new("APPID", &ApiKey::new("mock-server-search-key"))
+        .unwrap()
+        .with_base_urls(vec![format!("{}/1/", server.uri())])
+}
+
+async fn respond(server: &MockServer, http_method: &str, endpoint: &str, response: ResponseTemplate) {
+    Mock::given(method(http_method))
+        .and(path(endpoint))
+        .respond_with(response)
+        .mount(server)
+        .await;
+}
+
+async fn respond_logs(server: &MockServer, response: ResponseTemplate) {
+    respond(server, "GET", "/1/logs", response).await;
+}
+
+#[tokio::test]
+async fn total_records_reads_nb_hits_of_an_empty_query() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/1/indexes/products/query"))
+        .and(header("x-algolia-application-id", "APPID"))
+        .and(header("x-algolia-api-key", "mock-server-search-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "hits": [], "nbHits": 1234 })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    assert_eq!(client(&server).total_records(INDEX).await.unwrap(), 1234);
+}
+
+#[tokio::test]
+async fn total_records_without_nb_hits_is_a_missing_field() {
+    let server = MockServer::start().await;
+    let response = ResponseTemplate::new(200).set_body_json(json!({ "hits": [] }));
+    respond(&server, "POST", "/1/indexes/products/query", response).await;
+
+    let error = client(&server).total_records(INDEX).await.unwrap_err();
+
+    assert!(matches!(error, MonitorError::MissingField("nbHits")), "{error:?}");
+}
+
+#[tokio::test]
+async fn forbidden_query_is_an_api_error_with_the_message_of_the_body() {
+    let server = MockServer::start().await;
+    let body = json!({ "message": "Invalid Application-ID or API key", "status": 403 });
+    respond(&server, "POST", "/1/indexes/products/query", ResponseTemplate::new(403).set_body_json(body)).await;
+
+    let error = client(&server).total_records(INDEX).await.unwrap_err();
+
+    match &error {
+        MonitorError::Api { status, message } => {
+            assert_eq!(*status, StatusCode::FORBIDDEN);
+            assert_eq!(message, "Invalid Application-ID or API key");
+        }
+        error => panic!("expected an API error, got {error:?}"),
+    }
+    assert!(error.is_permanent());
+}
+
+#[tokio::test]
+async fn truncated_query_response_is_invalid_json() {
+    let server = MockServer::start().await;
+    let response = ResponseTemplate::new(200).set_body_string(r#"{"hits": [], "nbHi"#);
+    respond(&server, "POST", "/1/indexes/products/query", response).await;
+
+    let error = client(&server).total_records(INDEX).await.unwrap_err();
+
+    assert!(matches!(error, MonitorError::Json(_)), "{error:?}");
+}
+
+#[tokio::test]
+async fn query_response_that_isnt_json_is_invalid_json() {
+    let server = MockServer::start().await;
+    let response = ResponseTemplate::new(200).set_body_string("<html>Bad gateway</html>");
+    respond(&server, "POST", "/1/indexes/products/query", response).await;
+
+    let error = client(&server).total_records(INDEX).await.unwrap_err();
+
+    assert!(matches!(error, MonitorError::Json(_)), "{error:?}");
+}
+
+#[tokio::test]
+async fn get_logs_reads_the_entries_of_the_index() {
+    let server = MockServer::start().await;
+    let logs = json!({ "logs": [
+        {
+            "timestamp": "2024-05-02T10:14:03Z",
+            "method": "POST",
+            "answer_code": "200",
+            "url": "/1/indexes/products/batch",
+            "sha1": "a1",
+        },
+        {
+            "timestamp": "2024-05-02 10:14:01",
+            "method": "GET",
+            "answer_code": 404,
+            "url": "/1/indexes/products/task/42",
+            "sha1": "a2",
+        },
+    ]});
+    Mock::given(method("GET"))
+        .and(path("/1/logs"))
+        .and(query_param("indexName", INDEX))
+        .and(query_param("type", "build"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(logs))
+        .mount(&server)
+        .await;
+
+    let logs = client(&server).get_logs(INDEX, None).await.unwrap();
+
+    assert_eq!(logs.len(), 2);
+    assert_eq!(logs[0].timestamp.to_rfc3339(), "2024-05-02T10:14:03+00:00");
+    assert_eq!(logs[0].status_code(), Some(200));
+    assert!(logs[0].is_write());
+    assert_eq!(logs[1].timestamp.to_rfc3339(), "2024-05-02T10:14:01+00:00");
+    assert_eq!(logs[1].status_code(), Some(404));
+    assert_eq!(logs[1].extra.get("sha1"), Some(&json!("a2")));
+}
+
+#[tokio::test]
+async fn logs_since_a_timestamp_leaves_out_the_older_entries() {
+    let server = MockServer::start().await;
+    let logs = json!({ "logs": [
+        { "timestamp": "2024-05-02T10:14:05Z", "method": "POST", "sha1": "a1" },
+        { "timestamp": "2024-05-02T10:14:03Z", "method": "GET", "sha1": "a2" },
+        { "timestamp": "2024-05-02T10:14:01Z", "method": "GET", "sha1": "a3" },
+    ]});
+    respond_logs(&server, ResponseTemplate::new(200).set_body_json(logs)).await;
+    let since = "2024-05-02T10:14:03Z".parse().unwrap();
+
+    let logs = client(&server).logs_since(INDEX, since).await.unwrap();
+
+    let sha1s: Vec<_> = logs.iter().map(|log| log.extra.get("sha1").cloned()).collect();
+    assert_eq!(sha1s, [Some(json!("a1")), Some(json!("a2"))]);
+}
+
+#[tokio::test]
+async fn paging_of_the_logs_stops_short_of_an_offset_overflowing() {
+    let server = MockServer::start().await;
+    let logs: Vec<_> = (0..10)
+        .map(|second| json!({ "timestamp": format!("2024-05-02T10:14:{:02}Z", 59 - second), "method": "GET" }))
+        .collect();
+    Mock::given(method("GET"))
+        .and(path("/1/logs"))
+        .and(query_param("offset", (u32::MAX - 5).to_string()))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "logs": logs })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let client = client(&server).with_logs(LogType::Build, u32::MAX - 5, 10);
+    let since = "2024-05-02T10:00:00Z".parse().unwrap();
+
+    let logs = client.logs_since(INDEX, since).await.unwrap();
+
+    assert_eq!(logs.len(), 10);
+}
+
+#[tokio::test]
+async fn get_logs_of_an_empty_array_is_no_entries() {
+    let server = MockServer::start().await;
+    respond_logs(&server, ResponseTemplate::new(200).set_body_json(json!({ "logs": [] }))).await;
+
+    let logs = client(&server).get_logs(INDEX, None).await.unwrap();
+
+    assert!(logs.is_empty());
+}
+
+#[tokio::test]
+async fn get_logs_without_the_logs_field_is_a_missing_field() {
+    let server = MockServer::start().await;
+    respond_logs(&server, ResponseTemplate::new(200).set_body_json(json!({ "message": "ok" }))).await;
+
+    let error = client(&server).get_logs(INDEX, None).await.unwrap_err();
+
+    assert!(matches!(error, MonitorError::MissingField("logs")), "{error:?}");
+}
+
+#[tokio::test]
+async fn get_logs_skips_the_entries_without_a_timestamp() {
+    let server = MockServer::start().await;
+    let logs = json!({ "logs": [
+        { "method": "GET", "url": "/1/indexes/products/query", "sha1": "a1" },
+        { "timestamp": "not a time", "method": "GET", "sha1": "a2" },
+        { "timestamp": "2024-05-02T10:14:03Z", "method": "DELETE", "url": "/1/indexes/products", "sha1": "a3" },
+    ]});
+    respond_logs(&server, ResponseTemplate::new(200).set_body_json(logs)).await;
+
+    let logs = client(&server).get_logs(INDEX, None).await.unwrap();
+
+    assert_eq!(logs.len(), 1);
+    assert_eq!(logs[0].extra.get("sha1"), Some(&json!("a3")));
+}
+
+#[tokio::test]
+async fn forbidden_logs_tell_the_key_needs_the_logs_acl() {
+    let server = MockServer::start().await;
+    let body = json!({ "message": "Method not allowed with this API key", "status": 403 });
+    respond_logs(&server, ResponseTemplate::new(403).set_body_json(body)).await;
+
+    let error = client(&server).get_logs(INDEX, None).await.unwrap_err();
+
+    match &error {
+        MonitorError::Api { status, message } => {
+            assert_eq!(*status, StatusCode::FORBIDDEN);
+            assert_eq!(
+                message,
+                "Method not allowed with this API key, the API key needs the `logs` ACL to read the logs"
+            );
+        }
+        error => panic!("expected an API error, got {error:?}"),
+    }
+}
+
+#[tokio::test]
+async fn truncated_logs_response_is_invalid_json() {
+    let server = MockServer::start().await;
+    let response = ResponseTemplate::new(200).set_body_string(r#"{"logs": [{"timestamp": "2024-05-02T10:1"#);
+    respond_logs(&server, response).await;
+
+    let error = client(&server).get_logs(INDEX, None).await.unwrap_err();
+
+    assert!(matches!(error, MonitorError::Json(_)), "{error:?}");
+}
+
+#[tokio::test]
+async fn server_errors_are_retried_until_a_success() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/1/indexes/products/query"))
+        .respond_with(ResponseTemplate::new(503).set_body_json(json!({ "message": "Service unavailable" })))
+        .up_to_n_times(2)
+        .expect(2)
+        .mount(&server)
+        .await;
+    respond(
+        &server,
+        "POST",
+        "/1/indexes/products/query",
+        ResponseTemplate::new(200).set_body_json(json!({ "nbHits": 7 })),
+    )
+    .await;
+
+    let client = client(&server).with_retries(2, Duration::from_millis(1));
+
+    assert_eq!(client.total_records(INDEX).await.unwrap(), 7);
+}
+
+#[tokio::test]
+async fn response_slower_than_the_timeout_is_a_timeout() {
+    let server = MockServer::start().await;
+    let response = ResponseTemplate::new(200)
+        .set_body_json(json!({ "nbHits": 7 }))
+        .set_delay(Duration::from_secs(2));
+    respond(&server, "POST", "/1/indexes/products/query", response).await;
+
+    let client = client(&server).with_timeouts(Duration::from_millis(200), Duration::from_secs(1)).unwrap();
+
+    let error = client.total_records(INDEX).await.unwrap_err();
+
+    let timeout = Duration::from_millis(200);
+    assert!(matches!(error, MonitorError::Timeout(after) if after == timeout), "{error:?}");
+}
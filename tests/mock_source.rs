@@ -0,0 +1,111 @@
+//! Monitors of scripted responses, the way the alert handling of an application embedding the library is tested
+
+use algolia_monitor::{
+    AlgoliaLogEntry, Baseline, IndexStats, LogType, MockSource, Monitor, MonitorError, RecordSource, Thresholds,
+};
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+fn stats(records: u64) -> Result<IndexStats, MonitorError> {
+    Ok(IndexStats {
+        records,
+        data_size: None,
+        created_at: None,
+        updated_at: None,
+        processing_time_ms: None,
+    })
+}
+
+fn build_log(timestamp: DateTime<Utc>, task_id: u64) -> AlgoliaLogEntry {
+    serde_json::from_value(json!({
+        "timestamp": timestamp,
+        "method": "POST",
+        "url": "/1/indexes/products/batch",
+        "answer_code": "200",
+        "answer": format!(r#"{{"taskID":{task_id},"objectIDs":["1"]}}"#),
+    }))
+    .unwrap()
+}
+
+#[tokio::test]
+async fn indices_sharing_the_source_get_their_own_responses() {
+    let source = MockSource::new()
+        .with_records("products", [1000, 400])
+        .with_logs("products", [Ok(vec![])])
+        .with_records("pages", [50, 50]);
+    let source = Arc::new(source);
+    let thresholds = Thresholds::from_delta(-100);
+    let mut products = Monitor::builder(source.clone(), "products", Baseline::fixed(1000, None))
+        .with_thresholds(thresholds)
+        .build();
+    let mut pages = Monitor::builder(source, "pages", Baseline::fixed(50, None))
+        .with_thresholds(thresholds)
+        .build();
+
+    for expected in [(1000, 50), (400, 50)] {
+        let products = products.poll_records().await.unwrap();
+        let pages = pages.poll_records().await.unwrap();
+        assert_eq!((products.records.current_records, pages.records.current_records), expected);
+    }
+}
+
+#[tokio::test]
+async fn breach_callback_is_called_on_every_breached_poll_but_not_a_failed_one() {
+    let source = MockSource::new().with_stats(
+        "products",
+        [stats(800), Err(MonitorError::Timeout(Duration::from_secs(10))), stats(700), stats(1000), stats(600)],
+    );
+    // The logs are fetched on every breached poll
+    let source = source.with_logs("products", [Ok(vec![]), Ok(vec![]), Ok(vec![])]);
+    let alerts = Arc::new(Mutex::new(vec![]));
+    let mut monitor = Monitor::builder(Arc::new(source), "products", Baseline::fixed(1000, None))
+        .with_thresholds(Thresholds::from_delta(-100))
+        .on_breach({
+            let alerts = alerts.clone();
+            move |records, _| alerts.lock().unwrap().push(records.current_records)
+        })
+        .build();
+
+    assert!(monitor.poll_records().await.unwrap().breach.is_some());
+    assert!(monitor.poll_records().await.is_err());
+    assert!(monitor.poll_records().await.unwrap().breach.is_some());
+    assert!(monitor.poll_records().await.unwrap().recovered);
+    assert!(monitor.poll_records().await.unwrap().breach.is_some());
+
+    assert_eq!(*alerts.lock().unwrap(), [800, 700, 600]);
+}
+
+#[tokio::test]
+async fn poll_past_the_scripted_responses_is_the_end_of_the_replay() {
+    let source = MockSource::new().with_records("products", [1000]);
+    let mut monitor = Monitor::builder(Arc::new(source), "products", Baseline::fixed(1000, None)).build();
+
+    monitor.poll_records().await.unwrap();
+    let error = monitor.poll_records().await.unwrap_err();
+
+    assert!(error.is_replay_end(), "{error:?}");
+}
+
+#[tokio::test]
+async fn waiting_task_ages_with_the_time_of_the_source() {
+    let source = MockSource::new();
+    let builds = [Ok(vec![build_log(source.now(), 42)]), Ok(vec![]), Ok(vec![])];
+    let source = Arc::new(
+        source
+            .with_pending_tasks("products", [Ok(1), Ok(1), Ok(0)])
+            .with_logs_of_type("products", LogType::Build, builds)
+            .with_task_published("products", 42, [Ok(false), Ok(false)]),
+    );
+    let mut monitor = Monitor::builder(source.clone(), "products", Baseline::fixed(1000, None)).build();
+
+    assert_eq!(monitor.check_tasks().await.unwrap().oldest_age, Some(Duration::ZERO));
+
+    source.advance(Duration::from_secs(600));
+    assert_eq!(monitor.check_tasks().await.unwrap().oldest_age, Some(Duration::from_secs(600)));
+
+    // Once Algolia has nothing pending the task is no longer followed
+    let backlog = monitor.check_tasks().await.unwrap();
+    assert_eq!((backlog.pending, backlog.oldest_age), (0, None));
+}
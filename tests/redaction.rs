@@ -0,0 +1,101 @@
+//! Errors of the requests sending the API key, which must never show it in their messages or debug output
+
+use algolia_monitor::{AlgoliaClient, ApiKey, MonitorError};
+use serde_json::json;
+use std::time::Duration;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const KEY: &str = "0123456789abcdef-redaction-key";
+
+fn client(base_url: String) -> AlgoliaClient {
+    AlgoliaClient::new("APPID", &ApiKey::new(KEY)).unwrap().with_base_urls(vec![base_url])
+}
+
+/// Server answering the request for the permissions of the key itself, the one carrying it in its URL
+async fn keys_server(response: ResponseTemplate) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/1/keys/{KEY}")))
+        .respond_with(response)
+        .mount(&server)
+        .await;
+    server
+}
+
+async fn validate_key_error(base_url: String) -> MonitorError {
+    let key = ApiKey::new(KEY);
+    client(base_url)
+        .with_timeouts(Duration::from_millis(200), Duration::from_millis(200))
+        .unwrap()
+        .validate_key(&key)
+        .await
+        .unwrap_err()
+}
+
+fn assert_redacted(error: &MonitorError) {
+    let (display, debug) = (error.to_string(), format!("{error:?}"));
+
+    assert!(!display.contains(KEY), "key in the message: {display}");
+    assert!(!debug.contains(KEY), "key in the debug output: {debug}");
+}
+
+#[tokio::test]
+async fn http_error_leaves_the_key_out_of_the_url() {
+    // Nothing listens on the port once the listener is dropped
+    let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let base_url = format!("http://127.0.0.1:{port}/1/");
+
+    let error = validate_key_error(base_url).await;
+
+    assert!(matches!(error, MonitorError::Http(_)), "{error:?}");
+    assert!(error.to_string().contains("/1/keys/***"), "{error}");
+    assert_redacted(&error);
+}
+
+#[tokio::test]
+async fn api_error_masks_the_key_echoed_in_its_message() {
+    let body = json!({ "message": format!("Key {KEY} does not exist"), "status": 404 });
+    let server = keys_server(ResponseTemplate::new(404).set_body_json(body)).await;
+
+    let error = validate_key_error(format!("{}/1/", server.uri())).await;
+
+    assert!(matches!(error, MonitorError::Api { .. }), "{error:?}");
+    assert!(error.to_string().contains("Key ***-key does not exist"), "{error}");
+    assert_redacted(&error);
+}
+
+#[tokio::test]
+async fn json_error_leaves_out_the_body_echoing_the_key() {
+    let server = keys_server(ResponseTemplate::new(200).set_body_string(format!(r#"{{"value": "{KEY}""#))).await;
+
+    let error = validate_key_error(format!("{}/1/", server.uri())).await;
+
+    assert!(matches!(error, MonitorError::Json(_)), "{error:?}");
+    assert_redacted(&error);
+}
+
+#[tokio::test]
+async fn timeout_leaves_out_the_request() {
+    let response = ResponseTemplate::new(200)
+        .set_body_json(json!({ "acl": [] }))
+        .set_delay(Duration::from_secs(2));
+    let server = keys_server(response).await;
+
+    let error = validate_key_error(format!("{}/1/", server.uri())).await;
+
+    assert!(matches!(error, MonitorError::Timeout(_)), "{error:?}");
+    assert_redacted(&error);
+}
+
+#[test]
+fn invalid_argument_leaves_out_a_key_that_cant_be_sent() {
+    let key = format!("{KEY}\n");
+
+    let Err(error) = AlgoliaClient::new("APPID", &ApiKey::new(key.as_str())) else {
+        panic!("a key with a line break can't be sent in a header");
+    };
+
+    assert!(matches!(error, MonitorError::InvalidArgument(_)), "{error:?}");
+    assert_redacted(&error);
+}
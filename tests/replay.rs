@@ -0,0 +1,91 @@
+//! Replay of recorded log responses with a mix of status codes, through the answer code filters of a monitor
+
+use algolia_monitor::{AlgoliaLogEntry, Baseline, LogFilter, Monitor, MonitorError, Replay, ReplaySource};
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Two fetches of the logs of `products`, the second one returning the newest entry of the first again, then
+/// a fetch refused by Algolia
+const FIXTURE: &str = "tests/fixtures/answer_codes.jsonl";
+
+fn monitor(log_filter: LogFilter) -> Monitor<ReplaySource> {
+    let replay = Replay::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join(FIXTURE)).unwrap();
+    Monitor::builder(Arc::new(replay.source("APPID")), "products", Baseline::fixed(0, None))
+        .with_log_filter(log_filter)
+        .build()
+}
+
+fn sha1s(logs: &[AlgoliaLogEntry]) -> Vec<&str> {
+    logs.iter().map(|log| log.extra["sha1"].as_str().unwrap()).collect()
+}
+
+fn at(time: &str) -> Option<DateTime<Utc>> {
+    Some(format!("2024-05-02T{time}Z").parse().unwrap())
+}
+
+#[tokio::test]
+async fn min_answer_code_keeps_the_failures() {
+    let mut monitor = monitor(LogFilter::default().with_min_answer_code(400));
+
+    assert_eq!(sha1s(&monitor.poll_logs().await.unwrap()), ["a3", "a2", "a1"]);
+    assert_eq!(sha1s(&monitor.poll_logs().await.unwrap()), ["a6"]);
+}
+
+#[tokio::test]
+async fn answer_codes_keep_the_listed_ones() {
+    let mut monitor = monitor(LogFilter::default().with_answer_codes(vec![422, 429]));
+
+    assert_eq!(sha1s(&monitor.poll_logs().await.unwrap()), ["a2"]);
+    assert_eq!(sha1s(&monitor.poll_logs().await.unwrap()), ["a6"]);
+}
+
+#[tokio::test]
+async fn answer_codes_combine_with_only_writes() {
+    let mut monitor = monitor(LogFilter::default().with_min_answer_code(400).with_only_writes(true));
+
+    // The failed query is left out, while the failed object deletion and batch are writes
+    assert_eq!(sha1s(&monitor.poll_logs().await.unwrap()), ["a3", "a2"]);
+    // A rejected change of the settings leaves the records alone
+    assert!(monitor.poll_logs().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn filtered_out_entries_still_move_the_logs_on() {
+    let mut monitor = monitor(LogFilter::default().with_answer_codes(vec![500]));
+
+    assert_eq!(sha1s(&monitor.poll_logs().await.unwrap()), ["a3"]);
+    // The newest entry was answered with 200, yet the next fetch goes on from it
+    assert_eq!(monitor.logs_since(), at("10:00:04"));
+
+    assert!(monitor.poll_logs().await.unwrap().is_empty());
+    assert_eq!(monitor.logs_since(), at("10:00:06"));
+}
+
+#[tokio::test]
+async fn every_entry_is_handed_out_once_without_a_filter() {
+    let mut monitor = monitor(LogFilter::default());
+
+    assert_eq!(sha1s(&monitor.poll_logs().await.unwrap()), ["a4", "a3", "a2", "a1"]);
+    assert_eq!(sha1s(&monitor.poll_logs().await.unwrap()), ["a6", "a5"]);
+}
+
+#[tokio::test]
+async fn recorded_failure_is_replayed_before_the_end() {
+    let mut monitor = monitor(LogFilter::default());
+    monitor.poll_logs().await.unwrap();
+    monitor.poll_logs().await.unwrap();
+
+    let error = monitor.poll_logs().await.unwrap_err();
+    match &error {
+        MonitorError::Api { status, message } => {
+            assert_eq!(*status, StatusCode::FORBIDDEN);
+            assert!(message.starts_with("Method not allowed with this API key"), "{message}");
+        }
+        error => panic!("expected an API error, got {error:?}"),
+    }
+
+    let error = monitor.poll_logs().await.unwrap_err();
+    assert!(error.is_replay_end(), "{error:?}");
+}
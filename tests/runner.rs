@@ -0,0 +1,217 @@
+//! Runs of a monitor against a mock server, the polls, their checks and the controls of the run
+
+use algolia_monitor::{
+    AlgoliaClient, ApiKey, Baseline, Check, Control, Monitor, MonitorBuilder, MonitorEvent, PollDelay, RunEnd,
+    Thresholds,
+};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const INTERVAL: Duration = Duration::from_millis(10);
+
+/// Longest a run of a test may take before it is taken as stuck
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+async fn respond_count(server: &MockServer, response: ResponseTemplate) {
+    Mock::given(method("POST"))
+        .and(path("/1/indexes/products/query"))
+        .respond_with(response)
+        .mount(server)
+        .await;
+}
+
+fn count(records: u64) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({ "hits": [], "nbHits": records }))
+}
+
+/// Builder of a monitor of `products` on the server expecting 1000 records, along with the events of its run
+fn builder(server: &MockServer) -> (MonitorBuilder<AlgoliaClient>, UnboundedReceiver<MonitorEvent>) {
+    let client = AlgoliaClient::new("APPID", &ApiKey::new("mock-server-search-key"))
+        .unwrap()
+        .with_base_urls(vec![format!("{}/1/", server.uri())]);
+    let (events, received) = mpsc::unbounded_channel();
+    let builder = Monitor::builder(Arc::new(client), "products", Baseline::fixed(1000, None))
+        .with_interval(INTERVAL)
+        .with_event_channel(events);
+
+    (builder, received)
+}
+
+async fn run(monitor: &mut Monitor<AlgoliaClient>) -> RunEnd {
+    tokio::time::timeout(TIMEOUT, monitor.run()).await.expect("the run to end")
+}
+
+fn drain(received: &mut UnboundedReceiver<MonitorEvent>) -> Vec<MonitorEvent> {
+    let mut events = vec![];
+    while let Ok(event) = received.try_recv() {
+        events.push(event);
+    }
+    events
+}
+
+/// Records count, consecutive errors and whether the run gave up, of each end of a poll
+fn poll_ends(events: &[MonitorEvent]) -> Vec<(Option<u64>, u32, bool)> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            MonitorEvent::PollEnded(end) => Some((end.records, end.consecutive_errors, end.gives_up)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn run_ends_after_the_most_polls() {
+    let server = MockServer::start().await;
+    respond_count(&server, count(1000)).await;
+    let (builder, mut received) = builder(&server);
+    let mut monitor = builder.with_max_polls(Some(3)).build();
+
+    assert!(matches!(run(&mut monitor).await, RunEnd::Finished));
+
+    let events = drain(&mut received);
+    assert_eq!(poll_ends(&events), [(Some(1000), 0, false); 3]);
+    // Each poll comes out in full before it ends, and the wait for the next one after
+    let kinds: Vec<_> = events
+        .iter()
+        .map(|event| match event {
+            MonitorEvent::CountSampled(_) => "sampled",
+            MonitorEvent::Polled(_) => "polled",
+            MonitorEvent::PollEnded(_) => "ended",
+            MonitorEvent::NextPoll(_) => "next",
+            event => panic!("unexpected {event:?}"),
+        })
+        .collect();
+    let poll = ["sampled", "polled", "ended"];
+    assert_eq!(kinds, [&poll[..], &["next"], &poll, &["next"], &poll].concat());
+}
+
+#[tokio::test]
+async fn run_gives_up_after_too_many_failed_polls_in_a_row() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/1/indexes/products/query"))
+        .respond_with(count(1000))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    respond_count(&server, ResponseTemplate::new(503)).await;
+    let (builder, mut received) = builder(&server);
+    let mut monitor = builder.with_max_consecutive_errors(Some(3)).build();
+
+    assert!(matches!(run(&mut monitor).await, RunEnd::GaveUp));
+
+    let ends = poll_ends(&drain(&mut received));
+    assert_eq!(ends, [(Some(1000), 0, false), (None, 1, false), (None, 2, false), (None, 3, true)]);
+}
+
+#[tokio::test]
+async fn run_gives_up_right_away_on_a_rejected_key() {
+    let server = MockServer::start().await;
+    let body = json!({ "message": "Invalid Application-ID or API key", "status": 403 });
+    respond_count(&server, ResponseTemplate::new(403).set_body_json(body)).await;
+    let (builder, mut received) = builder(&server);
+    let mut monitor = builder.with_max_consecutive_errors(Some(3)).build();
+
+    assert!(matches!(run(&mut monitor).await, RunEnd::GaveUp));
+
+    assert_eq!(poll_ends(&drain(&mut received)), [(None, 1, true)]);
+}
+
+#[tokio::test]
+async fn check_the_key_has_no_permission_for_is_turned_off() {
+    let server = MockServer::start().await;
+    respond_count(&server, count(1000)).await;
+    let body = json!({ "message": "Method not allowed with this API key", "status": 403 });
+    Mock::given(method("GET"))
+        .and(path("/1/indexes/products/settings"))
+        .respond_with(ResponseTemplate::new(403).set_body_json(body))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let (builder, mut received) = builder(&server);
+    let mut monitor = builder
+        .with_settings_watch(vec![], None)
+        .with_settings_interval(Duration::ZERO)
+        .with_max_polls(Some(2))
+        .build();
+
+    assert!(matches!(run(&mut monitor).await, RunEnd::Finished));
+
+    let events = drain(&mut received);
+    let failed: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            MonitorEvent::CheckFailed { check, disabled, .. } => Some((*check, *disabled)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(failed, [(Check::Settings, true)]);
+    // The records count is still watched
+    assert_eq!(poll_ends(&events), [(Some(1000), 0, false); 2]);
+}
+
+#[tokio::test]
+async fn controls_poll_now_move_the_expected_count_and_stop_the_run() {
+    let server = MockServer::start().await;
+    respond_count(&server, count(800)).await;
+    Mock::given(method("GET"))
+        .and(path("/1/logs"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "logs": [] })))
+        .mount(&server)
+        .await;
+    let (builder, mut received) = builder(&server);
+    let (controls, control) = mpsc::unbounded_channel();
+    // Without a control the second poll would only come after an hour
+    let mut monitor = builder
+        .with_interval(Duration::from_secs(3600))
+        .with_thresholds(Thresholds::from_delta(-100))
+        .with_controls(control)
+        .build();
+    let run = tokio::spawn(async move { monitor.run().await });
+
+    let mut breaches = vec![];
+    while let Some(event) = tokio::time::timeout(TIMEOUT, received.recv()).await.unwrap() {
+        match event {
+            MonitorEvent::Polled(poll) => breaches.push(poll.breach.is_some()),
+            MonitorEvent::PollEnded(end) if end.polls == 1 => {
+                controls.send(Control::ExpectedRecords(800)).unwrap();
+                controls.send(Control::PollNow).unwrap();
+            }
+            MonitorEvent::PollEnded(_) => {
+                controls.send(Control::Stop).unwrap();
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    assert!(matches!(tokio::time::timeout(TIMEOUT, run).await.unwrap().unwrap(), RunEnd::Stopped));
+    assert_eq!(breaches, [true, false]);
+}
+
+#[tokio::test]
+async fn delay_backs_off_while_the_count_stays_the_same() {
+    let server = MockServer::start().await;
+    respond_count(&server, count(1000)).await;
+    let (builder, mut received) = builder(&server);
+    let poll_delay = PollDelay::new(Duration::from_millis(100), Some(Duration::from_secs(1)), None);
+    let mut monitor = builder.with_poll_delay(poll_delay).with_max_polls(Some(3)).build();
+
+    run(&mut monitor).await;
+
+    let delays: Vec<_> = drain(&mut received)
+        .into_iter()
+        .filter_map(|event| match event {
+            MonitorEvent::NextPoll(delay) => Some(delay),
+            _ => None,
+        })
+        .collect();
+    // The first poll has no count to compare to, the second one finds the same count
+    assert!(delays[0] <= Duration::from_millis(100), "{delays:?}");
+    assert!(delays[1] > Duration::from_millis(100), "{delays:?}");
+}